@@ -0,0 +1,81 @@
+// The warp as an Android app, every finger dragging the doggo around; see the
+// README for building it with cargo-apk. Elsewhere this builds to nothing.
+#![cfg(target_os = "android")]
+
+use std::time::Duration;
+use pixels::{Pixels, SurfaceTexture};
+use winit::event::TouchPhase;
+use winit::event_loop::ActiveEventLoop;
+use winit::platform::android::activity::AndroidApp;
+use winit::window::Window;
+use doggowarp::*;
+
+const DOGGO: &[u8] = include_bytes!("../src/doggo.jpg");
+
+struct Warp {
+    window: Window,
+    pixels: Pixels,
+    renderer: Renderer,
+}
+
+impl AppState for Warp {
+    type StartProps = Image;
+
+    type StartErr = pixels::Error;
+
+    type UserEvent = ();
+
+    fn start(_: &ActiveEventLoop, window: Window, img: Image) -> Result<Self, pixels::Error> {
+        let pixels = new_pixels(&window, &img)?;
+        let mut renderer = Renderer::new(img);
+        renderer.set_breathing(Some(Breathing::default()));
+        Ok(Self { window, pixels, renderer })
+    }
+
+    // there is no cursor, only fingers
+    type MouseMoveErr = ();
+
+    fn mousemove(&mut self, _: Pos) -> Result<(), ()> { Ok(()) }
+
+    type RenderErr = pixels::Error;
+
+    fn render(&mut self, delta: Duration) -> Result<(), pixels::Error> {
+        self.renderer.render(delta, self.pixels.frame_mut());
+        self.pixels.render()
+    }
+
+    fn window(&self) -> &Window { &self.window }
+
+    // the surface is destroyed whenever the app goes to the background
+    fn resumed(&mut self) {
+        if let Ok(pixels) = new_pixels(&self.window, self.renderer.image()) {
+            self.pixels = pixels;
+        }
+    }
+
+    fn resized(&mut self, width: u32, height: u32) {
+        let _ = self.pixels.resize_surface(width, height);
+    }
+
+    fn touch(&mut self, id: u64, phase: TouchPhase, pos: Pos) {
+        let p = frame_pos(&self.pixels, pos * self.window.scale_factor());
+        match phase {
+            TouchPhase::Started | TouchPhase::Moved => self.renderer.touch_move(id, p),
+            TouchPhase::Ended | TouchPhase::Cancelled => self.renderer.touch_end(id),
+        }
+    }
+}
+
+fn new_pixels(window: &Window, img: &Image) -> Result<Pixels, pixels::Error> {
+    let ws = window.inner_size();
+    let mut pixels = Pixels::new(img.width as u32, img.height as u32, SurfaceTexture::new(ws.width, ws.height, window))?;
+    // alpha stays opaque, only the colors get written
+    pixels.frame_mut().iter_mut().skip(3).step_by(4).for_each(|e| *e = 255);
+    Ok(pixels)
+}
+
+#[no_mangle]
+fn android_main(app: AndroidApp) {
+    let img = Image::from_jpeg(DOGGO).unwrap();
+    <Driver<Warp>>::new(img).android_app(app).run().unwrap();
+}
@@ -0,0 +1,99 @@
+// The warp in a browser, on a canvas appended to the page; see the README for
+// building it with wasm-bindgen. Elsewhere it only says so.
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    eprintln!("build this example for wasm32-unknown-unknown, see the README");
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    web::main();
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use pixels::{Pixels, SurfaceTexture};
+    use winit::dpi::LogicalSize;
+    use winit::event_loop::ActiveEventLoop;
+    use winit::platform::web::WindowAttributesExtWebSys;
+    use winit::window::Window;
+    use doggowarp::*;
+
+    const DOGGO: &[u8] = include_bytes!("../src/doggo.jpg");
+
+    struct Warp {
+        window: Rc<Window>,
+        // None until WebGPU or WebGL is set up and puts it in `ready`
+        pixels: Option<Pixels>,
+        ready: Rc<RefCell<Option<Pixels>>>,
+        renderer: Renderer,
+    }
+
+    impl AppState for Warp {
+        type StartProps = Image;
+
+        type StartErr = ();
+
+        type UserEvent = ();
+
+        fn window_options(img: &Image) -> WindowOptions {
+            WindowOptions::default().title("doggowarp")
+                .map(|a| a.with_inner_size(LogicalSize::new(img.width as f64, img.height as f64)).with_append(true))
+        }
+
+        // browsers only hand out GPU surfaces asynchronously
+        fn start(_: &ActiveEventLoop, window: Window, img: Image) -> Result<Self, ()> {
+            let (window, ready) = (Rc::new(window), Rc::new(RefCell::new(None)));
+            let (w, r, (width, height)) = (window.clone(), ready.clone(), (img.width as u32, img.height as u32));
+            wasm_bindgen_futures::spawn_local(async move {
+                let ws = w.inner_size();
+                let mut pixels = Pixels::new_async(width, height, SurfaceTexture::new(ws.width, ws.height, &*w)).await
+                    .expect("cannot set up the canvas");
+                pixels.frame_mut().iter_mut().skip(3).step_by(4).for_each(|e| *e = 255);
+                *r.borrow_mut() = Some(pixels);
+                w.request_redraw();
+            });
+            Ok(Self { window, pixels: None, ready, renderer: Renderer::new(img) })
+        }
+
+        type MouseMoveErr = ();
+
+        fn mousemove(&mut self, pos: Pos) -> Result<(), ()> {
+            if let Some(pixels) = &self.pixels {
+                self.renderer.mousemove(frame_pos(pixels, pos * self.window.scale_factor()));
+            }
+            Ok(())
+        }
+
+        type RenderErr = pixels::Error;
+
+        fn render(&mut self, delta: Duration) -> Result<(), pixels::Error> {
+            if self.pixels.is_none() {
+                self.pixels = self.ready.borrow_mut().take();
+            }
+            match &mut self.pixels {
+                Some(pixels) => {
+                    self.renderer.render(delta, pixels.frame_mut());
+                    pixels.render()
+                }
+                None => Ok(()),
+            }
+        }
+
+        fn window(&self) -> &Window { &self.window }
+
+        fn resized(&mut self, width: u32, height: u32) {
+            if let Some(pixels) = &mut self.pixels {
+                let _ = pixels.resize_surface(width, height);
+            }
+        }
+    }
+
+    pub fn main() {
+        <Driver<Warp>>::new(Image::from_jpeg(DOGGO).unwrap()).spawn().unwrap();
+    }
+}
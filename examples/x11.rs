@@ -0,0 +1,58 @@
+// The warp drawn through `X11Present` alone, without wgpu, for checking the
+// CPU presenter on a machine or VM with an X server but no usable GPU:
+//
+//     cargo run --release --example x11 --features x11
+
+use std::time::Duration;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::Window;
+use doggowarp::*;
+use doggowarp::present::{Present, PresentError, X11Present};
+
+const DOGGO: &[u8] = include_bytes!("../src/doggo.jpg");
+
+struct Warp {
+    window: Window,
+    present: X11Present,
+    renderer: Renderer,
+}
+
+impl AppState for Warp {
+    type StartProps = Image;
+
+    type StartErr = PresentError;
+
+    type UserEvent = ();
+
+    fn start(_: &ActiveEventLoop, window: Window, img: Image) -> Result<Self, PresentError> {
+        let mut present = X11Present::new(&window, img.width as u32, img.height as u32)?;
+        present.frame_mut().iter_mut().skip(3).step_by(4).for_each(|e| *e = 255);
+        Ok(Self { window, present, renderer: Renderer::new(img) })
+    }
+
+    type MouseMoveErr = ();
+
+    fn mousemove(&mut self, pos: Pos) -> Result<(), ()> {
+        let p = self.present.frame_pos(pos * self.window.scale_factor());
+        self.renderer.mousemove(p);
+        Ok(())
+    }
+
+    type RenderErr = PresentError;
+
+    fn render(&mut self, delta: Duration) -> Result<(), PresentError> {
+        self.renderer.render(delta, self.present.frame_mut());
+        self.present.present()
+    }
+
+    fn window(&self) -> &Window { &self.window }
+
+    fn resized(&mut self, width: u32, height: u32) {
+        let _ = self.present.resize_surface(width, height);
+    }
+}
+
+fn main() {
+    let img = Image::from_jpeg(DOGGO).unwrap();
+    <Driver<Warp>>::new(img).run().unwrap();
+}
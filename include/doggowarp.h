@@ -0,0 +1,67 @@
+/*
+ * C interface of doggowarp, available when the crate is built with the
+ * `capi` feature. Build it as a shared library with
+ *
+ *   cargo rustc --release --lib --no-default-features --features capi,parallel --crate-type cdylib
+ *
+ * or use `--crate-type staticlib` for a static archive.
+ *
+ * All frames are tightly packed 8-bit RGBA, row-major, with the same
+ * dimensions as the source image.
+ */
+#ifndef DOGGOWARP_H
+#define DOGGOWARP_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct DoggowarpImage DoggowarpImage;
+typedef struct DoggowarpRenderer DoggowarpRenderer;
+
+/* Decodes `len` bytes of JPEG data. Returns NULL on failure.
+ * `data` must point to at least `len` readable bytes. */
+DoggowarpImage *doggowarp_image_from_jpeg(const uint8_t *data, size_t len);
+
+/* Copies a width x height RGBA buffer; `len` must be width * height * 4.
+ * Returns NULL on invalid arguments. */
+DoggowarpImage *doggowarp_image_from_rgba(size_t width, size_t height, const uint8_t *data, size_t len);
+
+/* Frees an image that was not handed to doggowarp_renderer_new. NULL is ignored. */
+void doggowarp_image_free(DoggowarpImage *img);
+
+/* Creates a renderer, taking ownership of `img`; do not use or free `img`
+ * afterwards. Returns NULL if `img` is NULL. */
+DoggowarpRenderer *doggowarp_renderer_new(DoggowarpImage *img);
+
+/* Frees a renderer and its image. NULL is ignored. */
+void doggowarp_renderer_free(DoggowarpRenderer *r);
+
+/* Frame dimensions in pixels, 0 if `r` is NULL. */
+size_t doggowarp_renderer_width(const DoggowarpRenderer *r);
+size_t doggowarp_renderer_height(const DoggowarpRenderer *r);
+
+/* Moves the warp center, in image pixel coordinates. */
+void doggowarp_renderer_set_cursor(DoggowarpRenderer *r, double x, double y);
+
+/* Renders on a pool of `threads` threads of the renderer's own instead of
+ * the global one, one per core for 0. Does nothing when built without the
+ * `parallel` feature. Returns 0 on success, -1 if `r` is NULL or the
+ * threads cannot be started. */
+int32_t doggowarp_renderer_set_threads(DoggowarpRenderer *r, size_t threads);
+
+/* Renders one frame into `frame`, which must hold `len` writable bytes and
+ * `len` must be width * height * 4. `delta_secs` is the time since the
+ * previous frame, positive and within a 64-bit count of seconds, and
+ * drives the cursor velocity. Alpha bytes are left untouched. Returns 0
+ * on success, -1 on invalid arguments. */
+int32_t doggowarp_renderer_render(DoggowarpRenderer *r, double delta_secs, uint8_t *frame, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* DOGGOWARP_H */
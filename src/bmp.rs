@@ -0,0 +1,239 @@
+// BMP decoder to RGBA8: 1, 4 and 8-bit palettes, RLE8 and RLE4, and 16, 24
+// and 32-bit pixels with or without bit field masks, in either row order.
+
+use core::fmt;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::MAX_PIXELS;
+
+// region Error
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Signature,
+    Truncated,
+    Format(&'static str),
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Signature => f.write_str("not a BMP file"),
+            Error::Truncated => f.write_str("BMP data ends early"),
+            Error::Format(e) => write!(f, "malformed BMP: {}", e),
+            Error::Unsupported(e) => write!(f, "unsupported BMP: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// endregion
+// region Decoder
+
+pub const SIGNATURE: [u8; 2] = *b"BM";
+
+// compression methods
+const RGB: u32 = 0;
+const RLE8: u32 = 1;
+const RLE4: u32 = 2;
+const BITFIELDS: u32 = 3;
+const ALPHA_BITFIELDS: u32 = 6;
+
+// returns (width, height, RGBA bytes); only masks with an alpha channel
+// decode transparent, and so do pixels RLE skips over
+pub fn decode(data: &[u8]) -> Result<(usize, usize, Vec<u8>), Error> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err(Error::Signature);
+    }
+    let u16_at = |i: usize| data.get(i..i + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32).ok_or(Error::Truncated);
+    let u32_at = |i: usize| data.get(i..i + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or(Error::Truncated);
+    let offset = u32_at(10)? as usize;
+    let header = u32_at(14)? as usize;
+    // OS/2 1.x headers have 16-bit sizes and three-byte palette entries
+    let (width, height, bpp, compression, colors, entry) = match header {
+        12 => (u16_at(18)? as i32, u16_at(20)? as i32, u16_at(24)?, RGB, 0, 3),
+        40.. => (u32_at(18)? as i32, u32_at(22)? as i32, u16_at(28)?, u32_at(30)?, u32_at(46)? as usize, 4),
+        _ => return Err(Error::Unsupported("header size")),
+    };
+    // positive heights run bottom-up
+    let top_down = height < 0;
+    let (width, height) = (width.unsigned_abs() as usize, height.unsigned_abs() as usize);
+    if width == 0 || height == 0 || width.saturating_mul(height) > MAX_PIXELS {
+        return Err(Error::Format("size out of range"));
+    }
+
+    // red, green, blue and alpha; masks follow a plain info header, and
+    // later headers carry them at the same place
+    let masks = match (compression, bpp) {
+        (RGB, 16) => [0x7c00, 0x03e0, 0x001f, 0],
+        (RGB, 24 | 32) => [0xff_0000, 0xff00, 0xff, 0],
+        (BITFIELDS | ALPHA_BITFIELDS, 16 | 32) => {
+            let alpha = header >= 56 || compression == ALPHA_BITFIELDS;
+            [u32_at(54)?, u32_at(58)?, u32_at(62)?, if alpha { u32_at(66)? } else { 0 }]
+        }
+        (RGB, 1 | 4 | 8) | (RLE8, 8) | (RLE4, 4) => [0; 4],
+        (RGB | BITFIELDS | ALPHA_BITFIELDS, _) => return Err(Error::Unsupported("bit depth")),
+        (RLE8 | RLE4, _) => return Err(Error::Format("RLE with the wrong bit depth")),
+        _ => return Err(Error::Unsupported("compression")),
+    };
+    let palette: Vec<[u8; 4]> = if bpp <= 8 {
+        // some writers store fewer entries than the bit depth implies
+        let start = 14 + header;
+        let room = offset.saturating_sub(start) / entry;
+        let count = if colors == 0 { 1 << bpp } else { colors.min(256) };
+        let count = if room > 0 { count.min(room) } else { count };
+        let table = data.get(start..start + count * entry).ok_or(Error::Truncated)?;
+        table.chunks_exact(entry).map(|c| [c[2], c[1], c[0], 255]).collect()
+    } else {
+        Vec::new()
+    };
+    let color = |i: usize| palette.get(i).copied().unwrap_or([0, 0, 0, 255]);
+
+    let pixels = data.get(offset..).ok_or(Error::Truncated)?;
+    // rows are padded to four bytes, which the last one may leave out
+    let (stride, row_bytes) = ((width * bpp as usize).div_ceil(32) * 4, (width * bpp as usize).div_ceil(8));
+    let rle = compression == RLE8 || compression == RLE4;
+    if !rle && pixels.len() < stride * (height - 1) + row_bytes {
+        return Err(Error::Truncated);
+    }
+    let mut rgba = vec![0u8; width * height * 4];
+    if rle {
+        if top_down {
+            return Err(Error::Format("top-down RLE"));
+        }
+        decode_rle(pixels, width, height, bpp, |x, y, i| {
+            rgba[((height - 1 - y) * width + x) * 4..][..4].copy_from_slice(&color(i));
+        })?;
+        return Ok((width, height, rgba));
+    }
+    for (row, out) in rgba.chunks_exact_mut(width * 4).enumerate() {
+        let y = if top_down { row } else { height - 1 - row };
+        let line = &pixels[y * stride..][..row_bytes];
+        for (x, px) in out.chunks_exact_mut(4).enumerate() {
+            let c = match bpp {
+                1 | 4 | 8 => {
+                    let bit = x * bpp as usize;
+                    color((line[bit / 8] >> (8 - bpp as usize - bit % 8)) as usize & ((1 << bpp) - 1))
+                }
+                _ => {
+                    let v = match bpp {
+                        16 => u16::from_le_bytes([line[2 * x], line[2 * x + 1]]) as u32,
+                        24 => u32::from_le_bytes([line[3 * x], line[3 * x + 1], line[3 * x + 2], 0]),
+                        _ => u32::from_le_bytes([line[4 * x], line[4 * x + 1], line[4 * x + 2], line[4 * x + 3]]),
+                    };
+                    let alpha = if masks[3] == 0 { 255 } else { channel(v, masks[3]) };
+                    [channel(v, masks[0]), channel(v, masks[1]), channel(v, masks[2]), alpha]
+                }
+            };
+            px.copy_from_slice(&c);
+        }
+    }
+    Ok((width, height, rgba))
+}
+
+// the bits of `v` under `mask`, scaled to a byte
+fn channel(v: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let max = (mask >> mask.trailing_zeros()) as u64;
+    let v = ((v & mask) >> mask.trailing_zeros()) as u64;
+    ((v * 255 + max / 2) / max) as u8
+}
+
+// runs, literal stretches and jumps of palette indices, rows counted from the
+// bottom; `put` gets (x, y, index) for every pixel inside the image
+fn decode_rle(data: &[u8], width: usize, height: usize, bpp: u32, mut put: impl FnMut(usize, usize, usize)) -> Result<(), Error> {
+    let byte = |i: usize| data.get(i).copied().map(usize::from).ok_or(Error::Truncated);
+    // the k-th index of a run or stretch starting at byte `b`
+    let index = |b: usize, k: usize| if bpp == 8 { b } else if k.is_multiple_of(2) { b >> 4 } else { b & 0x0f };
+    let (mut x, mut y, mut i) = (0, 0, 0);
+    while y < height {
+        let (n, b) = (byte(i)?, byte(i + 1)?);
+        i += 2;
+        match (n, b) {
+            (0, 0) => (x, y) = (0, y + 1),
+            (0, 1) => break,
+            (0, 2) => {
+                (x, y) = (x + byte(i)?, y + byte(i + 1)?);
+                i += 2;
+            }
+            (0, n) => {
+                let bytes = if bpp == 8 { n } else { n.div_ceil(2) };
+                for k in 0..n {
+                    let b = byte(i + if bpp == 8 { k } else { k / 2 })?;
+                    if x < width && y < height {
+                        put(x, y, index(b, k));
+                    }
+                    x += 1;
+                }
+                // stretches end on a 16-bit boundary
+                i += bytes + bytes % 2;
+            }
+            (n, b) => {
+                for k in 0..n {
+                    if x < width && y < height {
+                        put(x, y, index(b, k));
+                    }
+                    x += 1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 13x5 crop of doggo with varying alpha, as Java's ImageIO writes each
+    // kind of file, next to the RGBA it decodes to
+    const FILES: [(&str, &[u8], &[u8]); 10] = [
+        ("rgb24", include_bytes!("../tests/data/rgb24.bmp"), include_bytes!("../tests/data/rgb24.bmp.rgba")),
+        ("td24", include_bytes!("../tests/data/td24.bmp"), include_bytes!("../tests/data/td24.bmp.rgba")),
+        ("rgb32", include_bytes!("../tests/data/rgb32.bmp"), include_bytes!("../tests/data/rgb32.bmp.rgba")),
+        ("rgb565", include_bytes!("../tests/data/rgb565.bmp"), include_bytes!("../tests/data/rgb565.bmp.rgba")),
+        ("rgb555", include_bytes!("../tests/data/rgb555.bmp"), include_bytes!("../tests/data/rgb555.bmp.rgba")),
+        ("idx8", include_bytes!("../tests/data/idx8.bmp"), include_bytes!("../tests/data/idx8.bmp.rgba")),
+        ("idx4", include_bytes!("../tests/data/idx4.bmp"), include_bytes!("../tests/data/idx4.bmp.rgba")),
+        ("bin1", include_bytes!("../tests/data/bin1.bmp"), include_bytes!("../tests/data/bin1.bmp.rgba")),
+        ("rle8", include_bytes!("../tests/data/rle8.bmp"), include_bytes!("../tests/data/rle8.bmp.rgba")),
+        ("rle4", include_bytes!("../tests/data/rle4.bmp"), include_bytes!("../tests/data/rle4.bmp.rgba")),
+    ];
+
+    #[test]
+    fn matches_imageio() {
+        for (name, bmp, rgba) in FILES {
+            assert_eq!(decode(bmp), Ok((13, 5, rgba.to_vec())), "{}", name);
+        }
+    }
+
+    // except that the padding of the last row may go missing
+    #[test]
+    fn truncated_data_errs() {
+        for (name, bmp, rgba) in FILES {
+            for len in 0..bmp.len() {
+                if let Ok((_, _, short)) = decode(&bmp[..len]) {
+                    assert!(bmp.len() - len <= 3 && short == rgba, "decoded {} of {} bytes of {}", len, bmp.len(), name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bad_headers_err() {
+        let mut huge = FILES[0].1.to_vec();
+        huge[18..26].copy_from_slice(&[0, 0, 1, 0, 0, 0, 1, 0]);
+        assert_eq!(decode(&huge), Err(Error::Format("size out of range")));
+        let mut empty = FILES[0].1.to_vec();
+        empty[18..22].fill(0);
+        assert_eq!(decode(&empty), Err(Error::Format("size out of range")));
+        assert_eq!(decode(b"BA"), Err(Error::Signature));
+    }
+}
@@ -0,0 +1,106 @@
+// Safety contracts for every function live next to its prototype in include/doggowarp.h
+#![allow(clippy::missing_safety_doc)]
+
+use std::ptr;
+use std::slice;
+use std::time::Duration;
+use crate::{Image, Pos, Renderer};
+
+pub struct DoggowarpImage(Image);
+
+pub struct DoggowarpRenderer(Renderer);
+
+// None where the size overflows, which would otherwise wrap past the check
+fn rgba_len(width: usize, height: usize) -> Option<usize> {
+    width.checked_mul(height)?.checked_mul(4)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_image_from_jpeg(data: *const u8, len: usize) -> *mut DoggowarpImage {
+    if data.is_null() {
+        return ptr::null_mut();
+    }
+    match Image::from_jpeg(slice::from_raw_parts(data, len)) {
+        Ok(img) => Box::into_raw(Box::new(DoggowarpImage(img))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_image_from_rgba(
+    width: usize, height: usize, data: *const u8, len: usize,
+) -> *mut DoggowarpImage {
+    if data.is_null() || width == 0 || height == 0 || rgba_len(width, height) != Some(len) {
+        return ptr::null_mut();
+    }
+    let data = slice::from_raw_parts(data, len).to_vec();
+    Box::into_raw(Box::new(DoggowarpImage(Image::new(width, height, data))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_image_free(img: *mut DoggowarpImage) {
+    if !img.is_null() {
+        drop(Box::from_raw(img));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_renderer_new(img: *mut DoggowarpImage) -> *mut DoggowarpRenderer {
+    if img.is_null() {
+        return ptr::null_mut();
+    }
+    let DoggowarpImage(img) = *Box::from_raw(img);
+    Box::into_raw(Box::new(DoggowarpRenderer(Renderer::new(img))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_renderer_free(r: *mut DoggowarpRenderer) {
+    if !r.is_null() {
+        drop(Box::from_raw(r));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_renderer_width(r: *const DoggowarpRenderer) -> usize {
+    r.as_ref().map_or(0, |r| r.0.image().width)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_renderer_height(r: *const DoggowarpRenderer) -> usize {
+    r.as_ref().map_or(0, |r| r.0.image().height)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_renderer_set_cursor(r: *mut DoggowarpRenderer, x: f64, y: f64) {
+    if let Some(r) = r.as_mut() {
+        r.0.mousemove(Pos::new(x, y));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_renderer_set_threads(r: *mut DoggowarpRenderer, threads: usize) -> i32 {
+    let Some(r) = r.as_mut() else { return -1 };
+    #[cfg(feature = "parallel")]
+    if r.0.set_threads(threads).is_err() {
+        return -1;
+    }
+    #[cfg(not(feature = "parallel"))]
+    let _ = (r, threads);
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn doggowarp_renderer_render(
+    r: *mut DoggowarpRenderer, delta_secs: f64, frame: *mut u8, len: usize,
+) -> i32 {
+    let Some(r) = r.as_mut() else { return -1 };
+    let img = r.0.image();
+    if frame.is_null() || rgba_len(img.width, img.height) != Some(len) || delta_secs <= 0.0 {
+        return -1;
+    }
+    // panicking here would abort the host, so NaN, infinite and huge deltas
+    // are turned away
+    let Ok(delta) = Duration::try_from_secs_f64(delta_secs) else { return -1 };
+    r.0.render(delta, slice::from_raw_parts_mut(frame, len));
+    0
+}
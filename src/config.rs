@@ -0,0 +1,395 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(feature = "windowed")]
+use winit::dpi::LogicalSize;
+#[cfg(feature = "windowed")]
+use winit::window::{Fullscreen, WindowAttributes};
+use crate::{Filter, Tiling, WarpParams};
+
+// region Toml
+
+// the scalar subset of TOML that config files need: [tables], `key = value`
+// with strings, integers, floats and booleans, and # comments
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Value {
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Int(i) => Some(i as f64),
+            Value::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            // keep a fractional part so it reads back as a float
+            Value::Float(v) if v.is_finite() && v.fract() == 0.0 => write!(f, "{:.1}", v),
+            Value::Float(v) if v.is_nan() => f.write_str("nan"),
+            Value::Float(v) if v.is_infinite() => f.write_str(if *v > 0.0 { "inf" } else { "-inf" }),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Str(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+pub struct Entry {
+    // dotted table name, empty for keys before the first table
+    pub table: String,
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+}
+
+pub fn parse_toml(src: &str) -> Result<Vec<Entry>, ConfigError> {
+    let mut table = String::new();
+    let mut entries = Vec::new();
+    for (i, raw) in src.lines().enumerate() {
+        let line = i + 1;
+        let err = |message: &str| ConfigError::Parse { line, message: message.into() };
+        let text = strip_comment(raw).trim();
+        if text.is_empty() {
+            continue;
+        }
+        if let Some(name) = text.strip_prefix('[') {
+            let name = name.strip_suffix(']').ok_or_else(|| err("unclosed table header"))?.trim();
+            if name.is_empty() || name.starts_with('[') {
+                return Err(err("array tables are not supported"));
+            }
+            table = name.into();
+            continue;
+        }
+        let (key, value) = text.split_once('=').ok_or_else(|| err("expected `key = value`"))?;
+        let key = key.trim().trim_matches('"');
+        if key.is_empty() {
+            return Err(err("empty key"));
+        }
+        let value = parse_value(value.trim()).map_err(err)?;
+        entries.push(Entry { table: table.clone(), key: key.into(), value, line });
+    }
+    Ok(entries)
+}
+
+// drops a trailing comment, minding '#' inside strings
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => (),
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn parse_value(v: &str) -> Result<Value, &'static str> {
+    match v {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        "inf" | "+inf" => return Ok(Value::Float(f64::INFINITY)),
+        "-inf" => return Ok(Value::Float(f64::NEG_INFINITY)),
+        "nan" | "+nan" | "-nan" => return Ok(Value::Float(f64::NAN)),
+        _ => (),
+    }
+    if let Some(s) = v.strip_prefix('\'') {
+        return s.strip_suffix('\'').map(|s| Value::Str(s.into())).ok_or("unclosed string");
+    }
+    if let Some(s) = v.strip_prefix('"') {
+        let s = s.strip_suffix('"').ok_or("unclosed string")?;
+        let mut out = String::with_capacity(s.len());
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            out.push(match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('"') => '"',
+                Some('\\') => '\\',
+                _ => return Err("unsupported escape sequence"),
+            });
+        }
+        return Ok(Value::Str(out));
+    }
+    if v.starts_with('[') || v.starts_with('{') {
+        return Err("arrays and inline tables are not supported");
+    }
+    let digits: String = v.chars().filter(|&c| c != '_').collect();
+    if let Ok(i) = digits.parse() {
+        return Ok(Value::Int(i));
+    }
+    digits.parse().map(Value::Float).map_err(|_| "expected a string, number or boolean")
+}
+
+// endregion
+// region Config
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self { ConfigError::Io(e) }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowConfig {
+    // inner size in logical pixels; the image's size if unset
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub resizable: bool,
+    pub fullscreen: bool,
+    // index into the available monitors, for fullscreen; the one the window
+    // opens on if unset
+    pub monitor: Option<usize>,
+    // see `AppState::set_cursor_hidden` and `AppState::confine_cursor`
+    pub hide_cursor: bool,
+    pub confine_cursor: bool,
+}
+
+#[cfg(feature = "windowed")]
+impl WindowConfig {
+    // `size` is used where width or height are unset
+    pub fn apply(&self, attributes: WindowAttributes, size: LogicalSize<f64>) -> WindowAttributes {
+        let size = LogicalSize::new(
+            self.width.map_or(size.width, f64::from),
+            self.height.map_or(size.height, f64::from),
+        );
+        attributes.with_inner_size(size)
+            .with_resizable(self.resizable)
+            .with_fullscreen(self.fullscreen.then_some(Fullscreen::Borderless(None)))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderConfig {
+    // frame rate cap, uncapped if unset
+    pub fps: Option<f64>,
+    pub filter: Filter,
+    pub tiling: Tiling,
+    // see `DynamicResolution`
+    pub dynamic_resolution: bool,
+    // render threads, rayon's default of one per core if unset
+    pub threads: Option<usize>,
+}
+
+// everything a config file can set; missing keys keep their defaults:
+//
+//     [window]
+//     width = 800
+//     resizable = true
+//     hide_cursor = true
+//
+//     [warp]
+//     radius = 250.0
+//     strength = -2.0
+//     taps = 16
+//     separation = 0.04
+//
+//     [render]
+//     fps = 60
+//     filter = "bilinear"
+//     tiling = "auto"
+//     dynamic_resolution = true
+//     threads = 4
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub warp: WarpParams,
+    pub render: RenderConfig,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // unknown keys are errors, so typos don't go unnoticed
+    pub fn parse(src: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        for Entry { table, key, value, line } in parse_toml(src)? {
+            let err = |message: &str| ConfigError::Parse { line, message: format!("{}.{}: {}", table, key, message) };
+            let num = || value.as_f64().ok_or_else(|| err("expected a number"));
+            let size = || num().and_then(|n| {
+                if n >= 1.0 && n.fract() == 0.0 && n <= u32::MAX as f64 { Ok(n as u32) } else { Err(err("expected a positive integer")) }
+            });
+            let flag = || value.as_bool().ok_or_else(|| err("expected true or false"));
+            match (table.as_str(), key.as_str()) {
+                ("window", "width") => config.window.width = Some(size()?),
+                ("window", "height") => config.window.height = Some(size()?),
+                ("window", "resizable") => config.window.resizable = flag()?,
+                ("window", "fullscreen") => config.window.fullscreen = flag()?,
+                ("window", "hide_cursor") => config.window.hide_cursor = flag()?,
+                ("window", "confine_cursor") => config.window.confine_cursor = flag()?,
+                ("window", "monitor") => config.window.monitor = match value {
+                    Value::Int(i) => Some(usize::try_from(i).map_err(|_| err("expected a monitor number from 0"))?),
+                    _ => return Err(err("expected a monitor number from 0")),
+                },
+                ("warp", "radius") => config.warp.radius = num()?,
+                ("warp", "strength") => config.warp.strength = num()?,
+                ("warp", "taps") => config.warp.taps = size()? as usize,
+                ("warp", "offset") => config.warp.offset = num()?,
+                ("warp", "spread") => config.warp.spread = num()?,
+                ("warp", "separation") => config.warp.separation = num()?,
+                ("render", "fps") => config.render.fps = Some(num()?).filter(|&f| f > 0.0),
+                ("render", "filter") => config.render.filter = match value.as_str() {
+                    Some("nearest") => Filter::Nearest,
+                    Some("bilinear") => Filter::Bilinear,
+                    _ => return Err(err("expected \"nearest\" or \"bilinear\"")),
+                },
+                ("render", "threads") => config.render.threads = Some(size()? as usize),
+                ("render", "dynamic_resolution") => config.render.dynamic_resolution = flag()?,
+                ("render", "tiling") => config.render.tiling = match value {
+                    Value::Str(s) if s == "rows" => Tiling::Rows,
+                    Value::Str(s) if s == "auto" => Tiling::Auto,
+                    Value::Int(side) if side > 0 => Tiling::Tiles(side as usize),
+                    _ => return Err(err("expected \"rows\", \"auto\" or a tile size")),
+                },
+                _ => return Err(err("unknown setting")),
+            }
+        }
+        Ok(config)
+    }
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(src: &str) -> Value {
+        let entries = parse_toml(src).unwrap();
+        assert_eq!(entries.len(), 1);
+        entries.into_iter().next().unwrap().value
+    }
+
+    fn error_line(src: &str) -> usize {
+        match parse_toml(src) {
+            Err(ConfigError::Parse { line, .. }) => line,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("parsed: {:?}", src),
+        }
+    }
+
+    #[test]
+    fn comments_inside_strings() {
+        assert_eq!(value(r#"a = "x # y" # z"#), Value::Str("x # y".into()));
+        assert_eq!(value("a = 'x # y' # z"), Value::Str("x # y".into()));
+        assert_eq!(value(r#"a = "x \" # y" # z"#), Value::Str("x \" # y".into()));
+        assert_eq!(value("a = 1 # \"z\""), Value::Int(1));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(value(r#"a = "tab\tnew\nline\r\\\"""#), Value::Str("tab\tnew\nline\r\\\"".into()));
+        // literal strings keep backslashes
+        assert_eq!(value(r"a = 'C:\dir\n'"), Value::Str(r"C:\dir\n".into()));
+        assert_eq!(error_line(r#"a = "\q""#), 1);
+    }
+
+    #[test]
+    fn digit_separators() {
+        assert_eq!(value("a = 1_000_000"), Value::Int(1_000_000));
+        assert_eq!(value("a = 1_000.25"), Value::Float(1000.25));
+    }
+
+    #[test]
+    fn ints_and_floats() {
+        assert_eq!(value("a = 3"), Value::Int(3));
+        assert_eq!(value("a = -3"), Value::Int(-3));
+        assert_eq!(value("a = +3"), Value::Int(3));
+        assert_eq!(value("a = 3.0"), Value::Float(3.0));
+        assert_eq!(value("a = 1e3"), Value::Float(1000.0));
+        assert_eq!(value("a = -2.5E-1"), Value::Float(-0.25));
+        assert_eq!(value("a = true"), Value::Bool(true));
+        assert_eq!(error_line("a = three"), 1);
+    }
+
+    #[test]
+    fn inf_and_nan() {
+        assert_eq!(value("a = inf"), Value::Float(f64::INFINITY));
+        assert_eq!(value("a = +inf"), Value::Float(f64::INFINITY));
+        assert_eq!(value("a = -inf"), Value::Float(f64::NEG_INFINITY));
+        for src in ["a = nan", "a = +nan", "a = -nan"] {
+            assert!(matches!(value(src), Value::Float(v) if v.is_nan()));
+        }
+    }
+
+    #[test]
+    fn arrays_and_inline_tables_are_rejected() {
+        assert_eq!(error_line("a = [1, 2]"), 1);
+        assert_eq!(error_line("a = { b = 1 }"), 1);
+        assert_eq!(error_line("[[tables]]"), 1);
+    }
+
+    #[test]
+    fn tables_and_line_numbers() {
+        let src = "# top\ntop = 1\n\n[window]\nwidth = 640 # px\n[render.tiles]\n\"quoted\" = 'x'\n";
+        let entries = parse_toml(src).unwrap();
+        let got: Vec<_> = entries.iter().map(|e| (e.table.as_str(), e.key.as_str(), e.line)).collect();
+        assert_eq!(got, [("", "top", 2), ("window", "width", 5), ("render.tiles", "quoted", 7)]);
+        assert_eq!(error_line("a = 1\n# fine\n\nb = [2]\n"), 4);
+        assert_eq!(error_line("a = 1\n[window\n"), 2);
+        assert_eq!(error_line("a = 1\njust words\n"), 2);
+        assert_eq!(error_line("= 1"), 1);
+        assert_eq!(error_line("a = \"open"), 1);
+    }
+
+    #[test]
+    fn values_read_back() {
+        for v in [Value::Int(-7), Value::Float(2.0), Value::Float(0.125), Value::Float(f64::NEG_INFINITY), Value::Str("a\"b\\".into())] {
+            assert_eq!(value(&format!("a = {}", v)), v);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// Blends from one image into the next over a set time, for slideshows; the
+// renderer warps whatever `Crossfade::image` gives at each step.
+
+use core::time::Duration;
+use crate::Image;
+
+// region Crossfade
+
+#[derive(Clone)]
+pub struct Crossfade {
+    from: Image,
+    to: Image,
+    // in seconds
+    duration: f64,
+    elapsed: f64,
+}
+
+impl Crossfade {
+    // `to` is letterboxed into the size of `from`, which frames keep
+    pub fn new(from: Image, to: &Image, duration: Duration) -> Self {
+        let to = to.letterbox(from.width, from.height);
+        Self { from, to, duration: duration.as_secs_f64(), elapsed: 0.0 }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.elapsed += delta.as_secs_f64();
+    }
+
+    // 0 at `from` to 1 at `to`, easing in and out
+    pub fn progress(&self) -> f64 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        let t = (self.elapsed / self.duration).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    pub fn is_done(&self) -> bool { self.elapsed >= self.duration }
+
+    // the blend as of the last update
+    pub fn image(&self) -> Image { self.from.mix(&self.to, self.progress()) }
+
+    // the letterboxed image it fades to
+    pub fn target(&self) -> &Image { &self.to }
+
+    pub fn into_target(self) -> Image { self.to }
+}
+
+// endregion
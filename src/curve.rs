@@ -0,0 +1,161 @@
+use core::fmt;
+use core::str::FromStr;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::Color;
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+// region Curve
+
+// tone curve through control points, both coordinates in [0, 1]; interpolated
+// with a monotone cubic so it never overshoots between points, and baked into a LUT
+#[derive(Clone)]
+pub struct Curve {
+    points: Vec<(f64, f64)>,
+    lut: [f64; 256],
+}
+
+impl Curve {
+    // points are sorted by x; ones sharing an x with an earlier one are dropped
+    pub fn new(points: &[(f64, f64)]) -> Self {
+        let mut points: Vec<(f64, f64)> = points.iter()
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .map(|&(x, y)| (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+            .collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|b, a| a.0 == b.0);
+        if points.is_empty() {
+            points = vec![(0.0, 0.0), (1.0, 1.0)];
+        }
+        let lut = bake(&points);
+        Self { points, lut }
+    }
+
+    pub fn identity() -> Self { Self::new(&[(0.0, 0.0), (1.0, 1.0)]) }
+
+    pub fn points(&self) -> &[(f64, f64)] { &self.points }
+
+    // maps a channel on the 0..255 scale
+    pub fn eval(&self, c: f64) -> f64 {
+        let c = if c.is_nan() { 0.0 } else { c.clamp(0.0, 255.0) };
+        let i = (c as usize).min(254);
+        let t = c - i as f64;
+        self.lut[i] + (self.lut[i + 1] - self.lut[i]) * t
+    }
+}
+
+impl Default for Curve {
+    fn default() -> Self { Self::identity() }
+}
+
+// Fritsch-Carlson tangents, then Hermite segments sampled at every input level
+fn bake(points: &[(f64, f64)]) -> [f64; 256] {
+    let n = points.len();
+    let secant: Vec<f64> = points.windows(2).map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0)).collect();
+    let mut tangent: Vec<f64> = (0..n).map(|i| match i {
+        0 => secant.first().copied().unwrap_or(0.0),
+        i if i == n - 1 => secant[i - 1],
+        i if secant[i - 1] * secant[i] <= 0.0 => 0.0,
+        i => (secant[i - 1] + secant[i]) / 2.0,
+    }).collect();
+    for (i, &d) in secant.iter().enumerate() {
+        if d == 0.0 {
+            tangent[i] = 0.0;
+            tangent[i + 1] = 0.0;
+            continue;
+        }
+        let (a, b) = (tangent[i] / d, tangent[i + 1] / d);
+        let h = a * a + b * b;
+        if h > 9.0 {
+            let t = 3.0 / h.sqrt();
+            tangent[i] = t * a * d;
+            tangent[i + 1] = t * b * d;
+        }
+    }
+
+    let mut lut = [0.0; 256];
+    let mut seg = 0;
+    for (i, e) in lut.iter_mut().enumerate() {
+        let x = i as f64 / 255.0;
+        let y = if x <= points[0].0 {
+            points[0].1
+        } else if x >= points[n - 1].0 {
+            points[n - 1].1
+        } else {
+            while points[seg + 1].0 < x {
+                seg += 1;
+            }
+            let ((x0, y0), (x1, y1)) = (points[seg], points[seg + 1]);
+            let h = x1 - x0;
+            let t = (x - x0) / h;
+            let (t2, t3) = (t * t, t * t * t);
+            (2.0 * t3 - 3.0 * t2 + 1.0) * y0 + (t3 - 2.0 * t2 + t) * h * tangent[seg]
+                + (-2.0 * t3 + 3.0 * t2) * y1 + (t3 - t2) * h * tangent[seg + 1]
+        };
+        *e = y.clamp(0.0, 1.0) * 255.0;
+    }
+    lut
+}
+
+// written as space-separated `x,y` pairs, e.g. "0,0 0.25,0.3 1,1"
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (x, y)) in self.points.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{x},{y}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCurveError;
+
+impl fmt::Display for ParseCurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected space-separated x,y pairs")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseCurveError {}
+
+impl FromStr for Curve {
+    type Err = ParseCurveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let points = s.split_whitespace().map(|pair| {
+            let (x, y) = pair.split_once(',').ok_or(ParseCurveError)?;
+            Ok((x.parse().map_err(|_| ParseCurveError)?, y.parse().map_err(|_| ParseCurveError)?))
+        }).collect::<Result<Vec<_>, _>>()?;
+        if points.is_empty() {
+            return Err(ParseCurveError);
+        }
+        Ok(Self::new(&points))
+    }
+}
+
+// endregion
+// region Curves
+
+// one curve per channel, applied after a master curve
+#[derive(Clone, Default)]
+pub struct Curves {
+    pub master: Curve,
+    pub red: Curve,
+    pub green: Curve,
+    pub blue: Curve,
+}
+
+impl Curves {
+    pub fn apply(&self, c: Color) -> Color {
+        let [r, g, b]: [f64; 3] = c.into();
+        let m = &self.master;
+        Color::new(self.red.eval(m.eval(r)), self.green.eval(m.eval(g)), self.blue.eval(m.eval(b)))
+    }
+}
+
+// endregion
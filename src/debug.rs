@@ -0,0 +1,152 @@
+// Debug overlays drawn into rendered frames, for seeing what an effect does
+// while developing it.
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use crate::Instant;
+use crate::text::{draw_text, fill, TextStyle};
+use crate::imageops::Histogram;
+use crate::{Color, Dither, Gradient, Pos};
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+// region Overlay
+
+// side of the squares that `timing` measures, in pixels
+pub const TILE: usize = 64;
+// displacement in pixels that the heatmap shows as red
+pub const HEAT_RANGE: f64 = 32.0;
+// of the histogram's plot, which has a column per level
+pub const HISTOGRAM_HEIGHT: usize = 80;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DebugOverlay {
+    // a line from each pointer along its velocity
+    pub velocity: bool,
+    // a circle around each pointer at the effect's radius
+    pub falloff: bool,
+    // tints pixels by how far they are displaced, from blue for still to red
+    // at `HEAT_RANGE` pixels
+    pub heatmap: bool,
+    // microseconds spent on each `TILE` square; only measured with std
+    pub timing: bool,
+    // the frame's red, green and blue histograms in the bottom left corner
+    pub histogram: bool,
+}
+
+impl DebugOverlay {
+    pub fn any(&self) -> bool {
+        self.velocity || self.falloff || self.heatmap || self.timing || self.histogram
+    }
+}
+
+pub fn heatmap() -> Gradient {
+    Gradient::even(&[Color::BLUE, Color::CYAN, Color::GREEN, Color::YELLOW, Color::RED])
+}
+
+// endregion
+// region Draw
+
+// draws with 2 pixel wide strokes so they stay visible over busy images
+fn plot(frame: &mut [u8], width: usize, p: Pos, color: Color) {
+    fill(frame, width, (p.x().floor() as isize, p.y().floor() as isize), (2, 2), color);
+}
+
+pub fn draw_line(frame: &mut [u8], width: usize, from: Pos, to: Pos, color: Color) {
+    let d = to - from;
+    let steps = d.x().abs().max(d.y().abs()).ceil().max(1.0) as usize;
+    for i in 0..=steps {
+        plot(frame, width, from + d * (i as f64 / steps as f64), color);
+    }
+}
+
+pub fn draw_circle(frame: &mut [u8], width: usize, center: Pos, radius: f64, color: Color) {
+    let steps = (core::f64::consts::TAU * radius).ceil().max(8.0) as usize;
+    for i in 0..steps {
+        plot(frame, width, center + Pos::from_polar(radius, core::f64::consts::TAU * i as f64 / steps as f64), color);
+    }
+}
+
+// labels each tile with its render time, on a background from the heatmap
+// relative to the slowest tile; `times` is in rows of tiles, as
+// `run_shader_timed` leaves them
+pub fn draw_tile_times(frame: &mut [u8], width: usize, tile: usize, times: &[Duration]) {
+    let cols = width.div_ceil(tile.max(1));
+    let slowest = times.iter().max().copied().unwrap_or_default().as_secs_f64().max(f64::EPSILON);
+    let heat = heatmap();
+    for (i, t) in times.iter().enumerate() {
+        let style = TextStyle { color: Color::BLACK, background: Some(heat.sample(t.as_secs_f64() / slowest)), scale: 1 };
+        let (x, y) = ((i % cols * tile + 3) as isize, (i / cols * tile + 3) as isize);
+        draw_text(frame, width, x, y, &format!("{}", t.as_micros()), &style);
+    }
+}
+
+// red, green and blue columns over a darkened backdrop, where they overlap
+// mixing into other colors, and scaled to the highest count; pure black and
+// white are left out of the scale since clipping piles up there
+pub fn draw_histogram(frame: &mut [u8], width: usize, h: &Histogram) {
+    const MARGIN: usize = 8;
+    let height = (frame.len() / 4).checked_div(width).unwrap_or(0);
+    let (left, bottom) = (MARGIN, height.saturating_sub(MARGIN));
+    let top = bottom.saturating_sub(HISTOGRAM_HEIGHT);
+    let channels = [&h.red, &h.green, &h.blue];
+    let peak = channels.iter().flat_map(|c| &c[1..255]).copied().max().unwrap_or(0).max(1) as f64;
+    for y in top..bottom {
+        // counts that reach this row, from 0 at the bottom
+        let row = (bottom - y) as f64 / HISTOGRAM_HEIGHT as f64 * peak;
+        for level in 0..256.min(width.saturating_sub(left)) {
+            let px = &mut frame[4 * (left + level + y * width)..][..3];
+            for (c, counts) in px.iter_mut().zip(channels) {
+                *c = if counts[level] as f64 >= row { 230 } else { *c / 4 };
+            }
+        }
+    }
+}
+
+// endregion
+// region Timing
+
+// like `run_shader_dithered`, also timing every `tile` pixel square into
+// `times`; rows of tiles are shaded in parallel, the tiles within them one
+// after the other. without std there is no clock and `times` stays empty
+pub fn run_shader_timed<F>(frame: &mut [u8], width: usize, dither: Dither, tile: usize, times: &mut Vec<Duration>, f: F)
+where
+    F: Fn(Pos) -> Color + Sync,
+{
+    times.clear();
+    #[cfg(not(feature = "std"))]
+    {
+        let _ = tile;
+        crate::run_shader_dithered(frame, width, dither, f);
+    }
+    #[cfg(feature = "std")]
+    {
+        let tile = tile.max(1);
+        let band = |(row, chunk): (usize, &mut [u8])| -> Vec<Duration> {
+            let rows = chunk.len() / 4 / width;
+            (0..width.div_ceil(tile)).map(|col| {
+                let start = Instant::now();
+                for y in 0..rows {
+                    for x in col * tile..((col + 1) * tile).min(width) {
+                        let (px, py) = (x, row * tile + y);
+                        f(Pos::new(px as f64, py as f64))
+                            .write_bytes_dithered(&mut chunk[4 * (x + y * width)..][..4], dither.threshold(px, py));
+                    }
+                }
+                start.elapsed()
+            }).collect()
+        };
+        #[cfg(feature = "parallel")]
+        let bands: Vec<Vec<Duration>> = {
+            use rayon::prelude::*;
+            frame.par_chunks_mut(4 * width * tile).enumerate().map(band).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let bands: Vec<Vec<Duration>> = frame.chunks_mut(4 * width * tile).enumerate().map(band).collect();
+        times.extend(bands.into_iter().flatten());
+    }
+}
+
+// endregion
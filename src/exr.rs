@@ -0,0 +1,398 @@
+// OpenEXR decoder to linear RGBA floats: single-part scanline images with
+// half, float or uint channels, uncompressed or with RLE, ZIPS or ZIP
+// compression. R, G, B and A are read, or Y for luminance-only images; other
+// channels and layers are skipped.
+
+use core::fmt;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::{zlib, MAX_FLOAT_PIXELS};
+
+// region Error
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Signature,
+    Truncated,
+    Format(&'static str),
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Signature => f.write_str("not an OpenEXR file"),
+            Error::Truncated => f.write_str("EXR data ends early"),
+            Error::Format(e) => write!(f, "malformed EXR: {}", e),
+            Error::Unsupported(e) => write!(f, "unsupported EXR: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// endregion
+// region Header
+
+pub const SIGNATURE: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+// version field flags
+const TILED: u32 = 0x200;
+const DEEP: u32 = 0x800;
+const MULTIPART: u32 = 0x1000;
+
+// compression methods, by the lines each chunk holds
+const NONE: u8 = 0;
+const RLE: u8 = 1;
+const ZIPS: u8 = 2;
+const ZIP: u8 = 3;
+
+// the most a byte of packed data can expand to, by compression method:
+// RLE turns two bytes into a run of 128 and deflate has a limit of 1032
+fn max_ratio(compression: u8) -> usize {
+    match compression {
+        RLE => 64,
+        ZIPS | ZIP => 1032,
+        _ => 1,
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Uint,
+    Half,
+    Float,
+}
+
+impl Kind {
+    fn size(self) -> usize {
+        if self == Kind::Half { 2 } else { 4 }
+    }
+}
+
+struct Channel<'a> {
+    name: &'a [u8],
+    kind: Kind,
+}
+
+struct Header<'a> {
+    // sorted by name, as the pixel data lays them out
+    channels: Vec<Channel<'a>>,
+    compression: u8,
+    y_min: i32,
+    width: usize,
+    height: usize,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let b = self.data.get(self.pos..self.pos.checked_add(n).ok_or(Error::Truncated)?).ok_or(Error::Truncated)?;
+        self.pos += n;
+        Ok(b)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, Error> { self.u32().map(|v| v as i32) }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+
+    // up to a NUL, which is skipped
+    fn name(&mut self) -> Result<&'a [u8], Error> {
+        let rest = self.data.get(self.pos..).ok_or(Error::Truncated)?;
+        let end = rest.iter().position(|&b| b == 0).ok_or(Error::Truncated)?;
+        self.pos += end + 1;
+        Ok(&rest[..end])
+    }
+}
+
+fn header<'a>(r: &mut Reader<'a>) -> Result<Header<'a>, Error> {
+    let (mut channels, mut compression, mut window) = (None, None, None);
+    loop {
+        let name = r.name()?;
+        if name.is_empty() {
+            break;
+        }
+        let _kind = r.name()?;
+        let size = r.u32()? as usize;
+        let mut value = Reader { data: r.bytes(size)?, pos: 0 };
+        match name {
+            b"channels" => {
+                let mut list = Vec::new();
+                loop {
+                    let name = value.name()?;
+                    if name.is_empty() {
+                        break;
+                    }
+                    let kind = match value.u32()? {
+                        0 => Kind::Uint,
+                        1 => Kind::Half,
+                        2 => Kind::Float,
+                        _ => return Err(Error::Format("unknown channel type")),
+                    };
+                    // linear flag and padding, then x and y subsampling
+                    value.bytes(4)?;
+                    if (value.u32()?, value.u32()?) != (1, 1) {
+                        return Err(Error::Unsupported("subsampled channels"));
+                    }
+                    list.push(Channel { name, kind });
+                }
+                list.sort_by_key(|c| c.name);
+                channels = Some(list);
+            }
+            b"compression" => compression = Some(*value.bytes(1)?.first().ok_or(Error::Truncated)?),
+            b"dataWindow" => window = Some([value.i32()?, value.i32()?, value.i32()?, value.i32()?]),
+            _ => (),
+        }
+    }
+    let channels = channels.ok_or(Error::Format("missing channel list"))?;
+    let compression = compression.ok_or(Error::Format("missing compression"))?;
+    let [x_min, y_min, x_max, y_max] = window.ok_or(Error::Format("missing data window"))?;
+    let size = |min: i32, max: i32| (max as i64 - min as i64 + 1).try_into().ok().filter(|&n: &usize| n > 0);
+    let (width, height) = size(x_min, x_max).zip(size(y_min, y_max)).ok_or(Error::Format("empty data window"))?;
+    if width.saturating_mul(height) > MAX_FLOAT_PIXELS {
+        return Err(Error::Format("size out of range"));
+    }
+    Ok(Header { channels, compression, y_min, width, height })
+}
+
+// endregion
+// region Decoder
+
+pub fn is_exr(data: &[u8]) -> bool {
+    data.starts_with(&SIGNATURE)
+}
+
+// returns (width, height, RGBA floats) of the data window
+pub fn decode(data: &[u8]) -> Result<(usize, usize, Vec<f32>), Error> {
+    if !is_exr(data) {
+        return Err(Error::Signature);
+    }
+    let mut r = Reader { data, pos: 4 };
+    let version = r.u32()?;
+    if version & 0xff != 2 {
+        return Err(Error::Unsupported("file format version"));
+    }
+    if version & (TILED | DEEP | MULTIPART) != 0 {
+        return Err(Error::Unsupported("tiled, deep or multi-part image"));
+    }
+    let h = header(&mut r)?;
+    let lines = match h.compression {
+        NONE | RLE | ZIPS => 1,
+        ZIP => 16,
+        _ => return Err(Error::Unsupported("compression other than RLE or ZIP")),
+    };
+
+    // where each of R, G, B and A sits in a line, luminance standing in for
+    // all three colors
+    let line_size: usize = h.channels.iter().map(|c| c.kind.size() * h.width).sum();
+    let mut offsets = [None; 4];
+    let mut at = 0;
+    for c in &h.channels {
+        let targets: &[usize] = match c.name {
+            b"R" => &[0],
+            b"G" => &[1],
+            b"B" => &[2],
+            b"A" => &[3],
+            b"Y" => &[0, 1, 2],
+            _ => &[],
+        };
+        for &t in targets {
+            // a luminance channel next to colors loses to them
+            if offsets[t].is_none() || c.name != b"Y" {
+                offsets[t] = Some((at, c.kind));
+            }
+        }
+        at += c.kind.size() * h.width;
+    }
+    if offsets[..3].iter().all(Option::is_none) {
+        return Err(Error::Unsupported("no R, G, B or Y channel"));
+    }
+
+    // both checked before anything sized by the header is allocated
+    let chunks = h.height.div_ceil(lines);
+    let rest = data.len() - r.pos;
+    if chunks > rest / 8 || h.height.saturating_mul(line_size) > rest.saturating_mul(max_ratio(h.compression)) {
+        return Err(Error::Truncated);
+    }
+    let mut table = Vec::with_capacity(chunks);
+    for _ in 0..chunks {
+        table.push(r.u64()?);
+    }
+    let mut out = vec![0f32; h.width * h.height * 4];
+    // alpha defaults to opaque
+    out.chunks_exact_mut(4).for_each(|px| px[3] = 1.0);
+    for offset in table {
+        let mut chunk = Reader { data, pos: usize::try_from(offset).map_err(|_| Error::Truncated)? };
+        let y = chunk.i32()? as i64 - h.y_min as i64;
+        let packed = chunk.u32()? as usize;
+        let packed = chunk.bytes(packed)?;
+        let first = usize::try_from(y).ok().filter(|&y| y < h.height && y % lines == 0).ok_or(Error::Format("chunk outside the data window"))?;
+        let rows = lines.min(h.height - first);
+        let size = rows * line_size;
+        // chunks that wouldn't shrink are stored as they are
+        let raw = if packed.len() == size || h.compression == NONE {
+            packed.to_vec()
+        } else if h.compression == RLE {
+            unpredict(rle(packed, size)?)
+        } else {
+            unpredict(zlib::decompress(packed, size).map_err(Error::Format)?)
+        };
+        if raw.len() != size {
+            return Err(Error::Format("chunk size mismatch"));
+        }
+        for (row, line) in raw.chunks_exact(line_size).enumerate() {
+            let y = first + row;
+            let dst = &mut out[y * h.width * 4..][..h.width * 4];
+            for (c, offset) in offsets.iter().enumerate() {
+                let Some((at, kind)) = *offset else { continue };
+                for (x, px) in dst.chunks_exact_mut(4).enumerate() {
+                    px[c] = sample(&line[at + x * kind.size()..], kind);
+                }
+            }
+        }
+    }
+    Ok((h.width, h.height, out))
+}
+
+fn sample(b: &[u8], kind: Kind) -> f32 {
+    match kind {
+        Kind::Half => half(u16::from_le_bytes([b[0], b[1]])),
+        Kind::Float => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        Kind::Uint => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+    }
+}
+
+// IEEE half precision, subnormals, infinities and NaN included
+fn half(h: u16) -> f32 {
+    let sign = ((h & 0x8000) as u32) << 16;
+    let (exp, mant) = ((h >> 10) & 0x1f, (h & 0x3ff) as u32);
+    let bits = match exp {
+        0 if mant == 0 => sign,
+        // subnormal: shift the mantissa up until its leading one is implicit
+        0 => {
+            let shift = mant.leading_zeros() - 21;
+            sign | (113 - shift) << 23 | ((mant << shift) & 0x3ff) << 13
+        }
+        31 => sign | 0x7f80_0000 | mant << 13,
+        e => sign | (e as u32 + 112) << 23 | mant << 13,
+    };
+    f32::from_bits(bits)
+}
+
+// runs of a byte or stretches of literal bytes, a signed count first
+fn rle(data: &[u8], size: usize) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(size);
+    let mut i = 0;
+    while i < data.len() && out.len() < size {
+        let n = data[i] as i8;
+        i += 1;
+        if n < 0 {
+            let n = -(n as isize) as usize;
+            out.extend_from_slice(data.get(i..i + n).ok_or(Error::Truncated)?);
+            i += n;
+        } else {
+            out.extend(core::iter::repeat_n(*data.get(i).ok_or(Error::Truncated)?, n as usize + 1));
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+// RLE and ZIP store byte deltas, with the even bytes of the data before the
+// odd ones
+fn unpredict(mut t: Vec<u8>) -> Vec<u8> {
+    for i in 1..t.len() {
+        t[i] = t[i - 1].wrapping_add(t[i]).wrapping_sub(128);
+    }
+    let half = t.len().div_ceil(2);
+    let mut out = Vec::with_capacity(t.len());
+    for i in 0..half {
+        out.push(t[i]);
+        if let Some(&b) = t.get(half + i) {
+            out.push(b);
+        }
+    }
+    out
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // name, width, height, file, little-endian RGBA floats
+    type Fixture = (&'static str, usize, usize, &'static [u8], &'static [u8]);
+
+    // random pixels by compression method and channel type, next to the
+    // RGBA floats they stand for; one is luminance only and one has channels
+    // that aren't read, and the data windows sit around the origin
+    const FILES: [Fixture; 6] = [
+        ("none_float", 5, 3, include_bytes!("../tests/data/none_float.exr"), include_bytes!("../tests/data/none_float.exr.f32")),
+        ("rle_uint", 5, 3, include_bytes!("../tests/data/rle_uint.exr"), include_bytes!("../tests/data/rle_uint.exr.f32")),
+        ("rle_luminance", 33, 20, include_bytes!("../tests/data/rle_luminance.exr"), include_bytes!("../tests/data/rle_luminance.exr.f32")),
+        ("zips_other_channels", 5, 3, include_bytes!("../tests/data/zips_other_channels.exr"), include_bytes!("../tests/data/zips_other_channels.exr.f32")),
+        ("zip_half", 5, 3, include_bytes!("../tests/data/zip_half.exr"), include_bytes!("../tests/data/zip_half.exr.f32")),
+        ("zip_two_chunks", 33, 20, include_bytes!("../tests/data/zip_two_chunks.exr"), include_bytes!("../tests/data/zip_two_chunks.exr.f32")),
+    ];
+
+    fn floats(le: &[u8]) -> Vec<f32> {
+        le.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+    }
+
+    #[test]
+    fn decodes_every_kind() {
+        for (name, width, height, exr, rgba) in FILES {
+            assert!(is_exr(exr));
+            assert_eq!(decode(exr), Ok((width, height, floats(rgba))), "{}", name);
+        }
+    }
+
+    #[test]
+    fn truncated_data_errs() {
+        for (name, _, _, exr, _) in FILES {
+            for len in 0..exr.len() {
+                assert!(decode(&exr[..len]).is_err(), "decoded {} of {} bytes of {}", len, exr.len(), name);
+            }
+        }
+    }
+
+    #[test]
+    fn halves() {
+        assert_eq!(half(0x3c00), 1.0);
+        assert_eq!(half(0xc000), -2.0);
+        assert_eq!(half(0x7bff), 65504.0);
+        assert_eq!(half(0x0001), 1.0 / 16_777_216.0);
+        assert_eq!(half(0x03ff), 1023.0 / 16_777_216.0);
+        assert_eq!(half(0x7c00), f32::INFINITY);
+        assert!(half(0x7e00).is_nan());
+        assert_eq!(half(0x8000).to_bits(), (-0.0f32).to_bits());
+    }
+
+    // a data window far bigger than what follows the header, which must
+    // fail before anything that big is allocated
+    #[test]
+    fn oversized_windows_err() {
+        let (_, _, _, exr, _) = FILES[0];
+        let at = exr.windows(17).position(|w| w == b"dataWindow\0box2i\0").unwrap() + 21;
+        let mut huge = exr.to_vec();
+        for (i, v) in [0, 0, 9999, 9999].into_iter().enumerate() {
+            huge[at + 4 * i..][..4].copy_from_slice(&i32::to_le_bytes(v));
+        }
+        assert_eq!(decode(&huge), Err(Error::Truncated));
+        huge[at + 8..at + 16].copy_from_slice(&[0xff, 0xff, 0, 0, 0xff, 0xff, 0, 0]);
+        assert_eq!(decode(&huge), Err(Error::Format("size out of range")));
+    }
+}
@@ -0,0 +1,215 @@
+// Radiance RGBE (.hdr) decoder to linear RGBA floats: flat, old-style and
+// per-channel run-length encoded scanlines, top-down or bottom-up.
+
+use core::fmt;
+use alloc::vec::Vec;
+use crate::MAX_FLOAT_PIXELS;
+
+// region Error
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Signature,
+    Truncated,
+    Format(&'static str),
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Signature => f.write_str("not a Radiance HDR file"),
+            Error::Truncated => f.write_str("HDR data ends early"),
+            Error::Format(e) => write!(f, "malformed HDR: {}", e),
+            Error::Unsupported(e) => write!(f, "unsupported HDR: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// endregion
+// region Decoder
+
+pub fn is_hdr(data: &[u8]) -> bool {
+    data.starts_with(b"#?RADIANCE\n") || data.starts_with(b"#?RGBE\n")
+}
+
+// returns (width, height, RGBA floats), alpha being 1
+pub fn decode(data: &[u8]) -> Result<(usize, usize, Vec<f32>), Error> {
+    if !is_hdr(data) {
+        return Err(Error::Signature);
+    }
+    let mut lines = Lines { data, pos: 0 };
+    lines.next()?;
+    // variables up to an empty line, of which only the format matters
+    loop {
+        let line = lines.next()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix(b"FORMAT=") {
+            if format != b"32-bit_rle_rgbe" {
+                return Err(Error::Unsupported("pixel format other than RGBE"));
+            }
+        }
+    }
+    // "-Y height +X width" for the usual top-down rows
+    let size = core::str::from_utf8(lines.next()?).map_err(|_| Error::Format("resolution line"))?;
+    let (top_down, height, width) = match size.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+        [y, h, "+X", w] if y == "-Y" || y == "+Y" => (y == "-Y", h, w),
+        [_, _, _, _] => return Err(Error::Unsupported("rotated or mirrored orientation")),
+        _ => return Err(Error::Format("resolution line")),
+    };
+    let parse = |v: &str| v.parse::<usize>().map_err(|_| Error::Format("resolution line"));
+    let (width, height) = (parse(width)?, parse(height)?);
+    if width == 0 || height == 0 || width.saturating_mul(height) > MAX_FLOAT_PIXELS {
+        return Err(Error::Format("size out of range"));
+    }
+
+    // every row takes a pixel at least
+    let mut pos = lines.pos;
+    if height > (data.len() - pos) / 4 {
+        return Err(Error::Truncated);
+    }
+    // grown a row at a time, so that a header claiming a huge image takes
+    // no more memory than the data that backs it
+    let (mut rgbe, mut out) = (Vec::new(), Vec::new());
+    for _ in 0..height {
+        pos = scanline(data, pos, width, &mut rgbe)?;
+        out.extend(rgbe.iter().flat_map(|&[r, g, b, e]| {
+            let scale = if e == 0 { 0.0 } else { exp2(e as i32 - 136) };
+            // the middle of each mantissa step, as Radiance itself decodes
+            let c = |v: u8| if e == 0 { 0.0 } else { (v as f32 + 0.5) * scale };
+            [c(r), c(g), c(b), 1.0]
+        }));
+    }
+    if !top_down {
+        let row = width * 4;
+        for y in 0..height / 2 {
+            let (top, bottom) = out.split_at_mut((height - 1 - y) * row);
+            top[y * row..][..row].swap_with_slice(&mut bottom[..row]);
+        }
+    }
+    Ok((width, height, out))
+}
+
+struct Lines<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn next(&mut self) -> Result<&'a [u8], Error> {
+        let rest = self.data.get(self.pos..).ok_or(Error::Truncated)?;
+        let end = rest.iter().position(|&b| b == b'\n').ok_or(Error::Truncated)?;
+        self.pos += end + 1;
+        Ok(&rest[..end])
+    }
+}
+
+// 2^e, in two halves so that RGBE's smallest exponents stay representable
+fn exp2(e: i32) -> f32 {
+    let pow = |e: i32| f32::from_bits(((e + 127) as u32) << 23);
+    pow(e / 2) * pow(e - e / 2)
+}
+
+// reads a row of `width` pixels into `rgbe`, returning where the next one
+// starts
+fn scanline(data: &[u8], mut pos: usize, width: usize, rgbe: &mut Vec<[u8; 4]>) -> Result<usize, Error> {
+    rgbe.clear();
+    let byte = |i: usize| data.get(i).copied().ok_or(Error::Truncated);
+    let head = data.get(pos..pos + 4).ok_or(Error::Truncated)?;
+    // 2, 2 and the width starts a row of four run-length encoded channels
+    if (8..0x8000).contains(&width) && head[0] == 2 && head[1] == 2 && head[2] & 0x80 == 0 {
+        if (head[2] as usize) << 8 | head[3] as usize != width {
+            return Err(Error::Format("scanline width mismatch"));
+        }
+        rgbe.resize(width, [0; 4]);
+        pos += 4;
+        for c in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let n = byte(pos)? as usize;
+                pos += 1;
+                let (run, n) = if n > 128 { (true, n - 128) } else { (false, n) };
+                if n == 0 || x + n > width {
+                    return Err(Error::Format("run overflows the scanline"));
+                }
+                for (k, px) in rgbe[x..x + n].iter_mut().enumerate() {
+                    px[c] = byte(if run { pos } else { pos + k })?;
+                }
+                pos += if run { 1 } else { n };
+                x += n;
+            }
+        }
+        return Ok(pos);
+    }
+    // plain pixels, where 1, 1, 1 repeats the previous pixel with the count
+    // in the exponent, shifted further by each such pixel in a row
+    let mut shift = 0;
+    while rgbe.len() < width {
+        let px = data.get(pos..pos + 4).ok_or(Error::Truncated)?;
+        pos += 4;
+        if px[..3] == [1, 1, 1] {
+            let prev = *rgbe.last().ok_or(Error::Format("repeat at the start of a scanline"))?;
+            let n = (px[3] as usize).checked_shl(shift).unwrap_or(width).min(width - rgbe.len());
+            rgbe.extend(core::iter::repeat_n(prev, n));
+            shift += 8;
+        } else {
+            rgbe.push([px[0], px[1], px[2], px[3]]);
+            shift = 0;
+        }
+    }
+    Ok(pos)
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // name, width, height, file, little-endian RGBA floats
+    type Fixture = (&'static str, usize, usize, &'static [u8], &'static [u8]);
+
+    // random RGBE pixels in each kind of scanline, next to the RGBA floats
+    // they stand for
+    const FILES: [Fixture; 3] = [
+        ("flat", 7, 3, include_bytes!("../tests/data/flat.hdr"), include_bytes!("../tests/data/flat.hdr.f32")),
+        ("old_rle_bottom_up", 40, 9, include_bytes!("../tests/data/old_rle_bottom_up.hdr"), include_bytes!("../tests/data/old_rle_bottom_up.hdr.f32")),
+        ("rle", 40, 9, include_bytes!("../tests/data/rle.hdr"), include_bytes!("../tests/data/rle.hdr.f32")),
+    ];
+
+    fn floats(le: &[u8]) -> Vec<f32> {
+        le.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+    }
+
+    #[test]
+    fn decodes_every_kind() {
+        for (name, width, height, hdr, rgba) in FILES {
+            assert!(is_hdr(hdr));
+            assert_eq!(decode(hdr), Ok((width, height, floats(rgba))), "{}", name);
+        }
+    }
+
+    #[test]
+    fn truncated_data_errs() {
+        for (name, _, _, hdr, _) in FILES {
+            for len in 0..hdr.len() {
+                assert!(decode(&hdr[..len]).is_err(), "decoded {} of {} bytes of {}", len, hdr.len(), name);
+            }
+        }
+    }
+
+    // sizes the few bytes after them can't hold, which must fail before
+    // anything that big is allocated
+    #[test]
+    fn oversized_headers_err() {
+        let with = |size: &str| [b"#?RADIANCE\n\n", size.as_bytes(), b"\n\x02\x02\x01\x00\x80\x80"].concat();
+        assert_eq!(decode(&with("-Y 1 +X 400000000")), Err(Error::Format("size out of range")));
+        assert_eq!(decode(&with("-Y 9000 +X 9000")), Err(Error::Truncated));
+        assert_eq!(decode(&with("-Y 1 +X 90000000")), Err(Error::Truncated));
+    }
+}
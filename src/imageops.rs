@@ -0,0 +1,230 @@
+// Whole-image filters. Sources can be softened with them before warping, and
+// the kernels and `convolve` work on any interleaved f64 plane, which makes
+// them building blocks for passes like bloom or depth of field as well.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::{Image, Pos};
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+// region Kernels
+
+// 2r + 1 equal weights
+pub fn box_kernel(radius: usize) -> Vec<f64> {
+    vec![1.0 / (2 * radius + 1) as f64; 2 * radius + 1]
+}
+
+// gaussian weights out to three sigmas either side, summing to 1; a sigma of
+// zero or less, or not finite, leaves the image as it is
+pub fn gaussian_kernel(sigma: f64) -> Vec<f64> {
+    if !(sigma > 0.0 && sigma.is_finite()) {
+        return vec![1.0];
+    }
+    let r = (3.0 * sigma).ceil() as isize;
+    let mut k: Vec<f64> = (-r..=r).map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp()).collect();
+    let sum: f64 = k.iter().sum();
+    k.iter_mut().for_each(|w| *w /= sum);
+    k
+}
+
+// endregion
+// region Convolution
+
+// `kernel` across and then down `src`, which holds `channels` values per pixel
+// in rows of `width` pixels; the kernel is centered, so odd lengths, and edges
+// are clamped. Rows run in parallel both ways
+pub fn convolve(src: &[f64], channels: usize, width: usize, kernel: &[f64]) -> Vec<f64> {
+    let stride = channels * width;
+    if stride == 0 || kernel.is_empty() {
+        return src.to_vec();
+    }
+    let (height, r) = (src.len() / stride, (kernel.len() / 2) as isize);
+    let mut across = vec![0.0; height * stride];
+    for_each_row(&mut across, stride, |y, out| {
+        let line = &src[y * stride..][..stride];
+        for x in 0..width {
+            for c in 0..channels {
+                out[x * channels + c] = kernel.iter().enumerate().map(|(k, w)| {
+                    let sx = (x as isize + k as isize - r).clamp(0, width as isize - 1) as usize;
+                    w * line[sx * channels + c]
+                }).sum();
+            }
+        }
+    });
+    let mut down = vec![0.0; height * stride];
+    for_each_row(&mut down, stride, |y, out| {
+        for (k, w) in kernel.iter().enumerate() {
+            let sy = (y as isize + k as isize - r).clamp(0, height as isize - 1) as usize;
+            out.iter_mut().zip(&across[sy * stride..][..stride]).for_each(|(o, v)| *o += w * v);
+        }
+    });
+    down
+}
+
+fn for_each_row<F>(data: &mut [f64], stride: usize, f: F)
+where
+    F: Fn(usize, &mut [f64]) + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        data.par_chunks_exact_mut(stride).enumerate().for_each(|(y, row)| f(y, row));
+    }
+    #[cfg(not(feature = "parallel"))]
+    data.chunks_exact_mut(stride).enumerate().for_each(|(y, row)| f(y, row));
+}
+
+// endregion
+// region Gradients
+
+// Sobel gradients of the luma of `img`, in levels per pixel, with edges
+// clamped; shared by `EdgeMap` and `Image::normal_map`
+pub fn sobel(img: &Image) -> Vec<Pos> {
+    let (width, height) = (img.width, img.height);
+    let luma: Vec<f64> = img.data.chunks_exact(4)
+        .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+        .collect();
+    let at = |x: isize, y: isize| {
+        luma[x.clamp(0, width as isize - 1) as usize + y.clamp(0, height as isize - 1) as usize * width]
+    };
+    (0..width * height).map(|i| {
+        let (x, y) = ((i % width) as isize, (i / width) as isize);
+        let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1) - at(x - 1, y - 1) - 2.0 * at(x - 1, y) - at(x - 1, y + 1);
+        let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1) - at(x - 1, y - 1) - 2.0 * at(x, y - 1) - at(x + 1, y - 1);
+        Pos::new(gx, gy) / 8.0
+    }).collect()
+}
+
+// endregion
+// region Distance
+
+// Euclidean distance from each pixel of `mask`, in rows of `width`, to the
+// nearest set one: 0 on those, infinite everywhere if there are none. Exact,
+// with the separable squared-distance transform of Felzenszwalb and Huttenlocher
+pub fn distance_transform(mask: &[bool], width: usize) -> Vec<f64> {
+    let mut d: Vec<f64> = mask.iter().map(|&m| if m { 0.0 } else { f64::INFINITY }).collect();
+    if width == 0 {
+        return d;
+    }
+    let height = d.len() / width;
+    let mut column = vec![0.0; height];
+    for x in 0..width {
+        column.iter_mut().enumerate().for_each(|(y, c)| *c = d[x + y * width]);
+        let squared = lower_envelope(&column);
+        squared.into_iter().enumerate().for_each(|(y, c)| d[x + y * width] = c);
+    }
+    for_each_row(&mut d, width, |_, row| {
+        let squared = lower_envelope(row);
+        row.iter_mut().zip(squared).for_each(|(v, s)| *v = s.sqrt());
+    });
+    d
+}
+
+// min over j of (i - j)^2 + f[j] for every i, in linear time; infinite `f`
+// values are left out, and an all-infinite `f` stays so
+fn lower_envelope(f: &[f64]) -> Vec<f64> {
+    let sq = |i: usize| (i * i) as f64;
+    // the parabolas on the envelope and where each one takes over
+    let (mut v, mut z) = (Vec::with_capacity(f.len()), Vec::with_capacity(f.len() + 1));
+    for (q, &fq) in f.iter().enumerate().filter(|(_, f)| f.is_finite()) {
+        loop {
+            let Some(&p) = v.last() else {
+                v.push(q);
+                z.push(f64::NEG_INFINITY);
+                break;
+            };
+            let s = ((fq + sq(q)) - (f[p] + sq(p))) / (2 * (q - p)) as f64;
+            if s <= z[z.len() - 1] {
+                v.pop();
+                z.pop();
+            } else {
+                v.push(q);
+                z.push(s);
+                break;
+            }
+        }
+    }
+    if v.is_empty() {
+        return f.to_vec();
+    }
+    z.push(f64::INFINITY);
+    let mut k = 0;
+    (0..f.len()).map(|i| {
+        while z[k + 1] < i as f64 {
+            k += 1;
+        }
+        let di = i.abs_diff(v[k]) as f64;
+        di * di + f[v[k]]
+    }).collect()
+}
+
+// endregion
+// region Histogram
+
+// how many times each level occurs in some RGBA pixels
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub red: [u64; 256],
+    pub green: [u64; 256],
+    pub blue: [u64; 256],
+    // of 0.299 r + 0.587 g + 0.114 b, rounded
+    pub luma: [u64; 256],
+}
+
+impl Histogram {
+    pub fn of(rgba: &[u8]) -> Self {
+        let mut h = Self { red: [0; 256], green: [0; 256], blue: [0; 256], luma: [0; 256] };
+        for p in rgba.chunks_exact(4) {
+            h.red[p[0] as usize] += 1;
+            h.green[p[1] as usize] += 1;
+            h.blue[p[2] as usize] += 1;
+            h.luma[(0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64).round() as usize] += 1;
+        }
+        h
+    }
+
+    // the three color channels pooled together
+    pub fn rgb(&self) -> [u64; 256] {
+        core::array::from_fn(|i| self.red[i] + self.green[i] + self.blue[i])
+    }
+
+    pub fn pixels(&self) -> u64 { self.luma.iter().sum() }
+}
+
+// the lowest level that at least `q` in [0, 1] of `counts` are at or below
+pub fn percentile(counts: &[u64; 256], q: f64) -> u8 {
+    let target = (counts.iter().sum::<u64>() as f64 * q.clamp(0.0, 1.0)).ceil() as u64;
+    let mut seen = 0;
+    for (level, &n) in counts.iter().enumerate() {
+        seen += n;
+        if seen >= target.max(1) {
+            return level as u8;
+        }
+    }
+    255
+}
+
+// endregion
+// region Image
+
+// every channel, alpha too, so transparent edges fade out; the result keeps
+// the filter and color space settings of `img`
+pub fn blur(img: &Image, kernel: &[f64]) -> Image {
+    let src: Vec<f64> = img.data.iter().map(|&b| b as f64).collect();
+    let mut out = img.clone();
+    for (b, v) in out.data.iter_mut().zip(convolve(&src, 4, img.width, kernel)) {
+        *b = v.round().clamp(0.0, 255.0) as u8;
+    }
+    out
+}
+
+pub fn box_blur(img: &Image, radius: usize) -> Image {
+    blur(img, &box_kernel(radius))
+}
+
+pub fn gaussian_blur(img: &Image, sigma: f64) -> Image {
+    blur(img, &gaussian_kernel(sigma))
+}
+
+// endregion
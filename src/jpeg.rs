@@ -0,0 +1,283 @@
+// Baseline (sequential, Huffman coded) JPEG encoder for RGBA frames, 4:4:4 with the
+// example tables from Annex K of the spec.
+
+use alloc::vec::Vec;
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+// region Tables
+
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5,
+    12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+const LUMA_QUANT: [u8; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55,
+    14, 13, 16, 24, 40, 57, 69, 56, 14, 17, 22, 29, 51, 87, 80, 62,
+    18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113, 92,
+    49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+const CHROMA_QUANT: [u8; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99,
+    24, 26, 56, 99, 99, 99, 99, 99, 47, 66, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+const DC_LUMA_BITS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const DC_CHROMA_BITS: [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const DC_VALUES: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+const AC_LUMA_BITS: [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d];
+const AC_LUMA_VALUES: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+    0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0,
+    0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28,
+    0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+    0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+    0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5,
+    0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2,
+    0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+    0xf9, 0xfa,
+];
+
+const AC_CHROMA_BITS: [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const AC_CHROMA_VALUES: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+    0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33, 0x52, 0xf0,
+    0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26,
+    0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+    0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+    0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5,
+    0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3,
+    0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda,
+    0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+    0xf9, 0xfa,
+];
+
+// (code, length) for every symbol, built as in Annex C
+struct Huffman {
+    codes: [(u16, u8); 256],
+}
+
+impl Huffman {
+    fn new(bits: &[u8; 16], values: &[u8]) -> Self {
+        let mut codes = [(0, 0); 256];
+        let mut code = 0u16;
+        let mut k = 0;
+        for (len, &n) in bits.iter().enumerate() {
+            for _ in 0..n {
+                codes[values[k] as usize] = (code, len as u8 + 1);
+                code += 1;
+                k += 1;
+            }
+            code <<= 1;
+        }
+        Self { codes }
+    }
+}
+
+// libjpeg's quality scaling of the base tables, stored in zigzag order
+fn quant_table(base: &[u8; 64], quality: u8) -> [u8; 64] {
+    let q = quality.clamp(1, 100) as u32;
+    let scale = if q < 50 { 5000 / q } else { 200 - 2 * q };
+    let mut t = [0u8; 64];
+    for (i, e) in t.iter_mut().enumerate() {
+        *e = ((base[ZIGZAG[i]] as u32 * scale + 50) / 100).clamp(1, 255) as u8;
+    }
+    t
+}
+
+// endregion
+// region Encoder
+
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    n: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, len: u8) {
+        self.acc = (self.acc << len) | (code as u32 & ((1 << len) - 1));
+        self.n += len as u32;
+        while self.n >= 8 {
+            let b = (self.acc >> (self.n - 8)) as u8;
+            self.out.push(b);
+            // 0xff in entropy coded data must be followed by a stuffed zero
+            if b == 0xff {
+                self.out.push(0);
+            }
+            self.n -= 8;
+        }
+        self.acc &= (1 << self.n) - 1;
+    }
+
+    fn flush(&mut self) {
+        if self.n > 0 {
+            let pad = 8 - self.n as u8;
+            self.write((1 << pad) - 1, pad);
+        }
+    }
+}
+
+// category (bit length) and the bits that encode `v` within it
+fn magnitude(v: i32) -> (u8, u16) {
+    let cat = (32 - v.unsigned_abs().leading_zeros()) as u8;
+    let bits = if v < 0 { (v - 1) as u16 } else { v as u16 };
+    (cat, bits)
+}
+
+fn segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    out.extend_from_slice(&[0xff, marker]);
+    out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    out.extend_from_slice(payload);
+}
+
+struct Component<'a> {
+    quant: &'a [u8; 64],
+    dc: &'a Huffman,
+    ac: &'a Huffman,
+    pred: i32,
+}
+
+impl Component<'_> {
+    // `block` is level-shifted samples in row-major order
+    fn encode_block(&mut self, w: &mut BitWriter, block: &[f64; 64], cos: &[[f64; 8]; 8]) {
+        // separable 2D DCT-II
+        let mut tmp = [0.0; 64];
+        for y in 0..8 {
+            for u in 0..8 {
+                tmp[y * 8 + u] = (0..8).map(|x| block[y * 8 + x] * cos[u][x]).sum();
+            }
+        }
+        let mut coef = [0i32; 64];
+        for (i, &zz) in ZIGZAG.iter().enumerate() {
+            let (v, u) = (zz / 8, zz % 8);
+            let d: f64 = (0..8).map(|y| tmp[y * 8 + u] * cos[v][y]).sum();
+            coef[i] = (d / self.quant[i] as f64).round() as i32;
+        }
+
+        let (cat, bits) = magnitude(coef[0] - self.pred);
+        self.pred = coef[0];
+        let (code, len) = self.dc.codes[cat as usize];
+        w.write(code, len);
+        w.write(bits, cat);
+
+        let mut run = 0;
+        for &c in &coef[1..] {
+            if c == 0 {
+                run += 1;
+                continue;
+            }
+            while run > 15 {
+                let (code, len) = self.ac.codes[0xf0];
+                w.write(code, len);
+                run -= 16;
+            }
+            let (cat, bits) = magnitude(c);
+            let (code, len) = self.ac.codes[(run << 4) | cat as usize];
+            w.write(code, len);
+            w.write(bits, cat);
+            run = 0;
+        }
+        if run > 0 {
+            let (code, len) = self.ac.codes[0x00];
+            w.write(code, len);
+        }
+    }
+}
+
+// encodes a tightly packed RGBA buffer, ignoring alpha; `quality` is 1..=100
+pub fn encode(width: usize, height: usize, rgba: &[u8], quality: u8) -> Vec<u8> {
+    assert!(width > 0 && height > 0 && width <= 0xffff && height <= 0xffff, "unsupported JPEG size");
+    assert!(rgba.len() >= width * height * 4, "buffer smaller than width * height * 4");
+
+    let luma_q = quant_table(&LUMA_QUANT, quality);
+    let chroma_q = quant_table(&CHROMA_QUANT, quality);
+    let dc_luma = Huffman::new(&DC_LUMA_BITS, &DC_VALUES);
+    let ac_luma = Huffman::new(&AC_LUMA_BITS, &AC_LUMA_VALUES);
+    let dc_chroma = Huffman::new(&DC_CHROMA_BITS, &DC_VALUES);
+    let ac_chroma = Huffman::new(&AC_CHROMA_BITS, &AC_CHROMA_VALUES);
+
+    let mut out = Vec::with_capacity(width * height / 4 + 1024);
+    out.extend_from_slice(&[0xff, 0xd8]);
+    segment(&mut out, 0xe0, &[b'J', b'F', b'I', b'F', 0, 1, 1, 0, 0, 1, 0, 1, 0, 0]);
+    for (id, table) in [(0u8, &luma_q), (1, &chroma_q)] {
+        let mut p = Vec::with_capacity(65);
+        p.push(id);
+        p.extend_from_slice(table);
+        segment(&mut out, 0xdb, &p);
+    }
+    let (w16, h16) = ((width as u16).to_be_bytes(), (height as u16).to_be_bytes());
+    segment(&mut out, 0xc0, &[
+        8, h16[0], h16[1], w16[0], w16[1], 3,
+        1, 0x11, 0,
+        2, 0x11, 1,
+        3, 0x11, 1,
+    ]);
+    for (class_id, bits, values) in [
+        (0x00u8, &DC_LUMA_BITS, &DC_VALUES[..]),
+        (0x10, &AC_LUMA_BITS, &AC_LUMA_VALUES[..]),
+        (0x01, &DC_CHROMA_BITS, &DC_VALUES[..]),
+        (0x11, &AC_CHROMA_BITS, &AC_CHROMA_VALUES[..]),
+    ] {
+        let mut p = Vec::with_capacity(17 + values.len());
+        p.push(class_id);
+        p.extend_from_slice(bits);
+        p.extend_from_slice(values);
+        segment(&mut out, 0xc4, &p);
+    }
+    segment(&mut out, 0xda, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    let mut cos = [[0.0; 8]; 8];
+    for (u, row) in cos.iter_mut().enumerate() {
+        let cu = if u == 0 { core::f64::consts::FRAC_1_SQRT_2 } else { 1.0 };
+        for (x, e) in row.iter_mut().enumerate() {
+            *e = 0.5 * cu * ((2 * x + 1) as f64 * u as f64 * core::f64::consts::PI / 16.0).cos();
+        }
+    }
+
+    let mut comps = [
+        Component { quant: &luma_q, dc: &dc_luma, ac: &ac_luma, pred: 0 },
+        Component { quant: &chroma_q, dc: &dc_chroma, ac: &ac_chroma, pred: 0 },
+        Component { quant: &chroma_q, dc: &dc_chroma, ac: &ac_chroma, pred: 0 },
+    ];
+    let mut w = BitWriter { out, acc: 0, n: 0 };
+    let mut blocks = [[0.0; 64]; 3];
+    for by in (0..height).step_by(8) {
+        for bx in (0..width).step_by(8) {
+            for i in 0..64 {
+                // edge blocks repeat the last row/column
+                let x = (bx + i % 8).min(width - 1);
+                let y = (by + i / 8).min(height - 1);
+                let p = &rgba[4 * (y * width + x)..];
+                let (r, g, b) = (p[0] as f64, p[1] as f64, p[2] as f64);
+                let ycc = [
+                    0.299 * r + 0.587 * g + 0.114 * b - 128.0,
+                    -0.168736 * r - 0.331264 * g + 0.5 * b,
+                    0.5 * r - 0.418688 * g - 0.081312 * b,
+                ];
+                for (block, v) in blocks.iter_mut().zip(ycc) {
+                    block[i] = v;
+                }
+            }
+            for (c, block) in comps.iter_mut().zip(&blocks) {
+                c.encode_block(&mut w, block, &cos);
+            }
+        }
+    }
+    w.flush();
+    let mut out = w.out;
+    out.extend_from_slice(&[0xff, 0xd9]);
+    out
+}
+
+// endregion
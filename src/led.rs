@@ -0,0 +1,147 @@
+use std::io::{self, Write};
+use std::net::{ToSocketAddrs, UdpSocket};
+
+// region Grid
+
+// how a cols x rows LED grid is wired and sampled from a frame
+#[derive(Copy, Clone)]
+pub struct Grid {
+    pub cols: usize,
+    pub rows: usize,
+    // every other row runs right to left, the usual way LED strips are zig-zagged into panels
+    pub serpentine: bool,
+}
+
+impl Grid {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self { cols, rows, serpentine: false }
+    }
+
+    pub fn serpentine(self, serpentine: bool) -> Self {
+        Self { serpentine, ..self }
+    }
+
+    pub fn len(&self) -> usize { self.cols * self.rows }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    // averages each cell of an RGBA frame into one RGB triplet, in wiring order
+    pub fn downsample(&self, frame: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.len() * 3);
+        for r in 0..self.rows {
+            let (y0, y1) = span(r, self.rows, height);
+            for c in 0..self.cols {
+                let c = if self.serpentine && r % 2 == 1 { self.cols - 1 - c } else { c };
+                let (x0, x1) = span(c, self.cols, width);
+                let mut sum = [0usize; 3];
+                for y in y0..y1 {
+                    for px in frame[4 * (y * width + x0)..4 * (y * width + x1)].chunks_exact(4) {
+                        sum.iter_mut().zip(px).for_each(|(s, &v)| *s += v as usize);
+                    }
+                }
+                let n = ((x1 - x0) * (y1 - y0)).max(1);
+                out.extend(sum.iter().map(|s| (s / n) as u8));
+            }
+        }
+        out
+    }
+}
+
+// pixel range covered by cell `i` of `n`, at least one pixel wide as long as `size > 0`
+fn span(i: usize, n: usize, size: usize) -> (usize, usize) {
+    let a = i * size / n;
+    (a, ((i + 1) * size / n).max(a + 1))
+}
+
+// endregion
+// region Sink
+
+pub trait OutputSink {
+    fn send_frame(&mut self, frame: &[u8], width: usize, height: usize) -> io::Result<()>;
+}
+
+// endregion
+// region ArtNet
+
+// streams the grid as ArtDmx packets, 170 RGB pixels per universe
+pub struct ArtNet {
+    grid: Grid,
+    socket: UdpSocket,
+    universe: u16,
+    sequence: u8,
+}
+
+impl ArtNet {
+    pub const PORT: u16 = 6454;
+    const PIXELS_PER_UNIVERSE: usize = 170;
+
+    // `target` is usually a node's address or the broadcast address, port `ArtNet::PORT`
+    pub fn new<A: ToSocketAddrs>(grid: Grid, target: A) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_broadcast(true)?;
+        socket.connect(target)?;
+        Ok(Self { grid, socket, universe: 0, sequence: 0 })
+    }
+
+    // first universe (15-bit port-address); consecutive universes follow it
+    pub fn universe(self, universe: u16) -> Self {
+        Self { universe: universe & 0x7fff, ..self }
+    }
+
+    fn send_universe(&mut self, universe: u16, data: &[u8]) -> io::Result<()> {
+        // ArtDmx wants an even channel count
+        let len = data.len() + data.len() % 2;
+        let mut packet = Vec::with_capacity(18 + len);
+        packet.extend_from_slice(b"Art-Net\0");
+        packet.extend_from_slice(&0x5000u16.to_le_bytes());
+        packet.extend_from_slice(&14u16.to_be_bytes());
+        packet.push(self.sequence);
+        packet.push(0);
+        packet.extend_from_slice(&universe.to_le_bytes());
+        packet.extend_from_slice(&(len as u16).to_be_bytes());
+        packet.extend_from_slice(data);
+        packet.resize(18 + len, 0);
+        self.socket.send(&packet)?;
+        Ok(())
+    }
+}
+
+impl OutputSink for ArtNet {
+    fn send_frame(&mut self, frame: &[u8], width: usize, height: usize) -> io::Result<()> {
+        // 0 means "sequencing disabled", so wrap from 255 to 1
+        self.sequence = self.sequence.checked_add(1).unwrap_or(1);
+        let rgb = self.grid.downsample(frame, width, height);
+        for (i, chunk) in rgb.chunks(Self::PIXELS_PER_UNIVERSE * 3).enumerate() {
+            self.send_universe(self.universe.wrapping_add(i as u16) & 0x7fff, chunk)?;
+        }
+        Ok(())
+    }
+}
+
+// endregion
+// region Adalight
+
+// the Adalight serial protocol understood by most Arduino LED sketches; `W` is
+// typically the serial device opened as a file, configured for the sketch's baud rate
+pub struct Adalight<W: Write> {
+    grid: Grid,
+    out: W,
+}
+
+impl<W: Write> Adalight<W> {
+    pub fn new(grid: Grid, out: W) -> Self {
+        Self { grid, out }
+    }
+}
+
+impl<W: Write> OutputSink for Adalight<W> {
+    fn send_frame(&mut self, frame: &[u8], width: usize, height: usize) -> io::Result<()> {
+        let rgb = self.grid.downsample(frame, width, height);
+        let [hi, lo] = (self.grid.len().saturating_sub(1) as u16).to_be_bytes();
+        self.out.write_all(&[b'A', b'd', b'a', hi, lo, hi ^ lo ^ 0x55])?;
+        self.out.write_all(&rgb)?;
+        self.out.flush()
+    }
+}
+
+// endregion
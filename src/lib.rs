@@ -1,34 +1,171 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use core::fmt;
+#[cfg(feature = "windowed")]
 use std::fmt::Debug;
-use std::ops::{Add, Div, Mul, Sub};
-use std::time::{Duration, Instant};
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "windowed")]
+use std::path::PathBuf;
+use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Range, Sub, SubAssign};
+use core::time::Duration;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub(crate) use std::time::Instant;
+// std's clock panics in browsers
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
+pub(crate) use web_time::Instant;
+#[cfg(feature = "jpeg")]
 use zune_jpeg::errors::DecodeErrors;
+#[cfg(feature = "jpeg")]
 use zune_jpeg::JpegDecoder;
+#[cfg(feature = "jpeg")]
 use zune_jpeg::zune_core::colorspace::ColorSpace;
+#[cfg(feature = "jpeg")]
 use zune_jpeg::zune_core::options::DecoderOptions;
-use std::vec::Vec;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(any(feature = "parallel", feature = "windowed"))]
+use alloc::sync::Arc;
+use curve::Curves;
+use debug::DebugOverlay;
+use postfx::PostFx;
+use present::{Present, PresentError};
+use scene::Scene;
+#[cfg(feature = "pixels")]
+use pixels::Pixels;
+#[cfg(feature = "windowed")]
 use winit::application::ApplicationHandler;
-use winit::error::EventLoopError;
-use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::window::{Window, WindowId};
+#[cfg(feature = "windowed")]
+use winit::error::{EventLoopError, ExternalError};
+#[cfg(feature = "windowed")]
+use winit::event::{DeviceEvent, DeviceId, ElementState, KeyEvent, MouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent};
+#[cfg(feature = "windowed")]
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
+#[cfg(feature = "windowed")]
+use winit::monitor::MonitorHandle;
+#[cfg(all(feature = "android", target_os = "android"))]
+use winit::platform::android::{activity::AndroidApp, EventLoopBuilderExtAndroid};
+#[cfg(feature = "windowed")]
+use winit::window::{CursorGrabMode, Fullscreen, Icon, Window, WindowAttributes, WindowId, WindowLevel};
+#[cfg(feature = "windowed")]
+use watchdog::{Incident, OnStuck, Watchdog};
+#[cfg(feature = "windowed")]
+use record::{Input, Recorder, Replayer};
+#[allow(unused_imports)]
+use prelude::*;
+
+pub mod bmp;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "std")]
+pub mod config;
+pub mod crossfade;
+pub mod curve;
+pub mod debug;
+#[cfg(feature = "exr")]
+pub mod exr;
+#[cfg(feature = "hdr")]
+pub mod hdr;
+pub mod imageops;
+pub mod jpeg;
+#[cfg(feature = "std")]
+pub mod led;
+pub mod lut;
+pub mod noise;
+pub mod png;
+pub mod postfx;
+pub mod present;
+#[cfg(feature = "std")]
+pub mod preset;
+pub mod qoi;
+#[cfg(feature = "windowed")]
+pub mod record;
+pub mod scene;
+#[cfg(feature = "simd")]
+pub mod simd;
+#[cfg(feature = "mjpeg")]
+pub mod stream;
+pub mod text;
+pub mod tga;
+#[cfg(feature = "std")]
+pub mod thumbs;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "std")]
+pub mod watchdog;
+#[cfg(feature = "webp")]
+pub mod webp;
+#[cfg(feature = "std")]
+pub mod worker;
+#[cfg(not(any(feature = "std", test)))]
+mod math;
+// what modules doing float math import: the methods from `math` without
+// std, and nothing with it, where the inherent ones are there; the test
+// harness links std as well
+mod prelude {
+    #[cfg(not(any(feature = "std", test)))]
+    pub(crate) use crate::math::F64Ext;
+}
+
+// the most pixels the image decoders accept: QOI's cap, which keeps an RGBA
+// buffer of bytes under 2 GiB
+pub(crate) const MAX_PIXELS: usize = 400_000_000;
+// a quarter of that for the decoders giving four f32s a pixel, which keeps
+// their buffers under the same 2 GiB
+#[cfg(any(feature = "hdr", feature = "exr"))]
+pub(crate) const MAX_FLOAT_PIXELS: usize = MAX_PIXELS / 4;
+mod zlib;
 
 // region Elapsed
 
+#[cfg(feature = "std")]
 pub struct Elapsed {
+    start: Instant,
     last: Instant,
+    paused_at: Option<Instant>,
+    // total time spent paused
+    paused: Duration,
 }
 
+#[cfg(feature = "std")]
 impl Elapsed {
-    pub fn new() -> Self { Self { last: Instant::now() } }
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self { start: now, last: now, paused_at: None, paused: Duration::ZERO }
+    }
 
+    // time since the last call, not counting time spent paused
     pub fn elapsed(&mut self) -> Duration {
-        let now = Instant::now();
-        let d = now.duration_since(self.last);
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        let d = now.saturating_duration_since(self.last);
         self.last = now;
         d
     }
+
+    // time since creation, not counting time spent paused
+    pub fn total(&self) -> Duration {
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        now.duration_since(self.start).saturating_sub(self.paused)
+    }
+
+    pub fn pause(&mut self) {
+        self.paused_at.get_or_insert_with(Instant::now);
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(at) = self.paused_at.take() {
+            let gap = at.elapsed();
+            self.paused += gap;
+            self.last += gap;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool { self.paused_at.is_some() }
 }
 
+#[cfg(feature = "std")]
 impl Default for Elapsed {
     fn default() -> Self { Self::new() }
 }
@@ -36,11 +173,13 @@ impl Default for Elapsed {
 // endregion
 // region Fps
 
+#[cfg(feature = "std")]
 pub struct Fps {
     count: usize,
     last: Instant,
 }
 
+#[cfg(feature = "std")]
 impl Fps {
     pub fn new() -> Self { Self { count: 0, last: Instant::now() } }
 
@@ -60,10 +199,141 @@ impl Fps {
     }
 }
 
+#[cfg(feature = "std")]
 impl Default for Fps {
     fn default() -> Self { Self::new() }
 }
 
+// endregion
+// region Stats
+
+// cheap per-run health report: frame counts, frame time percentiles and time per effect
+#[cfg(feature = "std")]
+pub struct SessionStats {
+    start: Instant,
+    frames: u64,
+    dropped: u64,
+    // frame time the display expects, e.g. one refresh interval
+    budget: Duration,
+    // frame times in 0.1 ms buckets, the last one collecting everything slower
+    histogram: Vec<u32>,
+    effects: Vec<(String, Duration)>,
+}
+
+#[cfg(feature = "std")]
+impl SessionStats {
+    const BUCKETS: usize = 2500;
+
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            frames: 0,
+            dropped: 0,
+            budget,
+            histogram: vec![0; Self::BUCKETS],
+            effects: Vec::new(),
+        }
+    }
+
+    // records one frame that took `delta` with `effect` active
+    pub fn frame(&mut self, delta: Duration, effect: &str) {
+        self.frames += 1;
+        let bucket = (delta.as_secs_f64() * 1e4) as usize;
+        self.histogram[bucket.min(Self::BUCKETS - 1)] += 1;
+        // every whole budget interval beyond the first is a missed refresh
+        let intervals = (delta.as_secs_f64() / self.budget.as_secs_f64().max(1e-6) + 0.5) as u64;
+        self.dropped += intervals.saturating_sub(1);
+        match self.effects.iter_mut().find(|(e, _)| e == effect) {
+            Some((_, t)) => *t += delta,
+            None => self.effects.push((effect.into(), delta)),
+        }
+    }
+
+    pub fn frames(&self) -> u64 { self.frames }
+
+    pub fn dropped(&self) -> u64 { self.dropped }
+
+    // frame time that `p` (in [0, 1]) of the frames were at most
+    pub fn percentile(&self, p: f64) -> Duration {
+        let rank = (p.clamp(0.0, 1.0) * self.frames as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, &n) in self.histogram.iter().enumerate() {
+            seen += n as u64;
+            if seen >= rank {
+                return Duration::from_secs_f64((i + 1) as f64 * 1e-4);
+            }
+        }
+        Duration::ZERO
+    }
+
+    pub fn summary(&self) -> String {
+        let secs = self.start.elapsed().as_secs_f64();
+        let ms = |d: Duration| d.as_secs_f64() * 1e3;
+        let p99 = self.percentile(0.99);
+        let mut s = format!(
+            "session {:.1} s, {} frames, {:.1} fps average\n\
+            frame time p50 {:.1} ms, p95 {:.1} ms, p99 {:.1} ms ({:.1} fps 1% low)\n\
+            {} dropped frames against a {:.1} ms budget\n",
+            secs, self.frames, self.frames as f64 / secs.max(1e-6),
+            ms(self.percentile(0.5)), ms(self.percentile(0.95)), ms(p99),
+            1.0 / p99.as_secs_f64().max(1e-6),
+            self.dropped, ms(self.budget),
+        );
+        for (effect, t) in &self.effects {
+            s += &format!("{}: {:.1} s\n", effect, t.as_secs_f64());
+        }
+        s
+    }
+}
+
+// endregion
+// region Resolution
+
+// picks `Renderer::set_resolution_scale` from how long frames take to render:
+// halves the resolution after a few frames over `target`, and doubles it back
+// once full resolution would fit again for a while
+#[derive(Clone, Debug)]
+pub struct DynamicResolution {
+    pub target: Duration,
+    scale: usize,
+    // consecutive frames over the target, and under it with room to scale up
+    over: u32,
+    under: u32,
+}
+
+impl DynamicResolution {
+    pub const MAX_SCALE: usize = 4;
+    // frames in a row before changing; going up is slower so it doesn't flicker
+    const DOWN_AFTER: u32 = 3;
+    const UP_AFTER: u32 = 60;
+
+    pub fn new(target: Duration) -> Self {
+        Self { target, scale: 1, over: 0, under: 0 }
+    }
+
+    pub fn scale(&self) -> usize { self.scale }
+
+    // takes how long the last frame took, returns the scale for the next one
+    pub fn frame(&mut self, took: Duration) -> usize {
+        // half the resolution is about a quarter of the work
+        let up = took * 4 < self.target.mul_f64(0.8);
+        if took > self.target {
+            (self.over, self.under) = (self.over + 1, 0);
+            if self.over >= Self::DOWN_AFTER && self.scale < Self::MAX_SCALE {
+                (self.scale, self.over) = (self.scale * 2, 0);
+            }
+        } else if up && self.scale > 1 {
+            (self.over, self.under) = (0, self.under + 1);
+            if self.under >= Self::UP_AFTER {
+                (self.scale, self.under) = (self.scale / 2, 0);
+            }
+        } else {
+            (self.over, self.under) = (0, 0);
+        }
+        self.scale
+    }
+}
+
 // endregion
 // region Smooth
 
@@ -98,16 +368,150 @@ where
 // endregion
 // region Image
 
+#[derive(Clone)]
 pub struct Image {
     pub width: usize,
     pub height: usize,
     pub data: Vec<u8>,
+    linear: bool,
+    filter: Filter,
+    storage: Storage,
+    tone: Option<Tone>,
+    alpha: AlphaMode,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+    #[default]
+    Nearest,
+    Bilinear,
+}
+
+// how color relates to alpha in an image or `Color`. Decoders give straight
+// alpha, except for OpenEXR, which is premultiplied by definition; filtering
+// and compositing are only exact on premultiplied colors, where a transparent
+// pixel's color can't bleed into its neighbors
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    // red, green and blue as they'd look fully opaque
+    #[default]
+    Straight,
+    // red, green and blue already scaled by alpha
+    Premultiplied,
+}
+
+// the pixels an image samples from; `data` always holds them as bytes for
+// everything that works on bytes, and deeper ones are kept here next to it.
+// Images derived from one, resized or blurred say, are 8-bit again
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Storage {
+    // `data` itself
+    #[default]
+    U8,
+    // RGBA, 65535 being white
+    U16(Vec<u16>),
+    // RGBA, 1.0 being white; HDR values go above it
+    F32(Vec<f32>),
+}
+
+// how float pixels of linear light come down to what a screen shows, see
+// `Image::set_tonemap`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Tonemap {
+    // cuts off at white
+    Clip,
+    // x / (1 + x), keeping some detail at every brightness
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve, with a bit more contrast
+    #[default]
+    Aces,
+}
+
+impl Tonemap {
+    pub const ALL: [Self; 3] = [Self::Clip, Self::Reinhard, Self::Aces];
+
+    // linear light, 1.0 being white, to 0..=1
+    pub fn apply(self, v: f64) -> f64 {
+        let v = v.max(0.0);
+        match self {
+            Self::Clip => v.min(1.0),
+            Self::Reinhard => v / (1.0 + v),
+            Self::Aces => (v * (2.51 * v + 0.03) / (v * (2.43 * v + 0.59) + 0.14)).min(1.0),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Clip => "clip",
+            Self::Reinhard => "reinhard",
+            Self::Aces => "aces",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Tone {
+    exposure: f64,
+    // 2^exposure
+    scale: f64,
+    curve: Tonemap,
+}
+
+impl Tone {
+    // a float sample of linear light on the 0..255 scale, still linear if
+    // `linear`, sRGB-encoded otherwise
+    #[inline(always)]
+    fn map(self, v: f32, linear: bool) -> f64 {
+        let v = 255.0 * self.curve.apply(v as f64 * self.scale);
+        if linear { v } else { linear_to_srgb_fast(v) }
+    }
 }
 
 impl Image {
     pub fn new(width: usize, height: usize, data: Vec<u8>) -> Self {
-        Self { width, height, data }
+        Self { width, height, data, linear: false, filter: Filter::Nearest, storage: Storage::U8, tone: None, alpha: AlphaMode::Straight }
+    }
+
+    pub fn from_u16(width: usize, height: usize, data: Vec<u16>) -> Self {
+        let bytes = data.iter().map(|&v| ((v as u32 + 128) / 257) as u8).collect();
+        Self { storage: Storage::U16(data), ..Self::new(width, height, bytes) }
+    }
+
+    pub fn from_f32(width: usize, height: usize, data: Vec<f32>) -> Self {
+        // NaN casts to 0
+        let bytes = data.iter().map(|&v| (v as f64 * 255.0).round().clamp(0.0, 255.0) as u8).collect();
+        Self { storage: Storage::F32(data), ..Self::new(width, height, bytes) }
+    }
+
+    pub fn storage(&self) -> &Storage { &self.storage }
+
+    fn samples(&self) -> Samples<'_> {
+        match &self.storage {
+            Storage::U8 => Samples::U8(&self.data),
+            Storage::U16(d) => Samples::U16(d),
+            Storage::F32(d) => Samples::F32(d, self.tone),
+        }
+    }
+
+    // treats float pixels as linear light: brightened by `exposure` stops,
+    // brought into range by `tonemap` and sRGB-encoded as they're sampled,
+    // and `data` redone to match. Images from HDR files start out with
+    // exposure 0 and the default curve; other storage ignores this
+    pub fn set_tonemap(&mut self, exposure: f64, tonemap: Tonemap) {
+        let Storage::F32(d) = &self.storage else { return };
+        let tone = Tone { exposure, scale: 2f64.powf(exposure), curve: tonemap };
+        for (px, f) in self.data.chunks_exact_mut(4).zip(d.chunks_exact(4)) {
+            for c in 0..3 {
+                px[c] = tone.map(f[c], false).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        self.tone = Some(tone);
     }
+
+    // exposure and curve, if the image is tone mapped
+    pub fn tonemap(&self) -> Option<(f64, Tonemap)> { self.tone.map(|t| (t.exposure, t.curve)) }
+
+    #[cfg(feature = "jpeg")]
     pub fn from_jpeg(jpeg_data: &[u8]) -> Result<Self, DecodeErrors> {
         let options = DecoderOptions::default().
             jpeg_set_out_colorspace(ColorSpace::RGBA);
@@ -118,184 +522,3964 @@ impl Image {
         Ok(Self::new(width, height, data))
     }
 
-    pub fn sample(&self, pos: Pos) -> Sampler {
-        let x = pos.x.trunc() as usize;
-        let x = x.clamp(0, self.width - 1);
-        let y = pos.y.trunc() as usize;
-        let y = y.clamp(0, self.height - 1);
-        Sampler { data: &self.data, idx: 4 * (x + self.width * y) }
+    // 16-bit PNGs keep all their precision
+    pub fn from_png(png_data: &[u8]) -> Result<Self, png::Error> {
+        if png::bit_depth(png_data) == Some(16) {
+            let (width, height, data) = png::decode_u16(png_data)?;
+            return Ok(Self::from_u16(width, height, data));
+        }
+        let (width, height, data) = png::decode(png_data)?;
+        Ok(Self::new(width, height, data))
     }
-}
 
-pub struct Sampler<'a> {
-    data: &'a [u8],
-    idx: usize,
-}
+    pub fn from_qoi(qoi_data: &[u8]) -> Result<Self, qoi::Error> {
+        let (width, height, data) = qoi::decode(qoi_data)?;
+        Ok(Self::new(width, height, data))
+    }
 
-impl<'a> Sampler<'a> {
-    pub fn red(self) -> f64 { self.data[self.idx] as f64 }
-    pub fn green(self) -> f64 { self.data[self.idx + 1] as f64 }
-    pub fn blue(self) -> f64 { self.data[self.idx + 2] as f64 }
-}
+    pub fn from_bmp(bmp_data: &[u8]) -> Result<Self, bmp::Error> {
+        let (width, height, data) = bmp::decode(bmp_data)?;
+        Ok(Self::new(width, height, data))
+    }
 
-// endregion
-// region Pos
+    pub fn from_tga(tga_data: &[u8]) -> Result<Self, tga::Error> {
+        let (width, height, data) = tga::decode(tga_data)?;
+        Ok(Self::new(width, height, data))
+    }
 
-#[derive(Copy, Clone, Default)]
-pub struct Pos {
-    x: f64,
-    y: f64,
-}
+    #[cfg(feature = "webp")]
+    pub fn from_webp(webp_data: &[u8]) -> Result<Self, webp::Error> {
+        let (width, height, data) = webp::decode(webp_data)?;
+        Ok(Self::new(width, height, data))
+    }
 
-impl Pos {
-    pub fn new(x: f64, y: f64) -> Self {
-        Self { x, y }
+    // linear light, tone mapped with the defaults
+    #[cfg(feature = "hdr")]
+    pub fn from_hdr(hdr_data: &[u8]) -> Result<Self, hdr::Error> {
+        let (width, height, data) = hdr::decode(hdr_data)?;
+        Ok(Self::from_f32(width, height, data).tonemapped())
     }
 
-    pub fn len(&self) -> f64 {
-        (self.x * self.x + self.y * self.y).sqrt()
+    #[cfg(feature = "exr")]
+    pub fn from_exr(exr_data: &[u8]) -> Result<Self, exr::Error> {
+        let (width, height, data) = exr::decode(exr_data)?;
+        let mut img = Self::from_f32(width, height, data).tonemapped();
+        img.alpha = AlphaMode::Premultiplied;
+        Ok(img)
     }
 
-    pub fn dist(&self, r: Self) -> f64 {
-        self.sub(r).len()
+    #[cfg(any(feature = "hdr", feature = "exr"))]
+    fn tonemapped(mut self) -> Self {
+        self.set_tonemap(0.0, Tonemap::default());
+        self
     }
-}
 
-impl Add for Pos {
-    type Output = Pos;
-    fn add(self, r: Pos) -> Pos {
-        Pos { x: self.x + r.x, y: self.y + r.y }
+    // picks the decoder from the file signature; TGA has none, so it's tried
+    // last, on a header that checks out
+    pub fn from_bytes(data: &[u8]) -> Result<Self, LoadError> {
+        if data.starts_with(&png::SIGNATURE) {
+            return Self::from_png(data).map_err(LoadError::Png);
+        }
+        if data.starts_with(&qoi::SIGNATURE) {
+            return Self::from_qoi(data).map_err(LoadError::Qoi);
+        }
+        #[cfg(feature = "webp")]
+        if webp::is_webp(data) {
+            return Self::from_webp(data).map_err(LoadError::Webp);
+        }
+        #[cfg(feature = "hdr")]
+        if hdr::is_hdr(data) {
+            return Self::from_hdr(data).map_err(LoadError::Hdr);
+        }
+        #[cfg(feature = "exr")]
+        if exr::is_exr(data) {
+            return Self::from_exr(data).map_err(LoadError::Exr);
+        }
+        #[cfg(feature = "jpeg")]
+        if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            return Self::from_jpeg(data).map_err(LoadError::Jpeg);
+        }
+        if data.starts_with(&bmp::SIGNATURE) {
+            return Self::from_bmp(data).map_err(LoadError::Bmp);
+        }
+        if tga::is_tga(data) {
+            return Self::from_tga(data).map_err(LoadError::Tga);
+        }
+        Err(LoadError::UnknownFormat)
     }
-}
 
-impl Sub for Pos {
-    type Output = Pos;
-    fn sub(self, r: Pos) -> Pos {
-        Pos { x: self.x - r.x, y: self.y - r.y }
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Png => png::encode(self.width, self.height, &self.data),
+            ImageFormat::Jpeg(quality) => jpeg::encode(self.width, self.height, &self.data, quality),
+            ImageFormat::Qoi => qoi::encode(self.width, self.height, &self.data),
+        }
     }
-}
 
-impl Mul<f64> for Pos {
-    type Output = Pos;
-    fn mul(self, f: f64) -> Pos {
-        Pos { x: self.x * f, y: self.y * f }
+    // encoded in the format the file extension names
+    #[cfg(feature = "std")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let format = path.extension().and_then(|e| e.to_str()).and_then(ImageFormat::from_extension).
+            ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "unknown image extension"))?;
+        std::fs::write(path, self.encode(format))
     }
-}
 
-impl Div<f64> for Pos {
-    type Output = Pos;
-    fn div(self, f: f64) -> Pos {
-        Pos { x: self.x / f, y: self.y / f }
+    pub fn sample(&self, pos: Pos) -> Sampler<'_> {
+        if self.filter == Filter::Bilinear {
+            return self.sample_bilinear(pos);
+        }
+        let x = pos.x.trunc() as usize;
+        let x = x.clamp(0, self.width - 1);
+        let y = pos.y.trunc() as usize;
+        let y = y.clamp(0, self.height - 1);
+        Sampler { data: self.samples(), idx: 4 * (x + self.width * y), linear: self.linear, bilinear: None }
     }
-}
 
-// endregion
-// region App
+    // blends the four pixels around `pos`, pixel centers being at +0.5
+    fn sample_bilinear(&self, pos: Pos) -> Sampler<'_> {
+        let axis = |v: f64, size: usize| {
+            let v = (v - 0.5).clamp(0.0, (size - 1) as f64);
+            let i = v.floor();
+            (i as usize, v - i, if (i as usize) + 1 < size { 1 } else { 0 })
+        };
+        let (x, fx, dx) = axis(pos.x, self.width);
+        let (y, fy, dy) = axis(pos.y, self.height);
+        Sampler {
+            data: self.samples(),
+            idx: 4 * (x + self.width * y),
+            linear: self.linear,
+            bilinear: Some(Bilinear { dx: 4 * dx, dy: 4 * self.width * dy, fx, fy }),
+        }
+    }
 
-pub trait AppState: Sized {
-    type StartProps;
-    type StartErr: Debug;
-    fn start(event_loop: &ActiveEventLoop, props: Self::StartProps) -> Result<Self, Self::StartErr>;
-    type MouseMoveErr: Debug;
-    fn mousemove(&mut self, pos: Pos) -> Result<(), Self::MouseMoveErr>;
-    type RenderErr: Debug;
-    fn render(&mut self, delta: Duration) -> Result<(), Self::RenderErr>;
-    fn window(&self) -> &Window;
-}
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
 
-pub struct Driver<State: AppState> {
-    props: Option<State::StartProps>,
-    state: Option<State>,
-    elapsed: Elapsed,
-}
+    pub fn filter(&self) -> Filter { self.filter }
 
-impl<State: AppState> Driver<State> {
-    pub fn new(props: State::StartProps) -> Self {
-        Self {
-            props: Some(props),
-            state: None,
-            elapsed: Elapsed::new(),
+    // when set, samples are decoded from sRGB to linear light so that averaging
+    // them is physically meaningful; convert back with `Color::to_srgb` when writing
+    pub fn set_linear(&mut self, linear: bool) {
+        self.linear = linear;
+    }
+
+    pub fn is_linear(&self) -> bool { self.linear }
+
+    // says what the pixels hold without changing them, for data from
+    // elsewhere; `to_premultiplied` and `to_straight` convert
+    pub fn set_alpha_mode(&mut self, alpha: AlphaMode) {
+        self.alpha = alpha;
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode { self.alpha }
+
+    // colors scaled by alpha, at every depth the image keeps
+    pub fn to_premultiplied(&self) -> Self {
+        if self.alpha == AlphaMode::Premultiplied {
+            return self.clone();
         }
+        self.convert_alpha(AlphaMode::Premultiplied, |c, a| c * a)
     }
 
-    pub fn run(&mut self) -> Result<(), EventLoopError> {
-        let event_loop = EventLoop::new()?;
-        event_loop.set_control_flow(ControlFlow::Wait);
-        event_loop.run_app(self)
+    // colors divided by alpha again; fully transparent pixels come out black
+    pub fn to_straight(&self) -> Self {
+        if self.alpha == AlphaMode::Straight {
+            return self.clone();
+        }
+        self.convert_alpha(AlphaMode::Straight, |c, a| if a > 0.0 { c / a } else { 0.0 })
     }
-}
 
-impl<State: AppState> ApplicationHandler for Driver<State> {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.state.is_none() {
-            let state = State::start(event_loop, self.props.take().unwrap()).unwrap();
-            self.state = Some(state);
-            self.elapsed.elapsed();
-            self.state.as_ref().unwrap().window().request_redraw();
+    // `f` takes a color channel and alpha, in [0, 1] but for HDR colors;
+    // the casts back saturate
+    fn convert_alpha(&self, alpha: AlphaMode, f: impl Fn(f64, f64) -> f64) -> Self {
+        let mut out = Self { alpha, ..self.clone() };
+        for px in out.data.chunks_exact_mut(4) {
+            let a = px[3] as f64 / 255.0;
+            px[..3].iter_mut().for_each(|c| *c = (f(*c as f64 / 255.0, a) * 255.0).round() as u8);
         }
+        match &mut out.storage {
+            Storage::U8 => (),
+            Storage::U16(d) => for px in d.chunks_exact_mut(4) {
+                let a = px[3] as f64 / 65535.0;
+                px[..3].iter_mut().for_each(|c| *c = (f(*c as f64 / 65535.0, a) * 65535.0).round() as u16);
+            },
+            Storage::F32(d) => for px in d.chunks_exact_mut(4) {
+                let a = (px[3] as f64).clamp(0.0, 1.0);
+                px[..3].iter_mut().for_each(|c| *c = f(*c as f64, a) as f32);
+            },
+        }
+        if let Some(tone) = out.tone {
+            out.set_tonemap(tone.exposure, tone.curve);
+        }
+        out
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
-        match event {
-            WindowEvent::RedrawRequested => {
-                let delta = self.elapsed.elapsed();
-                let state = self.state.as_mut().unwrap();
-                state.render(delta).unwrap();
-                state.window().request_redraw();
+    // box-filtered copy at another size; meant for shrinking, enlarging repeats pixels
+    pub fn resize(&self, width: usize, height: usize) -> Self {
+        let mut data = vec![0; width * height * 4];
+        if self.width == 0 || self.height == 0 {
+            return Self { data, width, height, linear: self.linear, filter: self.filter, storage: Storage::U8, tone: None, alpha: self.alpha };
+        }
+        let span = |i: usize, n: usize, size: usize| {
+            let a = (i * size / n).min(size - 1);
+            (a, ((i + 1) * size / n).clamp(a + 1, size))
+        };
+        for (y, row) in data.chunks_exact_mut(width * 4).enumerate() {
+            let (y0, y1) = span(y, height, self.height);
+            for (x, px) in row.chunks_exact_mut(4).enumerate() {
+                let (x0, x1) = span(x, width, self.width);
+                let mut sum = [0usize; 4];
+                for sy in y0..y1 {
+                    let src = &self.data[4 * (sy * self.width + x0)..4 * (sy * self.width + x1)];
+                    for s in src.chunks_exact(4) {
+                        sum.iter_mut().zip(s).for_each(|(a, &b)| *a += b as usize);
+                    }
+                }
+                let n = (x1 - x0) * (y1 - y0);
+                px.iter_mut().zip(sum).for_each(|(p, s)| *p = (s / n) as u8);
             }
-            WindowEvent::CursorMoved { device_id: _, position: pos } => {
-                let state = self.state.as_mut().unwrap();
-                let p = pos.to_logical(state.window().scale_factor());
-                state.mousemove(Pos::new(p.x, p.y)).unwrap();
+        }
+        Self { data, width, height, linear: self.linear, filter: self.filter, storage: Storage::U8, tone: None, alpha: self.alpha }
+    }
+
+    // largest size with this aspect ratio that fits in `max_width` x `max_height`
+    pub fn fit(&self, max_width: usize, max_height: usize) -> (usize, usize) {
+        if self.width == 0 || self.height == 0 {
+            return (0, 0);
+        }
+        let scale = (max_width as f64 / self.width as f64).min(max_height as f64 / self.height as f64);
+        (((self.width as f64 * scale) as usize).max(1), ((self.height as f64 * scale) as usize).max(1))
+    }
+
+    // fitted into `width` x `height` and centered, with opaque black around it
+    pub fn letterbox(&self, width: usize, height: usize) -> Self {
+        let (w, h) = self.fit(width, height);
+        let mut out = Self { linear: self.linear, filter: self.filter, ..Self::new(width, height, [0, 0, 0, 255].repeat(width * height)) };
+        let at = (width.saturating_sub(w) as isize / 2, height.saturating_sub(h) as isize / 2);
+        out.blit(&self.resize(w, h), at, BlendMode::Normal);
+        out
+    }
+
+    // `t` of the way from this image to `other`, byte by byte; 16-bit and
+    // float pixels come down to 8 bits
+    pub fn mix(&self, other: &Image, t: f64) -> Self {
+        assert!(self.width == other.width && self.height == other.height, "images of different sizes");
+        let t = t.clamp(0.0, 1.0);
+        let data = self.data.iter().zip(&other.data).map(|(&a, &b)| (a as f64 + (b as f64 - a as f64) * t + 0.5) as u8).collect();
+        Self { data, storage: Storage::U8, tone: None, ..self.clone() }
+    }
+
+    // reads luma as height and encodes the surface's normals as colors, for
+    // lighting the image like a relief: red and green along the image's x and
+    // y, so green points down, blue out of the screen, each mapped from
+    // [-1, 1] to [0, 255]; `strength` steepens the slopes, 0 leaving it flat
+    pub fn normal_map(&self, strength: f64) -> Self {
+        let mut data = vec![255; self.data.len()];
+        for (px, g) in data.chunks_exact_mut(4).zip(imageops::sobel(self)) {
+            let (x, y) = (-g.x * strength / 255.0, -g.y * strength / 255.0);
+            let len = (x * x + y * y + 1.0).sqrt();
+            for (c, n) in px.iter_mut().zip([x / len, y / len, 1.0 / len]) {
+                *c = ((n + 1.0) * 127.5).round() as u8;
             }
-            WindowEvent::CloseRequested => event_loop.exit(),
-            _ => (),
         }
+        Self { data, width: self.width, height: self.height, linear: false, filter: self.filter, storage: Storage::U8, tone: None, alpha: AlphaMode::Straight }
     }
-}
 
-// endregion
-// region Color
+    // signed distance in pixels to the outline of where alpha is above
+    // `threshold` in [0, 1], one gray level per pixel around 127.5 at the
+    // outline and clamped beyond; positive inside, so sampling it bilinearly
+    // keeps edges crisp at any scale. `imageops::distance_transform` has the
+    // unclamped distances
+    pub fn distance_field(&self, threshold: f64) -> Self {
+        let inside: Vec<bool> = self.data.chunks_exact(4).map(|p| p[3] as f64 > threshold * 255.0).collect();
+        let outside: Vec<bool> = inside.iter().map(|i| !i).collect();
+        let (to_inside, to_outside) = (imageops::distance_transform(&inside, self.width), imageops::distance_transform(&outside, self.width));
+        let mut data = vec![255; self.data.len()];
+        for (i, px) in data.chunks_exact_mut(4).enumerate() {
+            // the outline runs between pixel centers
+            let d = if inside[i] { to_outside[i] - 0.5 } else { 0.5 - to_inside[i] };
+            px[..3].fill((127.5 + d).round().clamp(0.0, 255.0) as u8);
+        }
+        Self { data, width: self.width, height: self.height, linear: false, filter: self.filter, storage: Storage::U8, tone: None, alpha: AlphaMode::Straight }
+    }
 
-#[derive(Copy, Clone, Default)]
-pub struct Color {
-    red: f64,
-    green: f64,
-    blue: f64,
-}
+    pub fn histogram(&self) -> imageops::Histogram { imageops::Histogram::of(&self.data) }
 
-impl Color {
-    pub fn new(red: f64, green: f64, blue: f64) -> Self {
-        Self { red, green, blue }
+    // stretches the levels so the darkest and brightest 0.5% of color values
+    // end up black and white; the same for all three channels, so a washed
+    // out photo gets its contrast back without a color cast
+    pub fn auto_levels(&self) -> Self {
+        let rgb = self.histogram().rgb();
+        let (lo, hi) = (imageops::percentile(&rgb, 0.005) as f64, imageops::percentile(&rgb, 0.995) as f64);
+        if hi <= lo {
+            return self.clone();
+        }
+        self.map_levels(core::array::from_fn(|v| ((v as f64 - lo) * 255.0 / (hi - lo)).round().clamp(0.0, 255.0) as u8))
     }
 
-    pub fn write_bytes(self, p: &mut [u8]) {
-        p[0] = self.red.floor() as u8;
-        p[1] = self.green.floor() as u8;
-        p[2] = self.blue.floor() as u8;
+    // spreads the levels out so they occur about equally often, with one
+    // curve for all three channels like `auto_levels`
+    pub fn equalize(&self) -> Self {
+        let rgb = self.histogram().rgb();
+        let mut cdf = [0; 256];
+        let mut total = 0;
+        for (c, n) in cdf.iter_mut().zip(rgb) {
+            total += n;
+            *c = total;
+        }
+        // the darkest level present stays black
+        let first = cdf.iter().copied().find(|&c| c > 0).unwrap_or(0);
+        if total == first {
+            return self.clone();
+        }
+        self.map_levels(cdf.map(|c| ((c.saturating_sub(first)) as f64 * 255.0 / (total - first) as f64).round() as u8))
     }
-}
 
-impl Add for Color {
-    type Output = Self;
-    fn add(self, r: Self) -> Self {
-        Self {
-            red: self.red + r.red,
-            green: self.green + r.green,
-            blue: self.blue + r.blue,
+    // composites `other` onto this image with its top left corner at `at`,
+    // clipped at the edges, for a watermark or a second picture to warp along
+    // with the rest; 16-bit and float pixels come down to 8 bits
+    pub fn blit(&mut self, other: &Image, at: (isize, isize), mode: BlendMode) {
+        composite(&mut self.data, self.width, self.alpha, at, other, mode);
+        self.storage = Storage::U8;
+        self.tone = None;
+    }
+
+    // graded through a 3D color lookup table, once rather than every frame
+    pub fn apply_lut(&self, lut: &lut::Lut) -> Self {
+        let mut out = Self { storage: Storage::U8, tone: None, ..self.clone() };
+        lut.apply(&mut out.data, self.width, 1.0);
+        out
+    }
+
+    // red, green and blue through `lut`, alpha as it is
+    fn map_levels(&self, lut: [u8; 256]) -> Self {
+        let mut out = Self { storage: Storage::U8, tone: None, ..self.clone() };
+        for px in out.data.chunks_exact_mut(4) {
+            px[..3].iter_mut().for_each(|c| *c = lut[*c as usize]);
         }
+        out
     }
 }
 
-impl Mul<f64> for Color {
-    type Output = Self;
-    fn mul(self, f: f64) -> Self {
-        Self {
-            red: self.red * f,
-            green: self.green * f,
-            blue: self.blue * f,
+// what `Image::encode` writes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    // RGB, or RGBA if any pixel isn't opaque
+    Png,
+    // quality 1..=100, alpha is dropped
+    Jpeg(u8),
+    Qoi,
+}
+
+impl ImageFormat {
+    // png, jpg or jpeg, and qoi, in any case; JPEGs at quality 90
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg(90)),
+            "qoi" => Some(Self::Qoi),
+            _ => None,
         }
     }
 }
 
-// endregion
\ No newline at end of file
+#[derive(Debug)]
+pub enum LoadError {
+    UnknownFormat,
+    #[cfg(feature = "jpeg")]
+    Jpeg(DecodeErrors),
+    Png(png::Error),
+    Qoi(qoi::Error),
+    #[cfg(feature = "webp")]
+    Webp(webp::Error),
+    Bmp(bmp::Error),
+    Tga(tga::Error),
+    #[cfg(feature = "hdr")]
+    Hdr(hdr::Error),
+    #[cfg(feature = "exr")]
+    Exr(exr::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::UnknownFormat => f.write_str("unknown image format"),
+            #[cfg(feature = "jpeg")]
+            LoadError::Jpeg(e) => write!(f, "{}", e),
+            LoadError::Png(e) => write!(f, "{}", e),
+            LoadError::Qoi(e) => write!(f, "{}", e),
+            #[cfg(feature = "webp")]
+            LoadError::Webp(e) => write!(f, "{}", e),
+            LoadError::Bmp(e) => write!(f, "{}", e),
+            LoadError::Tga(e) => write!(f, "{}", e),
+            #[cfg(feature = "hdr")]
+            LoadError::Hdr(e) => write!(f, "{}", e),
+            #[cfg(feature = "exr")]
+            LoadError::Exr(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LoadError {}
+
+// sRGB -> linear light on the same 0..255 scale; `SRGB_TO_LINEAR` has it for
+// every byte
+fn srgb_to_linear(c: f64) -> f64 {
+    let c = c / 255.0;
+    255.0 * if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    let c = (c / 255.0).max(0.0);
+    255.0 * if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+// sRGB byte -> linear light on the same 0..255 scale
+const SRGB_TO_LINEAR: [f64; 256] = [
+    0.0, 0.07739938080495357, 0.15479876160990713, 0.2321981424148607, 0.30959752321981426, 0.3869969040247678,
+    0.4643962848297214, 0.541795665634675, 0.6191950464396285, 0.696594427244582, 0.7739938080495355, 0.853366619794286,
+    0.9375093676320961, 1.0263028397165581, 1.1198177195396248, 1.218123137576901, 1.3212867590962885, 1.4293748641716943,
+    1.5424524208285488, 1.6605831521115912, 1.7838295977526737, 1.9122531710226747, 2.0459142112731734, 2.1848720326076734,
+    2.3291849690663007, 2.4789104166606517, 2.6341048725548686, 2.7948239716545027, 2.9611225208346905, 3.1330545310135474,
+    3.310673247254118, 3.49403117705887, 3.683180117003599, 3.878171177842741, 4.079054808204956, 4.285880816986283,
+    4.49869839453794, 4.7175561327368225, 4.942502044018609, 5.173583579446362, 5.4108476458809065, 5.654340622313782,
+    5.904108375418154, 6.160196274368713, 6.4226492049772155, 6.691511583186724, 6.966827367964098, 7.2486400736273024,
+    7.536992781641244, 7.8319281519133765, 8.133488433617941, 8.44171547557569, 8.756650736213954, 9.078335293130188,
+    9.40680985228051, 9.742114756813358, 10.084289995566873, 10.433375211247563, 10.789409708306467, 11.152432460528233,
+    11.522482118347263, 11.899597015904424, 12.283815177856802, 12.675174325952444, 13.073711885381025, 13.479464990910962,
+    13.892470492822804, 14.312764962648023, 14.740384698722144, 15.175365731560206, 15.61774382906249, 16.06755450155776,
+    16.524833006690972, 16.989614354162086, 17.461933310322042, 17.941824402631948, 18.42932192399096, 18.924459936938156,
+    19.4272722777335, 19.937792560322514, 20.456054180189422, 20.982090318102774, 21.515933943757947, 22.05761781932019,
+    22.607174502872102, 23.16463635176896, 23.7300355259053, 24.303403990896, 24.884773521174928, 25.47417570301404,
+    26.071641937465838, 26.67720344323157, 27.290891259458242, 27.91273624846633, 28.54276909841093, 29.1810203258783,
+    29.827520278420263, 30.482299137028132, 31.14538691854848, 31.816813478042373, 32.496608511090095, 33.18480155604289,
+    33.88142199622359, 34.58649906207744, 35.300061833275215, 36.02213924076927, 36.75276006880473, 37.49195295688672,
+    38.23974640170518, 38.996168759018296, 39.761248245495985, 40.5350129405245, 41.317490787973306, 42.10870959792524,
+    42.90869704837114, 43.71748068686976, 44.535087932174186, 45.36154607582542, 46.19688228371436, 47.041123597612454,
+    47.89429693667286, 48.756429098901805, 49.62754676260183, 50.50767648778696, 51.396844717571256, 52.295077779530814,
+    53.20240188704022, 54.11884314058408, 55.04442752904431, 55.97918093096365, 56.92312911578617, 57.876297745075085,
+    58.838712373709036, 59.81039845105633, 60.791381322129105, 61.78168622871649, 62.78133831049805, 63.790362606138125,
+    64.80878405436083, 65.83662749500694, 66.87391767007254, 67.92067922472992, 68.97693670833178, 70.04271457539808,
+    71.11803718658665, 72.20292880964799, 73.29741362036395, 74.40151570347165, 75.51525905357188, 76.63866757602301,
+    77.771765087821, 78.91457531846467, 80.06712191080797, 81.22942842189842, 82.40151832380243, 83.58341500441826,
+    84.77514176827573, 85.97672183732425, 87.18817835170853, 88.40953437053255, 89.64081287261203, 90.8820367572156,
+    92.13322884479493, 93.39441187770407, 94.66560852090802, 95.94684136268116, 97.23813291529518, 98.53950561569751,
+    99.85098182617944, 101.17258383503484, 102.50433385720993, 103.84625403494286, 105.19836643839545, 106.56069306627505,
+    107.93325584644828, 109.31607663654619, 110.70917722456097, 112.1125793294343, 113.5263046016381, 114.95037462374698,
+    116.38481091100319, 117.8296349118738, 119.28486800860054, 120.75053151774243, 122.22664669071084, 123.71323471429794,
+    125.21031671119808, 126.71791374052195, 128.236046798305, 129.76473681800812, 131.30400467101296, 132.85387116711036,
+    134.41435705498233, 135.98548302267884, 137.56726969808733, 139.15973764939758, 140.76290738556003, 142.3767993567383,
+    144.00143395475695, 145.63683151354263, 147.28301230956092, 148.93999656224685, 150.6078044344309, 152.28645603275965,
+    153.97597140811115, 155.6763705560055, 157.38767341701103, 159.10989987714441, 160.84306976826744, 162.58720286847833,
+    164.34231890249845, 166.10843754205516, 167.88557840625936, 169.67376106197938, 171.47300502421032, 173.28332975643892,
+    175.10475467100494, 176.93729912945741, 178.78098244290814, 180.63582387238012, 182.50184262915258, 184.37905787510218,
+    186.26748872304017, 188.16715423704636, 190.0780734327988, 192.00026527789984, 193.93374869219957, 195.8785425481143,
+    197.83466567094302, 199.80213683917935, 201.7809747848207, 203.7711981936743, 205.77282570565916, 207.7858759151058,
+    209.8103673710523, 211.84631857753692, 213.8937479938887, 215.9526740350138, 218.02311507167963, 220.10508943079608,
+    222.19861539569328, 224.30371120639708, 226.4203950599014, 228.54868511043793, 230.68859946974314, 232.84015620732188,
+    235.00337335071012, 237.17826888573305, 239.36486075676143, 241.56316686696596, 243.77320507856797, 245.9949932130886,
+    248.2285490515952, 250.47389033494488, 252.7310347640266, 255.0,
+];
+
+// linear light (i / 128)² -> sRGB on the 0..255 scale; spacing the entries by
+// the square root keeps the steep end near black smooth enough to interpolate
+const SRGB_BY_SQRT: [f64; 129] = [
+    0.0, 0.20108642578125, 0.804345703125, 1.80977783203125, 3.2173828125, 5.02716064453125,
+    7.239111328125, 9.853234863281251, 12.665660500359051, 15.41829587686441, 18.12031348133104, 20.77759035453595,
+    23.39484955185149, 25.97596411525023, 28.524163694213993, 31.042179744477643, 33.53235045217773, 35.996698378775164,
+    38.43698910905049, 40.854776348422284, 43.25143714947438, 45.628199813963796, 47.98616626963882, 50.32633021741185,
+    52.649591997497694, 54.95677087969932, 57.24861530930827, 59.52581151420482, 61.788990786237406, 64.03873568110558,
+    66.2755853291108, 68.5000400096542, 70.71256511199206, 72.91359458118383, 75.10353392970697, 77.28276288063611,
+    79.45163769669102, 81.61049324016777, 83.75964480127584, 85.89938972632308, 88.03000887222487, 90.15176790973878,
+    92.26491849445974, 94.36969932182085, 96.46633708001735, 98.5550473128238, 100.6360352026358, 102.70949628268488,
+    104.77561708620163, 106.83457573930598, 108.88654250354942, 110.93168027330609, 112.97014503258082, 115.00208627526122,
+    117.02764739237358, 119.04696602949538, 121.06017441712484, 123.06739967649943, 125.06876410308647, 127.06438542973193,
+    129.0543770712475, 131.0388483520319, 133.0179047181623, 134.9916479352482, 136.96017627321453, 138.92358467906755,
+    140.88196493859866, 142.8354058278897, 144.78399325540735, 146.72781039539885, 148.66693781324156, 150.6014535833384,
+    152.5314334001019, 154.45695068252158, 156.37807667276908, 158.29488052925657, 160.20742941453008, 162.115788578349,
+    164.02002143627385, 165.92018964406012, 167.8163531681322, 169.70857035238947, 171.59689798157981, 173.48139134145526,
+    175.36210427591067, 177.23908924129032, 179.11239735803537, 180.98207845983052, 182.84818114039916, 184.71075279808483,
+    186.5698396783469, 188.42548691429113, 190.27773856534512, 192.12663765418426, 193.9722262020043, 195.8145452622317,
+    197.65363495275665, 199.48953448676824, 201.3222822022659, 203.15191559031734, 204.97847132212803, 206.8019852749832,
+    208.62249255712123, 210.44002753159108, 212.25462383914544, 214.06631442021754, 215.87513153602634, 217.68110678885282,
+    219.484271141527, 221.28465493616403, 223.08228791218443, 224.87719922365187, 226.66941745596134, 228.458970641906,
+    230.24588627715283, 232.03019133515235, 233.81191228150894, 235.59107508783512, 237.3677052451129, 239.14182777658326,
+    240.9134672501853, 242.68264779056304, 244.44939309065958, 246.21372642291502, 247.97567065008568, 249.7352482357,
+    251.49248125416574, 253.24739140054356, 255.0,
+];
+
+// `linear_to_srgb` for 0..=255, to within a twentieth of a level
+#[inline(always)]
+fn linear_to_srgb_fast(c: f64) -> f64 {
+    let u = (c / 255.0).clamp(0.0, 1.0).sqrt() * 128.0;
+    let i = (u as usize).min(127);
+    SRGB_BY_SQRT[i] + (SRGB_BY_SQRT[i + 1] - SRGB_BY_SQRT[i]) * (u - i as f64)
+}
+
+#[derive(Copy, Clone)]
+pub struct Sampler<'a> {
+    data: Samples<'a>,
+    idx: usize,
+    linear: bool,
+    bilinear: Option<Bilinear>,
+}
+
+#[derive(Copy, Clone)]
+enum Samples<'a> {
+    U8(&'a [u8]),
+    U16(&'a [u16]),
+    F32(&'a [f32], Option<Tone>),
+}
+
+// sample offsets to the right and lower neighbours (0 at the edges) and the weights
+#[derive(Copy, Clone)]
+struct Bilinear {
+    dx: usize,
+    dy: usize,
+    fx: f64,
+    fy: f64,
+}
+
+impl<'a> Sampler<'a> {
+    pub fn red(self) -> f64 { self.channel(0) }
+    pub fn green(self) -> f64 { self.channel(1) }
+    pub fn blue(self) -> f64 { self.channel(2) }
+
+    fn channel(self, c: usize) -> f64 {
+        // every storage comes out on the same 0..255 scale
+        let deep = |v: f64| if self.linear { srgb_to_linear(v) } else { v };
+        match self.data {
+            Samples::U8(d) if self.linear => self.blend(|i| SRGB_TO_LINEAR[d[i + c] as usize]),
+            Samples::U8(d) => self.blend(|i| d[i + c] as f64),
+            Samples::U16(d) => self.blend(|i| deep(d[i + c] as f64 / 257.0)),
+            Samples::F32(d, None) => self.blend(|i| deep(d[i + c] as f64 * 255.0)),
+            Samples::F32(d, Some(tone)) => self.blend(|i| tone.map(d[i + c], self.linear)),
+        }
+    }
+
+    // the value `at` reads at the sample's index, or between its neighbours
+    #[inline(always)]
+    fn blend(self, at: impl Fn(usize) -> f64) -> f64 {
+        match self.bilinear {
+            None => at(self.idx),
+            Some(Bilinear { dx, dy, fx, fy }) => {
+                let (i, j) = (self.idx, self.idx + dy);
+                let top = at(i) + (at(i + dx) - at(i)) * fx;
+                let bottom = at(j) + (at(j + dx) - at(j)) * fx;
+                top + (bottom - top) * fy
+            }
+        }
+    }
+
+    pub fn color(self) -> Color { Color::new(self.red(), self.green(), self.blue()) }
+
+    // on the same 0..255 scale, and never linearized
+    pub fn alpha(self) -> f64 {
+        match self.data {
+            Samples::U8(d) => self.blend(|i| d[i + 3] as f64),
+            Samples::U16(d) => self.blend(|i| d[i + 3] as f64 / 257.0),
+            Samples::F32(d, _) => self.blend(|i| d[i + 3] as f64 * 255.0),
+        }
+    }
+}
+
+// endregion
+// region Equirect
+
+impl Image {
+    // treats the image as an equirectangular panorama: longitude wraps around, and
+    // running past a pole continues down the opposite meridian
+    pub fn sample_equirect(&self, pos: Pos) -> Sampler<'_> {
+        let (w, h) = (self.width as f64, self.height as f64);
+        let (mut x, mut y) = (pos.x, pos.y);
+        // fold latitude into [0, 2h) first, then reflect the far half over the pole
+        y %= 2.0 * h;
+        if y < 0.0 {
+            y += 2.0 * h;
+        }
+        if y >= h {
+            y = 2.0 * h - y;
+            x += w / 2.0;
+        }
+        x %= w;
+        if x < 0.0 {
+            x += w;
+        }
+        self.sample(Pos::new(x, y))
+    }
+}
+
+// a pinhole camera looking out from the center of an equirectangular panorama
+#[derive(Copy, Clone)]
+pub struct Viewport {
+    pub width: usize,
+    pub height: usize,
+    // radians; yaw turns right, pitch looks up
+    pub yaw: f64,
+    pub pitch: f64,
+    // horizontal field of view in radians
+    pub fov: f64,
+}
+
+impl Viewport {
+    const MIN_FOV: f64 = 10.0 * core::f64::consts::PI / 180.0;
+    const MAX_FOV: f64 = 150.0 * core::f64::consts::PI / 180.0;
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, yaw: 0.0, pitch: 0.0, fov: core::f64::consts::FRAC_PI_2 }
+    }
+
+    pub fn pan(&mut self, yaw: f64, pitch: f64) {
+        use core::f64::consts::{FRAC_PI_2, TAU};
+        self.yaw = (self.yaw + yaw) % TAU;
+        self.pitch = (self.pitch + pitch).clamp(-FRAC_PI_2, FRAC_PI_2);
+    }
+
+    // factors above 1 zoom in
+    pub fn zoom(&mut self, factor: f64) {
+        if factor > 0.0 {
+            self.fov = (self.fov / factor).clamp(Self::MIN_FOV, Self::MAX_FOV);
+        }
+    }
+
+    // where output pixel `p` looks, in pixel coordinates of a `pano_width` x `pano_height` panorama
+    pub fn source(&self, p: Pos, pano_width: usize, pano_height: usize) -> Pos {
+        use core::f64::consts::{PI, TAU};
+        let (s, c) = (self.fov / 2.0).sin_cos();
+        let focal = self.width as f64 / 2.0 * c / s;
+        // camera space: x right, y up, z forward
+        let x = p.x - self.width as f64 / 2.0;
+        let y = self.height as f64 / 2.0 - p.y;
+        let z = focal;
+        let (sp, cp) = self.pitch.sin_cos();
+        let (y, z) = (y * cp + z * sp, z * cp - y * sp);
+        let (sy, cy) = self.yaw.sin_cos();
+        let (x, z) = (x * cy + z * sy, z * cy - x * sy);
+        let lon = x.atan2(z);
+        let lat = y.atan2((x * x + z * z).sqrt());
+        Pos::new((lon / TAU + 0.5) * pano_width as f64, (0.5 - lat / PI) * pano_height as f64)
+    }
+
+    // flat, opaque view of `pano` as seen through the viewport
+    pub fn project(&self, pano: &Image) -> Image {
+        let mut data = vec![255; self.width * self.height * 4];
+        run_shader(&mut data, self.width, |p| {
+            let centered = p + Pos::new(0.5, 0.5);
+            pano.sample_equirect(self.source(centered, pano.width, pano.height)).color()
+        });
+        Image::new(self.width, self.height, data)
+    }
+}
+
+// endregion
+// region Pos
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Pos {
+    x: f64,
+    y: f64,
+}
+
+impl Pos {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn x(&self) -> f64 { self.x }
+    pub fn y(&self) -> f64 { self.y }
+
+    pub fn with_x(&self, x: f64) -> Self { Self { x, y: self.y } }
+    pub fn with_y(&self, y: f64) -> Self { Self { x: self.x, y } }
+
+    pub fn min(&self, r: Self) -> Self {
+        Self { x: self.x.min(r.x), y: self.y.min(r.y) }
+    }
+
+    pub fn max(&self, r: Self) -> Self {
+        Self { x: self.x.max(r.x), y: self.y.max(r.y) }
+    }
+
+    pub fn abs(&self) -> Self {
+        Self { x: self.x.abs(), y: self.y.abs() }
+    }
+
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        let (s, c) = theta.sin_cos();
+        Self { x: r * c, y: r * s }
+    }
+
+    // radians in (-pi, pi], measured from the +x axis
+    pub fn angle(&self) -> f64 {
+        self.y.atan2(self.x)
+    }
+
+    // (length, angle), the inverse of `from_polar`
+    pub fn to_polar(&self) -> (f64, f64) {
+        (self.len(), self.angle())
+    }
+
+    pub fn rotate(&self, angle: f64) -> Self {
+        let (s, c) = angle.sin_cos();
+        Self { x: self.x * c - self.y * s, y: self.x * s + self.y * c }
+    }
+
+    pub fn len_sq(&self) -> f64 {
+        self.dot(*self)
+    }
+
+    pub fn len(&self) -> f64 {
+        self.len_sq().sqrt()
+    }
+
+    pub fn dist(&self, r: Self) -> f64 {
+        self.sub(r).len()
+    }
+
+    pub fn dot(&self, r: Self) -> f64 {
+        self.x * r.x + self.y * r.y
+    }
+
+    // zero vector stays zero instead of turning into NaNs
+    pub fn normalize(&self) -> Self {
+        let l = self.len();
+        if l > 0.0 { *self / l } else { *self }
+    }
+
+    pub fn lerp(&self, r: Self, t: f64) -> Self {
+        *self + (r - *self) * t
+    }
+
+    // (-y, x), i.e. rotated by 90 degrees
+    pub fn perp(&self) -> Self {
+        Pos { x: -self.y, y: self.x }
+    }
+}
+
+impl Add for Pos {
+    type Output = Pos;
+    fn add(self, r: Pos) -> Pos {
+        Pos { x: self.x + r.x, y: self.y + r.y }
+    }
+}
+
+impl Sub for Pos {
+    type Output = Pos;
+    fn sub(self, r: Pos) -> Pos {
+        Pos { x: self.x - r.x, y: self.y - r.y }
+    }
+}
+
+impl Mul<f64> for Pos {
+    type Output = Pos;
+    fn mul(self, f: f64) -> Pos {
+        Pos { x: self.x * f, y: self.y * f }
+    }
+}
+
+impl Div<f64> for Pos {
+    type Output = Pos;
+    fn div(self, f: f64) -> Pos {
+        Pos { x: self.x / f, y: self.y / f }
+    }
+}
+
+impl Neg for Pos {
+    type Output = Pos;
+    fn neg(self) -> Pos {
+        Pos { x: -self.x, y: -self.y }
+    }
+}
+
+impl AddAssign for Pos {
+    fn add_assign(&mut self, r: Pos) {
+        *self = *self + r;
+    }
+}
+
+impl SubAssign for Pos {
+    fn sub_assign(&mut self, r: Pos) {
+        *self = *self - r;
+    }
+}
+
+impl MulAssign<f64> for Pos {
+    fn mul_assign(&mut self, f: f64) {
+        *self = *self * f;
+    }
+}
+
+// endregion
+// region Rect
+
+// axis aligned, `min` inclusive and `max` exclusive
+#[derive(Copy, Clone, Default)]
+pub struct Rect {
+    pub min: Pos,
+    pub max: Pos,
+}
+
+impl Rect {
+    pub fn new(min: Pos, max: Pos) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_size(origin: Pos, width: f64, height: f64) -> Self {
+        Self::new(origin, origin + Pos::new(width, height))
+    }
+
+    pub fn around(center: Pos, radius: f64) -> Self {
+        let r = Pos::new(radius, radius);
+        Self::new(center - r, center + r)
+    }
+
+    pub fn width(&self) -> f64 { (self.max.x - self.min.x).max(0.0) }
+    pub fn height(&self) -> f64 { (self.max.y - self.min.y).max(0.0) }
+
+    pub fn is_empty(&self) -> bool {
+        self.width() <= 0.0 || self.height() <= 0.0
+    }
+
+    pub fn contains(&self, p: Pos) -> bool {
+        p.x >= self.min.x && p.x < self.max.x && p.y >= self.min.y && p.y < self.max.y
+    }
+
+    // nearest point of the closed rect, so `max` itself can come back and
+    // `contains` may reject the result; distances to the rect want exactly that
+    pub fn clamp(&self, p: Pos) -> Pos {
+        p.max(self.min).min(self.max)
+    }
+
+    pub fn intersection(&self, r: Self) -> Option<Self> {
+        let i = Self::new(self.min.max(r.min), self.max.min(r.max));
+        if i.is_empty() { None } else { Some(i) }
+    }
+
+    pub fn union(&self, r: Self) -> Self {
+        Self::new(self.min.min(r.min), self.max.max(r.max))
+    }
+
+    // integer pixel coordinates of every pixel the rect touches, row by row;
+    // negative coordinates are skipped
+    pub fn pixels(&self) -> impl Iterator<Item=(usize, usize)> {
+        let (x0, x1) = (self.min.x.floor().max(0.0) as usize, self.max.x.ceil().max(0.0) as usize);
+        let (y0, y1) = (self.min.y.floor().max(0.0) as usize, self.max.y.ceil().max(0.0) as usize);
+        (y0..y1).flat_map(move |y| (x0..x1).map(move |x| (x, y)))
+    }
+
+    // the columns and rows of a `width` x `height` frame that `pixels` covers
+    pub fn pixel_ranges(&self, width: usize, height: usize) -> (Range<usize>, Range<usize>) {
+        let span = |min: f64, max: f64, size: usize| {
+            let start = (min.floor().max(0.0) as usize).min(size);
+            start..(max.ceil().max(0.0) as usize).clamp(start, size)
+        };
+        (span(self.min.x, self.max.x, width), span(self.min.y, self.max.y, height))
+    }
+}
+
+// endregion
+// region Mat
+
+// row-major 2x2 matrix, linear transforms of `Pos`
+#[derive(Copy, Clone)]
+pub struct Mat2 {
+    m: [[f64; 2]; 2],
+}
+
+impl Mat2 {
+    pub const IDENTITY: Self = Self { m: [[1.0, 0.0], [0.0, 1.0]] };
+
+    pub fn new(m00: f64, m01: f64, m10: f64, m11: f64) -> Self {
+        Self { m: [[m00, m01], [m10, m11]] }
+    }
+
+    pub fn rotation(angle: f64) -> Self {
+        let (s, c) = angle.sin_cos();
+        Self::new(c, -s, s, c)
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        Self::new(sx, 0.0, 0.0, sy)
+    }
+
+    pub fn shear(kx: f64, ky: f64) -> Self {
+        Self::new(1.0, kx, ky, 1.0)
+    }
+
+    pub fn determinant(&self) -> f64 {
+        self.m[0][0] * self.m[1][1] - self.m[0][1] * self.m[1][0]
+    }
+
+    pub fn inverse(&self) -> Option<Self> {
+        let d = self.determinant();
+        if d == 0.0 || !d.is_finite() {
+            return None;
+        }
+        let [[a, b], [c, e]] = self.m;
+        Some(Self::new(e / d, -b / d, -c / d, a / d))
+    }
+}
+
+impl Default for Mat2 {
+    fn default() -> Self { Self::IDENTITY }
+}
+
+impl Mul for Mat2 {
+    type Output = Self;
+    fn mul(self, r: Self) -> Self {
+        let (a, b) = (self.m, r.m);
+        Self::new(
+            a[0][0] * b[0][0] + a[0][1] * b[1][0], a[0][0] * b[0][1] + a[0][1] * b[1][1],
+            a[1][0] * b[0][0] + a[1][1] * b[1][0], a[1][0] * b[0][1] + a[1][1] * b[1][1],
+        )
+    }
+}
+
+impl Mul<Pos> for Mat2 {
+    type Output = Pos;
+    fn mul(self, p: Pos) -> Pos {
+        Pos::new(self.m[0][0] * p.x + self.m[0][1] * p.y, self.m[1][0] * p.x + self.m[1][1] * p.y)
+    }
+}
+
+// row-major 3x3 matrix in homogeneous coordinates, affine transforms of `Pos`;
+// the last row is always [0, 0, 1]
+#[derive(Copy, Clone)]
+pub struct Mat3 {
+    linear: Mat2,
+    offset: Pos,
+}
+
+impl Mat3 {
+    pub const IDENTITY: Self = Self { linear: Mat2::IDENTITY, offset: Pos { x: 0.0, y: 0.0 } };
+
+    pub fn new(linear: Mat2, offset: Pos) -> Self {
+        Self { linear, offset }
+    }
+
+    pub fn translation(offset: Pos) -> Self {
+        Self::new(Mat2::IDENTITY, offset)
+    }
+
+    pub fn rotation(angle: f64) -> Self {
+        Mat2::rotation(angle).into()
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        Mat2::scale(sx, sy).into()
+    }
+
+    pub fn shear(kx: f64, ky: f64) -> Self {
+        Mat2::shear(kx, ky).into()
+    }
+
+    // `m` applied around `center` instead of the origin
+    pub fn around(center: Pos, m: Mat2) -> Self {
+        Self::translation(center) * Self::from(m) * Self::translation(-center)
+    }
+
+    pub fn linear(&self) -> Mat2 { self.linear }
+    pub fn offset(&self) -> Pos { self.offset }
+
+    // applies only the linear part, for directions rather than points
+    pub fn transform_vector(&self, v: Pos) -> Pos {
+        self.linear * v
+    }
+
+    pub fn inverse(&self) -> Option<Self> {
+        let inv = self.linear.inverse()?;
+        Some(Self::new(inv, -(inv * self.offset)))
+    }
+}
+
+impl Default for Mat3 {
+    fn default() -> Self { Self::IDENTITY }
+}
+
+impl From<Mat2> for Mat3 {
+    fn from(linear: Mat2) -> Self { Self::new(linear, Pos::default()) }
+}
+
+impl Mul for Mat3 {
+    type Output = Self;
+    fn mul(self, r: Self) -> Self {
+        Self::new(self.linear * r.linear, self.linear * r.offset + self.offset)
+    }
+}
+
+impl Mul<Pos> for Mat3 {
+    type Output = Pos;
+    fn mul(self, p: Pos) -> Pos {
+        self.linear * p + self.offset
+    }
+}
+
+// endregion
+// region App
+
+#[cfg(feature = "windowed")]
+pub trait AppState: Sized {
+    type StartProps;
+    type StartErr: Debug;
+    // what other threads or futures send through `Driver::proxy`; `()` if
+    // nothing does
+    type UserEvent: 'static;
+    // how `Driver` creates the window that `start` gets
+    fn window_options(_props: &Self::StartProps) -> WindowOptions { WindowOptions::default() }
+    fn start(event_loop: &ActiveEventLoop, window: Window, props: Self::StartProps) -> Result<Self, Self::StartErr>;
+    type MouseMoveErr: Debug;
+    fn mousemove(&mut self, pos: Pos) -> Result<(), Self::MouseMoveErr>;
+    type RenderErr: Debug;
+    fn render(&mut self, delta: Duration) -> Result<(), Self::RenderErr>;
+    fn window(&self) -> &Window;
+    // called once before the process exits, whether the window was closed or
+    // the loop exited otherwise; the place to save anything worth keeping
+    fn stopping(&mut self) {}
+    // with a watchdog on `Driver`, stalls and render errors land here instead of
+    // panicking; returning false gives up, and `Driver::run` returns the
+    // incident once the loop has exited
+    fn recover(&mut self, _incident: &Incident) -> bool { false }
+    // when the frame being rendered off the event loop, e.g. on a
+    // `RenderThread`, started, if one is; the watchdog only times `render`
+    // otherwise, which doesn't wait for it
+    fn rendering_since(&self) -> Option<Instant> { None }
+    // the window got hidden (occluded or minimized) or shown again; no frames
+    // are rendered while it is hidden
+    fn visibility_changed(&mut self, _visible: bool) {}
+    // asked after every frame: how long the frames would keep looking like
+    // this one if no input came, `Duration::MAX` for indefinitely; the
+    // driver stops rendering until then or until the next event
+    fn idle(&self) -> Option<Duration> { None }
+    // the app went to the background, where mobile platforms destroy the
+    // window's surface; anything drawing to it must be dropped here and made
+    // anew in `resumed`. no frames are rendered in between
+    fn suspended(&mut self) {}
+    fn resumed(&mut self) {}
+    // the window gained or lost keyboard focus; see `Driver::focus_policy`
+    fn focus_changed(&mut self, _focused: bool) {}
+    // the window's inner size changed, in physical pixels
+    fn resized(&mut self, _width: u32, _height: u32) {}
+    // the window moved to a monitor with another DPI, or the setting changed;
+    // a `resized` usually follows
+    fn scale_changed(&mut self, _scale_factor: f64) {}
+    // a file is dragged over the window, or no longer is
+    fn file_hovered(&mut self, _path: Option<&Path>) {}
+    fn file_dropped(&mut self, _path: PathBuf) {}
+    // `pos` is in logical pixels, like the cursor's
+    fn touch(&mut self, _id: u64, _phase: TouchPhase, _pos: Pos) {}
+    // presses, repeats and releases while the window has keyboard focus
+    fn keyboard(&mut self, _event: &KeyEvent) {}
+    fn mouse_button(&mut self, _button: MouseButton, _pressed: bool) {}
+    fn mouse_wheel(&mut self, _delta: MouseScrollDelta) {}
+    // every cursor position since the last frame, oldest first, with how long
+    // before it they were reported; called before `render`, after `mousemove`
+    fn cursor_path(&mut self, _path: &[(Duration, Pos)]) {}
+    // unaccelerated mouse movement at the device's own rate, while focused;
+    // only with `Driver::raw_motion`
+    fn mouse_motion(&mut self, _delta: Pos) {}
+    // events of windows besides `window`, which the state opens itself, e.g. in
+    // `start` or right here; they get no frame loop, closing them doesn't exit,
+    // and their redraws are up to the state
+    fn other_window_event(&mut self, _event_loop: &ActiveEventLoop, _id: WindowId, _event: WindowEvent) {}
+    // an event sent through `Driver::proxy`; request a redraw if it changes anything
+    fn user_event(&mut self, _event: Self::UserEvent) {}
+
+    // switches the window to `mode` on the `monitor`th of the available
+    // monitors, or the one it is on if that's None; the new size arrives
+    // through `resized` like any other
+    fn set_screen_mode(&self, mode: ScreenMode, monitor: Option<usize>) {
+        let window = self.window();
+        let monitor = monitor.and_then(|i| window.available_monitors().nth(i)).or_else(|| window.current_monitor());
+        // the largest mode, and the fastest of those
+        let video = |m: &MonitorHandle| m.video_modes()
+            .max_by_key(|v| (v.size().width * v.size().height, v.refresh_rate_millihertz()));
+        window.set_fullscreen(match mode {
+            ScreenMode::Windowed => None,
+            ScreenMode::Exclusive => match monitor.as_ref().and_then(video) {
+                Some(v) => Some(Fullscreen::Exclusive(v)),
+                None => Some(Fullscreen::Borderless(monitor)),
+            },
+            ScreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+        });
+    }
+
+    // hides the OS cursor over the window, leaving the warp to show where it is
+    fn set_cursor_hidden(&self, hidden: bool) {
+        self.window().set_cursor_visible(!hidden);
+    }
+
+    // keeps the cursor within the window, e.g. for kiosks; not every platform
+    // can, and the others return an error
+    fn confine_cursor(&self, confine: bool) -> Result<(), ExternalError> {
+        self.window().set_cursor_grab(if confine { CursorGrabMode::Confined } else { CursorGrabMode::None })
+    }
+
+    fn screen_mode(&self) -> ScreenMode {
+        match self.window().fullscreen() {
+            None => ScreenMode::Windowed,
+            Some(Fullscreen::Borderless(_)) => ScreenMode::Borderless,
+            Some(Fullscreen::Exclusive(_)) => ScreenMode::Exclusive,
+        }
+    }
+}
+
+#[cfg(feature = "windowed")]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ScreenMode {
+    #[default]
+    Windowed,
+    // a window covering the monitor, at the desktop's resolution
+    Borderless,
+    // takes over the monitor at its largest video mode; not on every platform
+    Exclusive,
+}
+
+// the attributes of the window `Driver` opens, see `AppState::window_options`
+#[cfg(feature = "windowed")]
+#[derive(Clone, Debug, Default)]
+pub struct WindowOptions {
+    attributes: WindowAttributes,
+}
+
+#[cfg(feature = "windowed")]
+impl WindowOptions {
+    pub fn title(self, title: &str) -> Self {
+        Self { attributes: self.attributes.with_title(title) }
+    }
+
+    // shrunk to at most 256 pixels a side
+    pub fn icon(self, icon: &Image) -> Self {
+        let icon = match icon.fit(256, 256) {
+            (w, h) if w < icon.width || h < icon.height => icon.resize(w, h),
+            _ => icon.clone(),
+        };
+        let icon = Icon::from_rgba(icon.data, icon.width as u32, icon.height as u32).ok();
+        Self { attributes: self.attributes.with_window_icon(icon) }
+    }
+
+    pub fn decorations(self, decorations: bool) -> Self {
+        Self { attributes: self.attributes.with_decorations(decorations) }
+    }
+
+    pub fn always_on_top(self, on_top: bool) -> Self {
+        let level = if on_top { WindowLevel::AlwaysOnTop } else { WindowLevel::Normal };
+        Self { attributes: self.attributes.with_window_level(level) }
+    }
+
+    // lets the alpha channel of the frame through, where the platform allows
+    pub fn transparent(self, transparent: bool) -> Self {
+        Self { attributes: self.attributes.with_transparent(transparent) }
+    }
+
+    // for everything else winit offers
+    pub fn map(self, f: impl FnOnce(WindowAttributes) -> WindowAttributes) -> Self {
+        Self { attributes: f(self.attributes) }
+    }
+
+    pub fn attributes(&self) -> WindowAttributes { self.attributes.clone() }
+}
+
+// where a cursor at `physical` window pixels lands in the frame that `pixels`
+// scales (and maybe letterboxes) onto the window, clamped to its edges; the
+// positions `AppState` gets are logical, so multiply them by the scale factor
+#[cfg(feature = "pixels")]
+pub fn frame_pos(pixels: &Pixels, physical: Pos) -> Pos {
+    let (x, y) = pixels.window_pos_to_pixel((physical.x as f32, physical.y as f32))
+        .unwrap_or_else(|p| pixels.clamp_pixel_pos(p));
+    Pos::new(x as f64, y as f64)
+}
+
+// what the frame loop does while the window doesn't have focus
+#[cfg(feature = "windowed")]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum FocusPolicy {
+    #[default]
+    FullSpeed,
+    // at most this many frames per second
+    Capped(f64),
+    Paused,
+}
+
+// why `Driver::run` returned early
+#[cfg(feature = "windowed")]
+#[derive(Debug)]
+pub enum RunError {
+    EventLoop(EventLoopError),
+    // `AppState::recover` gave up on it
+    Unrecovered(Incident),
+    // the loop ran to the end, but `Driver::record` couldn't save to the path
+    Recording(PathBuf, std::io::Error),
+}
+
+#[cfg(feature = "windowed")]
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::EventLoop(e) => write!(f, "{}", e),
+            RunError::Unrecovered(incident) => write!(f, "could not recover from {:?}", incident),
+            RunError::Recording(path, e) => write!(f, "cannot save recording to {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(feature = "windowed")]
+impl std::error::Error for RunError {}
+
+#[cfg(feature = "windowed")]
+impl From<EventLoopError> for RunError {
+    fn from(e: EventLoopError) -> Self { RunError::EventLoop(e) }
+}
+
+#[cfg(feature = "windowed")]
+pub struct Driver<State: AppState> {
+    props: Option<State::StartProps>,
+    state: Option<State>,
+    elapsed: Elapsed,
+    watchdog_threshold: Option<Duration>,
+    on_stuck: Option<OnStuck>,
+    watchdog: Option<Watchdog>,
+    // what `AppState::recover` gave up on, for `run` to return
+    unrecovered: Option<Incident>,
+    occluded: bool,
+    minimized: bool,
+    suspended: bool,
+    focused: bool,
+    focus_policy: FocusPolicy,
+    fps_cap: Option<f64>,
+    // when the next frame is due while capped or idle
+    next_frame: Option<Instant>,
+    // paused by `AppState::idle` until an event comes
+    idle: bool,
+    recorder: Option<Recorder>,
+    // why the recording couldn't be saved, for `run` to return
+    unsaved: Option<std::io::Error>,
+    replayer: Option<Replayer>,
+    raw_motion: bool,
+    // cursor positions since the last frame
+    path: Vec<(Instant, Pos)>,
+    // made early by `proxy`, otherwise by `run`
+    event_loop: Option<EventLoop<State::UserEvent>>,
+    stopped: bool,
+    #[cfg(all(feature = "android", target_os = "android"))]
+    android_app: Option<AndroidApp>,
+}
+
+#[cfg(feature = "windowed")]
+impl<State: AppState> Driver<State> {
+    pub fn new(props: State::StartProps) -> Self {
+        Self {
+            props: Some(props),
+            state: None,
+            elapsed: Elapsed::new(),
+            watchdog_threshold: None,
+            on_stuck: None,
+            watchdog: None,
+            unrecovered: None,
+            occluded: false,
+            minimized: false,
+            suspended: false,
+            focused: true,
+            focus_policy: FocusPolicy::FullSpeed,
+            fps_cap: None,
+            next_frame: None,
+            idle: false,
+            recorder: None,
+            unsaved: None,
+            replayer: None,
+            raw_motion: false,
+            path: Vec::new(),
+            event_loop: None,
+            stopped: false,
+            #[cfg(all(feature = "android", target_os = "android"))]
+            android_app: None,
+        }
+    }
+
+    pub fn focus_policy(self, focus_policy: FocusPolicy) -> Self {
+        Self { focus_policy, ..self }
+    }
+
+    // at most `fps` frames per second, focused or not
+    pub fn fps_cap(self, fps: Option<f64>) -> Self {
+        Self { fps_cap: fps, ..self }
+    }
+
+    // the parts of `config` that concern the frame loop
+    pub fn config(self, config: &config::Config) -> Self {
+        self.fps_cap(config.render.fps)
+    }
+
+    // watches for frames slower than `threshold` and hands them and render
+    // errors to `AppState::recover`
+    pub fn watchdog(self, threshold: Duration) -> Self {
+        Self { watchdog_threshold: Some(threshold), ..self }
+    }
+
+    // called from the watchdog's own thread while a frame is still rendering
+    // past the threshold, once per such frame
+    pub fn on_stuck(self, on_stuck: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        Self { on_stuck: Some(Arc::new(on_stuck)), ..self }
+    }
+
+    // records cursor moves and mouse buttons, saving them when the loop exits;
+    // `run` returns an error if that fails
+    pub fn record(self, recorder: Option<Recorder>) -> Self {
+        Self { recorder, ..self }
+    }
+
+    // feeds a recording to the state instead of the real mouse
+    pub fn replay(self, replayer: Option<Replayer>) -> Self {
+        Self { replayer, ..self }
+    }
+
+    // passes raw mouse deltas to `AppState::mouse_motion`, besides the cursor
+    pub fn raw_motion(self, raw_motion: bool) -> Self {
+        Self { raw_motion, ..self }
+    }
+
+    // the app `android_main` was given, which the event loop needs there
+    #[cfg(all(feature = "android", target_os = "android"))]
+    pub fn android_app(self, app: AndroidApp) -> Self {
+        Self { android_app: Some(app), ..self }
+    }
+
+    // a handle for other threads to wake the loop with events for
+    // `AppState::user_event`; events before `AppState::start` are dropped,
+    // and sending fails once the loop has exited
+    pub fn proxy(&mut self) -> Result<EventLoopProxy<State::UserEvent>, EventLoopError> {
+        Ok(self.event_loop()?.create_proxy())
+    }
+
+    fn event_loop(&mut self) -> Result<&EventLoop<State::UserEvent>, EventLoopError> {
+        if self.event_loop.is_none() {
+            let mut builder = EventLoop::with_user_event();
+            #[cfg(all(feature = "android", target_os = "android"))]
+            if let Some(app) = self.android_app.take() {
+                builder.with_android_app(app);
+            }
+            self.event_loop = Some(builder.build()?);
+        }
+        Ok(self.event_loop.as_ref().unwrap())
+    }
+
+    pub fn run(&mut self) -> Result<(), RunError> {
+        self.event_loop()?;
+        let event_loop = self.event_loop.take().unwrap();
+        event_loop.set_control_flow(ControlFlow::Wait);
+        event_loop.run_app(self)?;
+        if let Some(incident) = self.unrecovered.take() {
+            return Err(RunError::Unrecovered(incident));
+        }
+        match (self.unsaved.take(), &self.recorder) {
+            (Some(e), Some(recorder)) => Err(RunError::Recording(recorder.path().to_path_buf(), e)),
+            _ => Ok(()),
+        }
+    }
+
+    // the browser's counterpart of `run`: hands the loop over to the page's own
+    // event loop and returns right away; redraws follow requestAnimationFrame
+    #[cfg(target_arch = "wasm32")]
+    pub fn spawn(mut self) -> Result<(), EventLoopError>
+    where
+        Self: 'static,
+    {
+        use winit::platform::web::EventLoopExtWebSys;
+        self.event_loop()?;
+        let event_loop = self.event_loop.take().unwrap();
+        event_loop.set_control_flow(ControlFlow::Wait);
+        event_loop.spawn_app(self);
+        Ok(())
+    }
+
+    fn visible(&self) -> bool { !self.occluded && !self.minimized && !self.suspended }
+
+    // whether frames should be rendered at all
+    fn active(&self) -> bool {
+        self.visible() && (self.focused || self.focus_policy != FocusPolicy::Paused)
+    }
+
+    // minimum time between frames under the fps cap and the current focus policy
+    fn frame_interval(&self) -> Option<Duration> {
+        let focus = match self.focus_policy {
+            FocusPolicy::Capped(fps) if !self.focused => Some(fps),
+            _ => None,
+        };
+        [self.fps_cap, focus].into_iter().flatten()
+            .filter(|&fps| fps > 0.0)
+            .map(|fps| Duration::from_secs_f64(1.0 / fps))
+            .max()
+    }
+
+    // applies `change` to the window flags, pausing or resuming the frame loop on a transition
+    fn set_flags(&mut self, change: impl FnOnce(&mut Self)) {
+        let (was_visible, was_active) = (self.visible(), self.active());
+        change(self);
+        let (visible, active) = (self.visible(), self.active());
+        if visible != was_visible {
+            if let Some(state) = self.state.as_mut() {
+                state.visibility_changed(visible);
+            }
+        }
+        if active == was_active {
+            return;
+        }
+        if active {
+            self.elapsed.resume();
+        } else {
+            self.elapsed.pause();
+        }
+        if active {
+            if let Some(state) = self.state.as_ref() {
+                state.window().request_redraw();
+            }
+        }
+    }
+
+    // ends an `AppState::idle` pause, since the event may change what is drawn
+    fn wake(&mut self, event_loop: &ActiveEventLoop) {
+        if !core::mem::take(&mut self.idle) {
+            return;
+        }
+        self.next_frame = None;
+        event_loop.set_control_flow(ControlFlow::Wait);
+        if let Some(state) = self.state.as_ref().filter(|_| self.active()) {
+            state.window().request_redraw();
+        }
+    }
+
+    // runs `AppState::stopping` and saves the recording, once
+    fn stop(&mut self) {
+        if self.stopped {
+            return;
+        }
+        self.stopped = true;
+        if let Some(state) = self.state.as_mut() {
+            state.stopping();
+        }
+        if let Some(recorder) = &self.recorder {
+            self.unsaved = recorder.save().err();
+        }
+    }
+}
+
+#[cfg(feature = "windowed")]
+impl<State: AppState> ApplicationHandler<State::UserEvent> for Driver<State> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_none() {
+            let props = self.props.take().unwrap();
+            let window = event_loop.create_window(State::window_options(&props).attributes()).unwrap();
+            let state = State::start(event_loop, window, props).unwrap();
+            self.state = Some(state);
+            self.watchdog = self.watchdog_threshold.map(|t| Watchdog::new(t, self.on_stuck.clone()));
+            self.elapsed.elapsed();
+            self.state.as_ref().unwrap().window().request_redraw();
+            return;
+        }
+        if let Some(state) = self.state.as_mut() {
+            state.resumed();
+        }
+        self.set_flags(|d| d.suspended = false);
+    }
+
+    // on Android the surface goes away until the next `resumed`
+    fn suspended(&mut self, _: &ActiveEventLoop) {
+        if let Some(state) = self.state.as_mut() {
+            state.suspended();
+        }
+        self.set_flags(|d| d.suspended = true);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        if let Some(state) = self.state.as_mut().filter(|s| s.window().id() != id) {
+            return state.other_window_event(event_loop, id, event);
+        }
+        if !matches!(event, WindowEvent::RedrawRequested) {
+            self.wake(event_loop);
+        }
+        match event {
+            WindowEvent::RedrawRequested => {
+                self.idle = false;
+                let frame_start = Instant::now();
+                let delta = self.elapsed.elapsed();
+                let (active, interval) = (self.active(), self.frame_interval());
+                let state = self.state.as_mut().unwrap();
+                if !self.path.is_empty() {
+                    let path: Vec<_> = self.path.drain(..).map(|(t, p)| (frame_start.saturating_duration_since(t), p)).collect();
+                    state.cursor_path(&path);
+                }
+                if let Some(replayer) = &mut self.replayer {
+                    for input in replayer.advance(delta) {
+                        match input {
+                            Input::Move(p) => state.mousemove(p).unwrap(),
+                            Input::Button { button, pressed } => state.mouse_button(button, pressed),
+                        }
+                    }
+                }
+                match &self.watchdog {
+                    None => state.render(delta).unwrap(),
+                    Some(watchdog) => {
+                        watchdog.frame_started();
+                        let result = state.render(delta);
+                        watchdog.frame_finished();
+                        let incident = match result {
+                            Err(e) => Some(Incident::RenderError(format!("{:?}", e))),
+                            Ok(()) => watchdog.take_stall()
+                                .or_else(|| watchdog.stalled_elsewhere(state.rendering_since()))
+                                .map(Incident::Stall),
+                        };
+                        if let Some(incident) = incident {
+                            watchdog.incident();
+                            if !state.recover(&incident) {
+                                self.unrecovered = Some(incident);
+                                self.stop();
+                                event_loop.exit();
+                                return;
+                            }
+                        }
+                    }
+                }
+                // a replay moves the cursor from here, so it never idles
+                let replaying = self.replayer.as_ref().is_some_and(|r| !r.is_done());
+                match (interval, state.idle().filter(|_| !replaying)) {
+                    _ if !active => (),
+                    (_, Some(idle)) => {
+                        self.idle = true;
+                        self.next_frame = frame_start.checked_add(idle);
+                        event_loop.set_control_flow(self.next_frame.map_or(ControlFlow::Wait, ControlFlow::WaitUntil));
+                    }
+                    (None, None) => state.window().request_redraw(),
+                    // `about_to_wait` asks for it once it's due
+                    (Some(interval), None) => {
+                        let due = frame_start + interval;
+                        self.next_frame = Some(due);
+                        event_loop.set_control_flow(ControlFlow::WaitUntil(due));
+                    }
+                }
+            }
+            WindowEvent::Focused(focused) => {
+                self.set_flags(|d| d.focused = focused);
+                if let Some(state) = self.state.as_mut() {
+                    state.focus_changed(focused);
+                }
+                // pick up full speed right away instead of waiting out the cap
+                if self.frame_interval().is_none() && self.next_frame.take().is_some() {
+                    event_loop.set_control_flow(ControlFlow::Wait);
+                    if let Some(state) = self.state.as_ref() {
+                        state.window().request_redraw();
+                    }
+                }
+            }
+            WindowEvent::Occluded(occluded) => self.set_flags(|d| d.occluded = occluded),
+            // some platforms only report minimizing as a resize to zero
+            WindowEvent::Resized(size) => {
+                let minimized = size.width == 0 || size.height == 0
+                    || self.state.as_ref().and_then(|s| s.window().is_minimized()).unwrap_or(false);
+                self.set_flags(|d| d.minimized = minimized);
+                if let Some(state) = self.state.as_mut().filter(|_| !minimized) {
+                    state.resized(size.width, size.height);
+                }
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => self.state.as_mut().unwrap().scale_changed(scale_factor),
+            WindowEvent::HoveredFile(path) => self.state.as_mut().unwrap().file_hovered(Some(&path)),
+            WindowEvent::HoveredFileCancelled => self.state.as_mut().unwrap().file_hovered(None),
+            WindowEvent::DroppedFile(path) => {
+                let state = self.state.as_mut().unwrap();
+                state.file_hovered(None);
+                state.file_dropped(path);
+            }
+            WindowEvent::KeyboardInput { event, .. } => self.state.as_mut().unwrap().keyboard(&event),
+            // the replay has the mouse to itself while it runs
+            WindowEvent::MouseInput { .. } | WindowEvent::CursorMoved { .. }
+                if self.replayer.as_ref().is_some_and(|r| !r.is_done()) => (),
+            WindowEvent::MouseInput { state: pressed, button, .. } => {
+                let pressed = pressed == ElementState::Pressed;
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.record(Input::Button { button, pressed });
+                }
+                self.state.as_mut().unwrap().mouse_button(button, pressed);
+            }
+            WindowEvent::MouseWheel { delta, .. } => self.state.as_mut().unwrap().mouse_wheel(delta),
+            WindowEvent::Touch(Touch { phase, location, id, .. }) => {
+                let state = self.state.as_mut().unwrap();
+                let p = location.to_logical(state.window().scale_factor());
+                state.touch(id, phase, Pos::new(p.x, p.y));
+            }
+            WindowEvent::CursorMoved { device_id: _, position: pos } => {
+                let active = self.active();
+                let state = self.state.as_mut().unwrap();
+                let p = pos.to_logical(state.window().scale_factor());
+                let p = Pos::new(p.x, p.y);
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.record(Input::Move(p));
+                }
+                // nothing drains it while no frames are rendered
+                if active {
+                    self.path.push((Instant::now(), p));
+                }
+                state.mousemove(p).unwrap();
+            }
+            WindowEvent::CloseRequested => {
+                self.stop();
+                event_loop.exit();
+            }
+            _ => (),
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(due) = self.next_frame {
+            if Instant::now() < due {
+                return;
+            }
+            self.next_frame = None;
+            event_loop.set_control_flow(ControlFlow::Wait);
+            if let Some(state) = self.state.as_ref().filter(|_| self.active()) {
+                state.window().request_redraw();
+            }
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: State::UserEvent) {
+        self.wake(event_loop);
+        if let Some(state) = self.state.as_mut() {
+            state.user_event(event);
+        }
+    }
+
+    fn device_event(&mut self, event_loop: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
+        let replaying = self.replayer.as_ref().is_some_and(|r| !r.is_done());
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            if self.raw_motion && self.focused && !replaying && self.state.is_some() {
+                self.wake(event_loop);
+                self.state.as_mut().unwrap().mouse_motion(Pos::new(x, y));
+            }
+        }
+    }
+
+    fn exiting(&mut self, _: &ActiveEventLoop) {
+        self.stop();
+    }
+}
+
+// endregion
+// region Color
+
+// alpha is on the same 0..255 scale as the colors, and the arithmetic
+// carries it along with them; the default is transparent black
+#[derive(Copy, Clone, Default)]
+pub struct Color {
+    red: f64,
+    green: f64,
+    blue: f64,
+    alpha: f64,
+    mode: AlphaMode,
+}
+
+impl Color {
+    pub const TRANSPARENT: Self = Self::rgba(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Self = Self::rgba(0.0, 0.0, 0.0, 255.0);
+    pub const WHITE: Self = Self::rgba(255.0, 255.0, 255.0, 255.0);
+    pub const GRAY: Self = Self::rgba(128.0, 128.0, 128.0, 255.0);
+    pub const RED: Self = Self::rgba(255.0, 0.0, 0.0, 255.0);
+    pub const GREEN: Self = Self::rgba(0.0, 255.0, 0.0, 255.0);
+    pub const BLUE: Self = Self::rgba(0.0, 0.0, 255.0, 255.0);
+    pub const YELLOW: Self = Self::rgba(255.0, 255.0, 0.0, 255.0);
+    pub const CYAN: Self = Self::rgba(0.0, 255.0, 255.0, 255.0);
+    pub const MAGENTA: Self = Self::rgba(255.0, 0.0, 255.0, 255.0);
+
+    // opaque
+    pub fn new(red: f64, green: f64, blue: f64) -> Self {
+        Self::rgba(red, green, blue, 255.0)
+    }
+
+    // straight alpha
+    pub const fn rgba(red: f64, green: f64, blue: f64, alpha: f64) -> Self {
+        Self { red, green, blue, alpha, mode: AlphaMode::Straight }
+    }
+
+    pub fn alpha(self) -> f64 { self.alpha }
+
+    pub fn with_alpha(self, alpha: f64) -> Self {
+        Self { alpha, ..self }
+    }
+
+    pub fn alpha_mode(self) -> AlphaMode { self.mode }
+
+    // says what the channels hold without changing them, see `to_premultiplied`
+    pub fn with_alpha_mode(self, mode: AlphaMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    pub fn to_premultiplied(self) -> Self {
+        if self.mode == AlphaMode::Premultiplied {
+            return self;
+        }
+        let a = self.alpha / 255.0;
+        Self { red: self.red * a, green: self.green * a, blue: self.blue * a, alpha: self.alpha, mode: AlphaMode::Premultiplied }
+    }
+
+    // fully transparent colors come out black
+    pub fn to_straight(self) -> Self {
+        if self.mode == AlphaMode::Straight {
+            return self;
+        }
+        let a = if self.alpha > 0.0 { 255.0 / self.alpha } else { 0.0 };
+        Self { red: self.red * a, green: self.green * a, blue: self.blue * a, alpha: self.alpha, mode: AlphaMode::Straight }
+    }
+
+    // Porter-Duff source over: `self` in front of `background`, in the
+    // alpha mode of `self`
+    pub fn over(self, background: Self) -> Self {
+        let (s, b) = (self.to_premultiplied(), background.to_premultiplied());
+        let k = 1.0 - (s.alpha / 255.0).clamp(0.0, 1.0);
+        let out = Self {
+            red: s.red + b.red * k,
+            green: s.green + b.green * k,
+            blue: s.blue + b.blue * k,
+            alpha: s.alpha + b.alpha * k,
+            mode: AlphaMode::Premultiplied,
+        };
+        if self.mode == AlphaMode::Straight { out.to_straight() } else { out }
+    }
+
+    // "#rrggbb" or "#rgb", the '#' being optional
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize, n: usize| u8::from_str_radix(&hex[i..i + n], 16).ok();
+        let (r, g, b) = match hex.len() {
+            6 => (digit(0, 2)?, digit(2, 2)?, digit(4, 2)?),
+            3 => (digit(0, 1)? * 17, digit(1, 1)? * 17, digit(2, 1)? * 17),
+            _ => return None,
+        };
+        Some(Self::new(r as f64, g as f64, b as f64))
+    }
+
+    // hue in degrees [0, 360), saturation and value in [0, 1]; channels are on the 0..255 scale
+    pub fn to_hsv(self) -> (f64, f64, f64) {
+        let (max, min, hue) = self.hue();
+        let s = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (hue, s, max / 255.0)
+    }
+
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Self {
+        let c = v * s;
+        Self::from_hue_chroma(h, c, v - c)
+    }
+
+    // hue in degrees [0, 360), saturation and lightness in [0, 1]
+    pub fn to_hsl(self) -> (f64, f64, f64) {
+        let (max, min, hue) = self.hue();
+        let (max, min) = (max / 255.0, min / 255.0);
+        let l = (max + min) / 2.0;
+        let d = max - min;
+        let s = if d > 0.0 { d / (1.0 - (2.0 * l - 1.0).abs()) } else { 0.0 };
+        (hue, s, l)
+    }
+
+    pub fn from_hsl(h: f64, s: f64, l: f64) -> Self {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_hue_chroma(h, c, l - c / 2.0)
+    }
+
+    // (max, min, hue in degrees) shared by the HSV and HSL conversions
+    fn hue(self) -> (f64, f64, f64) {
+        let (r, g, b) = (self.red, self.green, self.blue);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let d = max - min;
+        let h = if d <= 0.0 {
+            0.0
+        } else if max == r {
+            (g - b) / d
+        } else if max == g {
+            (b - r) / d + 2.0
+        } else {
+            (r - g) / d + 4.0
+        };
+        let h = h * 60.0;
+        (max, min, if h < 0.0 { h + 360.0 } else { h })
+    }
+
+    // chroma `c` and offset `m` are in [0, 1]
+    fn from_hue_chroma(h: f64, c: f64, m: f64) -> Self {
+        let h = h % 360.0;
+        let h = (if h < 0.0 { h + 360.0 } else { h }) / 60.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        Self::new((r + m) * 255.0, (g + m) * 255.0, (b + m) * 255.0)
+    }
+
+    // sRGB transfer function per channel, on the 0..255 scale
+    pub fn to_linear(self) -> Self {
+        Self { red: srgb_to_linear(self.red), green: srgb_to_linear(self.green), blue: srgb_to_linear(self.blue), ..self }
+    }
+
+    pub fn to_srgb(self) -> Self {
+        Self { red: linear_to_srgb(self.red), green: linear_to_srgb(self.green), blue: linear_to_srgb(self.blue), ..self }
+    }
+
+    pub fn lerp(self, r: Self, t: f64) -> Self {
+        self + (r - self) * t
+    }
+
+    // interpolates perceptually, avoiding the muddy midpoints of `lerp`
+    pub fn lerp_oklab(self, r: Self, t: f64) -> Self {
+        let (l0, a0, b0) = self.to_oklab();
+        let (l1, a1, b1) = r.to_oklab();
+        Self::from_oklab(l0 + (l1 - l0) * t, a0 + (a1 - a0) * t, b0 + (b1 - b0) * t)
+    }
+
+    // (L, a, b) with L in [0, 1]; `self` is taken as sRGB-encoded
+    pub fn to_oklab(self) -> (f64, f64, f64) {
+        let c = self.to_linear() / 255.0;
+        let (r, g, b) = (c.red, c.green, c.blue);
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        (
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        )
+    }
+
+    pub fn from_oklab(l: f64, a: f64, b: f64) -> Self {
+        let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+        let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+        let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+        let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+        let linear = Self::new(
+            4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+        );
+        (linear * 255.0).to_srgb()
+    }
+
+    // (L, chroma, hue in degrees [0, 360))
+    pub fn to_oklch(self) -> (f64, f64, f64) {
+        let (l, a, b) = self.to_oklab();
+        let h = b.atan2(a).to_degrees();
+        (l, a.hypot(b), if h < 0.0 { h + 360.0 } else { h })
+    }
+
+    pub fn from_oklch(l: f64, c: f64, h: f64) -> Self {
+        let (s, co) = h.to_radians().sin_cos();
+        Self::from_oklab(l, c * co, c * s)
+    }
+
+    // channels limited to 0..=255, NaN becomes 0
+    pub fn clamp(self) -> Self {
+        let f = |c: f64| if c.is_nan() { 0.0 } else { c.clamp(0.0, 255.0) };
+        Self { red: f(self.red), green: f(self.green), blue: f(self.blue), alpha: f(self.alpha), mode: self.mode }
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.red.is_finite() && self.green.is_finite() && self.blue.is_finite() && self.alpha.is_finite()
+    }
+
+    // red, green and blue as they are, whatever the alpha mode; out-of-range
+    // channels saturate instead of depending on cast semantics
+    pub fn write_bytes(self, p: &mut [u8]) {
+        self.write_bytes_dithered(p, 0.0);
+    }
+
+    // adds `threshold` in [0, 1) before truncating, see `Dither::threshold`
+    pub fn write_bytes_dithered(self, p: &mut [u8], threshold: f64) {
+        let Self { red, green, blue, .. } = (self + Self::rgba(threshold, threshold, threshold, 0.0)).clamp();
+        p[0] = red.floor() as u8;
+        p[1] = green.floor() as u8;
+        p[2] = blue.floor() as u8;
+    }
+}
+
+impl Add for Color {
+    type Output = Self;
+    fn add(self, r: Self) -> Self {
+        Self {
+            red: self.red + r.red,
+            green: self.green + r.green,
+            blue: self.blue + r.blue,
+            alpha: self.alpha + r.alpha,
+            mode: self.mode,
+        }
+    }
+}
+
+impl Sub for Color {
+    type Output = Self;
+    fn sub(self, r: Self) -> Self {
+        Self {
+            red: self.red - r.red,
+            green: self.green - r.green,
+            blue: self.blue - r.blue,
+            alpha: self.alpha - r.alpha,
+            mode: self.mode,
+        }
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, r: Self) {
+        *self = *self + r;
+    }
+}
+
+impl Mul<f64> for Color {
+    type Output = Self;
+    fn mul(self, f: f64) -> Self {
+        Self {
+            red: self.red * f,
+            green: self.green * f,
+            blue: self.blue * f,
+            alpha: self.alpha * f,
+            mode: self.mode,
+        }
+    }
+}
+
+impl Div<f64> for Color {
+    type Output = Self;
+    fn div(self, f: f64) -> Self {
+        Self {
+            red: self.red / f,
+            green: self.green / f,
+            blue: self.blue / f,
+            alpha: self.alpha / f,
+            mode: self.mode,
+        }
+    }
+}
+
+impl From<[f64; 3]> for Color {
+    fn from([red, green, blue]: [f64; 3]) -> Self { Self::new(red, green, blue) }
+}
+
+impl From<[f64; 4]> for Color {
+    fn from([red, green, blue, alpha]: [f64; 4]) -> Self { Self::rgba(red, green, blue, alpha) }
+}
+
+impl From<Color> for [f64; 3] {
+    fn from(c: Color) -> Self { [c.red, c.green, c.blue] }
+}
+
+impl From<Color> for [f64; 4] {
+    fn from(c: Color) -> Self { [c.red, c.green, c.blue, c.alpha] }
+}
+
+// endregion
+// region Blend
+
+// separable blend modes, as in the W3C compositing spec
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    SoftLight,
+    Additive,
+    Lighten,
+    Darken,
+}
+
+impl BlendMode {
+    // `b` is the backdrop and `s` the source channel, both in [0, 1]
+    fn apply(self, b: f64, s: f64) -> f64 {
+        match self {
+            BlendMode::Normal => s,
+            BlendMode::Multiply => b * s,
+            BlendMode::Screen => b + s - b * s,
+            BlendMode::Overlay => {
+                if b <= 0.5 { 2.0 * b * s } else { 1.0 - 2.0 * (1.0 - b) * (1.0 - s) }
+            }
+            BlendMode::SoftLight => {
+                if s <= 0.5 {
+                    b - (1.0 - 2.0 * s) * b * (1.0 - b)
+                } else {
+                    let d = if b <= 0.25 { ((16.0 * b - 12.0) * b + 4.0) * b } else { b.sqrt() };
+                    b + (2.0 * s - 1.0) * (d - b)
+                }
+            }
+            BlendMode::Additive => (b + s).min(1.0),
+            BlendMode::Lighten => b.max(s),
+            BlendMode::Darken => b.min(s),
+        }
+    }
+}
+
+impl Color {
+    // composites `other` over `self` with `mode`, then mixes the result back in by `alpha`
+    pub fn blend(self, other: Self, mode: BlendMode, alpha: f64) -> Self {
+        let f = |b: f64, s: f64| {
+            let (b, s) = ((b / 255.0).clamp(0.0, 1.0), (s / 255.0).clamp(0.0, 1.0));
+            255.0 * (b + (mode.apply(b, s) - b) * alpha)
+        };
+        Self { red: f(self.red, other.red), green: f(self.green, other.green), blue: f(self.blue, other.blue), ..self }
+    }
+
+    // `over` with the colors mixed by `mode` first, as the W3C compositing
+    // spec has it: the mode takes over as far as `background` is opaque, so
+    // it leaves transparent areas to plain source over
+    pub fn over_with(self, background: Self, mode: BlendMode) -> Self {
+        if mode == BlendMode::Normal {
+            return self.over(background);
+        }
+        let (s, b) = (self.to_straight(), background.to_straight());
+        let backdrop = (b.alpha / 255.0).clamp(0.0, 1.0);
+        let f = |b: f64, s: f64| {
+            let blended = 255.0 * mode.apply((b / 255.0).clamp(0.0, 1.0), (s / 255.0).clamp(0.0, 1.0));
+            s + (blended - s) * backdrop
+        };
+        let out = Self { red: f(b.red, s.red), green: f(b.green, s.green), blue: f(b.blue, s.blue), ..s }.over(background);
+        if self.mode == AlphaMode::Premultiplied { out.to_premultiplied() } else { out }
+    }
+}
+
+// composites `img` over an RGBA `frame` that is `width` pixels wide, with its
+// top left corner at (x, y) and clipped at the edges, such as a transparent
+// foreground over the warped background; see `Image::blit` for the source
+pub fn draw_image(frame: &mut [u8], width: usize, x: isize, y: isize, img: &Image, mode: BlendMode) {
+    composite(frame, width, AlphaMode::Straight, (x, y), img, mode);
+}
+
+// `img` over `dst`, whose pixels are in `alpha` mode
+fn composite(dst: &mut [u8], width: usize, alpha: AlphaMode, (x, y): (isize, isize), img: &Image, mode: BlendMode) {
+    let height = (dst.len() / 4).checked_div(width).unwrap_or(0);
+    let clip = |v: isize, max: usize| v.clamp(0, max as isize) as usize;
+    let rgba = |p: &[u8], alpha| Color::rgba(p[0] as f64, p[1] as f64, p[2] as f64, p[3] as f64).with_alpha_mode(alpha);
+    for py in clip(y, height)..clip(y.saturating_add(img.height as isize), height) {
+        for px in clip(x, width)..clip(x.saturating_add(img.width as isize), width) {
+            let src = &img.data[4 * ((px as isize - x) as usize + (py as isize - y) as usize * img.width)..][..4];
+            if src[3] == 0 && img.alpha == AlphaMode::Straight {
+                continue;
+            }
+            let p = &mut dst[4 * (px + py * width)..][..4];
+            let out = rgba(src, img.alpha).over_with(rgba(p, alpha), mode);
+            let out = if alpha == AlphaMode::Straight { out.to_straight() } else { out.to_premultiplied() };
+            out.write_bytes_dithered(p, 0.5);
+            p[3] = (out.alpha + 0.5) as u8;
+        }
+    }
+}
+
+// endregion
+// region Warp
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WarpParams {
+    // how far from the cursor pixels get dragged along
+    pub radius: f64,
+    // displacement per unit of cursor velocity at the cursor itself
+    pub strength: f64,
+    // samples averaged per channel, smeared along the displacement
+    pub taps: usize,
+    // how far along the displacement the green samples start, and how far
+    // the taps spread from there
+    pub offset: f64,
+    pub spread: f64,
+    // red samples start this much before green, blue this much after
+    pub separation: f64,
+}
+
+impl Default for WarpParams {
+    fn default() -> Self {
+        Self { radius: 190.0, strength: -1.5, taps: 10, offset: 0.2, spread: 0.05, separation: 0.025 }
+    }
+}
+
+#[inline(always)]
+pub fn warp_displacement(p: Pos, l: Pos, v: Pos) -> Pos {
+    warp_displacement_with(&WarpParams::default(), p, l, v)
+}
+
+#[inline(always)]
+pub fn warp_displacement_with(w: &WarpParams, p: Pos, l: Pos, v: Pos) -> Pos {
+    let m = 1.0 - l.dist(p) / w.radius;
+    let m = m.clamp(0.0, 1.0);
+    v * m * m * w.strength
+}
+
+#[inline(always)]
+pub fn warp(a: &Image, p: Pos, l: Pos, v: Pos) -> Color {
+    warp_with(&WarpParams::default(), a, p, l, v)
+}
+
+#[inline(always)]
+pub fn warp_with(w: &WarpParams, a: &Image, p: Pos, l: Pos, v: Pos) -> Color {
+    warp_split(w, a, p, warp_displacement_with(w, p, l, v))
+}
+
+// the warp of several cursors or fingers at once, as (position, velocity) pairs
+#[inline(always)]
+pub fn warp_displacement_sum(w: &WarpParams, p: Pos, influencers: &[(Pos, Pos)]) -> Pos {
+    influencers.iter().fold(Pos::default(), |m, &(l, v)| m + warp_displacement_with(w, p, l, v))
+}
+
+// the RGB-split smear of `p` along a displacement `m`, however that was found
+#[inline(always)]
+pub fn warp_split(w: &WarpParams, a: &Image, p: Pos, m: Pos) -> Color {
+    let taps = w.taps.max(1);
+    // summed apart rather than as colors, which would carry alpha along
+    let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+    for j in 0..taps {
+        let s = j as f64 * w.spread / taps as f64 + w.offset;
+        r += a.sample(p + m * (s - w.separation)).red();
+        g += a.sample(p + m * s).green();
+        b += a.sample(p + m * (s + w.separation)).blue();
+    }
+    let n = taps as f64;
+    Color::new(r / n, g / n, b / n)
+}
+
+// endregion
+// region Effect
+
+// the selectable shaders; each one pulls pixels around the cursor `l`, some
+// of them driven by its velocity `v` or by the time `t` in seconds
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Effect {
+    // the RGB-split drag
+    Warp(WarpParams),
+    // twists by up to `angle` radians at the cursor
+    Swirl { radius: f64, angle: f64 },
+    // squeezes towards the cursor; negative strengths bulge instead
+    Pinch { radius: f64, strength: f64 },
+    // rings travelling outwards at `speed` pixels per second
+    Ripple { radius: f64, wavelength: f64, amplitude: f64, speed: f64 },
+    // a lens `zoom` times magnifying within `radius` that eases back to no
+    // zoom over the outer `falloff` fraction of it; a fisheye lens magnifies
+    // the center most and bends smoothly into its surroundings
+    Magnifier { radius: f64, zoom: f64, falloff: f64, fisheye: bool },
+    // the warp, trailing behind the cursor; `Renderer` runs it through a
+    // `DisplacementField`, elsewhere it is the plain warp
+    Fluid(WarpParams),
+    // rings on a water surface that the cursor and clicks disturb; `Renderer`
+    // simulates it with a `WaterSurface`, elsewhere the surface stays flat.
+    // `damping` is kept per step, `refraction` is pixels per unit of slope
+    Water { damping: f64, refraction: f64 },
+    // an elastic sheet that the cursor grabs within `radius` and that springs
+    // back when let go; `Renderer` simulates it with a `SpringMesh`
+    Spring { radius: f64 },
+    // `segments` mirrored wedges around the cursor, turned by `spin` radians
+    // per unit of horizontal cursor velocity
+    Kaleidoscope { segments: usize, spin: f64 },
+    // `block` pixel squares of flat color within `radius`
+    Mosaic { radius: f64, block: f64 },
+    // shimmering around the cursor: noise `scale` pixels across, rising at
+    // `speed` pixels per second, shifting samples by up to `amplitude`
+    HeatHaze { radius: f64, amplitude: f64, scale: f64, speed: f64 },
+    // the ring between `radius / scale` and `radius` around the cursor,
+    // repeated inwards and outwards forever along a spiral that zooms in by
+    // `zoom` rings and turns by `spin` radians per second
+    Droste { radius: f64, scale: f64, zoom: f64, spin: f64 },
+    // smoke-like smearing within `radius`: samples follow a curl noise flow
+    // `scale` pixels across in `steps` steps, moving up to `strength` pixels
+    // in all near the cursor, while the flow drifts at `speed` pixels per second
+    Flow { radius: f64, strength: f64, scale: f64, steps: usize, speed: f64 },
+}
+
+// the heat haze's noise, the same on every run
+static HAZE: noise::Noise = noise::Noise::new(0x4a5e);
+// and the flow's
+static FLOW: noise::Noise = noise::Noise::new(0xf10);
+
+impl Default for Effect {
+    fn default() -> Self { Effect::Warp(WarpParams::default()) }
+}
+
+impl Effect {
+    // one of each, in the order the demo binds them to number keys
+    pub fn all() -> [Effect; 14] {
+        [
+            Effect::default(),
+            Effect::Swirl { radius: 200.0, angle: 2.5 },
+            Effect::Pinch { radius: 200.0, strength: 0.5 },
+            Effect::Ripple { radius: 300.0, wavelength: 40.0, amplitude: 6.0, speed: 120.0 },
+            Effect::Magnifier { radius: 120.0, zoom: 2.0, falloff: 0.2, fisheye: false },
+            Effect::Fluid(WarpParams::default()),
+            Effect::Water { damping: 0.985, refraction: 1.0 },
+            Effect::Spring { radius: 80.0 },
+            Effect::Magnifier { radius: 160.0, zoom: 2.5, falloff: 0.0, fisheye: true },
+            Effect::Kaleidoscope { segments: 6, spin: 0.002 },
+            Effect::Mosaic { radius: 150.0, block: 16.0 },
+            Effect::HeatHaze { radius: 220.0, amplitude: 4.0, scale: 24.0, speed: 40.0 },
+            Effect::Droste { radius: 180.0, scale: 4.0, zoom: 0.25, spin: 0.1 },
+            Effect::Flow { radius: 220.0, strength: 12.0, scale: 120.0, steps: 8, speed: 30.0 },
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::Warp(_) => "warp",
+            Effect::Swirl { .. } => "swirl",
+            Effect::Pinch { .. } => "pinch",
+            Effect::Ripple { .. } => "ripple",
+            Effect::Magnifier { fisheye: false, .. } => "magnifier",
+            Effect::Magnifier { fisheye: true, .. } => "fisheye",
+            Effect::Fluid(_) => "fluid",
+            Effect::Water { .. } => "water",
+            Effect::Spring { .. } => "spring",
+            Effect::Kaleidoscope { .. } => "kaleidoscope",
+            Effect::Mosaic { .. } => "mosaic",
+            Effect::HeatHaze { .. } => "heat haze",
+            Effect::Droste { .. } => "droste",
+            Effect::Flow { .. } => "flow",
+        }
+    }
+
+    // how far from the cursor the effect reaches, for those with a hard edge
+    pub fn radius(&self) -> Option<f64> {
+        match *self {
+            Effect::Warp(w) | Effect::Fluid(w) => Some(w.radius),
+            Effect::Swirl { radius, .. }
+            | Effect::Pinch { radius, .. }
+            | Effect::Ripple { radius, .. }
+            | Effect::Magnifier { radius, .. }
+            | Effect::Spring { radius }
+            | Effect::Mosaic { radius, .. }
+            | Effect::HeatHaze { radius, .. }
+            | Effect::Flow { radius, .. } => Some(radius),
+            Effect::Water { .. } | Effect::Kaleidoscope { .. } | Effect::Droste { .. } => None,
+        }
+    }
+
+    // offset from `p` to where it samples the image
+    pub fn displacement(&self, p: Pos, l: Pos, v: Pos, t: f64) -> Pos {
+        let d = p - l;
+        let r = d.len();
+        let falloff = |radius: f64| (1.0 - r / radius).clamp(0.0, 1.0);
+        match *self {
+            Effect::Warp(ref w) | Effect::Fluid(ref w) => warp_displacement_with(w, p, l, v),
+            Effect::Swirl { radius, angle } => {
+                let f = falloff(radius);
+                d.rotate(angle * f * f) - d
+            }
+            Effect::Pinch { radius, strength } => {
+                let f = falloff(radius);
+                d * strength * f * f
+            }
+            Effect::Ripple { radius, wavelength, amplitude, speed } => {
+                let phase = (r - speed * t) / wavelength * core::f64::consts::TAU;
+                d.normalize() * phase.sin() * amplitude * falloff(radius)
+            }
+            Effect::Magnifier { radius, zoom, falloff, fisheye } if r < radius => {
+                let u = r / radius;
+                let scale = if fisheye { u.max(f64::EPSILON).powf(zoom - 1.0) } else { 1.0 / zoom };
+                let edge = if falloff > 0.0 { ((u - 1.0 + falloff) / falloff).clamp(0.0, 1.0) } else { 0.0 };
+                let edge = edge * edge * (3.0 - 2.0 * edge);
+                d * (scale + (1.0 - scale) * edge - 1.0)
+            }
+            Effect::Kaleidoscope { segments, spin } => {
+                let turn = v.x * spin;
+                let wedge = core::f64::consts::TAU / segments.max(1) as f64;
+                let (r, a) = d.to_polar();
+                let a = ((a - turn) % wedge + wedge) % wedge;
+                // every other wedge is mirrored so the seams line up
+                let a = if a > wedge / 2.0 { wedge - a } else { a };
+                Pos::from_polar(r, a + turn) - d
+            }
+            Effect::Mosaic { radius, block } => {
+                let block = block.max(1.0);
+                let center = Pos::new(((p.x / block).floor() + 0.5) * block, ((p.y / block).floor() + 0.5) * block);
+                // whole blocks in or out, so the edge is blocky too
+                if center.dist(l) < radius { center - p } else { Pos::default() }
+            }
+            Effect::HeatHaze { radius, amplitude, scale, speed } => {
+                let q = Pos::new(p.x, p.y + speed * t) / scale.max(1.0);
+                HAZE.vector(q, noise::Basis::Simplex) * amplitude * falloff(radius)
+            }
+            Effect::Droste { radius, scale, zoom, spin } => {
+                let ring = scale.max(1.001).ln();
+                let (r, a) = d.to_polar();
+                // log-polar, sheared so that a turn around the cursor goes one
+                // ring further in, and wrapped into the one ring there is
+                let rho = r.max(f64::EPSILON).ln() - radius.max(1.0).ln() + (a / core::f64::consts::TAU - zoom * t) * ring;
+                let rho = (rho % ring + ring) % ring - ring;
+                Pos::from_polar(radius.max(1.0) * rho.exp(), a + spin * t) - d
+            }
+            Effect::Flow { radius, strength, scale, steps, speed } => {
+                let (steps, drift) = (steps.max(1), Pos::new(0.0, speed * t));
+                let mut q = p;
+                // weaker further out at every step, so the smear trails off
+                for _ in 0..steps {
+                    let f = (1.0 - q.dist(l) / radius).clamp(0.0, 1.0);
+                    if f == 0.0 {
+                        break;
+                    }
+                    let flow = FLOW.curl((q + drift) / scale.max(1.0), noise::Basis::Simplex);
+                    q += flow * (strength * f * f / steps as f64);
+                }
+                q - p
+            }
+            Effect::Magnifier { .. } | Effect::Water { .. } | Effect::Spring { .. } => Pos::default(),
+        }
+    }
+
+    pub fn shade(&self, a: &Image, p: Pos, l: Pos, v: Pos, t: f64) -> Color {
+        match self {
+            Effect::Warp(w) | Effect::Fluid(w) => warp_with(w, a, p, l, v),
+            _ => a.sample(p + self.displacement(p, l, v, t)).color(),
+        }
+    }
+}
+
+// endregion
+// region Shockwave
+
+// a ring expanding from `center` that pushes pixels radially as it passes
+#[derive(Copy, Clone)]
+pub struct Shockwave {
+    pub center: Pos,
+    pub radius: f64,
+    // peak displacement in pixels
+    pub amplitude: f64,
+    // fraction of the amplitude lost per second, exponentially
+    pub decay: f64,
+}
+
+// the waves in flight; these go on top of whichever effect is active
+#[derive(Clone)]
+pub struct Shockwaves {
+    waves: Vec<Shockwave>,
+    // in pixels per second
+    pub speed: f64,
+    // thickness of a ring in pixels
+    pub width: f64,
+    // amplitude and decay of new waves
+    pub amplitude: f64,
+    pub decay: f64,
+}
+
+impl Default for Shockwaves {
+    fn default() -> Self {
+        Self { waves: Vec::new(), speed: 450.0, width: 40.0, amplitude: 14.0, decay: 2.0 }
+    }
+}
+
+impl Shockwaves {
+    pub fn push(&mut self, center: Pos) {
+        self.waves.push(Shockwave { center, radius: 0.0, amplitude: self.amplitude, decay: self.decay });
+    }
+
+    pub fn waves(&self) -> &[Shockwave] { &self.waves }
+
+    pub fn is_empty(&self) -> bool { self.waves.is_empty() }
+
+    pub fn clear(&mut self) {
+        self.waves.clear();
+    }
+
+    // grows and fades the rings, dropping the ones that are barely visible
+    pub fn update(&mut self, delta: Duration) {
+        let dt = delta.as_secs_f64();
+        for w in &mut self.waves {
+            w.radius += self.speed * dt;
+            w.amplitude *= (-w.decay * dt).exp();
+        }
+        self.waves.retain(|w| w.amplitude.abs() > 0.1);
+    }
+
+    // offset from `p` to where it samples the image
+    pub fn displacement(&self, p: Pos) -> Pos {
+        let half = self.width / 2.0;
+        self.waves.iter().fold(Pos::default(), |acc, w| {
+            let d = p - w.center;
+            let x = (d.len() - w.radius) / half;
+            if !(-1.0..1.0).contains(&x) {
+                return acc;
+            }
+            // pulls in ahead of the crest and pushes out behind it
+            acc + d.normalize() * (x * core::f64::consts::PI).sin() * (1.0 - x.abs()) * w.amplitude
+        })
+    }
+}
+
+// endregion
+// region Field
+
+// a coarse grid of displacements that the cursor stirs and that then drifts,
+// spreads out and settles down on its own
+#[derive(Clone)]
+pub struct DisplacementField {
+    cols: usize,
+    rows: usize,
+    cell: f64,
+    data: Vec<Pos>,
+    // how quickly the field takes on what the cursor injects, per second
+    pub injection: f64,
+    // fraction lost per second, exponentially
+    pub decay: f64,
+    // how fast cells blend with their neighbours, per second
+    pub diffusion: f64,
+    // how far the field carries itself along, in pixels per second per pixel
+    pub advection: f64,
+}
+
+impl DisplacementField {
+    // covers a `width` x `height` image with `cell` x `cell` pixel cells
+    pub fn new(width: usize, height: usize, cell: usize) -> Self {
+        let cell = cell.max(1);
+        let (cols, rows) = (width.div_ceil(cell).max(1), height.div_ceil(cell).max(1));
+        Self {
+            cols,
+            rows,
+            cell: cell as f64,
+            data: vec![Pos::default(); cols * rows],
+            injection: 8.0,
+            decay: 0.8,
+            diffusion: 2.0,
+            advection: 3.0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.data.fill(Pos::default());
+    }
+
+    fn center(&self, i: usize) -> Pos {
+        Pos::new((i % self.cols) as f64 + 0.5, (i / self.cols) as f64 + 0.5) * self.cell
+    }
+
+    fn at(&self, x: isize, y: isize) -> Pos {
+        let x = x.clamp(0, self.cols as isize - 1) as usize;
+        let y = y.clamp(0, self.rows as isize - 1) as usize;
+        self.data[x + y * self.cols]
+    }
+
+    // pulls the field towards the warp of the cursor at `l` moving at `v`
+    pub fn inject(&mut self, w: &WarpParams, l: Pos, v: Pos, delta: Duration) {
+        let k = 1.0 - (-self.injection * delta.as_secs_f64()).exp();
+        for i in 0..self.data.len() {
+            let d = warp_displacement_with(w, self.center(i), l, v);
+            if d.len_sq() > 0.0 {
+                self.data[i] = self.data[i].lerp(d, k);
+            }
+        }
+    }
+
+    // one step of advection, diffusion and decay
+    pub fn update(&mut self, delta: Duration) {
+        let dt = delta.as_secs_f64();
+        // semi-Lagrangian: each cell takes what flowed in from upstream
+        let advected: Vec<Pos> = (0..self.data.len())
+            .map(|i| {
+                let p = self.center(i);
+                self.sample(p + self.data[i] * self.advection * dt)
+            })
+            .collect();
+        let diffuse = (self.diffusion * dt).min(1.0);
+        let fade = (-self.decay * dt).exp();
+        for (i, e) in self.data.iter_mut().enumerate() {
+            let (x, y) = ((i % self.cols) as isize, (i / self.cols) as isize);
+            let near = |dx: isize, dy: isize| {
+                let (x, y) = ((x + dx).clamp(0, self.cols as isize - 1), (y + dy).clamp(0, self.rows as isize - 1));
+                advected[x as usize + y as usize * self.cols]
+            };
+            let avg = (near(-1, 0) + near(1, 0) + near(0, -1) + near(0, 1)) * 0.25;
+            *e = advected[i].lerp(avg, diffuse) * fade;
+        }
+    }
+
+    // bilinear, at image coordinates
+    pub fn sample(&self, p: Pos) -> Pos {
+        let (x, y) = (p.x / self.cell - 0.5, p.y / self.cell - 0.5);
+        let (fx, fy) = (x.floor(), y.floor());
+        let (tx, ty) = (x - fx, y - fy);
+        let (x, y) = (fx as isize, fy as isize);
+        let top = self.at(x, y).lerp(self.at(x + 1, y), tx);
+        let bottom = self.at(x, y + 1).lerp(self.at(x + 1, y + 1), tx);
+        top.lerp(bottom, ty)
+    }
+}
+
+// endregion
+// region Water
+
+// height field for the classic two-buffer water ripple algorithm
+#[derive(Clone)]
+pub struct WaterSurface {
+    cols: usize,
+    rows: usize,
+    cell: f64,
+    cur: Vec<f64>,
+    prev: Vec<f64>,
+    // time not yet simulated, as it runs in fixed steps
+    pending: f64,
+    // fraction of the height kept per step
+    pub damping: f64,
+}
+
+impl WaterSurface {
+    // steps per second, independent of the frame rate
+    const RATE: f64 = 60.0;
+    // catching up on more than this many steps at once only makes a slow frame slower
+    const MAX_STEPS: usize = 4;
+
+    // covers a `width` x `height` image with `cell` x `cell` pixel cells
+    pub fn new(width: usize, height: usize, cell: usize) -> Self {
+        let cell = cell.max(1);
+        let (cols, rows) = (width.div_ceil(cell).max(1), height.div_ceil(cell).max(1));
+        Self {
+            cols,
+            rows,
+            cell: cell as f64,
+            cur: vec![0.0; cols * rows],
+            prev: vec![0.0; cols * rows],
+            pending: 0.0,
+            damping: 0.985,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cur.fill(0.0);
+        self.prev.fill(0.0);
+    }
+
+    fn at(&self, x: isize, y: isize) -> f64 {
+        let x = x.clamp(0, self.cols as isize - 1) as usize;
+        let y = y.clamp(0, self.rows as isize - 1) as usize;
+        self.cur[x + y * self.cols]
+    }
+
+    // raises the surface by up to `amount` around `p`, in image coordinates
+    pub fn disturb(&mut self, p: Pos, radius: f64, amount: f64) {
+        let r = radius / self.cell;
+        let (cx, cy) = (p.x / self.cell, p.y / self.cell);
+        let (x0, x1) = ((cx - r).floor().max(0.0) as usize, ((cx + r).ceil().max(0.0) as usize).min(self.cols));
+        let (y0, y1) = ((cy - r).floor().max(0.0) as usize, ((cy + r).ceil().max(0.0) as usize).min(self.rows));
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let d = Pos::new(x as f64 + 0.5 - cx, y as f64 + 0.5 - cy).len() / r;
+                if d < 1.0 {
+                    self.cur[x + y * self.cols] += amount * 0.5 * (1.0 + (d * core::f64::consts::PI).cos());
+                }
+            }
+        }
+    }
+
+    // runs as many fixed steps as `delta` covers
+    pub fn update(&mut self, delta: Duration) {
+        self.pending += delta.as_secs_f64() * Self::RATE;
+        let mut steps = 0;
+        while self.pending >= 1.0 && steps < Self::MAX_STEPS {
+            self.step();
+            self.pending -= 1.0;
+            steps += 1;
+        }
+        if steps == Self::MAX_STEPS {
+            self.pending = 0.0;
+        }
+    }
+
+    // the next heights are half the neighbours' sum minus the previous ones,
+    // written over the previous buffer which then becomes the current one
+    pub fn step(&mut self) {
+        let (cols, rows, damping) = (self.cols as isize, self.rows as isize, self.damping);
+        let cur = &self.cur;
+        let h = |x: isize, y: isize| cur[(x.clamp(0, cols - 1) + y.clamp(0, rows - 1) * cols) as usize];
+        let row = |(y, out): (usize, &mut [f64])| {
+            let y = y as isize;
+            for (x, e) in out.iter_mut().enumerate() {
+                let x = x as isize;
+                *e = ((h(x - 1, y) + h(x + 1, y) + h(x, y - 1) + h(x, y + 1)) / 2.0 - *e) * damping;
+            }
+        };
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            self.prev.par_chunks_mut(self.cols).enumerate().for_each(row);
+        }
+        #[cfg(not(feature = "parallel"))]
+        self.prev.chunks_mut(self.cols).enumerate().for_each(row);
+        core::mem::swap(&mut self.cur, &mut self.prev);
+    }
+
+    // slope of the surface at `p`, bilinear between cell centers
+    pub fn gradient(&self, p: Pos) -> Pos {
+        let (x, y) = (p.x / self.cell - 0.5, p.y / self.cell - 0.5);
+        let (fx, fy) = (x.floor(), y.floor());
+        let (tx, ty) = (x - fx, y - fy);
+        let (x, y) = (fx as isize, fy as isize);
+        let g = |x: isize, y: isize| Pos::new(self.at(x + 1, y) - self.at(x - 1, y), self.at(x, y + 1) - self.at(x, y - 1)) * 0.5;
+        let top = g(x, y).lerp(g(x + 1, y), tx);
+        let bottom = g(x, y + 1).lerp(g(x + 1, y + 1), tx);
+        top.lerp(bottom, ty)
+    }
+}
+
+// endregion
+// region Spring
+
+// control points on a grid, each tied to its rest position and to its
+// neighbours by springs; displacements between points are interpolated
+#[derive(Clone)]
+pub struct SpringMesh {
+    cols: usize,
+    rows: usize,
+    spacing: f64,
+    offset: Vec<Pos>,
+    velocity: Vec<Pos>,
+    // time not yet simulated, as it runs in fixed steps
+    pending: f64,
+    // pull towards the rest position and towards the neighbours, per second squared
+    pub stiffness: f64,
+    pub coupling: f64,
+    // velocity lost per second
+    pub damping: f64,
+}
+
+impl SpringMesh {
+    const RATE: f64 = 120.0;
+    const MAX_STEPS: usize = 8;
+
+    // covers a `width` x `height` image with points `spacing` pixels apart
+    pub fn new(width: usize, height: usize, spacing: usize) -> Self {
+        let spacing = spacing.max(1);
+        let (cols, rows) = (width.div_ceil(spacing) + 1, height.div_ceil(spacing) + 1);
+        Self {
+            cols,
+            rows,
+            spacing: spacing as f64,
+            offset: vec![Pos::default(); cols * rows],
+            velocity: vec![Pos::default(); cols * rows],
+            pending: 0.0,
+            stiffness: 60.0,
+            coupling: 400.0,
+            damping: 3.0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.offset.fill(Pos::default());
+        self.velocity.fill(Pos::default());
+    }
+
+    fn at(&self, x: isize, y: isize) -> Pos {
+        let x = x.clamp(0, self.cols as isize - 1) as usize;
+        let y = y.clamp(0, self.rows as isize - 1) as usize;
+        self.offset[x + y * self.cols]
+    }
+
+    // carries the points within `radius` of `l` along by `moved` pixels,
+    // fully at `l` and less so further out
+    pub fn drag(&mut self, l: Pos, moved: Pos, radius: f64) {
+        for (i, o) in self.offset.iter_mut().enumerate() {
+            let p = Pos::new((i % self.cols) as f64, (i / self.cols) as f64) * self.spacing + *o;
+            let f = (1.0 - p.dist(l) / radius).clamp(0.0, 1.0);
+            // sampling further back is what moves the image forward
+            *o -= moved * (f * f);
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.pending += delta.as_secs_f64() * Self::RATE;
+        let mut steps = 0;
+        while self.pending >= 1.0 && steps < Self::MAX_STEPS {
+            self.step(1.0 / Self::RATE);
+            self.pending -= 1.0;
+            steps += 1;
+        }
+        if steps == Self::MAX_STEPS {
+            self.pending = 0.0;
+        }
+    }
+
+    // semi-implicit Euler
+    fn step(&mut self, dt: f64) {
+        let cols = self.cols as isize;
+        for i in 0..self.offset.len() {
+            let (x, y) = (i as isize % cols, i as isize / cols);
+            let o = self.offset[i];
+            let near = (self.at(x - 1, y) + self.at(x + 1, y) + self.at(x, y - 1) + self.at(x, y + 1)) * 0.25;
+            let force = (near - o) * self.coupling - o * self.stiffness - self.velocity[i] * self.damping;
+            self.velocity[i] += force * dt;
+        }
+        for (o, &v) in self.offset.iter_mut().zip(&self.velocity) {
+            *o += v * dt;
+        }
+    }
+
+    // bilinear between control points, at image coordinates
+    pub fn sample(&self, p: Pos) -> Pos {
+        let (x, y) = (p.x / self.spacing, p.y / self.spacing);
+        let (fx, fy) = (x.floor(), y.floor());
+        let (tx, ty) = (x - fx, y - fy);
+        let (x, y) = (fx as isize, fy as isize);
+        let top = self.at(x, y).lerp(self.at(x + 1, y), tx);
+        let bottom = self.at(x, y + 1).lerp(self.at(x + 1, y + 1), tx);
+        top.lerp(bottom, ty)
+    }
+}
+
+// endregion
+// region Idle
+
+// a slow swell and drift that keeps the image alive while the cursor rests
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Breathing {
+    // seconds without cursor movement before it starts
+    pub after: f64,
+    // seconds to fade in, and back out once the cursor moves
+    pub fade: f64,
+    // seconds per breath, and how far it swells as a fraction of the distance
+    // to the image center
+    pub period: f64,
+    pub swell: f64,
+    // drift in pixels, from noise `scale` pixels across
+    pub amplitude: f64,
+    pub scale: f64,
+}
+
+impl Default for Breathing {
+    fn default() -> Self {
+        Self { after: 3.0, fade: 1.5, period: 5.0, swell: 0.015, amplitude: 3.0, scale: 140.0 }
+    }
+}
+
+static DRIFT: noise::Noise = noise::Noise::new(0xb7ea);
+
+impl Breathing {
+    // offset from `p` to where it samples a `width` x `height` image at time `t`
+    pub fn displacement(&self, p: Pos, width: usize, height: usize, t: f64) -> Pos {
+        let center = Pos::new(width as f64, height as f64) / 2.0;
+        let swell = (t / self.period.max(f64::EPSILON) * core::f64::consts::TAU).sin() * self.swell;
+        let q = p / self.scale.max(1.0) + Pos::new(t * 0.05, t * 0.08);
+        (center - p) * swell + DRIFT.vector(q, noise::Basis::Simplex) * self.amplitude
+    }
+}
+
+// endregion
+// region Dither
+
+// how the float shader output is quantized to bytes; dithering trades the
+// banding of smooth gradients for fine, barely visible noise
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Dither {
+    #[default]
+    None,
+    // 4x4 Bayer matrix
+    Ordered4x4,
+    // Jimenez's interleaved gradient noise, which spreads its thresholds
+    // nearly as evenly as blue noise without a texture
+    InterleavedGradient,
+    // 64x64 void-and-cluster blue noise tile
+    BlueNoise,
+}
+
+impl Dither {
+    const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+    // rounding threshold in [0, 1) for pixel (x, y); 0 means plain truncation
+    pub fn threshold(self, x: usize, y: usize) -> f64 {
+        match self {
+            Dither::None => 0.0,
+            Dither::Ordered4x4 => (Self::BAYER[y % 4][x % 4] as f64 + 0.5) / 16.0,
+            Dither::InterleavedGradient => {
+                // wrapped so the products stay small enough for `% 1.0` to keep its
+                // fraction; the seam every 4096 pixels is invisible in the noise
+                let f = (0.06711056 * (x % 4096) as f64 + 0.00583715 * (y % 4096) as f64) % 1.0;
+                (52.9829189 * f) % 1.0
+            }
+            Dither::BlueNoise => (Self::BLUE_NOISE[y % 64][x % 64] as f64 + 0.5) / 256.0,
+        }
+    }
+
+    // ranks from Ulichney's void-and-cluster method (gaussian sigma 1.5, toroidal),
+    // scaled to 0..256 so each value appears 16 times
+    const BLUE_NOISE: [[u8; 64]; 64] = [
+        [90, 65, 243, 129, 74, 249, 30, 49, 228, 64, 187, 124, 78, 203, 130, 67, 106, 24, 50, 243, 34, 83, 135, 211, 59, 171, 252, 132, 86, 31, 200, 78,
+         223, 56, 14, 87, 151, 239, 127, 202, 95, 67, 114, 83, 246, 121, 51, 151, 111, 42, 145, 120, 184, 36, 163, 189, 232, 44, 154, 90, 124, 167, 105, 51],
+        [224, 159, 184, 40, 214, 172, 112, 206, 143, 4, 254, 154, 51, 175, 12, 231, 155, 176, 74, 144, 208, 164, 108, 23, 187, 90, 36, 68, 233, 143, 161, 43,
+         119, 167, 142, 231, 20, 187, 79, 0, 175, 139, 211, 7, 179, 25, 201, 236, 21, 228, 199, 16, 83, 214, 59, 102, 14, 71, 182, 215, 56, 251, 15, 197],
+        [25, 113, 8, 143, 95, 16, 133, 72, 178, 104, 82, 25, 234, 102, 147, 36, 216, 93, 196, 104, 11, 63, 248, 46, 221, 120, 154, 190, 14, 58, 212, 97,
+         247, 28, 185, 63, 106, 45, 216, 117, 227, 40, 237, 57, 153, 98, 133, 73, 164, 95, 57, 254, 110, 152, 239, 125, 197, 247, 109, 5, 139, 80, 156, 130],
+        [246, 83, 231, 55, 194, 234, 45, 242, 24, 219, 192, 118, 210, 69, 199, 122, 59, 1, 254, 44, 220, 186, 126, 150, 78, 4, 241, 107, 219, 126, 177, 2,
+         138, 201, 83, 128, 250, 166, 145, 64, 23, 158, 103, 128, 206, 241, 45, 193, 0, 124, 180, 142, 46, 4, 177, 24, 86, 149, 39, 169, 230, 33, 206, 63],
+        [44, 168, 204, 122, 153, 81, 170, 97, 158, 58, 139, 40, 156, 7, 88, 240, 191, 164, 119, 137, 158, 89, 17, 229, 174, 205, 51, 168, 74, 35, 87, 238,
+         68, 41, 224, 9, 205, 31, 94, 243, 196, 84, 188, 30, 70, 13, 174, 87, 245, 213, 31, 75, 196, 222, 70, 136, 209, 56, 222, 91, 191, 120, 99, 178],
+        [136, 106, 29, 71, 12, 220, 32, 202, 117, 9, 226, 94, 248, 185, 47, 143, 27, 76, 214, 19, 71, 242, 39, 103, 60, 130, 97, 17, 145, 231, 191, 153,
+         115, 172, 99, 157, 55, 119, 179, 15, 135, 50, 252, 171, 96, 225, 115, 138, 60, 150, 99, 231, 121, 165, 98, 243, 25, 164, 127, 20, 66, 239, 7, 215],
+        [22, 226, 158, 254, 184, 108, 140, 68, 250, 182, 77, 166, 29, 124, 223, 109, 176, 95, 54, 235, 181, 115, 203, 144, 189, 30, 215, 255, 179, 111, 19, 51,
+         219, 15, 241, 134, 192, 223, 73, 232, 109, 214, 4, 141, 209, 156, 24, 234, 39, 183, 11, 158, 22, 60, 35, 181, 109, 76, 251, 184, 146, 49, 160, 74],
+        [94, 187, 42, 89, 131, 52, 237, 16, 151, 43, 131, 201, 57, 150, 70, 12, 250, 205, 147, 106, 29, 165, 65, 7, 245, 162, 77, 136, 42, 67, 209, 130,
+         92, 198, 70, 37, 88, 2, 148, 43, 166, 65, 119, 79, 37, 58, 195, 82, 205, 112, 250, 85, 203, 235, 139, 218, 49, 206, 1, 102, 219, 128, 194, 249],
+        [151, 123, 67, 203, 4, 211, 171, 85, 195, 101, 233, 3, 241, 100, 193, 158, 42, 124, 5, 196, 49, 216, 126, 225, 88, 48, 117, 1, 193, 100, 161, 250,
+         32, 180, 151, 116, 254, 171, 102, 207, 28, 187, 226, 161, 238, 129, 105, 5, 163, 70, 132, 46, 185, 114, 87, 13, 148, 117, 174, 41, 80, 27, 108, 13],
+        [221, 33, 236, 167, 101, 148, 33, 115, 218, 24, 65, 116, 175, 19, 218, 87, 228, 66, 167, 233, 79, 152, 98, 22, 141, 180, 237, 212, 144, 228, 13, 79,
+         141, 60, 229, 22, 203, 53, 126, 237, 86, 133, 23, 98, 15, 188, 251, 141, 216, 30, 223, 148, 3, 66, 168, 249, 193, 60, 238, 154, 228, 181, 211, 56],
+        [83, 195, 139, 20, 247, 73, 227, 59, 176, 135, 162, 204, 78, 139, 50, 117, 23, 185, 93, 133, 15, 252, 194, 41, 207, 105, 66, 29, 87, 50, 170, 113,
+         216, 5, 97, 159, 78, 188, 19, 70, 156, 249, 54, 213, 170, 64, 42, 90, 180, 58, 100, 175, 241, 209, 40, 101, 23, 81, 133, 12, 98, 67, 131, 169],
+        [2, 100, 59, 118, 40, 188, 125, 8, 236, 45, 89, 225, 30, 252, 170, 211, 144, 246, 35, 213, 114, 60, 173, 77, 243, 10, 148, 175, 118, 246, 200, 39,
+         183, 242, 129, 211, 33, 137, 220, 175, 9, 104, 191, 125, 80, 149, 224, 119, 11, 246, 127, 21, 83, 121, 143, 225, 159, 217, 179, 53, 255, 160, 37, 241],
+        [148, 229, 182, 219, 163, 92, 206, 145, 99, 196, 15, 150, 58, 125, 97, 1, 74, 108, 58, 162, 188, 31, 136, 110, 159, 57, 225, 194, 72, 133, 22, 95,
+         150, 73, 48, 174, 110, 248, 93, 46, 206, 141, 33, 231, 8, 199, 30, 165, 208, 75, 194, 162, 51, 199, 9, 62, 123, 36, 103, 208, 120, 18, 204, 114],
+        [172, 29, 78, 9, 142, 53, 26, 254, 62, 169, 243, 107, 190, 219, 37, 183, 204, 154, 227, 7, 90, 241, 221, 16, 202, 126, 94, 41, 5, 233, 156, 59,
+         205, 116, 20, 224, 61, 4, 154, 122, 239, 85, 61, 173, 114, 88, 243, 53, 102, 147, 35, 226, 110, 238, 183, 93, 246, 192, 6, 151, 77, 185, 92, 48],
+        [213, 131, 251, 105, 199, 230, 82, 180, 118, 30, 79, 134, 9, 159, 84, 243, 52, 28, 127, 199, 144, 71, 100, 50, 180, 26, 255, 165, 211, 104, 177, 223,
+         10, 251, 139, 185, 84, 168, 197, 68, 23, 183, 154, 255, 45, 139, 181, 123, 22, 239, 62, 136, 15, 77, 151, 27, 167, 73, 230, 41, 221, 135, 245, 69],
+        [98, 195, 62, 125, 37, 167, 132, 1, 221, 156, 199, 40, 238, 66, 116, 137, 174, 99, 255, 61, 20, 171, 208, 153, 229, 80, 111, 140, 52, 84, 31, 123,
+         76, 163, 98, 42, 241, 129, 37, 233, 113, 215, 2, 101, 203, 16, 66, 229, 160, 186, 88, 216, 171, 43, 124, 222, 54, 138, 117, 174, 60, 26, 164, 7],
+        [144, 42, 176, 15, 241, 94, 64, 204, 102, 51, 228, 95, 179, 206, 24, 227, 10, 77, 186, 113, 218, 43, 117, 2, 132, 60, 201, 14, 184, 245, 145, 198,
+         49, 189, 26, 217, 108, 12, 211, 96, 149, 53, 129, 73, 223, 148, 193, 82, 3, 111, 28, 201, 101, 254, 191, 97, 203, 18, 85, 250, 103, 214, 119, 230],
+        [19, 239, 87, 211, 147, 189, 22, 246, 144, 75, 20, 122, 143, 53, 163, 104, 213, 146, 36, 163, 93, 247, 188, 86, 241, 34, 156, 234, 68, 114, 2, 228,
+         89, 237, 149, 70, 200, 139, 77, 177, 18, 193, 242, 160, 26, 97, 47, 210, 135, 249, 155, 48, 131, 4, 69, 25, 159, 237, 146, 1, 198, 38, 80, 189],
+        [106, 163, 134, 71, 112, 49, 162, 119, 34, 183, 165, 252, 4, 83, 240, 39, 63, 195, 234, 5, 137, 69, 29, 145, 207, 176, 94, 126, 41, 210, 158, 36,
+         136, 116, 7, 177, 49, 165, 243, 42, 227, 86, 109, 43, 177, 234, 119, 171, 36, 64, 191, 80, 235, 150, 177, 226, 111, 46, 186, 64, 168, 132, 155, 51],
+        [65, 203, 27, 221, 10, 229, 198, 84, 233, 211, 104, 65, 216, 194, 112, 178, 132, 89, 120, 56, 202, 231, 168, 51, 110, 72, 24, 222, 170, 79, 101, 182,
+         58, 211, 82, 254, 95, 22, 114, 64, 133, 164, 11, 203, 130, 61, 12, 242, 100, 224, 122, 20, 210, 92, 40, 134, 77, 213, 96, 121, 222, 22, 208, 254],
+        [174, 122, 45, 181, 158, 101, 65, 6, 132, 52, 18, 152, 131, 30, 156, 13, 223, 22, 245, 179, 102, 17, 125, 218, 7, 252, 192, 141, 12, 249, 199, 18,
+         242, 161, 32, 135, 223, 190, 155, 201, 29, 215, 67, 252, 87, 188, 152, 79, 142, 9, 181, 157, 60, 116, 190, 248, 29, 171, 11, 246, 44, 93, 113, 8],
+        [80, 230, 94, 241, 130, 33, 249, 154, 176, 92, 189, 231, 44, 97, 248, 59, 192, 158, 80, 34, 154, 72, 184, 90, 159, 121, 44, 97, 63, 115, 46, 145,
+         71, 106, 188, 54, 121, 0, 84, 247, 99, 182, 118, 146, 19, 232, 34, 195, 217, 49, 95, 245, 34, 225, 14, 149, 62, 127, 197, 154, 72, 233, 191, 148],
+        [212, 19, 144, 58, 78, 190, 114, 207, 35, 241, 121, 73, 166, 199, 78, 143, 109, 47, 217, 127, 210, 246, 38, 229, 63, 208, 175, 233, 152, 220, 178, 127,
+         205, 8, 226, 147, 74, 214, 38, 138, 55, 6, 224, 44, 103, 163, 123, 67, 111, 166, 204, 77, 139, 167, 83, 207, 104, 238, 87, 30, 132, 171, 57, 35],
+        [99, 169, 196, 3, 215, 149, 15, 87, 61, 143, 1, 208, 23, 118, 227, 8, 236, 90, 173, 1, 54, 110, 135, 14, 148, 31, 82, 0, 196, 24, 85, 35,
+         234, 93, 171, 24, 238, 162, 110, 176, 236, 154, 81, 174, 200, 54, 222, 14, 255, 30, 129, 0, 194, 109, 53, 179, 5, 221, 52, 187, 218, 2, 124, 246],
+        [136, 43, 117, 253, 103, 52, 231, 169, 219, 192, 103, 252, 56, 182, 39, 164, 124, 30, 254, 138, 199, 79, 191, 170, 103, 237, 117, 137, 56, 105, 244, 161,
+         60, 118, 43, 196, 100, 60, 204, 21, 70, 196, 131, 21, 247, 94, 140, 178, 84, 153, 213, 65, 234, 29, 251, 131, 42, 161, 115, 147, 79, 103, 201, 67],
+        [182, 233, 81, 164, 32, 183, 129, 28, 113, 43, 81, 152, 129, 91, 216, 71, 206, 187, 68, 100, 162, 21, 242, 53, 212, 69, 185, 250, 167, 209, 133, 14,
+         187, 141, 247, 76, 132, 11, 253, 124, 96, 33, 229, 111, 69, 4, 206, 43, 106, 226, 47, 119, 174, 92, 152, 213, 81, 194, 25, 244, 18, 227, 162, 23],
+        [219, 12, 63, 142, 209, 93, 244, 72, 160, 236, 13, 171, 224, 19, 147, 106, 50, 143, 15, 213, 232, 41, 122, 92, 6, 157, 44, 18, 90, 33, 69, 225,
+         82, 215, 5, 153, 222, 186, 46, 158, 180, 208, 52, 152, 189, 123, 158, 240, 17, 188, 80, 147, 13, 220, 70, 17, 121, 232, 99, 67, 177, 49, 142, 90],
+        [124, 200, 106, 237, 16, 60, 151, 5, 206, 131, 187, 68, 38, 192, 249, 5, 229, 168, 116, 56, 88, 153, 205, 175, 132, 228, 107, 214, 148, 193, 113, 170,
+         37, 105, 57, 173, 34, 112, 79, 231, 19, 137, 84, 244, 39, 222, 76, 56, 134, 163, 28, 247, 191, 108, 40, 203, 174, 55, 157, 135, 209, 115, 248, 35],
+        [151, 169, 45, 180, 126, 197, 108, 224, 91, 50, 102, 240, 115, 82, 127, 176, 92, 36, 244, 191, 135, 10, 70, 252, 29, 189, 72, 124, 51, 228, 6, 254,
+         146, 193, 126, 211, 92, 241, 145, 62, 117, 218, 10, 171, 98, 25, 182, 116, 200, 233, 99, 130, 55, 168, 241, 141, 93, 8, 254, 36, 83, 6, 189, 73],
+        [58, 0, 216, 82, 26, 247, 42, 166, 26, 198, 141, 25, 213, 164, 44, 65, 215, 131, 73, 26, 172, 220, 113, 48, 86, 152, 11, 244, 178, 84, 130, 55,
+         91, 16, 237, 70, 23, 183, 0, 195, 94, 162, 59, 125, 212, 143, 252, 88, 13, 41, 68, 208, 4, 80, 125, 28, 221, 197, 108, 185, 224, 161, 104, 230],
+        [120, 252, 101, 141, 160, 69, 188, 134, 80, 254, 175, 61, 149, 11, 239, 185, 21, 157, 202, 101, 237, 35, 164, 195, 231, 101, 207, 37, 158, 21, 187, 219,
+         155, 205, 45, 137, 163, 119, 214, 42, 249, 30, 231, 197, 71, 2, 50, 165, 218, 111, 175, 141, 230, 195, 160, 50, 74, 149, 22, 64, 131, 49, 28, 202],
+        [86, 34, 186, 53, 228, 116, 9, 234, 46, 120, 3, 227, 91, 204, 110, 140, 96, 253, 0, 123, 54, 85, 129, 6, 138, 57, 173, 116, 64, 237, 103, 33,
+         74, 116, 179, 96, 252, 58, 85, 149, 176, 103, 138, 42, 112, 179, 225, 126, 74, 153, 250, 33, 90, 19, 107, 248, 179, 118, 238, 170, 95, 246, 140, 174],
+        [210, 130, 235, 13, 173, 89, 213, 150, 96, 208, 72, 166, 126, 50, 75, 222, 38, 62, 178, 153, 212, 184, 247, 72, 214, 26, 249, 144, 91, 202, 136, 165,
+         247, 3, 223, 31, 199, 12, 229, 128, 69, 7, 186, 84, 240, 147, 96, 23, 205, 10, 59, 184, 122, 223, 64, 209, 0, 87, 42, 213, 10, 191, 74, 16],
+        [58, 154, 77, 107, 202, 32, 59, 175, 17, 186, 137, 37, 248, 193, 20, 160, 197, 113, 230, 78, 18, 108, 31, 160, 104, 181, 80, 1, 224, 46, 14, 62,
+         193, 88, 145, 65, 154, 105, 188, 25, 213, 238, 157, 19, 211, 34, 61, 245, 173, 134, 103, 236, 47, 138, 166, 36, 145, 227, 125, 155, 63, 113, 225, 162],
+        [242, 24, 219, 46, 151, 129, 251, 105, 225, 54, 236, 99, 12, 174, 118, 243, 83, 13, 138, 45, 242, 145, 202, 48, 236, 123, 53, 195, 155, 118, 180, 231,
+         129, 43, 176, 117, 239, 40, 167, 55, 93, 118, 198, 67, 128, 168, 195, 113, 39, 79, 212, 162, 8, 202, 82, 111, 194, 51, 178, 26, 200, 137, 41, 104],
+        [89, 195, 119, 177, 238, 2, 73, 143, 34, 121, 156, 78, 220, 142, 67, 33, 151, 181, 209, 96, 172, 66, 128, 90, 14, 211, 165, 97, 28, 252, 76, 105,
+         24, 207, 233, 9, 78, 212, 133, 248, 150, 30, 47, 107, 255, 14, 87, 140, 233, 190, 27, 68, 99, 243, 183, 17, 250, 75, 103, 233, 85, 253, 2, 180],
+        [47, 136, 12, 66, 93, 208, 164, 197, 86, 209, 22, 191, 109, 47, 202, 95, 219, 54, 125, 27, 220, 3, 233, 192, 146, 36, 240, 134, 205, 56, 146, 219,
+         156, 98, 53, 138, 192, 96, 13, 71, 201, 178, 136, 215, 77, 153, 224, 55, 0, 152, 120, 219, 144, 32, 123, 61, 136, 217, 7, 149, 45, 165, 117, 217],
+        [80, 249, 162, 228, 34, 118, 50, 20, 243, 172, 56, 253, 2, 157, 237, 121, 7, 244, 74, 189, 151, 115, 53, 163, 76, 111, 64, 8, 85, 177, 39, 4,
+         194, 71, 170, 255, 34, 161, 122, 230, 20, 86, 232, 4, 188, 44, 201, 107, 178, 86, 252, 49, 172, 77, 230, 160, 40, 171, 118, 206, 66, 189, 21, 145],
+        [205, 27, 105, 191, 143, 223, 181, 135, 110, 71, 147, 128, 177, 75, 28, 186, 146, 169, 106, 42, 254, 82, 216, 30, 248, 185, 219, 167, 230, 114, 240, 91,
+         128, 226, 16, 115, 62, 222, 186, 48, 111, 159, 57, 169, 102, 133, 18, 235, 65, 205, 18, 106, 193, 5, 212, 101, 198, 81, 31, 245, 132, 99, 237, 63],
+        [180, 127, 51, 82, 8, 99, 67, 234, 11, 212, 38, 89, 226, 105, 210, 59, 85, 20, 202, 138, 12, 176, 102, 137, 10, 127, 44, 102, 21, 141, 199, 61,
+         175, 36, 150, 204, 89, 1, 140, 80, 208, 249, 124, 34, 240, 68, 172, 121, 147, 40, 166, 130, 240, 91, 50, 140, 13, 232, 179, 91, 11, 197, 40, 156],
+        [4, 234, 210, 167, 243, 152, 37, 196, 92, 159, 240, 16, 198, 44, 137, 250, 116, 224, 52, 235, 66, 209, 39, 168, 205, 89, 153, 210, 74, 49, 160, 17,
+         248, 108, 73, 239, 130, 169, 245, 31, 149, 9, 93, 196, 142, 218, 89, 29, 247, 96, 223, 68, 34, 157, 180, 255, 65, 113, 153, 53, 225, 164, 122, 85],
+        [111, 37, 138, 63, 23, 203, 114, 169, 51, 126, 184, 111, 67, 165, 6, 174, 34, 190, 128, 90, 155, 120, 231, 79, 53, 240, 26, 182, 254, 122, 214, 98,
+         137, 198, 47, 181, 22, 55, 107, 198, 67, 179, 222, 74, 14, 48, 185, 209, 57, 177, 15, 143, 208, 109, 18, 126, 203, 37, 215, 134, 76, 29, 210, 252],
+        [71, 190, 93, 227, 125, 86, 254, 1, 220, 73, 22, 151, 235, 123, 221, 79, 100, 150, 16, 181, 31, 193, 3, 144, 188, 123, 62, 108, 1, 173, 33, 79,
+         222, 5, 158, 95, 221, 191, 84, 235, 129, 43, 108, 166, 253, 118, 155, 6, 135, 110, 196, 83, 234, 56, 191, 81, 168, 100, 3, 247, 182, 104, 52, 145],
+        [204, 157, 16, 172, 46, 186, 61, 144, 100, 194, 249, 49, 86, 27, 193, 56, 241, 206, 70, 238, 112, 59, 251, 106, 19, 224, 162, 204, 82, 142, 232, 183,
+         57, 120, 236, 33, 144, 123, 6, 160, 21, 228, 143, 27, 192, 62, 98, 226, 73, 244, 45, 162, 1, 134, 243, 27, 212, 55, 161, 119, 21, 229, 170, 11],
+        [123, 57, 245, 105, 217, 133, 28, 235, 164, 38, 132, 174, 214, 142, 107, 157, 10, 123, 45, 166, 215, 88, 159, 210, 47, 93, 134, 36, 238, 48, 114, 18,
+         152, 195, 81, 176, 64, 253, 47, 209, 95, 187, 52, 86, 129, 235, 31, 167, 188, 24, 122, 223, 98, 175, 71, 147, 115, 237, 84, 198, 69, 137, 87, 240],
+        [100, 212, 35, 148, 7, 77, 196, 110, 81, 15, 207, 96, 4, 68, 248, 38, 185, 222, 98, 139, 9, 37, 134, 70, 183, 247, 9, 186, 102, 157, 209, 69,
+         251, 40, 106, 10, 204, 101, 172, 75, 120, 246, 152, 217, 2, 204, 146, 47, 89, 142, 200, 64, 29, 204, 46, 224, 10, 186, 32, 147, 213, 44, 185, 28],
+        [75, 136, 187, 87, 175, 242, 155, 45, 227, 181, 115, 51, 230, 170, 201, 87, 135, 28, 75, 245, 195, 172, 239, 24, 126, 168, 75, 216, 60, 13, 178, 91,
+         131, 214, 148, 241, 128, 25, 220, 139, 35, 12, 66, 104, 172, 72, 110, 249, 217, 9, 103, 155, 253, 130, 108, 170, 90, 134, 60, 253, 8, 110, 225, 161],
+        [0, 250, 52, 226, 112, 63, 17, 213, 127, 62, 246, 159, 130, 20, 114, 54, 236, 177, 155, 48, 115, 60, 102, 190, 90, 44, 146, 116, 244, 137, 228, 32,
+         172, 16, 74, 182, 54, 159, 69, 238, 178, 202, 163, 232, 43, 190, 17, 130, 61, 173, 237, 48, 184, 77, 19, 245, 38, 218, 160, 100, 178, 130, 57, 201],
+        [107, 167, 21, 128, 32, 198, 139, 96, 169, 9, 88, 32, 197, 78, 150, 214, 0, 101, 199, 16, 226, 153, 218, 2, 206, 236, 16, 199, 35, 73, 109, 201,
+         57, 121, 229, 31, 94, 195, 1, 105, 45, 79, 123, 23, 140, 239, 86, 161, 206, 27, 82, 117, 7, 215, 146, 196, 69, 122, 200, 25, 78, 238, 32, 146],
+        [65, 209, 84, 217, 161, 253, 76, 36, 231, 192, 138, 218, 104, 253, 38, 171, 122, 67, 251, 131, 91, 32, 72, 142, 52, 109, 157, 94, 169, 190, 2, 148,
+         246, 85, 160, 210, 139, 232, 122, 207, 150, 252, 95, 210, 56, 117, 216, 39, 107, 146, 194, 227, 134, 95, 52, 162, 102, 6, 242, 52, 155, 189, 89, 230],
+        [14, 136, 185, 58, 100, 3, 187, 151, 109, 49, 70, 174, 6, 61, 186, 88, 224, 20, 148, 55, 210, 183, 239, 120, 180, 221, 66, 253, 49, 127, 235, 97,
+         43, 184, 7, 111, 41, 78, 172, 57, 25, 180, 6, 148, 184, 75, 11, 179, 251, 69, 43, 161, 25, 182, 234, 30, 221, 176, 82, 140, 217, 10, 124, 173],
+        [222, 109, 36, 153, 229, 121, 54, 239, 23, 203, 245, 121, 155, 228, 128, 26, 159, 192, 80, 172, 6, 105, 158, 14, 84, 31, 134, 19, 211, 84, 31, 165,
+         207, 133, 64, 255, 190, 20, 242, 92, 222, 129, 68, 225, 34, 244, 152, 92, 131, 2, 216, 90, 244, 72, 112, 141, 62, 127, 194, 38, 113, 61, 251, 42],
+        [80, 193, 247, 18, 82, 205, 168, 91, 132, 160, 17, 89, 45, 200, 74, 242, 108, 45, 232, 119, 243, 41, 63, 208, 246, 166, 200, 107, 146, 182, 220, 71,
+         15, 104, 222, 152, 53, 163, 108, 145, 37, 193, 112, 169, 96, 127, 201, 47, 232, 183, 115, 144, 50, 202, 4, 170, 255, 21, 98, 236, 171, 198, 96, 157],
+        [21, 61, 129, 176, 48, 140, 12, 221, 59, 81, 227, 189, 107, 13, 146, 54, 203, 136, 24, 94, 201, 140, 187, 125, 101, 44, 70, 235, 9, 58, 112, 140,
+         242, 197, 33, 91, 124, 217, 4, 208, 76, 248, 12, 53, 233, 18, 66, 168, 31, 81, 206, 15, 176, 131, 225, 81, 48, 207, 158, 5, 76, 27, 138, 210],
+        [232, 167, 92, 226, 110, 244, 39, 194, 114, 174, 35, 140, 247, 166, 221, 94, 3, 165, 217, 56, 156, 75, 29, 225, 17, 150, 192, 86, 167, 249, 25, 161,
+         50, 79, 168, 13, 239, 73, 183, 56, 166, 139, 88, 212, 144, 182, 113, 217, 101, 159, 58, 248, 104, 37, 94, 150, 184, 115, 63, 223, 126, 243, 51, 115],
+        [149, 38, 202, 8, 70, 184, 97, 148, 255, 1, 216, 55, 78, 27, 120, 188, 251, 66, 124, 183, 9, 250, 91, 165, 57, 231, 128, 35, 117, 201, 95, 228,
+         128, 183, 113, 203, 149, 40, 135, 114, 232, 27, 188, 119, 33, 81, 255, 8, 133, 235, 25, 153, 67, 190, 242, 12, 216, 33, 141, 181, 92, 156, 189, 0],
+        [255, 83, 141, 118, 217, 161, 24, 76, 49, 122, 92, 185, 132, 213, 62, 38, 145, 85, 234, 35, 113, 214, 132, 203, 111, 177, 2, 215, 142, 47, 76, 193,
+         3, 41, 251, 61, 99, 226, 199, 20, 97, 47, 242, 159, 60, 208, 150, 54, 197, 74, 181, 121, 223, 140, 28, 123, 75, 97, 250, 17, 41, 220, 71, 106],
+        [54, 214, 181, 30, 51, 124, 229, 198, 170, 232, 157, 206, 15, 173, 229, 110, 177, 21, 200, 97, 162, 63, 40, 14, 76, 255, 97, 66, 239, 175, 19, 151,
+         102, 215, 137, 27, 177, 7, 84, 170, 221, 128, 72, 1, 227, 98, 25, 173, 116, 38, 212, 0, 85, 48, 202, 156, 228, 167, 195, 59, 121, 201, 26, 169],
+        [125, 10, 75, 153, 236, 68, 6, 135, 103, 18, 68, 41, 106, 81, 155, 10, 239, 129, 52, 224, 140, 187, 235, 171, 149, 46, 197, 157, 29, 107, 204, 245,
+         65, 165, 86, 234, 125, 154, 250, 63, 143, 204, 180, 112, 169, 135, 200, 88, 245, 143, 96, 164, 253, 111, 178, 66, 41, 3, 105, 149, 240, 87, 139, 227],
+        [95, 197, 244, 103, 192, 172, 90, 211, 37, 244, 149, 220, 128, 250, 46, 99, 191, 76, 168, 0, 81, 24, 116, 89, 223, 133, 19, 114, 212, 83, 135, 50,
+         118, 17, 190, 48, 75, 187, 43, 109, 11, 35, 88, 250, 29, 50, 234, 6, 62, 218, 22, 189, 63, 14, 218, 92, 240, 130, 212, 77, 173, 11, 186, 38],
+        [155, 58, 133, 16, 39, 142, 251, 55, 178, 119, 88, 189, 3, 180, 69, 207, 147, 32, 253, 105, 197, 245, 55, 207, 10, 68, 182, 230, 41, 166, 5, 218,
+         175, 229, 145, 209, 116, 23, 200, 235, 165, 218, 59, 150, 190, 77, 126, 156, 179, 112, 48, 127, 230, 147, 120, 23, 152, 184, 46, 27, 226, 55, 109, 249],
+        [20, 176, 220, 83, 205, 109, 23, 156, 76, 200, 28, 59, 230, 142, 28, 226, 120, 57, 210, 157, 131, 38, 154, 178, 125, 244, 94, 141, 73, 248, 194, 94,
+         32, 72, 101, 3, 244, 138, 93, 72, 120, 185, 100, 8, 225, 107, 205, 39, 83, 238, 198, 158, 78, 39, 173, 198, 71, 99, 253, 114, 136, 159, 207, 72],
+        [235, 112, 46, 164, 232, 61, 126, 220, 11, 240, 137, 169, 93, 112, 164, 85, 7, 179, 89, 17, 65, 227, 102, 75, 32, 200, 52, 163, 22, 125, 58, 154,
+         133, 253, 42, 163, 65, 179, 220, 15, 144, 47, 233, 132, 64, 166, 13, 251, 136, 26, 98, 5, 207, 104, 248, 54, 221, 8, 170, 64, 192, 85, 3, 127],
+        [146, 26, 199, 100, 0, 151, 190, 91, 163, 107, 40, 215, 17, 246, 44, 192, 240, 138, 219, 117, 173, 194, 5, 238, 147, 110, 8, 216, 186, 106, 237, 11,
+         181, 108, 196, 214, 112, 32, 55, 161, 255, 26, 196, 160, 36, 215, 91, 184, 69, 217, 173, 62, 236, 134, 18, 88, 142, 119, 207, 21, 236, 39, 219, 182],
+    ];
+}
+
+// endregion
+// region Gradient
+
+// piecewise linear color ramp, e.g. for heatmaps of speed or distance
+#[derive(Clone)]
+pub struct Gradient {
+    stops: Vec<(f64, Color)>,
+}
+
+impl Gradient {
+    // stops are (position, color) pairs and get sorted by position
+    pub fn new(stops: &[(f64, Color)]) -> Self {
+        let mut stops: Vec<_> = stops.iter().copied().filter(|(t, _)| !t.is_nan()).collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    // evenly spaced over [0, 1]
+    pub fn even(colors: &[Color]) -> Self {
+        let n = colors.len().saturating_sub(1).max(1) as f64;
+        Self { stops: colors.iter().enumerate().map(|(i, &c)| (i as f64 / n, c)).collect() }
+    }
+
+    pub fn stops(&self) -> &[(f64, Color)] { &self.stops }
+
+    // clamps to the end colors outside the stops; black if there are none
+    pub fn sample(&self, t: f64) -> Color {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(&f), Some(&l)) => (f, l),
+            _ => return Color::default(),
+        };
+        if t.is_nan() || t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+        let i = self.stops.partition_point(|&(p, _)| p <= t);
+        let ((p0, c0), (p1, c1)) = (self.stops[i - 1], self.stops[i]);
+        c0.lerp(c1, (t - p0) / (p1 - p0))
+    }
+}
+
+// endregion
+// region Parallax
+
+// "2.5D photo" wiggle: near parts of the picture follow the cursor, far parts lag behind
+pub struct Parallax {
+    // brighter is nearer; any size, stretched over the image it applies to
+    depth: Image,
+    // offset in pixels at full depth when the cursor is at an edge
+    pub strength: f64,
+    // depth in [0, 1] that stays put
+    pub focus: f64,
+}
+
+impl Parallax {
+    pub fn new(depth: Image) -> Self {
+        Self { depth, strength: 12.0, focus: 0.5 }
+    }
+
+    // loads the depth map from a (grayscale) JPEG
+    #[cfg(feature = "jpeg")]
+    pub fn from_jpeg(jpeg_data: &[u8]) -> Result<Self, DecodeErrors> {
+        Image::from_jpeg(jpeg_data).map(Self::new)
+    }
+
+    // depth in [0, 1] at `p` of a `width` x `height` image
+    pub fn depth(&self, p: Pos, width: usize, height: usize) -> f64 {
+        let scale = Pos::new(
+            self.depth.width as f64 / width.max(1) as f64,
+            self.depth.height as f64 / height.max(1) as f64,
+        );
+        let s = self.depth.sample(Pos::new(p.x * scale.x, p.y * scale.y));
+        // Rec. 601 luma, so a color map works too
+        (0.299 * s.red() + 0.587 * s.green() + 0.114 * s.blue()) / 255.0
+    }
+
+    // where to sample a `width` x `height` image for `p` with the cursor at `l`
+    pub fn source(&self, p: Pos, l: Pos, width: usize, height: usize) -> Pos {
+        let half = Pos::new(width as f64, height as f64) / 2.0;
+        let lean = Pos::new((l.x - half.x) / half.x.max(1.0), (l.y - half.y) / half.y.max(1.0));
+        p - lean * (self.depth(p, width, height) - self.focus) * self.strength
+    }
+}
+
+// endregion
+// region DisplacementMap
+
+// offsets samples by a second image, stretched over the one it applies to:
+// red and green are x and y with mid-gray at rest, or, for a grayscale map,
+// the slopes of the gray values push samples like a refracting surface
+#[derive(Clone)]
+pub struct DisplacementMap {
+    map: Image,
+    gray: bool,
+    // offset in pixels at full red or green, or per unit of slope
+    pub strength: f64,
+}
+
+impl DisplacementMap {
+    pub fn new(map: Image) -> Self {
+        let gray = map.data.chunks_exact(4).all(|p| p[0] == p[1] && p[1] == p[2]);
+        Self { map, gray, strength: 20.0 }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, LoadError> {
+        Image::from_bytes(data).map(Self::new)
+    }
+
+    pub fn is_gray(&self) -> bool { self.gray }
+
+    // offset at `p` of a `width` x `height` image
+    pub fn offset(&self, p: Pos, width: usize, height: usize) -> Pos {
+        let scale = Pos::new(
+            self.map.width as f64 / width.max(1) as f64,
+            self.map.height as f64 / height.max(1) as f64,
+        );
+        let at = |q: Pos| self.map.sample(Pos::new(q.x * scale.x, q.y * scale.y));
+        if !self.gray {
+            let s = at(p);
+            return Pos::new(s.red() / 127.5 - 1.0, s.green() / 127.5 - 1.0) * self.strength;
+        }
+        // one map pixel either side
+        let (dx, dy) = (Pos::new(1.0 / scale.x, 0.0), Pos::new(0.0, 1.0 / scale.y));
+        let slope = Pos::new(at(p + dx).red() - at(p - dx).red(), at(p + dy).red() - at(p - dy).red());
+        slope / 510.0 * self.strength
+    }
+
+    // where to sample a `width` x `height` image for `p`
+    pub fn source(&self, p: Pos, width: usize, height: usize) -> Pos {
+        p + self.offset(p, width, height)
+    }
+}
+
+// endregion
+// region EdgeMap
+
+// how busy the image is around each pixel: the magnitudes of Sobel gradients
+// of its luma, spread over `blur` pixels so that detailed areas like faces read
+// as solid regions, and scaled to [0, 1]
+#[derive(Clone)]
+pub struct EdgeMap {
+    width: usize,
+    height: usize,
+    blur: usize,
+    // luma levels per pixel, before blurring
+    gradient: Vec<Pos>,
+    strength: Vec<f64>,
+    // how much of a displacement the strongest edges hold back; 1 stops it
+    pub resistance: f64,
+}
+
+impl EdgeMap {
+    pub fn new(img: &Image, blur: usize) -> Self {
+        let (width, height) = (img.width, img.height);
+        let gradient = imageops::sobel(img);
+        let mut strength: Vec<f64> = gradient.iter().map(Pos::len).collect();
+        if blur > 0 {
+            strength = imageops::convolve(&strength, 1, width, &imageops::box_kernel(blur));
+        }
+        let max = strength.iter().copied().fold(0.0, f64::max);
+        if max > 0.0 {
+            strength.iter_mut().for_each(|s| *s /= max);
+        }
+        Self { width, height, blur, gradient, strength, resistance: 0.8 }
+    }
+
+    pub fn resistance(self, resistance: f64) -> Self {
+        Self { resistance, ..self }
+    }
+
+    pub fn blur(&self) -> usize { self.blur }
+
+    fn index(&self, p: Pos) -> Option<usize> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let x = (p.x.max(0.0) as usize).min(self.width - 1);
+        let y = (p.y.max(0.0) as usize).min(self.height - 1);
+        Some(x + y * self.width)
+    }
+
+    // the luma gradient at the pixel under `p`, in levels per pixel
+    pub fn gradient(&self, p: Pos) -> Pos {
+        self.index(p).map_or(Pos::default(), |i| self.gradient[i])
+    }
+
+    // in [0, 1], at the pixel under `p`
+    pub fn strength(&self, p: Pos) -> f64 {
+        self.index(p).map_or(0.0, |i| self.strength[i])
+    }
+
+    // the fraction of a displacement at `p` that gets through
+    pub fn attenuation(&self, p: Pos) -> f64 {
+        (1.0 - self.resistance * self.strength(p)).clamp(0.0, 1.0)
+    }
+}
+
+// endregion
+// region Render
+
+// runs `f` for every pixel of an RGBA frame; alpha bytes are left untouched
+pub fn run_shader<F>(frame: &mut [u8], width: usize, f: F)
+where
+    F: Fn(Pos) -> Color + Sync,
+{
+    run_shader_dithered(frame, width, Dither::None, f);
+}
+
+// like `run_shader`, quantizing to bytes with `dither`
+pub fn run_shader_dithered<F>(frame: &mut [u8], width: usize, dither: Dither, f: F)
+where
+    F: Fn(Pos) -> Color + Sync,
+{
+    for_each_pixel_rows(frame, width, |x, y, pixel_bytes| {
+        f(Pos::new(x as f64, y as f64)).write_bytes_dithered(pixel_bytes, dither.threshold(x, y));
+    });
+}
+
+// calls `f(x, y, pixel)` for every 4 byte pixel of an RGBA frame; rows are
+// spread over threads and x counts up within each, sparing a division per pixel.
+// a cached buffer of every pixel's `Pos` measured no faster than converting
+// these, while costing 16 bytes a pixel, so there isn't one
+pub fn for_each_pixel_rows<F>(frame: &mut [u8], width: usize, f: F)
+where
+    F: Fn(usize, usize, &mut [u8]) + Sync,
+{
+    if width == 0 {
+        return;
+    }
+    let row = |(y, row): (usize, &mut [u8])| {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            f(x, y, pixel);
+        }
+    };
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        frame.par_chunks_exact_mut(4 * width).enumerate().for_each(row);
+    }
+    #[cfg(not(feature = "parallel"))]
+    frame.chunks_exact_mut(4 * width).enumerate().for_each(row);
+}
+
+// like `run_shader_dithered`, only for the pixels `rect` covers
+pub fn run_shader_region<F>(frame: &mut [u8], width: usize, dither: Dither, rect: Rect, f: F)
+where
+    F: Fn(Pos) -> Color + Sync,
+{
+    if width == 0 {
+        return;
+    }
+    let (xs, ys) = rect.pixel_ranges(width, frame.len() / 4 / width);
+    let row = |(y, row): (usize, &mut [u8])| {
+        for (x, pixel_bytes) in (xs.start..).zip(row[4 * xs.start..4 * xs.end].chunks_exact_mut(4)) {
+            f(Pos::new(x as f64, y as f64)).write_bytes_dithered(pixel_bytes, dither.threshold(x, y));
+        }
+    };
+    let band = &mut frame[4 * width * ys.start..4 * width * ys.end];
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        band.par_chunks_exact_mut(4 * width).enumerate().for_each(|(i, r)| row((ys.start + i, r)));
+    }
+    #[cfg(not(feature = "parallel"))]
+    band.chunks_exact_mut(4 * width).enumerate().for_each(|(i, r)| row((ys.start + i, r)));
+}
+
+// how frames are split between threads
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Tiling {
+    // whole rows, see `for_each_pixel_rows`
+    #[default]
+    Rows,
+    // squares this many pixels a side, see `run_shader_tiled`
+    Tiles(usize),
+    // squares sized so that every thread gets a few rows of them
+    Auto,
+}
+
+impl Tiling {
+    // side of the squares for a frame `height` pixels tall, None for rows
+    pub fn tile_size(self, height: usize) -> Option<usize> {
+        match self {
+            Tiling::Rows => None,
+            Tiling::Tiles(side) => Some(side.max(1)),
+            Tiling::Auto => {
+                #[cfg(feature = "parallel")]
+                let threads = rayon::current_num_threads();
+                #[cfg(not(feature = "parallel"))]
+                let threads = 1;
+                Some((height / (4 * threads)).next_power_of_two().clamp(16, 128))
+            }
+        }
+    }
+}
+
+// calls `tile(rect)` for every `side` pixel square of an RGBA frame, then the
+// closure it returns with `(x, y, pixel)` for each pixel inside; `rect` is
+// half-open like `Rect::contains`. rows of squares run in parallel, the
+// squares in each one after the other
+pub fn for_each_tile<T, P>(frame: &mut [u8], width: usize, side: usize, tile: T)
+where
+    T: Fn(Rect) -> P + Sync,
+    P: FnMut(usize, usize, &mut [u8]),
+{
+    if width == 0 {
+        return;
+    }
+    let side = side.max(1);
+    let band = |(row, band): (usize, &mut [u8])| {
+        let (y0, rows) = (row * side, band.len() / 4 / width);
+        for x0 in (0..width).step_by(side) {
+            let x1 = (x0 + side).min(width);
+            let mut pixel = tile(Rect::new(Pos::new(x0 as f64, y0 as f64), Pos::new(x1 as f64, (y0 + rows) as f64)));
+            for y in 0..rows {
+                for (x, px) in band[4 * (x0 + y * width)..4 * (x1 + y * width)].chunks_exact_mut(4).enumerate() {
+                    pixel(x0 + x, y0 + y, px);
+                }
+            }
+        }
+    };
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        frame.par_chunks_mut(4 * width * side).enumerate().for_each(band);
+    }
+    #[cfg(not(feature = "parallel"))]
+    frame.chunks_mut(4 * width * side).enumerate().for_each(band);
+}
+
+// like `run_shader_dithered`, a `side` pixel square at a time so that nearby
+// pixels sample nearby parts of the source; `f(tile)` gives the shader for
+// the pixels of `tile`, which lets it skip work the effect doesn't need there
+pub fn run_shader_tiled<F, S>(frame: &mut [u8], width: usize, dither: Dither, side: usize, f: F)
+where
+    F: Fn(Rect) -> S + Sync,
+    S: Fn(Pos) -> Color,
+{
+    for_each_tile(frame, width, side, |tile| {
+        let shader = f(tile);
+        move |x, y, pixel_bytes: &mut [u8]| {
+            shader(Pos::new(x as f64, y as f64)).write_bytes_dithered(pixel_bytes, dither.threshold(x, y));
+        }
+    });
+}
+
+#[derive(Copy, Clone)]
+pub enum StereoMode {
+    // red from the left eye, green and blue from the right; for red/cyan glasses
+    Anaglyph,
+    // each eye squeezed to half width, left eye on the left (half-SBS)
+    SideBySide,
+}
+
+#[derive(Copy, Clone)]
+pub struct Stereo {
+    pub mode: StereoMode,
+    // horizontal eye offset per pixel of displacement
+    pub separation: f64,
+}
+
+impl Stereo {
+    pub fn new(mode: StereoMode) -> Self {
+        Self { mode, separation: 0.15 }
+    }
+}
+
+// like `run_shader`, but `f(p, eye)` is shaded for the left (eye = -1.0) and
+// right (eye = 1.0) eyes and the two views are composited according to `mode`
+pub fn run_stereo<F>(frame: &mut [u8], width: usize, mode: StereoMode, f: F)
+where
+    F: Fn(Pos, f64) -> Color + Sync,
+{
+    run_shader(frame, width, |p| stereo_view(p, width, mode, &f));
+}
+
+fn stereo_view<F>(p: Pos, width: usize, mode: StereoMode, f: &F) -> Color
+where
+    F: Fn(Pos, f64) -> Color,
+{
+    let half = width as f64 / 2.0;
+    match mode {
+        StereoMode::Anaglyph => {
+            let (l, r) = (f(p, -1.0), f(p, 1.0));
+            Color::new(l.red, r.green, r.blue)
+        }
+        StereoMode::SideBySide if p.x < half => f(Pos::new(p.x * 2.0, p.y), -1.0),
+        StereoMode::SideBySide => f(Pos::new((p.x - half) * 2.0, p.y), 1.0),
+    }
+}
+
+// a finger on a touchscreen
+struct Pointer {
+    id: u64,
+    pos: Pos,
+    last: Pos,
+    velocity: Smooth<Pos>,
+}
+
+// pointer velocity below which the warp moves pixels by about a hundredth
+// of a pixel
+const STILL: f64 = 0.01;
+
+// offscreen counterpart of the demo: tracks the cursor and renders warped frames of `img`
+pub struct Renderer {
+    img: Image,
+    cursor: Pos,
+    last: Pos,
+    velocity: Smooth<Pos>,
+    // raw mouse movement since the last update, if any came
+    raw: Option<Pos>,
+    // see `cursor_path`, with ages in seconds
+    path: Vec<(f64, Pos)>,
+    touches: Vec<Pointer>,
+    // the cursor and then every touch, as of the last update
+    influencers: Vec<(Pos, Pos)>,
+    stereo: Option<Stereo>,
+    // source panorama and camera when `img` is a projected view of it
+    panorama: Option<(Image, Viewport)>,
+    parallax: Option<Parallax>,
+    map: Option<DisplacementMap>,
+    // holds displacement back around edges, see `EdgeMap`
+    edges: Option<EdgeMap>,
+    curves: Option<Curves>,
+    dither: Dither,
+    tiling: Tiling,
+    // shades every `resolution`th pixel each way into `low` and enlarges that
+    resolution: usize,
+    low: Vec<u8>,
+    effect: Effect,
+    shockwaves: Shockwaves,
+    // only while the effect is `Effect::Fluid`
+    field: Option<DisplacementField>,
+    // only while the effect is `Effect::Water`
+    water: Option<WaterSurface>,
+    // only while the effect is `Effect::Spring`
+    mesh: Option<SpringMesh>,
+    breathing: Option<Breathing>,
+    // seconds since the cursor last moved, and how far breathing has faded in
+    idle: f64,
+    idle_weight: f64,
+    // seconds rendered so far, for the animated effects
+    time: f64,
+    debug: DebugOverlay,
+    // per tile, while `debug.timing` is on
+    tile_times: Vec<Duration>,
+    // the image with no effect, encoded and dithered, which the plain warp
+    // copies wherever no pointer is moving
+    clean: Option<Vec<u8>>,
+    // whether the last frame was all `clean`
+    settled: bool,
+    postfx: Option<PostFx>,
+    // composited over the image after the effect, before `postfx`
+    scene: Option<Scene>,
+    // shades on this instead of rayon's global pool when set
+    #[cfg(feature = "parallel")]
+    pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl Renderer {
+    pub fn new(img: Image) -> Self {
+        Self {
+            img,
+            cursor: Pos::default(),
+            last: Pos::default(),
+            velocity: Smooth::default(),
+            raw: None,
+            path: Vec::new(),
+            touches: Vec::new(),
+            influencers: Vec::new(),
+            stereo: None,
+            panorama: None,
+            parallax: None,
+            map: None,
+            edges: None,
+            curves: None,
+            dither: Dither::None,
+            tiling: Tiling::Rows,
+            resolution: 1,
+            low: Vec::new(),
+            effect: Effect::default(),
+            shockwaves: Shockwaves::default(),
+            field: None,
+            water: None,
+            mesh: None,
+            breathing: None,
+            idle: 0.0,
+            idle_weight: 0.0,
+            time: 0.0,
+            debug: DebugOverlay::default(),
+            tile_times: Vec::new(),
+            clean: None,
+            settled: false,
+            postfx: None,
+            scene: None,
+            #[cfg(feature = "parallel")]
+            pool: None,
+        }
+    }
+
+    // warps what `viewport` sees of an equirectangular `pano` instead of a flat image
+    pub fn panorama(pano: Image, viewport: Viewport) -> Self {
+        let mut r = Self::new(viewport.project(&pano));
+        r.panorama = Some((pano, viewport));
+        r
+    }
+
+    pub fn viewport(&self) -> Option<&Viewport> {
+        self.panorama.as_ref().map(|(_, v)| v)
+    }
+
+    // moves the panorama camera; does nothing for flat images
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        if let Some((pano, v)) = &mut self.panorama {
+            *v = viewport;
+            let (linear, filter) = (self.img.is_linear(), self.img.filter());
+            self.img = viewport.project(pano);
+            self.img.set_linear(linear);
+            self.img.set_filter(filter);
+            self.clean = None;
+            if let Some(edges) = &mut self.edges {
+                *edges = EdgeMap::new(&self.img, edges.blur).resistance(edges.resistance);
+            }
+        }
+    }
+
+    pub fn set_stereo(&mut self, stereo: Option<Stereo>) {
+        self.stereo = stereo;
+    }
+
+    pub fn set_parallax(&mut self, parallax: Option<Parallax>) {
+        self.parallax = parallax;
+    }
+
+    // idle animation once the cursor has been still for a while
+    pub fn set_breathing(&mut self, breathing: Option<Breathing>) {
+        self.breathing = breathing;
+    }
+
+    // applied under whichever effect is active
+    pub fn set_displacement_map(&mut self, map: Option<DisplacementMap>) {
+        self.map = map;
+    }
+
+    pub fn displacement_map(&self) -> Option<&DisplacementMap> { self.map.as_ref() }
+
+    // weakens whichever effect is active around edges; it has to be made
+    // from this renderer's image
+    pub fn set_edge_map(&mut self, edges: Option<EdgeMap>) {
+        self.edges = edges;
+    }
+
+    pub fn edge_map(&self) -> Option<&EdgeMap> { self.edges.as_ref() }
+
+    // tone curves applied to the output, after it has been encoded back to sRGB
+    pub fn set_curves(&mut self, curves: Option<Curves>) {
+        self.curves = curves;
+        self.clean = None;
+    }
+
+    pub fn set_dither(&mut self, dither: Dither) {
+        self.dither = dither;
+        self.clean = None;
+    }
+
+    pub fn set_tiling(&mut self, tiling: Tiling) {
+        self.tiling = tiling;
+    }
+
+    pub fn tiling(&self) -> Tiling { self.tiling }
+
+    // 1 for full resolution, 2 for half each way and so on, see
+    // `DynamicResolution`; skipped while timing tiles
+    pub fn set_resolution_scale(&mut self, scale: usize) {
+        self.resolution = scale.max(1);
+    }
+
+    pub fn resolution_scale(&self) -> usize { self.resolution }
+
+    // renders on `pool` rather than rayon's global one, so embedders can keep
+    // it off the cores their audio or other real-time threads run on
+    #[cfg(feature = "parallel")]
+    pub fn set_thread_pool(&mut self, pool: Option<Arc<rayon::ThreadPool>>) {
+        self.pool = pool;
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool(&self) -> Option<&Arc<rayon::ThreadPool>> { self.pool.as_ref() }
+
+    // a pool of its own with `threads` workers, 0 for one per core
+    #[cfg(feature = "parallel")]
+    pub fn set_threads(&mut self, threads: usize) -> Result<(), rayon::ThreadPoolBuildError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| alloc::format!("doggowarp-{}", i))
+            .build()?;
+        self.pool = Some(Arc::new(pool));
+        Ok(())
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.img.set_filter(filter);
+        self.clean = None;
+    }
+
+    // see `Image::set_tonemap`
+    pub fn set_tonemap(&mut self, exposure: f64, tonemap: Tonemap) {
+        self.img.set_tonemap(exposure, tonemap);
+        self.clean = None;
+    }
+
+    // passes over every frame after the effect, under the debug overlays
+    pub fn set_postfx(&mut self, postfx: Option<PostFx>) {
+        self.postfx = postfx;
+    }
+
+    pub fn postfx(&self) -> Option<&PostFx> { self.postfx.as_ref() }
+
+    // for switching passes on and off between frames
+    pub fn postfx_mut(&mut self) -> Option<&mut PostFx> { self.postfx.as_mut() }
+
+    // images over the warped one, see `scene::Layer`
+    pub fn set_scene(&mut self, scene: Option<Scene>) {
+        self.scene = scene;
+    }
+
+    pub fn scene(&self) -> Option<&Scene> { self.scene.as_ref() }
+
+    // for moving and fading layers between frames
+    pub fn scene_mut(&mut self) -> Option<&mut Scene> { self.scene.as_mut() }
+
+    pub fn set_debug(&mut self, debug: DebugOverlay) {
+        self.debug = debug;
+    }
+
+    pub fn debug(&self) -> DebugOverlay { self.debug }
+
+    // render time of every `debug::TILE` square in the last frame, in rows;
+    // empty unless timing is on
+    pub fn tile_times(&self) -> &[Duration] { &self.tile_times }
+
+    // how long frames stay like the last one if the cursor doesn't move, see
+    // `AppState::idle`; None unless that was the unwarped image
+    pub fn settled(&self) -> Option<Duration> {
+        if !self.settled {
+            return None;
+        }
+        match &self.breathing {
+            Some(b) => Some(Duration::from_secs_f64((b.after - self.idle).max(0.0))),
+            None => Some(Duration::MAX),
+        }
+    }
+
+    pub fn effect(&self) -> Effect { self.effect }
+
+    pub fn set_effect(&mut self, effect: Effect) {
+        self.effect = effect;
+        if !matches!(effect, Effect::Fluid(_)) {
+            self.field = None;
+        }
+        if !matches!(effect, Effect::Water { .. }) {
+            self.water = None;
+        }
+        if !matches!(effect, Effect::Spring { .. }) {
+            self.mesh = None;
+        }
+    }
+
+    // starts a shockwave at the cursor
+    pub fn shockwave(&mut self) {
+        self.shockwaves.push(self.cursor);
+    }
+
+    // a click at the cursor: a splash on water, a shockwave otherwise
+    pub fn click(&mut self) {
+        match &mut self.water {
+            Some(water) => water.disturb(self.cursor, 24.0, 80.0),
+            None => self.shockwave(),
+        }
+    }
+
+    pub fn shockwaves(&mut self) -> &mut Shockwaves { &mut self.shockwaves }
+
+    pub fn image(&self) -> &Image { &self.img }
+
+    // warps `img` from now on, with the same filter and everything else; the
+    // simulations start over if the size changes, and an edge map is redone
+    pub fn set_image(&mut self, mut img: Image) {
+        if (img.width, img.height) != (self.img.width, self.img.height) {
+            (self.field, self.water, self.mesh) = (None, None, None);
+        }
+        img.set_filter(self.img.filter());
+        img.set_linear(self.img.is_linear());
+        self.img = img;
+        // a view of the panorama would be drawn over it
+        self.panorama = None;
+        self.edges = self.edges.take().map(|e| EdgeMap::new(&self.img, e.blur()).resistance(e.resistance));
+        self.clean = None;
+    }
+
+    // blend the warp's taps in linear light instead of on sRGB-encoded bytes
+    pub fn set_linear(&mut self, linear: bool) {
+        self.img.set_linear(linear);
+        self.clean = None;
+    }
+
+    pub fn mousemove(&mut self, pos: Pos) {
+        self.cursor = pos;
+    }
+
+    // every cursor position since the last frame, oldest first, with how long
+    // before the frame it was reported; without it only the last one counts
+    pub fn cursor_path(&mut self, path: &[(Duration, Pos)]) {
+        self.path.extend(path.iter().map(|&(age, p)| (age.as_secs_f64(), p)));
+    }
+
+    // raw mouse movement in frame pixels; while it keeps coming, the velocity
+    // is estimated from it rather than from the coalesced cursor positions
+    pub fn raw_motion(&mut self, delta: Pos) {
+        self.raw = Some(self.raw.unwrap_or_default() + delta);
+    }
+
+    // a finger `id` went down or moved; the warp follows every finger
+    pub fn touch_move(&mut self, id: u64, pos: Pos) {
+        match self.touches.iter_mut().find(|t| t.id == id) {
+            Some(t) => t.pos = pos,
+            None => self.touches.push(Pointer { id, pos, last: pos, velocity: Smooth::default() }),
+        }
+    }
+
+    pub fn touch_end(&mut self, id: u64) {
+        self.touches.retain(|t| t.id != id);
+    }
+
+    // cursor velocity in pixels per second over the path since the last update,
+    // weighing later movement more so the warp follows the end of a flick
+    fn path_velocity(&self, delta: Duration) -> Option<Pos> {
+        let frame = delta.as_secs_f64();
+        let (mut sum, mut weights) = (Pos::default(), 0.0);
+        let mut prev = (frame, self.last);
+        for &(age, p) in &self.path {
+            let age = age.min(frame);
+            let dt = prev.0 - age;
+            if dt > 0.0 {
+                let w = dt * (1.0 - age / frame);
+                sum += (p - prev.1) / dt * w;
+                weights += w;
+            }
+            prev = (age, p);
+        }
+        (weights > 0.0).then(|| sum / weights)
+    }
+
+    // forgets the motion history, e.g. after a long stall would produce a huge jump
+    pub fn reset(&mut self) {
+        self.last = self.cursor;
+        self.raw = None;
+        self.path.clear();
+        self.velocity = Smooth::default();
+        for t in &mut self.touches {
+            t.last = t.pos;
+            t.velocity = Smooth::default();
+        }
+        if let Some(field) = &mut self.field {
+            field.clear();
+        }
+        if let Some(water) = &mut self.water {
+            water.clear();
+        }
+        if let Some(mesh) = &mut self.mesh {
+            mesh.clear();
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) -> (Pos, Pos) {
+        let location = self.cursor;
+        let rate = match self.raw.take() {
+            Some(raw) => raw / delta.as_secs_f64(),
+            None => self.path_velocity(delta).unwrap_or((location - self.last) / delta.as_secs_f64()),
+        };
+        self.path.clear();
+        // smoothing only ever approaches zero, so slow enough pointers count as still
+        let settle = |v: Pos| if v.len_sq() < STILL * STILL { Pos::default() } else { v };
+        let velocity = settle(self.velocity.update(rate * 0.2));
+        let moved = location - self.last;
+        self.last = location;
+        self.influencers.clear();
+        self.influencers.push((location, velocity));
+        for t in &mut self.touches {
+            let v = settle(t.velocity.update((t.pos - t.last) * 0.2 / delta.as_secs_f64()));
+            t.last = t.pos;
+            self.influencers.push((t.pos, v));
+        }
+        self.time += delta.as_secs_f64();
+        self.shockwaves.update(delta);
+        if let Some(b) = &self.breathing {
+            self.idle = if moved.len_sq() > 0.0 { 0.0 } else { self.idle + delta.as_secs_f64() };
+            let target = if self.idle >= b.after { 1.0 } else { 0.0 };
+            let step = delta.as_secs_f64() / b.fade.max(f64::EPSILON);
+            self.idle_weight = (self.idle_weight + (target - self.idle_weight).clamp(-step, step)).clamp(0.0, 1.0);
+        }
+        if let Effect::Fluid(w) = &self.effect {
+            let (width, height) = (self.img.width, self.img.height);
+            let field = self.field.get_or_insert_with(|| DisplacementField::new(width, height, 8));
+            for &(l, v) in &self.influencers {
+                field.inject(w, l, v, delta);
+            }
+            field.update(delta);
+        }
+        if let Effect::Water { damping, .. } = self.effect {
+            let (width, height) = (self.img.width, self.img.height);
+            let water = self.water.get_or_insert_with(|| WaterSurface::new(width, height, 2));
+            water.damping = damping;
+            // a wake behind the cursor, deeper the faster it moves
+            if moved.len() > 0.0 {
+                water.disturb(location, 10.0, moved.len().min(40.0) * 0.5);
+            }
+            water.update(delta);
+        }
+        if let Effect::Spring { radius } = self.effect {
+            let (width, height) = (self.img.width, self.img.height);
+            let mesh = self.mesh.get_or_insert_with(|| SpringMesh::new(width, height, 16));
+            mesh.drag(location, moved, radius);
+            mesh.update(delta);
+        }
+        (location, velocity)
+    }
+
+    // renders into `surface` and shows it; its buffer has to be the image's size
+    pub fn render_to<P: Present + ?Sized>(&mut self, delta: Duration, surface: &mut P) -> Result<(), PresentError> {
+        self.render(delta, surface.frame_mut());
+        surface.present()
+    }
+
+    // `frame` is RGBA with the same dimensions as the image
+    pub fn render(&mut self, delta: Duration, frame: &mut [u8]) {
+        #[cfg(feature = "parallel")]
+        if let Some(pool) = self.pool.clone() {
+            return pool.install(|| self.render_frame(delta, frame));
+        }
+        self.render_frame(delta, frame)
+    }
+
+    fn render_frame(&mut self, delta: Duration, frame: &mut [u8]) {
+        let (location, velocity) = self.update(delta);
+        self.settled = false;
+        let img = &self.img;
+        let curves = self.curves.as_ref();
+        let encode = |c: Color| {
+            let c = if img.is_linear() { c.to_srgb() } else { c };
+            curves.map_or(c, |curves| curves.apply(c))
+        };
+        let parallax = self.parallax.as_ref();
+        let (map, shockwaves) = (self.map.as_ref(), Some(&self.shockwaves).filter(|s| !s.is_empty()));
+        let weight = self.idle_weight * self.idle_weight * (3.0 - 2.0 * self.idle_weight);
+        let (breathing, t) = (self.breathing.as_ref().filter(|_| weight > 0.0), self.time);
+        let source = |p: Pos| {
+            let p = breathing.map_or(p, |b| p + b.displacement(p, img.width, img.height, t) * weight);
+            let p = match parallax {
+                Some(px) => px.source(p, location, img.width, img.height),
+                None => p,
+            };
+            let p = map.map_or(p, |m| m.source(p, img.width, img.height));
+            shockwaves.map_or(p, |s| p + s.displacement(p))
+        };
+        let (width, dither, effect, time) = (img.width, self.dither, &self.effect, self.time);
+        let influencers = &self.influencers[..];
+        let scene = self.scene.as_ref().filter(|s| s.is_active()).map(|s| s.frame(influencers, location, velocity));
+        // whether anything but the effect moves pixels around, or covers them
+        let layered = breathing.is_some() || parallax.is_some() || map.is_some() || shockwaves.is_some() || self.stereo.is_some()
+            || scene.is_some();
+        // still pointers don't warp anything, so the plain warp only shades
+        // around moving ones and copies the `clean` frame everywhere else
+        let scaled = self.resolution > 1 && !self.debug.timing;
+        let edges = self.edges.as_ref();
+        if let (Effect::Warp(w), false) = (effect, layered || scaled || edges.is_some() || self.debug.heatmap || self.debug.timing) {
+            if self.clean.as_ref().is_some_and(|c| c.len() != frame.len()) {
+                self.clean = None;
+            }
+            let clean = self.clean.get_or_insert_with(|| {
+                let mut clean = frame.to_vec();
+                run_shader_dithered(&mut clean, width, dither, |p| encode(img.sample(p).color()));
+                clean
+            });
+            frame.copy_from_slice(clean);
+            let bounds = Rect::from_size(Pos::default(), width as f64, img.height as f64);
+            let dirty = influencers.iter().filter(|(_, v)| v.len_sq() > 0.0)
+                .filter_map(|&(l, _)| Rect::around(l, w.radius).intersection(bounds))
+                .reduce(|a, b| a.union(b));
+            self.settled = dirty.is_none();
+            let shade = |p: Pos| encode(warp_split(w, img, p, warp_displacement_sum(w, p, influencers)));
+            match dirty {
+                // vectorized when there is nothing to encode
+                #[cfg(feature = "simd")]
+                Some(dirty) if curves.is_none() && !img.is_linear() => {
+                    simd::run_warp_region(frame, width, dither, dirty, w, img, influencers);
+                }
+                Some(dirty) => run_shader_region(frame, width, dither, dirty, shade),
+                None => {}
+            }
+            self.tile_times.clear();
+            self.post_process(frame);
+            self.draw_debug(frame);
+            return;
+        }
+        let (field, water, mesh) = (self.field.as_ref(), self.water.as_ref(), self.mesh.as_ref());
+        let displacement = |p: Pos| {
+            let d = match (effect, field, water, mesh) {
+                (Effect::Warp(w), ..) => warp_displacement_sum(w, p, influencers),
+                (Effect::Fluid(_), Some(field), ..) => field.sample(p),
+                (Effect::Water { refraction, .. }, _, Some(water), _) => water.gradient(p) * *refraction,
+                (Effect::Spring { .. }, .., Some(mesh)) => mesh.sample(p),
+                _ => effect.displacement(p, location, velocity, time),
+            };
+            edges.map_or(d, |e| d * e.attenuation(p))
+        };
+        let shade = |p: Pos| match effect {
+            Effect::Warp(w) | Effect::Fluid(w) => warp_split(w, img, p, displacement(p)),
+            _ => img.sample(p + displacement(p)).color(),
+        };
+        // the color of `p`, which samples around `q` after the layers
+        let heat = self.debug.heatmap.then(debug::heatmap);
+        let scene = scene.as_deref().unwrap_or_default();
+        let pixel = |p: Pos, q: Pos| {
+            let c = scene.iter().fold(shade(q), |c, layer| layer.over(c, p, time, img.is_linear()));
+            let c = encode(c);
+            match &heat {
+                Some(g) => c.lerp(g.sample((q - p + displacement(q)).len() / debug::HEAT_RANGE), 0.5),
+                None => c,
+            }
+        };
+        // the warp leaves tiles out of every pointer's reach as they are
+        let reach = match effect {
+            Effect::Warp(w) if !layered => Some(w.radius),
+            _ => None,
+        };
+        let still = |tile: Rect| reach.is_some_and(|r| influencers.iter().all(|&(l, _)| tile.clamp(l).dist(l) >= r));
+        let plain = |p: Pos| {
+            let c = encode(img.sample(p).color());
+            heat.as_ref().map_or(c, |g| c.lerp(g.sample(0.0), 0.5))
+        };
+        // scaled down, each pixel of `low` stands for the center of a k x k block
+        let k = if scaled { self.resolution } else { 1 };
+        let offset = (k - 1) as f64 / 2.0;
+        let up = |p: Pos| p * k as f64 + Pos::new(offset, offset);
+        let (out, out_width) = match k {
+            1 => (&mut *frame, width),
+            _ => {
+                let (low_width, low_height) = (width.div_ceil(k), img.height.div_ceil(k));
+                self.low.resize(low_width * low_height * 4, 255);
+                (&mut self.low[..], low_width)
+            }
+        };
+        let still = |tile: Rect| still(Rect::new(up(tile.min), up(tile.max)));
+        let side = self.tiling.tile_size(out.len() / 4 / out_width.max(1));
+        self.tile_times.clear();
+        let times = self.debug.timing.then_some(&mut self.tile_times);
+        match self.stereo {
+            None => shade_frame(out, out_width, dither, side, times, still, |p, still| {
+                let p = up(p);
+                if still { plain(p) } else { pixel(p, source(p)) }
+            }),
+            // parts that are pushed around more pop out further
+            Some(stereo) => {
+                let eye = |p: Pos, eye: f64| {
+                    let shift = displacement(p).len() * stereo.separation * eye;
+                    pixel(p, source(p + Pos::new(shift, 0.0)))
+                };
+                shade_frame(out, out_width, dither, side, times, still, |p, _| stereo_view(up(p), width, stereo.mode, &eye))
+            }
+        }
+        if k > 1 {
+            upscale(&self.low, width.div_ceil(k), frame, width, k);
+        }
+        self.post_process(frame);
+        self.draw_debug(frame);
+    }
+
+    // animated passes keep the frame from ever settling
+    fn post_process(&mut self, frame: &mut [u8]) {
+        if let Some(postfx) = self.postfx.as_mut().filter(|p| p.is_active()) {
+            postfx.apply(frame, self.img.width);
+            self.settled &= !postfx.animated();
+        }
+    }
+
+    fn draw_debug(&self, frame: &mut [u8]) {
+        let width = self.img.width;
+        for &(l, v) in &self.influencers {
+            if let Some(radius) = self.effect.radius().filter(|_| self.debug.falloff) {
+                debug::draw_circle(frame, width, l, radius, Color::YELLOW);
+            }
+            // as long as the warp's displacement at the cursor at unit strength
+            if self.debug.velocity {
+                debug::draw_line(frame, width, l, l + v, Color::RED);
+            }
+        }
+        // of the frame as rendered, before the other overlays land on it
+        let histogram = self.debug.histogram.then(|| imageops::Histogram::of(frame));
+        if self.debug.timing {
+            debug::draw_tile_times(frame, width, debug::TILE, &self.tile_times);
+        }
+        if let Some(h) = histogram {
+            debug::draw_histogram(frame, width, &h);
+        }
+    }
+}
+
+// bilinear `k` times enlargement of RGBA `low` onto the colors of `frame`,
+// with each pixel of `low` at the center of its k x k block
+fn upscale(low: &[u8], low_width: usize, frame: &mut [u8], width: usize, k: usize) {
+    let low_height = low.len() / 4 / low_width.max(1);
+    if low_width == 0 || low_height == 0 {
+        return;
+    }
+    let axis = |v: usize, size: usize| {
+        let s = ((v as f64 + 0.5) / k as f64 - 0.5).clamp(0.0, (size - 1) as f64);
+        let i = s.floor();
+        (i as usize, s - i, (i as usize + 1).min(size - 1))
+    };
+    for_each_pixel_rows(frame, width, |x, y, px| {
+        let ((x0, fx, x1), (y0, fy, y1)) = (axis(x, low_width), axis(y, low_height));
+        for (c, byte) in px[..3].iter_mut().enumerate() {
+            let at = |x: usize, y: usize| low[4 * (x + y * low_width) + c] as f64;
+            let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * fx;
+            let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * fx;
+            *byte = (top + (bottom - top) * fy + 0.5) as u8;
+        }
+    });
+}
+
+// `debug::run_shader_timed` into `times` if given, else `run_shader_tiled`
+// with `side` or `run_shader_dithered`; `f(p, still)` is told whether
+// `still` found the tile of `p` out of the effect's reach
+fn shade_frame<S, F>(frame: &mut [u8], width: usize, dither: Dither, side: Option<usize>, times: Option<&mut Vec<Duration>>, still: S, f: F)
+where
+    S: Fn(Rect) -> bool + Sync,
+    F: Fn(Pos, bool) -> Color + Sync,
+{
+    match (times, side) {
+        (Some(times), _) => debug::run_shader_timed(frame, width, dither, debug::TILE, times, |p| f(p, false)),
+        (None, Some(side)) => run_shader_tiled(frame, width, dither, side, |tile| {
+            let (f, still) = (&f, still(tile));
+            move |p| f(p, still)
+        }),
+        (None, None) => run_shader_dithered(frame, width, dither, |p| f(p, false)),
+    }
+}
+
+// endregion
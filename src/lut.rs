@@ -0,0 +1,136 @@
+// 3D color lookup tables from `.cube` files, the format grading tools like
+// Resolve export, for film looks without shader code. `Image::apply_lut`
+// grades a source once, `postfx::Pass::Lut` every finished frame.
+
+use core::fmt;
+use core::str::FromStr;
+use alloc::vec::Vec;
+use crate::for_each_pixel_rows;
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+// region Lut
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut {
+    // samples along each axis
+    size: usize,
+    // outputs with red changing fastest, then green, then blue, as in the file
+    table: Vec<[f64; 3]>,
+    // the input range the table spans, per channel
+    min: [f64; 3],
+    max: [f64; 3],
+}
+
+impl Lut {
+    pub fn size(&self) -> usize { self.size }
+
+    // the graded color for `rgb` in the table's domain, [0, 1] unless the file
+    // said otherwise, interpolated trilinearly; inputs outside are clamped
+    pub fn lookup(&self, rgb: [f64; 3]) -> [f64; 3] {
+        let n = self.size - 1;
+        let mut i = [0; 3];
+        let mut t = [0.0; 3];
+        for c in 0..3 {
+            let span = self.max[c] - self.min[c];
+            let v = ((rgb[c] - self.min[c]) / span * n as f64).clamp(0.0, n as f64);
+            // the last cell takes the top edge, so `i + 1` stays in the table
+            i[c] = (v as usize).min(n - 1);
+            t[c] = v - i[c] as f64;
+        }
+        let at = |r: usize, g: usize, b: usize| self.table[(i[0] + r) + (i[1] + g) * self.size + (i[2] + b) * self.size * self.size];
+        let lerp = |a: [f64; 3], b: [f64; 3], t: f64| [0, 1, 2].map(|c| a[c] + (b[c] - a[c]) * t);
+        let plane = |b: usize| {
+            let low = lerp(at(0, 0, b), at(1, 0, b), t[0]);
+            lerp(low, lerp(at(0, 1, b), at(1, 1, b), t[0]), t[1])
+        };
+        lerp(plane(0), plane(1), t[2])
+    }
+
+    // grades RGBA `frame` in place, blending `mix` in [0, 1] of the graded
+    // color over the original; alpha is left alone
+    pub fn apply(&self, frame: &mut [u8], width: usize, mix: f64) {
+        for_each_pixel_rows(frame, width, |_, _, px| {
+            let rgb = [0, 1, 2].map(|c| px[c] as f64 / 255.0);
+            for (c, v) in self.lookup(rgb).into_iter().enumerate() {
+                let v = rgb[c] + (v - rgb[c]) * mix;
+                px[c] = (v * 255.0).round().clamp(0.0, 255.0) as u8;
+            }
+        });
+    }
+}
+
+// endregion
+// region Parsing
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLutError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseLutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseLutError {}
+
+// the `.cube` text: keywords, then one `r g b` line per entry; titles and
+// comments are skipped, and 1D tables aren't supported
+impl FromStr for Lut {
+    type Err = ParseLutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut size, mut table) = (0, Vec::new());
+        let (mut min, mut max) = ([0.0; 3], [1.0; 3]);
+        let mut lines = 0;
+        for (i, line) in s.lines().enumerate() {
+            lines = i + 1;
+            let err = |message| ParseLutError { line: i + 1, message };
+            let words: Vec<&str> = line.split('#').next().unwrap_or("").split_whitespace().collect();
+            let floats = |words: &[&str]| -> Result<Vec<f64>, ParseLutError> {
+                words.iter().map(|w| w.parse().map_err(|_| err("expected a number"))).collect()
+            };
+            match words[..] {
+                [] | ["TITLE", ..] => {}
+                ["LUT_3D_SIZE", n] => {
+                    size = n.parse().ok().filter(|n| (2..=256).contains(n)).ok_or(err("LUT_3D_SIZE must be 2 to 256"))?;
+                    table.reserve(size * size * size);
+                }
+                ["LUT_1D_SIZE", ..] => return Err(err("1D LUTs aren't supported")),
+                ["DOMAIN_MIN", ref rest @ ..] => min = floats(rest)?.try_into().map_err(|_| err("DOMAIN_MIN takes three numbers"))?,
+                ["DOMAIN_MAX", ref rest @ ..] => max = floats(rest)?.try_into().map_err(|_| err("DOMAIN_MAX takes three numbers"))?,
+                // Resolve's single range for all channels
+                ["LUT_3D_INPUT_RANGE", lo, hi] => {
+                    let [lo, hi]: [f64; 2] = floats(&[lo, hi])?.try_into().unwrap();
+                    (min, max) = ([lo; 3], [hi; 3]);
+                }
+                [w, ..] if w.starts_with(|c: char| c.is_ascii_alphabetic()) => return Err(err("unknown keyword")),
+                _ if size == 0 => return Err(err("entries before LUT_3D_SIZE")),
+                _ => {
+                    let rgb = floats(&words)?.try_into().map_err(|_| err("expected `r g b`"))?;
+                    if table.len() == size * size * size {
+                        return Err(err("more entries than LUT_3D_SIZE calls for"));
+                    }
+                    table.push(rgb);
+                }
+            }
+        }
+        let err = |message| ParseLutError { line: lines, message };
+        if size == 0 {
+            return Err(err("no LUT_3D_SIZE"));
+        }
+        if table.len() < size * size * size {
+            return Err(err("fewer entries than LUT_3D_SIZE calls for"));
+        }
+        if !(0..3).all(|c| max[c] > min[c]) {
+            return Err(err("DOMAIN_MAX must be above DOMAIN_MIN"));
+        }
+        Ok(Self { size, table, min, max })
+    }
+}
+
+// endregion
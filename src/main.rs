@@ -1,105 +1,927 @@
 #![windows_subsystem = "windows"]
 
-use std::time::Duration;
+#[cfg(not(any(feature = "pixels", feature = "x11")))]
+compile_error!("the demo needs the pixels or x11 feature to show anything");
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+#[cfg(feature = "pixels")]
 use pixels::{Pixels, SurfaceTexture};
-use rayon::prelude::*;
-use winit::dpi::{LogicalSize, Size};
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, TouchPhase};
 use winit::event_loop::ActiveEventLoop;
+use winit::keyboard::{Key, NamedKey};
 use winit::window::Window;
-use anyhow::{Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use doggowarp::*;
+use doggowarp::config::Config;
+use doggowarp::crossfade::Crossfade;
+use doggowarp::lut::Lut;
+use doggowarp::postfx::{Pass, PostFx};
+use doggowarp::present::Present;
+use doggowarp::preset::Preset;
+use doggowarp::record::{Recorder, Recording, Replayer};
+use doggowarp::text::{draw_text, TextStyle};
+#[cfg(feature = "watch")]
+use doggowarp::watch::Watch;
+use doggowarp::watchdog::Incident;
+use doggowarp::worker::RenderThread;
+
+const DOGGO: &[u8] = include_bytes!("doggo.jpg");
+
+// frames slower than this count as stalls
+const WATCHDOG: Duration = Duration::from_secs(2);
+
+// appends a session summary to this file on exit, if set
+const STATS_ENV: &str = "DOGGOWARP_STATS";
+
+// read from the working directory if `--config` is not given
+const CONFIG: &str = "doggowarp.toml";
+
+// where S saves presets and P cycles through them
+const PRESETS: &str = "presets";
+
+// how far E spreads edges, so whole faces hold still rather than just outlines
+const EDGE_BLUR: usize = 6;
+
+// what a directory given as the image contributes to the slideshow
+const IMAGE_EXTENSIONS: [&str; 9] = ["jpg", "jpeg", "png", "qoi", "webp", "bmp", "tga", "hdr", "exr"];
+
+// how often files are checked for changes
+#[cfg(feature = "watch")]
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+#[cfg(feature = "mjpeg")]
+const MJPEG_ADDR: &str = "0.0.0.0:8080";
 
-const DOGGO: &[u8] = include_bytes!("../../doggo.jpg");
+const USAGE: &str = r"usage: doggowarp [IMAGE... | DIR] [--hold SECONDS] [--fade SECONDS] [--scale FACTOR] [--fullscreen] [--monitor N]
+                 [--fps-cap FPS] [--config FILE] [--map IMAGE] [--blur SIGMA] [--levels auto|equalize] [--lut FILE]
+                 [--record FILE | --replay FILE [--loop]] [--raw-motion] [--cpu]";
 
 fn main() -> Result<()> {
-    Ok(<Driver<Warp>>::new(Image::from_jpeg(DOGGO)?).run()?)
+    let mut args = Args::parse(env::args().skip(1))?;
+    let paths = match &args.paths[..] {
+        [dir] if dir.is_dir() => images_in(dir).with_context(|| dir.display().to_string())?,
+        paths => paths.to_vec(),
+    };
+    let img = match paths.first() {
+        Some(path) => load(path).with_context(|| path.display().to_string())?,
+        None => Image::from_jpeg(DOGGO)?,
+    };
+    if let Some(path) = &args.lut {
+        let text = fs::read_to_string(path).with_context(|| path.display().to_string())?;
+        args.prepare.lut = Some(text.parse().with_context(|| path.display().to_string())?);
+    }
+    let img = args.prepare.apply(&img);
+    let slideshow = (paths.len() > 1).then(|| Slideshow {
+        paths,
+        current: 0,
+        hold: args.hold,
+        fade: Duration::from_secs_f64(args.fade),
+        shown: 0.0,
+        image: img.clone(),
+        crossfade: None,
+    });
+    let mut config = match &args.config {
+        Some(path) => Config::load(path).with_context(|| path.display().to_string())?,
+        None if Path::new(CONFIG).exists() => Config::load(CONFIG).context(CONFIG)?,
+        None => Config::default(),
+    };
+    let map = match &args.map {
+        Some(path) => Some(DisplacementMap::from_bytes(&fs::read(path).with_context(|| path.display().to_string())?)?),
+        None => None,
+    };
+    let replayer = match &args.replay {
+        Some(path) => {
+            let mut replayer = Replayer::new(Recording::load(path).with_context(|| path.display().to_string())?);
+            replayer.looping = args.looping;
+            Some(replayer)
+        }
+        None => None,
+    };
+    let recorder = args.record.as_ref().map(Recorder::new);
+    // a directory given is watched for new images too
+    #[cfg(feature = "watch")]
+    let watch = Watch::new(args.paths.clone());
+    // the command line wins over the file
+    config.window.fullscreen |= args.fullscreen;
+    config.window.monitor = args.monitor.or(config.window.monitor);
+    config.render.fps = args.fps_cap.or(config.render.fps);
+    let props = Props {
+        img, map, scale: args.scale, prepare: args.prepare, slideshow, cpu: args.cpu, config: config.clone(),
+        #[cfg(feature = "watch")]
+        watch: watch.clone(),
+    };
+    let mut driver = <Driver<Warp>>::new(props)
+        .watchdog(WATCHDOG).on_stuck(|took| eprintln!("watchdog: frame still rendering after {:?}", took))
+        .focus_policy(FocusPolicy::Capped(15.0)).config(&config)
+        .record(recorder).replay(replayer).raw_motion(args.raw_motion);
+    // changed files come in as user events
+    #[cfg(feature = "watch")]
+    let _watching = {
+        let proxy = driver.proxy()?;
+        watch.spawn(WATCH_INTERVAL, move |path| { let _ = proxy.send_event(path.to_path_buf()); })
+    };
+    Ok(driver.run()?)
 }
 
-#[inline(always)]
-fn shader(a: &Image, p: Pos, l: Pos, v: Pos) -> Color {
-    let m = 1.0 - l.dist(p) / 190.0;
-    let m = m.clamp(0.0, 1.0);
-    let m = v * m * m * -1.5;
+// the images in `dir` by their extensions, sorted by name
+fn images_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?.filter_map(|e| Some(e.ok()?.path())).filter(|p| is_image(p)).collect();
+    if paths.is_empty() {
+        bail!("no images in the directory");
+    }
+    paths.sort();
+    Ok(paths)
+}
 
-    let mut c = Color::default();
-    for j in 0..10 {
-        let s = j as f64 * 0.005;
-        c = c + Color::new(
-            a.sample(p + m * (s + 0.175)).red(),
-            a.sample(p + m * (s + 0.200)).green(),
-            a.sample(p + m * (s + 0.225)).blue(),
-        );
+fn is_image(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+fn load(path: &Path) -> Result<Image> {
+    Ok(Image::from_bytes(&fs::read(path)?)?)
+}
+
+struct Args {
+    // the embedded doggo if none are given; several, or a directory of them,
+    // make a slideshow
+    paths: Vec<PathBuf>,
+    // seconds each slide stays, counted from when it starts fading in, and
+    // how long that takes
+    hold: f64,
+    fade: f64,
+    // window size relative to the image
+    scale: f64,
+    fullscreen: bool,
+    monitor: Option<usize>,
+    fps_cap: Option<f64>,
+    config: Option<PathBuf>,
+    // displacement map, see `DisplacementMap`
+    map: Option<PathBuf>,
+    // done to the image and dropped ones before warping
+    prepare: Prepare,
+    // a `.cube` file to grade with, read into `prepare`
+    lut: Option<PathBuf>,
+    // mouse input to save, or to play back instead of the real mouse
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    looping: bool,
+    // velocity from the mouse itself, for high polling rates
+    raw_motion: bool,
+    // presents without the GPU, see `X11Present`
+    cpu: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item=String>) -> Result<Self> {
+        let mut parsed = Self {
+            paths: Vec::new(), hold: 6.0, fade: 1.5, scale: 1.0, fullscreen: false, monitor: None, fps_cap: None, config: None, map: None,
+            prepare: Prepare::default(), lut: None, record: None, replay: None, looping: false, raw_motion: false, cpu: false,
+        };
+        while let Some(arg) = args.next() {
+            // both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => (f.to_string(), Some(v.to_string())),
+                _ => (arg, None),
+            };
+            let mut value = || inline.clone().or_else(|| args.next())
+                .ok_or_else(|| anyhow!("{} needs a value\n{}", flag, USAGE));
+            // switches are only ever given bare
+            let switch = || match inline {
+                Some(_) => Err(anyhow!("{} takes no value\n{}", flag, USAGE)),
+                None => Ok(true),
+            };
+            match flag.as_str() {
+                "--hold" => parsed.hold = value()?.parse().context("--hold")?,
+                "--fade" => parsed.fade = value()?.parse().context("--fade")?,
+                "--scale" => parsed.scale = value()?.parse().context("--scale")?,
+                "--fullscreen" => parsed.fullscreen = switch()?,
+                "--monitor" => parsed.monitor = Some(value()?.parse().context("--monitor")?),
+                "--fps-cap" => parsed.fps_cap = Some(value()?.parse().context("--fps-cap")?),
+                "--config" => parsed.config = Some(PathBuf::from(value()?)),
+                "--map" => parsed.map = Some(PathBuf::from(value()?)),
+                "--blur" => parsed.prepare.blur = value()?.parse().context("--blur")?,
+                "--lut" => parsed.lut = Some(PathBuf::from(value()?)),
+                "--levels" => parsed.prepare.levels = Some(match value()?.as_str() {
+                    "auto" => Levels::Auto,
+                    "equalize" => Levels::Equalize,
+                    v => bail!("--levels takes auto or equalize, not {}", v),
+                }),
+                "--record" => parsed.record = Some(PathBuf::from(value()?)),
+                "--replay" => parsed.replay = Some(PathBuf::from(value()?)),
+                "--loop" => parsed.looping = switch()?,
+                "--raw-motion" => parsed.raw_motion = switch()?,
+                "--cpu" => parsed.cpu = switch()?,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                f if f.starts_with('-') => bail!("unknown option {}\n{}", f, USAGE),
+                _ => parsed.paths.push(PathBuf::from(flag)),
+            }
+        }
+        if !(parsed.scale > 0.0 && parsed.scale.is_finite()) {
+            bail!("--scale must be positive");
+        }
+        if !(parsed.prepare.blur >= 0.0 && parsed.prepare.blur.is_finite()) {
+            bail!("--blur can't be negative");
+        }
+        if !(parsed.fade >= 0.0 && parsed.hold >= parsed.fade && parsed.hold > 0.0 && parsed.hold.is_finite()) {
+            bail!("--hold must be positive and at least --fade, which can't be negative");
+        }
+        if parsed.record.is_some() && parsed.replay.is_some() {
+            bail!("--record and --replay don't go together");
+        }
+        Ok(parsed)
     }
-    c * 0.1
 }
 
-struct Warp {
-    window: Window,
-    pixels: Pixels,
-    img: Image,
-    cursor: Pos,
-    last: Pos,
-    velocity: Smooth<Pos>,
-    fps: Fps,
+#[derive(Clone, Default)]
+struct Prepare {
+    // gaussian sigma for a softer warp, 0 leaving images sharp
+    blur: f64,
+    // for washed out photos
+    levels: Option<Levels>,
+    // grades the image once, which looks all but the same as grading every
+    // frame since warping mostly moves colors around
+    lut: Option<Lut>,
 }
 
-impl Warp {
-    fn update(&mut self, delta: Duration) -> (Pos, Pos) {
-        let location = self.cursor;
-        let velocity = (location - self.last) * 0.2 / delta.as_secs_f64();
-        let velocity = self.velocity.update(velocity);
-        self.last = location;
-        (location, velocity)
+#[derive(Copy, Clone)]
+enum Levels {
+    Auto,
+    Equalize,
+}
+
+impl Prepare {
+    fn apply(&self, img: &Image) -> Image {
+        let img = match self.levels {
+            Some(Levels::Auto) => img.auto_levels(),
+            Some(Levels::Equalize) => img.equalize(),
+            None => img.clone(),
+        };
+        let img = match &self.lut {
+            Some(lut) => img.apply_lut(lut),
+            None => img,
+        };
+        if self.blur > 0.0 { imageops::gaussian_blur(&img, self.blur) } else { img }
+    }
+}
+
+// cycles through the images given on the command line, fading each into the
+// next while the warp goes on
+struct Slideshow {
+    paths: Vec<PathBuf>,
+    current: usize,
+    // in seconds
+    hold: f64,
+    fade: Duration,
+    shown: f64,
+    // the one on screen, at the size of the first
+    image: Image,
+    crossfade: Option<Crossfade>,
+}
+
+impl Slideshow {
+    // the image to warp next, if it changed
+    fn advance(&mut self, delta: Duration, prepare: &Prepare) -> Option<Image> {
+        self.shown += delta.as_secs_f64();
+        if let Some(fade) = &mut self.crossfade {
+            fade.update(delta);
+            if !fade.is_done() {
+                return Some(fade.image());
+            }
+            self.image = self.crossfade.take()?.into_target();
+            return Some(self.image.clone());
+        }
+        if self.shown < self.hold {
+            return None;
+        }
+        // images that fail to load are skipped, unless none of them loads
+        for _ in 1..self.paths.len() {
+            self.current = (self.current + 1) % self.paths.len();
+            let path = &self.paths[self.current];
+            match load(path) {
+                Ok(next) => {
+                    self.shown = 0.0;
+                    self.crossfade = Some(Crossfade::new(self.image.clone(), &prepare.apply(&next), self.fade));
+                    return None;
+                }
+                Err(e) => eprintln!("cannot load {}: {}", path.display(), e),
+            }
+        }
+        self.shown = 0.0;
+        None
+    }
+
+    // a watched file changed: the slide on screen is reloaded, unless it is
+    // fading out, and images new to a directory join in by name
+    fn changed(&mut self, path: &Path, prepare: &Prepare) -> Option<Image> {
+        if !self.paths.iter().any(|p| p == path) {
+            let current = self.paths[self.current].clone();
+            self.paths.push(path.to_path_buf());
+            self.paths.sort();
+            self.current = self.paths.iter().position(|p| *p == current)?;
+            return None;
+        }
+        if self.paths[self.current] != path || self.crossfade.is_some() {
+            return None;
+        }
+        match load(path) {
+            Ok(img) => {
+                self.image = prepare.apply(&img).letterbox(self.image.width, self.image.height);
+                Some(self.image.clone())
+            }
+            Err(e) => {
+                eprintln!("cannot reload {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    // until the next fade starts, so frames can pause in between
+    fn until_next(&self) -> Option<Duration> {
+        self.crossfade.is_none().then(|| Duration::from_secs_f64((self.hold - self.shown).max(0.0)))
     }
 }
 
+struct Props {
+    img: Image,
+    map: Option<DisplacementMap>,
+    scale: f64,
+    prepare: Prepare,
+    slideshow: Option<Slideshow>,
+    cpu: bool,
+    config: Config,
+    #[cfg(feature = "watch")]
+    watch: Watch,
+}
+
+struct Warp {
+    window: Window,
+    scale: f64,
+    // `--blur`, `--levels` and `--lut`, for dropped images and slides
+    prepare: Prepare,
+    // until an image is dropped
+    slideshow: Option<Slideshow>,
+    // the images given, or the one dropped last
+    #[cfg(feature = "watch")]
+    watch: Watch,
+    // kept up to date by `scale_changed`
+    scale_factor: f64,
+    pixels: Box<dyn Present>,
+    // so `resumed` recreates the same kind
+    cpu: bool,
+    // shades frames while the event loop keeps handling input
+    renderer: RenderThread,
+    // the stuck frame a reset went out for, see `recover`
+    reset_after: Option<Instant>,
+    // which passes of `postfx_off` F6 to F9 and C have switched on
+    postfx: [bool; 5],
+    // where F10 and F11 go fullscreen and M moves to
+    monitor: Option<usize>,
+    warp: WarpParams,
+    // the preset P loads next
+    preset: usize,
+    fps: Fps,
+    // the title bar is hidden in fullscreen, so F1 shows this in the frame
+    overlay: bool,
+    shown_fps: usize,
+    stats: SessionStats,
+    // the frame after an idle pause spans all of it, which isn't a slow frame
+    resting: bool,
+    // lowers the render resolution while frames take longer than a refresh
+    resolution: Option<DynamicResolution>,
+    #[cfg(feature = "mjpeg")]
+    stream: doggowarp::stream::MjpegServer,
+}
+
 impl AppState for Warp {
-    type StartProps = Image;
+    type StartProps = Props;
 
     type StartErr = Error;
 
-    fn start(event_loop: &ActiveEventLoop, img: Image) -> Result<Self> {
-        let size = Size::Logical(LogicalSize::new(img.width as f64, img.height as f64));
-        let window = event_loop.create_window(Window::default_attributes()
-            .with_title("doggowarp").with_inner_size(size).with_resizable(false))?;
-        let ws = window.inner_size();
-        let tx = SurfaceTexture::new(ws.width, ws.height, &window);
-        let mut pixels = Pixels::new(img.width as u32, img.height as u32, tx).unwrap();
-        // write alpha channel as opaque, it never changes
-        pixels.frame_mut().iter_mut().skip(3).step_by(4).for_each(|e| *e = 255);
-        Ok(Self {
+    // a watched file that changed
+    type UserEvent = PathBuf;
+
+    fn window_options(Props { img, scale, config, .. }: &Props) -> WindowOptions {
+        let size = LogicalSize::new(img.width as f64 * scale, img.height as f64 * scale);
+        WindowOptions::default().title("doggowarp").icon(img)
+            .map(|a| config.window.apply(a, size))
+    }
+
+    fn start(_: &ActiveEventLoop, window: Window, props: Props) -> Result<Self> {
+        let Props {
+            img, map, scale, prepare, slideshow, cpu, config,
+            #[cfg(feature = "watch")]
+            watch,
+        } = props;
+        let pixels = new_pixels(&window, img.width as u32, img.height as u32, cpu)?;
+        let refresh = window.current_monitor().and_then(|m| m.refresh_rate_millihertz()).unwrap_or(60_000);
+        let budget = Duration::from_secs_f64(1000.0 / refresh as f64);
+        let stats = SessionStats::new(budget);
+        let mut renderer = Renderer::new(img);
+        renderer.set_effect(Effect::Warp(config.warp));
+        renderer.set_filter(config.render.filter);
+        renderer.set_tiling(config.render.tiling);
+        if let Some(threads) = config.render.threads {
+            renderer.set_threads(threads)?;
+        }
+        renderer.set_displacement_map(map);
+        renderer.set_breathing(Some(Breathing::default()));
+        renderer.set_postfx(Some(postfx_off()));
+        let state = Self {
             pixels,
+            cpu,
+            scale_factor: window.scale_factor(),
             window,
-            img,
-            cursor: Pos::default(),
-            last: Pos::default(),
-            velocity: Smooth::default(),
+            scale,
+            prepare,
+            slideshow,
+            #[cfg(feature = "watch")]
+            watch,
+            renderer: RenderThread::spawn(renderer),
+            reset_after: None,
+            postfx: [false; 5],
+            monitor: config.window.monitor,
+            warp: config.warp,
+            preset: 0,
             fps: Fps::default(),
-        })
+            overlay: false,
+            shown_fps: 0,
+            resting: false,
+            resolution: config.render.dynamic_resolution.then(|| DynamicResolution::new(budget)),
+            stats,
+            #[cfg(feature = "mjpeg")]
+            stream: doggowarp::stream::MjpegServer::bind(MJPEG_ADDR)?,
+        };
+        // the attributes can only go fullscreen on the current monitor
+        if config.window.fullscreen && state.monitor.is_some() {
+            state.set_screen_mode(ScreenMode::Borderless, state.monitor);
+        }
+        state.set_cursor_hidden(config.window.hide_cursor);
+        if config.window.confine_cursor {
+            if let Err(e) = state.confine_cursor(true) {
+                eprintln!("cannot confine the cursor: {}", e);
+            }
+        }
+        Ok(state)
     }
 
     type MouseMoveErr = Error;
 
     fn mousemove(&mut self, pos: Pos) -> Result<()> {
-        self.cursor = pos;
+        let pos = self.to_pixel(pos);
+        self.renderer.update(move |r| r.mousemove(pos));
         Ok(())
     }
     type RenderErr = Error;
     fn render(&mut self, delta: Duration) -> Result<()> {
-        let (location, velocity) = self.update(delta);
-        let width = self.img.width;
-        self.pixels.frame_mut()
-            .par_chunks_exact_mut(4)
-            .enumerate()
-            .for_each(|(idx, pixel_bytes)| {
-                let pixel = Pos::new((idx % width) as f64, (idx / width) as f64);
-                shader(&self.img, pixel, location, velocity).write_bytes(pixel_bytes);
-            });
-        self.pixels.render()?;
+        if !self.resting {
+            self.stats.frame(delta, self.renderer.info().effect.name());
+        }
         if let Some(fps) = self.fps.tick() {
+            self.shown_fps = fps;
             self.window.set_title(&format!("doggowarp | {} fps", fps));
         }
-        Ok(())
+        if let Some(img) = self.slideshow.as_mut().and_then(|s| s.advance(delta, &self.prepare)) {
+            self.renderer.update(move |r| r.set_image(img));
+        }
+        // a slow frame shows the previous one again rather than holding up input
+        if self.renderer.latest() {
+            if let Some(resolution) = &mut self.resolution {
+                let (before, scale) = (resolution.scale(), resolution.frame(self.renderer.info().took));
+                if scale != before {
+                    self.renderer.update(move |r| r.set_resolution_scale(scale));
+                }
+            }
+        }
+        let frame = self.renderer.frame();
+        // right after a new image is dropped the frame may still be of the old one
+        if frame.len() == self.pixels.frame().len() {
+            self.pixels.frame_mut().copy_from_slice(frame);
+        }
+        if self.overlay {
+            self.draw_overlay();
+        }
+        #[cfg(feature = "mjpeg")]
+        {
+            let (width, height) = self.pixels.size();
+            self.stream.publish(self.pixels.frame(), width as usize, height as usize);
+        }
+        self.resting = self.idle().is_some();
+        self.pixels.present().map_err(|e| anyhow!(e))
     }
     fn window(&self) -> &Window { &self.window }
+
+    // the fps overlay would go stale, and so would a fading slideshow
+    fn idle(&self) -> Option<Duration> {
+        let settled = self.renderer.settled().filter(|_| !self.overlay)?;
+        match &self.slideshow {
+            Some(slideshow) => Some(settled.min(slideshow.until_next()?)),
+            None => Some(settled),
+        }
+    }
+
+    fn rendering_since(&self) -> Option<Instant> { self.renderer.rendering_since() }
+
+    // the render thread only takes the reset once its frame finishes, and being
+    // reported stuck again means it hasn't; nothing short of restarting the
+    // process frees it then, so this gives up
+    fn recover(&mut self, incident: &Incident) -> bool {
+        eprintln!("watchdog: {:?}", incident);
+        if let Incident::Stall(_) = incident {
+            let stuck = self.renderer.rendering_since();
+            if stuck.is_some() && stuck == self.reset_after {
+                return false;
+            }
+            self.reset_after = stuck;
+        }
+        if let Incident::RenderError(_) = incident {
+            // reconfigures the surface, which is what a lost or outdated one needs
+            let ws = self.window.inner_size();
+            if self.pixels.resize_surface(ws.width, ws.height).is_err() {
+                return false;
+            }
+        }
+        self.renderer.update(Renderer::reset);
+        true
+    }
+
+    fn resized(&mut self, width: u32, height: u32) {
+        let _ = self.pixels.resize_surface(width, height);
+    }
+
+    // the old surface is gone on Android, so this starts from a new one
+    fn resumed(&mut self) {
+        let (width, height) = self.pixels.size();
+        match new_pixels(&self.window, width, height, self.cpu) {
+            Ok(pixels) => self.pixels = pixels,
+            Err(e) => eprintln!("cannot recreate the surface: {}", e),
+        }
+    }
+
+    fn scale_changed(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn file_hovered(&mut self, path: Option<&Path>) {
+        if path.is_some() {
+            self.window.set_title("doggowarp | drop to load");
+        }
+    }
+
+    // swaps in a dropped JPEG, PNG, QOI, WebP, BMP, TGA, HDR or EXR, resizing the window to it
+    fn file_dropped(&mut self, path: PathBuf) {
+        let img = match load(&path) {
+            Ok(img) => self.prepare.apply(&img),
+            Err(e) => {
+                eprintln!("cannot load {}: {}", path.display(), e);
+                return;
+            }
+        };
+        self.slideshow = None;
+        #[cfg(feature = "watch")]
+        self.watch.set_paths(vec![path]);
+        if !self.fit_window(&img) {
+            return;
+        }
+        self.renderer.update(move |r| {
+            let mut renderer = Renderer::new(img);
+            renderer.set_displacement_map(r.displacement_map().cloned());
+            renderer.set_breathing(Some(Breathing::default()));
+            renderer.set_effect(r.effect());
+            renderer.set_filter(r.image().filter());
+            renderer.set_tiling(r.tiling());
+            renderer.set_thread_pool(r.thread_pool().cloned());
+            renderer.set_postfx(r.postfx().cloned());
+            renderer.set_scene(r.scene().cloned());
+            renderer.set_resolution_scale(r.resolution_scale());
+            let edges = r.edge_map().map(|e| EdgeMap::new(renderer.image(), e.blur()).resistance(e.resistance));
+            renderer.set_edge_map(edges);
+            *r = renderer;
+        });
+    }
+
+    // swaps a watched image that changed on disk into the running effect,
+    // which carries on as it was unless the size changed
+    fn user_event(&mut self, path: PathBuf) {
+        if !is_image(&path) {
+            return;
+        }
+        if let Some(slideshow) = &mut self.slideshow {
+            if let Some(img) = slideshow.changed(&path, &self.prepare) {
+                self.renderer.update(move |r| r.set_image(img));
+                self.window.request_redraw();
+            }
+            return;
+        }
+        let img = match load(&path) {
+            Ok(img) => self.prepare.apply(&img),
+            Err(e) => {
+                eprintln!("cannot reload {}: {}", path.display(), e);
+                return;
+            }
+        };
+        if self.pixels.size() != (img.width as u32, img.height as u32) && !self.fit_window(&img) {
+            return;
+        }
+        self.renderer.update(move |r| r.set_image(img));
+        self.window.request_redraw();
+    }
+
+    // number keys pick one of the first ten effects and tab cycles through
+    // all of them; the arrows tune the warp's strength and color separation
+    fn keyboard(&mut self, event: &KeyEvent) {
+        if event.state != ElementState::Pressed {
+            return;
+        }
+        let w = &mut self.warp;
+        match &event.logical_key {
+            Key::Named(NamedKey::ArrowUp) => w.strength *= 1.25,
+            Key::Named(NamedKey::ArrowDown) => w.strength /= 1.25,
+            Key::Named(NamedKey::ArrowRight) => w.separation += 0.005,
+            Key::Named(NamedKey::ArrowLeft) => w.separation = (w.separation - 0.005).max(0.0),
+            Key::Named(key @ (NamedKey::F10 | NamedKey::F11)) => {
+                let mode = if *key == NamedKey::F10 { ScreenMode::Exclusive } else { ScreenMode::Borderless };
+                let mode = if self.screen_mode() == mode { ScreenMode::Windowed } else { mode };
+                return self.set_screen_mode(mode, self.monitor);
+            }
+            Key::Character(c) if c.eq_ignore_ascii_case("m") => return self.next_monitor(),
+            Key::Named(NamedKey::F1) => {
+                self.overlay = !self.overlay;
+                return;
+            }
+            Key::Named(key @ (NamedKey::F2 | NamedKey::F3 | NamedKey::F4 | NamedKey::F5)) => {
+                let key = *key;
+                self.renderer.update(move |r| {
+                    let mut debug = r.debug();
+                    let flag = match key {
+                        NamedKey::F2 => &mut debug.velocity,
+                        NamedKey::F3 => &mut debug.falloff,
+                        NamedKey::F4 => &mut debug.heatmap,
+                        _ => &mut debug.timing,
+                    };
+                    *flag = !*flag;
+                    r.set_debug(debug);
+                });
+                return;
+            }
+            Key::Character(c) if c.eq_ignore_ascii_case("h") => {
+                self.renderer.update(|r| {
+                    let mut debug = r.debug();
+                    debug.histogram = !debug.histogram;
+                    r.set_debug(debug);
+                });
+                return;
+            }
+            Key::Named(key @ (NamedKey::F6 | NamedKey::F7 | NamedKey::F8 | NamedKey::F9)) => {
+                let i = match key {
+                    NamedKey::F6 => 0,
+                    NamedKey::F7 => 1,
+                    NamedKey::F8 => 2,
+                    _ => 3,
+                };
+                return self.toggle_postfx(i);
+            }
+            Key::Character(c) if c.eq_ignore_ascii_case("c") => return self.toggle_postfx(4),
+            Key::Character(c) if c.eq_ignore_ascii_case("e") => {
+                self.renderer.update(|r| {
+                    let edges = match r.edge_map() {
+                        Some(_) => None,
+                        None => Some(EdgeMap::new(r.image(), EDGE_BLUR)),
+                    };
+                    r.set_edge_map(edges);
+                });
+                return;
+            }
+            Key::Named(NamedKey::Tab) => {
+                let name = self.renderer.query(|r| r.effect().name());
+                let i = Effect::all().iter().position(|e| e.name() == name).map_or(0, |i| i + 1);
+                self.select(i % Effect::all().len());
+                return;
+            }
+            // exposure and tone curve, for HDR images
+            Key::Character(c) if c == "[" || c == "]" => {
+                let stops = if c == "]" { 0.5 } else { -0.5 };
+                self.renderer.update(move |r| if let Some((exposure, curve)) = r.image().tonemap() {
+                    r.set_tonemap(exposure + stops, curve);
+                });
+                return;
+            }
+            Key::Character(c) if c.eq_ignore_ascii_case("t") => {
+                self.renderer.update(|r| if let Some((exposure, curve)) = r.image().tonemap() {
+                    let i = Tonemap::ALL.iter().position(|&t| t == curve).map_or(0, |i| i + 1);
+                    r.set_tonemap(exposure, Tonemap::ALL[i % Tonemap::ALL.len()]);
+                });
+                return;
+            }
+            Key::Character(c) if c.eq_ignore_ascii_case("s") => return self.save_preset(),
+            Key::Character(c) if c.eq_ignore_ascii_case("p") => return self.next_preset(),
+            // 1 to 9, then 0 for the tenth
+            Key::Character(c) => {
+                if let Ok(n) = c.parse::<usize>() {
+                    self.select((n + 9) % 10);
+                }
+                return;
+            }
+            _ => return,
+        }
+        let warp = self.warp;
+        self.renderer.update(move |r| match r.effect() {
+            Effect::Warp(_) => r.set_effect(Effect::Warp(warp)),
+            Effect::Fluid(_) => r.set_effect(Effect::Fluid(warp)),
+            _ => (),
+        });
+    }
+
+    fn touch(&mut self, id: u64, phase: TouchPhase, pos: Pos) {
+        match phase {
+            TouchPhase::Started | TouchPhase::Moved => {
+                let pos = self.to_pixel(pos);
+                self.renderer.update(move |r| r.touch_move(id, pos));
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => self.renderer.update(move |r| r.touch_end(id)),
+        }
+    }
+
+    // clicks send out a shockwave, or splash on water
+    fn mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        if button == MouseButton::Left && pressed {
+            self.renderer.update(Renderer::click);
+        }
+    }
+
+    fn cursor_path(&mut self, path: &[(Duration, Pos)]) {
+        let path: Vec<_> = path.iter().map(|&(age, p)| (age, self.to_pixel(p))).collect();
+        self.renderer.update(move |r| r.cursor_path(&path));
+    }
+
+    // device units are roughly physical pixels
+    fn mouse_motion(&mut self, delta: Pos) {
+        let ratio = self.pixels.size().0 as f64 / self.window.inner_size().width.max(1) as f64;
+        self.renderer.update(move |r| r.raw_motion(delta * ratio));
+    }
+
+    // scrolling resizes the mosaic's blocks
+    fn mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y as f64,
+            MouseScrollDelta::PixelDelta(p) => p.y / 20.0,
+        };
+        self.renderer.update(move |r| {
+            if let Effect::Mosaic { radius, block } = r.effect() {
+                let block = (block + lines * 2.0).clamp(2.0, 64.0);
+                r.set_effect(Effect::Mosaic { radius, block });
+            }
+        });
+    }
+
+    fn stopping(&mut self) {
+        if let Ok(path) = env::var(STATS_ENV) {
+            let _ = OpenOptions::new().create(true).append(true).open(path)
+                .and_then(|mut f| writeln!(f, "{}", self.stats.summary()));
+        }
+    }
+}
+
+impl Warp {
+    // resizes the frame buffer to `img` and asks for a window to match
+    fn fit_window(&mut self, img: &Image) -> bool {
+        if self.pixels.resize_buffer(img.width as u32, img.height as u32).is_err() {
+            return false;
+        }
+        opaque(&mut *self.pixels);
+        let size = LogicalSize::new(img.width as f64 * self.scale, img.height as f64 * self.scale);
+        // otherwise the surface follows once the `Resized` event arrives
+        if let Some(ws) = self.window.request_inner_size(size) {
+            self.resized(ws.width, ws.height);
+        }
+        true
+    }
+
+    fn draw_overlay(&mut self) {
+        let effect = self.renderer.info().effect;
+        let mut text = format!("{} fps\n{}", self.shown_fps, effect.name());
+        if let Effect::Warp(w) | Effect::Fluid(w) = effect {
+            text += &format!("\nstrength {:.2}\nsplit {:.3}", w.strength, w.separation);
+        }
+        for (pass, _) in Pass::all().iter().zip(self.postfx).filter(|(_, on)| *on) {
+            text += &format!("\n+{}", pass.name());
+        }
+        let width = self.pixels.size().0 as usize;
+        let style = TextStyle { scale: (width / 480).max(1), ..TextStyle::default() };
+        let pad = 4 * style.scale as isize;
+        draw_text(self.pixels.frame_mut(), width, pad, pad, &text, &style);
+    }
+
+    // fullscreen moves over right away, a window once it goes fullscreen
+    fn next_monitor(&mut self) {
+        let count = self.window.available_monitors().count();
+        if count == 0 {
+            return;
+        }
+        let current = self.window.current_monitor().and_then(|c| self.window.available_monitors().position(|m| m == c));
+        let next = (self.monitor.or(current).map_or(0, |i| i + 1)) % count;
+        self.monitor = Some(next);
+        if self.screen_mode() != ScreenMode::Windowed {
+            self.set_screen_mode(self.screen_mode(), self.monitor);
+        }
+    }
+
+    fn select(&mut self, i: usize) {
+        let effect = match Effect::all().get(i) {
+            // keeps whatever the warp was tuned to
+            Some(Effect::Warp(_)) => Effect::Warp(self.warp),
+            Some(Effect::Fluid(_)) => Effect::Fluid(self.warp),
+            Some(&effect) => effect,
+            None => return,
+        };
+        self.renderer.update(move |r| r.set_effect(effect));
+    }
+
+    fn toggle_postfx(&mut self, i: usize) {
+        let on = !self.postfx[i];
+        self.postfx[i] = on;
+        self.renderer.update(move |r| {
+            if let Some(postfx) = r.postfx_mut() {
+                postfx.set_enabled(i, on);
+            }
+        });
+    }
+
+    fn save_preset(&mut self) {
+        let effect = self.renderer.query(Renderer::effect);
+        let path = (1..).map(|n| Path::new(PRESETS).join(format!("{}-{}.toml", effect.name().replace(' ', "-"), n)))
+            .find(|p| !p.exists()).unwrap();
+        match fs::create_dir_all(PRESETS).and_then(|_| Preset::new(effect.name(), effect).save(&path)) {
+            Ok(()) => self.window.set_title(&format!("doggowarp | saved {}", path.display())),
+            Err(e) => eprintln!("cannot save {}: {}", path.display(), e),
+        }
+    }
+
+    fn next_preset(&mut self) {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(PRESETS) {
+            Ok(dir) => dir.filter_map(|e| Some(e.ok()?.path())).filter(|p| p.extension() == Some("toml".as_ref())).collect(),
+            Err(_) => return,
+        };
+        if paths.is_empty() {
+            return;
+        }
+        paths.sort();
+        let path = &paths[self.preset % paths.len()];
+        self.preset = (self.preset + 1) % paths.len();
+        match Preset::load(path) {
+            Ok(preset) => {
+                if let Effect::Warp(w) | Effect::Fluid(w) = preset.effect {
+                    self.warp = w;
+                }
+                self.renderer.update(move |r| r.set_effect(preset.effect));
+                self.window.set_title(&format!("doggowarp | {}", preset.name));
+            }
+            Err(e) => eprintln!("cannot load {}: {}", path.display(), e),
+        }
+    }
+
+    fn to_pixel(&self, pos: Pos) -> Pos {
+        self.pixels.frame_pos(pos * self.scale_factor)
+    }
+}
+
+// every pass of `Pass::all`, in the order F6 to F9 and C toggle them, all off
+fn postfx_off() -> PostFx {
+    let mut postfx = PostFx::new();
+    for (i, pass) in Pass::all().into_iter().enumerate() {
+        postfx.push(pass);
+        postfx.set_enabled(i, false);
+    }
+    postfx
+}
+
+// a `width` x `height` buffer, scaled onto all of `window`
+fn new_pixels(window: &Window, width: u32, height: u32, cpu: bool) -> Result<Box<dyn Present>> {
+    let mut pixels: Box<dyn Present> = match cpu {
+        #[cfg(feature = "x11")]
+        true => Box::new(doggowarp::present::X11Present::new(window, width, height).map_err(|e| anyhow!(e))?),
+        #[cfg(not(feature = "x11"))]
+        true => bail!("--cpu needs the x11 feature"),
+        #[cfg(feature = "pixels")]
+        false => {
+            let ws = window.inner_size();
+            Box::new(Pixels::new(width, height, SurfaceTexture::new(ws.width, ws.height, window))?)
+        }
+        #[cfg(not(feature = "pixels"))]
+        false => bail!("built without the pixels feature, only --cpu works"),
+    };
+    opaque(&mut *pixels);
+    Ok(pixels)
+}
+
+// write alpha channel as opaque, it never changes
+fn opaque(pixels: &mut dyn Present) {
+    pixels.frame_mut().iter_mut().skip(3).step_by(4).for_each(|e| *e = 255);
 }
@@ -0,0 +1,247 @@
+// Float functions `core` does not provide, for builds without the `std` feature.
+// Method names mirror the inherent `f64` ones so call sites read the same in
+// both configurations; with `std` the inherent methods are used instead.
+
+// the set is shared by everything that may need it, not all of it is used yet
+#![allow(dead_code)]
+
+use core::f64::consts::{FRAC_PI_2, LN_2, PI};
+
+pub(crate) trait F64Ext {
+    fn trunc(self) -> f64;
+    fn floor(self) -> f64;
+    fn ceil(self) -> f64;
+    fn round(self) -> f64;
+    fn fract(self) -> f64;
+    fn sqrt(self) -> f64;
+    fn sin(self) -> f64;
+    fn cos(self) -> f64;
+    fn sin_cos(self) -> (f64, f64);
+    fn atan(self) -> f64;
+    fn atan2(self, x: f64) -> f64;
+    fn exp(self) -> f64;
+    fn ln(self) -> f64;
+    fn powf(self, n: f64) -> f64;
+    fn powi(self, n: i32) -> f64;
+    fn hypot(self, y: f64) -> f64;
+    fn cbrt(self) -> f64;
+}
+
+// 2^52, above which every f64 is already an integer
+const INTEGRAL: f64 = 4503599627370496.0;
+
+impl F64Ext for f64 {
+    fn trunc(self) -> f64 {
+        if self.is_nan() || self.abs() >= INTEGRAL { self } else { self as i64 as f64 }
+    }
+
+    fn floor(self) -> f64 {
+        let t = F64Ext::trunc(self);
+        if t > self { t - 1.0 } else { t }
+    }
+
+    fn ceil(self) -> f64 {
+        let t = F64Ext::trunc(self);
+        if t < self { t + 1.0 } else { t }
+    }
+
+    // half-way cases round away from zero, like `f64::round`
+    fn round(self) -> f64 {
+        let t = F64Ext::trunc(self);
+        if (self - t).abs() >= 0.5 { t + self.signum() } else { t }
+    }
+
+    fn fract(self) -> f64 {
+        self - F64Ext::trunc(self)
+    }
+
+    fn sqrt(self) -> f64 {
+        if self.is_nan() || self < 0.0 {
+            return f64::NAN;
+        }
+        if self == 0.0 || self.is_infinite() {
+            return self;
+        }
+        // halving the exponent bits gets within a few percent, Newton does the rest
+        let mut r = f64::from_bits((self.to_bits() >> 1) + (1023u64 << 51));
+        for _ in 0..6 {
+            r = 0.5 * (r + self / r);
+        }
+        r
+    }
+
+    fn sin(self) -> f64 { F64Ext::sin_cos(self).0 }
+
+    fn cos(self) -> f64 { F64Ext::sin_cos(self).1 }
+
+    fn sin_cos(self) -> (f64, f64) {
+        if !self.is_finite() {
+            return (f64::NAN, f64::NAN);
+        }
+        // reduce to [-pi/4, pi/4] around the nearest multiple of pi/2,
+        // subtracting pi/2 in two parts to keep the low bits
+        const PI_2_HI: f64 = 1.5707963267341256;
+        const PI_2_LO: f64 = 6.077100506506192e-11;
+        let k = F64Ext::round(self / FRAC_PI_2);
+        let r = (self - k * PI_2_HI) - k * PI_2_LO;
+        let r2 = r * r;
+        let s = r * (1.0 + r2 * (-1.0 / 6.0 + r2 * (1.0 / 120.0 + r2 * (-1.0 / 5040.0
+            + r2 * (1.0 / 362880.0 + r2 * (-1.0 / 39916800.0 + r2 / 6227020800.0))))));
+        let c = 1.0 + r2 * (-0.5 + r2 * (1.0 / 24.0 + r2 * (-1.0 / 720.0
+            + r2 * (1.0 / 40320.0 + r2 * (-1.0 / 3628800.0 + r2 / 479001600.0)))));
+        match (k as i64).rem_euclid(4) {
+            0 => (s, c),
+            1 => (c, -s),
+            2 => (-s, -c),
+            _ => (-c, s),
+        }
+    }
+
+    fn atan(self) -> f64 {
+        if self.is_nan() {
+            return self;
+        }
+        let (x, sign) = if self < 0.0 { (-self, -1.0) } else { (self, 1.0) };
+        // atan(x) = pi/2 - atan(1/x), then atan(x) = pi/6 + atan((x*sqrt3 - 1) / (x + sqrt3))
+        // leaves |x| <= 2 - sqrt3 where the series converges quickly
+        let (x, mut base, flip) = if x > 1.0 { (1.0 / x, 0.0, true) } else { (x, 0.0, false) };
+        const SQRT3: f64 = 1.7320508075688772;
+        let x = if x > 0.2679491924311227 {
+            base = PI / 6.0;
+            (x * SQRT3 - 1.0) / (x + SQRT3)
+        } else {
+            x
+        };
+        let x2 = x * x;
+        let mut term = x;
+        let mut sum = 0.0;
+        for n in 0..12 {
+            sum += term / (2 * n + 1) as f64;
+            term *= -x2;
+        }
+        let a = base + sum;
+        sign * if flip { FRAC_PI_2 - a } else { a }
+    }
+
+    fn atan2(self, x: f64) -> f64 {
+        let y = self;
+        if x.is_nan() || y.is_nan() {
+            return f64::NAN;
+        }
+        if x == 0.0 {
+            return if y > 0.0 { FRAC_PI_2 } else if y < 0.0 { -FRAC_PI_2 } else { 0.0 };
+        }
+        let a = F64Ext::atan(y / x);
+        if x > 0.0 { a } else if y >= 0.0 { a + PI } else { a - PI }
+    }
+
+    fn exp(self) -> f64 {
+        if self.is_nan() {
+            return self;
+        }
+        if self > 709.8 {
+            return f64::INFINITY;
+        }
+        if self < -745.2 {
+            return 0.0;
+        }
+        // e^x = 2^k * e^r with |r| <= ln2 / 2
+        let k = F64Ext::round(self / LN_2);
+        let r = self - k * LN_2;
+        let mut term = 1.0;
+        let mut sum = 1.0;
+        for n in 1..16 {
+            term *= r / n as f64;
+            sum += term;
+        }
+        // split the scale so subnormal results don't overflow the exponent field
+        let k = k as i32;
+        let (k1, k2) = (k / 2, k - k / 2);
+        sum * pow2(k1) * pow2(k2)
+    }
+
+    fn ln(self) -> f64 {
+        if self.is_nan() || self < 0.0 {
+            return f64::NAN;
+        }
+        if self == 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        if self.is_infinite() {
+            return self;
+        }
+        // x = m * 2^e with m in [sqrt(1/2), sqrt(2)), then ln(m) = 2 * atanh((m - 1) / (m + 1))
+        let (mut m, mut e) = if self < f64::MIN_POSITIVE {
+            (self * pow2(54), -54)
+        } else {
+            (self, 0)
+        };
+        let bits = m.to_bits();
+        e += ((bits >> 52) & 0x7ff) as i32 - 1023;
+        m = f64::from_bits((bits & !(0x7ffu64 << 52)) | (1023u64 << 52));
+        if m > core::f64::consts::SQRT_2 {
+            m *= 0.5;
+            e += 1;
+        }
+        let s = (m - 1.0) / (m + 1.0);
+        let s2 = s * s;
+        let mut term = s;
+        let mut sum = 0.0;
+        for n in 0..10 {
+            sum += term / (2 * n + 1) as f64;
+            term *= s2;
+        }
+        2.0 * sum + e as f64 * LN_2
+    }
+
+    fn powf(self, n: f64) -> f64 {
+        if n == 0.0 {
+            return 1.0;
+        }
+        if self < 0.0 && F64Ext::trunc(n) == n && n.abs() < i32::MAX as f64 {
+            return F64Ext::powi(self, n as i32);
+        }
+        if self == 0.0 {
+            return if n > 0.0 { 0.0 } else { f64::INFINITY };
+        }
+        F64Ext::exp(n * F64Ext::ln(self))
+    }
+
+    fn powi(self, n: i32) -> f64 {
+        let mut base = if n < 0 { 1.0 / self } else { self };
+        let mut n = n.unsigned_abs();
+        let mut r = 1.0;
+        while n > 0 {
+            if n & 1 == 1 {
+                r *= base;
+            }
+            base *= base;
+            n >>= 1;
+        }
+        r
+    }
+
+    fn hypot(self, y: f64) -> f64 {
+        F64Ext::sqrt(self * self + y * y)
+    }
+
+    fn cbrt(self) -> f64 {
+        if self == 0.0 || !self.is_finite() {
+            return self;
+        }
+        let a = self.abs();
+        // exp/ln gets close, one Newton step polishes the last bits
+        let r = F64Ext::exp(F64Ext::ln(a) / 3.0);
+        let r = r - (r * r * r - a) / (3.0 * r * r);
+        if self < 0.0 { -r } else { r }
+    }
+}
+
+fn pow2(k: i32) -> f64 {
+    let k = k.clamp(-1074, 1023);
+    if k >= -1022 {
+        f64::from_bits(((k + 1023) as u64) << 52)
+    } else {
+        f64::from_bits(1u64 << (k + 1074))
+    }
+}
@@ -0,0 +1,163 @@
+use crate::Pos;
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+// region Noise
+
+#[derive(Copy, Clone)]
+pub enum Basis {
+    Perlin,
+    Simplex,
+}
+
+// octave stacking for fractal Brownian motion
+#[derive(Copy, Clone)]
+pub struct Octaves {
+    pub count: u32,
+    // frequency multiplier between octaves
+    pub lacunarity: f64,
+    // amplitude multiplier between octaves
+    pub gain: f64,
+}
+
+impl Default for Octaves {
+    fn default() -> Self { Self { count: 4, lacunarity: 2.0, gain: 0.5 } }
+}
+
+// shift between the two channels of the vector functions
+const DECORRELATE: Pos = Pos { x: 31.416, y: -47.853 };
+
+// seeded 2D gradient noise; every function returns values roughly in [-1, 1]
+#[derive(Clone)]
+pub struct Noise {
+    perm: [u8; 512],
+}
+
+impl Noise {
+    // const, so shaders can share a table built at compile time
+    pub const fn new(seed: u64) -> Self {
+        let mut p = [0u8; 256];
+        let mut i = 0;
+        while i < 256 {
+            p[i] = i as u8;
+            i += 1;
+        }
+        // Fisher-Yates driven by splitmix64
+        let mut state = seed;
+        let mut i = 255;
+        while i > 0 {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^= z >> 31;
+            p.swap(i, (z % (i as u64 + 1)) as usize);
+            i -= 1;
+        }
+        let mut perm = [0u8; 512];
+        let mut i = 0;
+        while i < 512 {
+            perm[i] = p[i & 255];
+            i += 1;
+        }
+        Self { perm }
+    }
+
+    fn hash(&self, x: i64, y: i64) -> u8 {
+        self.perm[self.perm[(x & 255) as usize] as usize + (y & 255) as usize]
+    }
+
+    pub fn perlin(&self, p: Pos) -> f64 {
+        let (x0, y0) = (p.x().floor(), p.y().floor());
+        let (fx, fy) = (p.x() - x0, p.y() - y0);
+        let (ix, iy) = (x0 as i64, y0 as i64);
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let (u, v) = (fade(fx), fade(fy));
+        let g = |dx: i64, dy: i64| perlin_grad(self.hash(ix + dx, iy + dy), fx - dx as f64, fy - dy as f64);
+        let a = g(0, 0) + u * (g(1, 0) - g(0, 0));
+        let b = g(0, 1) + u * (g(1, 1) - g(0, 1));
+        a + v * (b - a)
+    }
+
+    pub fn simplex(&self, p: Pos) -> f64 {
+        const F2: f64 = 0.3660254037844386; // (sqrt(3) - 1) / 2
+        const G2: f64 = 0.21132486540518713; // (3 - sqrt(3)) / 6
+        let s = (p.x() + p.y()) * F2;
+        let (i, j) = ((p.x() + s).floor(), (p.y() + s).floor());
+        let t = (i + j) * G2;
+        let (x0, y0) = (p.x() - (i - t), p.y() - (j - t));
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let (x1, y1) = (x0 - i1 as f64 + G2, y0 - j1 as f64 + G2);
+        let (x2, y2) = (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+        let (ii, jj) = (i as i64, j as i64);
+        let corner = |h: u8, x: f64, y: f64| {
+            let t = 0.5 - x * x - y * y;
+            if t < 0.0 { 0.0 } else { t * t * t * t * simplex_grad(h, x, y) }
+        };
+        40.0 * (corner(self.hash(ii, jj), x0, y0)
+            + corner(self.hash(ii + i1, jj + j1), x1, y1)
+            + corner(self.hash(ii + 1, jj + 1), x2, y2))
+    }
+
+    pub fn sample(&self, p: Pos, basis: Basis) -> f64 {
+        match basis {
+            Basis::Perlin => self.perlin(p),
+            Basis::Simplex => self.simplex(p),
+        }
+    }
+
+    // sum of octaves, normalized by the total amplitude
+    pub fn fbm(&self, p: Pos, basis: Basis, octaves: Octaves) -> f64 {
+        let (mut sum, mut norm, mut amp, mut freq) = (0.0, 0.0, 1.0, 1.0);
+        for _ in 0..octaves.count {
+            sum += amp * self.sample(p * freq, basis);
+            norm += amp;
+            amp *= octaves.gain;
+            freq *= octaves.lacunarity;
+        }
+        if norm > 0.0 { sum / norm } else { 0.0 }
+    }
+
+    // two decorrelated channels, e.g. a displacement to add to a sample position
+    pub fn vector(&self, p: Pos, basis: Basis) -> Pos {
+        Pos::new(self.sample(p, basis), self.sample(p + DECORRELATE, basis))
+    }
+
+    pub fn fbm_vector(&self, p: Pos, basis: Basis, octaves: Octaves) -> Pos {
+        Pos::new(self.fbm(p, basis, octaves), self.fbm(p + DECORRELATE, basis, octaves))
+    }
+
+    // the curl of the noise taken as a stream function: a flow that neither
+    // converges nor diverges anywhere, so whatever follows it swirls like smoke
+    pub fn curl(&self, p: Pos, basis: Basis) -> Pos {
+        const E: f64 = 1e-3;
+        let dx = self.sample(p + Pos::new(E, 0.0), basis) - self.sample(p - Pos::new(E, 0.0), basis);
+        let dy = self.sample(p + Pos::new(0.0, E), basis) - self.sample(p - Pos::new(0.0, E), basis);
+        Pos::new(dy, -dx) / (2.0 * E)
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self { Self::new(0) }
+}
+
+fn perlin_grad(h: u8, x: f64, y: f64) -> f64 {
+    match h & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+fn simplex_grad(h: u8, x: f64, y: f64) -> f64 {
+    // 8 directions of uneven length, as in Gustavson's simplexnoise1234, hence the 40x scale
+    let (u, v) = if h & 4 == 0 { (x, y) } else { (y, x) };
+    (if h & 1 == 0 { u } else { -u }) + 2.0 * (if h & 2 == 0 { v } else { -v })
+}
+
+// endregion
@@ -0,0 +1,398 @@
+// PNG decoder to RGBA8, or RGBA16 for the full precision of 16-bit images:
+// every color type and bit depth, Adam7 interlacing, tRNS transparency. The
+// encoder writes 8-bit RGB or RGBA.
+
+use core::fmt;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::{zlib, MAX_PIXELS};
+
+// region Error
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Signature,
+    Truncated,
+    Checksum,
+    Format(&'static str),
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Signature => f.write_str("not a PNG file"),
+            Error::Truncated => f.write_str("PNG data ends early"),
+            Error::Checksum => f.write_str("PNG chunk checksum mismatch"),
+            Error::Format(e) => write!(f, "malformed PNG: {}", e),
+            Error::Unsupported(e) => write!(f, "unsupported PNG: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// endregion
+// region Decoder
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+// (x0, y0, dx, dy) of the seven Adam7 passes
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2),
+];
+
+struct Header {
+    width: usize,
+    height: usize,
+    depth: u8,
+    color: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color {
+            0 | 3 => 1,
+            4 => 2,
+            2 => 3,
+            _ => 4,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize { self.channels() * self.depth as usize }
+
+    // (width, height, bytes per row) of an interlace pass, or of the image
+    fn pass(&self, (x0, y0, dx, dy): (usize, usize, usize, usize)) -> (usize, usize, usize) {
+        let pw = (self.width + dx - 1 - x0) / dx;
+        let ph = (self.height + dy - 1 - y0) / dy;
+        (pw, ph, (pw * self.bits_per_pixel()).div_ceil(8))
+    }
+}
+
+// returns (width, height, RGBA bytes)
+pub fn decode(data: &[u8]) -> Result<(usize, usize, Vec<u8>), Error> {
+    let (width, height, rgba) = decode_u16(data)?;
+    Ok((width, height, rgba.iter().map(|&v| (v / 257) as u8).collect()))
+}
+
+// bits per sample as the header states them, if `data` is a PNG
+pub fn bit_depth(data: &[u8]) -> Option<u8> {
+    // IHDR always comes first
+    (data.starts_with(&SIGNATURE) && data.get(12..16) == Some(b"IHDR")).then(|| data.get(24).copied()).flatten()
+}
+
+// returns (width, height, RGBA samples), every bit depth scaled to 16 bits
+pub fn decode_u16(data: &[u8]) -> Result<(usize, usize, Vec<u16>), Error> {
+    if data.len() < 8 || data[..8] != SIGNATURE {
+        return Err(Error::Signature);
+    }
+    let mut pos = 8;
+    let mut header = None;
+    let mut palette: Vec<[u16; 4]> = Vec::new();
+    // color key for gray / RGB images, at the image's bit depth
+    let mut key: Option<[u16; 3]> = None;
+    let mut idat = Vec::new();
+    loop {
+        let len = data.get(pos..pos + 4).ok_or(Error::Truncated)?;
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let kind = data.get(pos + 4..pos + 8).ok_or(Error::Truncated)?;
+        let body = data.get(pos + 8..pos + 8 + len).ok_or(Error::Truncated)?;
+        let crc = data.get(pos + 8 + len..pos + 12 + len).ok_or(Error::Truncated)?;
+        if zlib::crc32(&[kind, body]).to_be_bytes() != crc {
+            return Err(Error::Checksum);
+        }
+        pos += 12 + len;
+        match kind {
+            b"IHDR" => header = Some(parse_header(body)?),
+            b"PLTE" => palette = body.chunks_exact(3).map(|c| [c[0], c[1], c[2], 255].map(|v| v as u16 * 257)).collect(),
+            b"tRNS" => {
+                let h = header.as_ref().ok_or(Error::Format("tRNS before IHDR"))?;
+                let word = |i: usize| body.get(2 * i..2 * i + 2).map(|w| u16::from_be_bytes([w[0], w[1]]));
+                match h.color {
+                    3 => palette.iter_mut().zip(body).for_each(|(p, &a)| p[3] = a as u16 * 257),
+                    0 => key = word(0).map(|g| [g, g, g]),
+                    2 => key = (|| Some([word(0)?, word(1)?, word(2)?]))(),
+                    _ => (),
+                }
+            }
+            b"IDAT" => idat.extend_from_slice(body),
+            b"IEND" => break,
+            // ancillary chunks are fine to skip, critical ones are not
+            _ if kind[0] & 0x20 == 0 => return Err(Error::Unsupported("unknown critical chunk")),
+            _ => (),
+        }
+    }
+    let h = header.ok_or(Error::Format("missing IHDR"))?;
+    if h.color == 3 && palette.is_empty() {
+        return Err(Error::Format("missing PLTE"));
+    }
+    let passes: &[_] = if h.interlaced { &ADAM7 } else { &[(0, 0, 1, 1)] };
+    // each row of a pass is filtered on its own, a type byte first
+    let expected = passes.iter().map(|&p| match h.pass(p) {
+        (0, _, _) | (_, 0, _) => 0,
+        (_, ph, stride) => (stride + 1).saturating_mul(ph),
+    });
+    let raw = zlib::decompress(&idat, expected.fold(0, usize::saturating_add)).map_err(Error::Format)?;
+
+    let mut rgba = vec![0u16; h.width * h.height * 4];
+    let mut offset = 0;
+    for &(x0, y0, dx, dy) in passes {
+        let (pw, ph, stride) = h.pass((x0, y0, dx, dy));
+        if pw == 0 || ph == 0 {
+            continue;
+        }
+        let size = (stride + 1) * ph;
+        let pass = raw.get(offset..offset + size).ok_or(Error::Truncated)?;
+        offset += size;
+        let rows = unfilter(pass, stride, h.bits_per_pixel().div_ceil(8))?;
+        for (py, row) in rows.chunks_exact(stride).enumerate() {
+            for px in 0..pw {
+                let (x, y) = (x0 + px * dx, y0 + py * dy);
+                let out = &mut rgba[4 * (y * h.width + x)..][..4];
+                out.copy_from_slice(&to_rgba(&h, row, px, &palette, key));
+            }
+        }
+    }
+    Ok((h.width, h.height, rgba))
+}
+
+fn parse_header(body: &[u8]) -> Result<Header, Error> {
+    if body.len() != 13 {
+        return Err(Error::Format("bad IHDR length"));
+    }
+    let dim = |i: usize| u32::from_be_bytes([body[i], body[i + 1], body[i + 2], body[i + 3]]) as usize;
+    let h = Header {
+        width: dim(0),
+        height: dim(4),
+        depth: body[8],
+        color: body[9],
+        interlaced: body[12] == 1,
+    };
+    let depth_ok = match h.color {
+        0 => matches!(h.depth, 1 | 2 | 4 | 8 | 16),
+        3 => matches!(h.depth, 1 | 2 | 4 | 8),
+        2 | 4 | 6 => matches!(h.depth, 8 | 16),
+        _ => false,
+    };
+    if !depth_ok {
+        return Err(Error::Format("invalid color type and bit depth"));
+    }
+    if body[10] != 0 || body[11] != 0 || body[12] > 1 {
+        return Err(Error::Unsupported("compression, filter or interlace method"));
+    }
+    if h.width == 0 || h.height == 0 {
+        return Err(Error::Format("empty image"));
+    }
+    if h.width.saturating_mul(h.height) > MAX_PIXELS {
+        return Err(Error::Format("size out of range"));
+    }
+    Ok(h)
+}
+
+// reverses the per-scanline filters, dropping the filter type bytes
+fn unfilter(data: &[u8], stride: usize, bpp: usize) -> Result<Vec<u8>, Error> {
+    let rows = data.len() / (stride + 1);
+    let mut out = vec![0u8; rows * stride];
+    for r in 0..rows {
+        let (done, rest) = out.split_at_mut(r * stride);
+        let prev = if r > 0 { &done[(r - 1) * stride..] } else { &[][..] };
+        let cur = &mut rest[..stride];
+        let line = &data[r * (stride + 1)..(r + 1) * (stride + 1)];
+        cur.copy_from_slice(&line[1..]);
+        let up = |i: usize| prev.get(i).copied().unwrap_or(0);
+        for i in 0..stride {
+            let a = if i >= bpp { cur[i - bpp] } else { 0 };
+            let (b, c) = (up(i), if i >= bpp { up(i - bpp) } else { 0 });
+            cur[i] = cur[i].wrapping_add(match line[0] {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(Error::Format("invalid filter type")),
+            });
+        }
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+// sample `i` of an unfiltered scanline, at the image's bit depth
+fn sample(row: &[u8], i: usize, depth: u8) -> u16 {
+    match depth {
+        16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]),
+        8 => row[i] as u16,
+        d => {
+            let bit = i * d as usize;
+            let shift = 8 - d as usize - bit % 8;
+            ((row[bit / 8] >> shift) & ((1 << d) - 1)) as u16
+        }
+    }
+}
+
+// every depth divides 65535 evenly, so 8-bit values come out as multiples of 257
+fn to_rgba(h: &Header, row: &[u8], x: usize, palette: &[[u16; 4]], key: Option<[u16; 3]>) -> [u16; 4] {
+    let n = h.channels();
+    let s = |c: usize| sample(row, x * n + c, h.depth);
+    let max = (1u32 << h.depth) - 1;
+    let to16 = |v: u16| (v as u32 * 65535 / max) as u16;
+    let keyed = |rgb: [u16; 3]| if key == Some(rgb) { 0 } else { 65535 };
+    match h.color {
+        0 => {
+            let g = s(0);
+            let v = to16(g);
+            [v, v, v, keyed([g, g, g])]
+        }
+        2 => {
+            let rgb = [s(0), s(1), s(2)];
+            [to16(rgb[0]), to16(rgb[1]), to16(rgb[2]), keyed(rgb)]
+        }
+        3 => palette.get(s(0) as usize).copied().unwrap_or([0, 0, 0, 65535]),
+        4 => {
+            let v = to16(s(0));
+            [v, v, v, to16(s(1))]
+        }
+        _ => [to16(s(0)), to16(s(1)), to16(s(2)), to16(s(3))],
+    }
+}
+
+// endregion
+// region Encoder
+
+// encodes a tightly packed RGBA buffer, dropping alpha when it's all opaque
+pub fn encode(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    assert!(width > 0 && height > 0 && width <= 0x7fff_ffff && height <= 0x7fff_ffff, "unsupported PNG size");
+    assert!(rgba.len() >= width * height * 4, "buffer smaller than width * height * 4");
+    let rgba = &rgba[..width * height * 4];
+    let opaque = rgba.chunks_exact(4).all(|px| px[3] == 255);
+    let bpp = if opaque { 3 } else { 4 };
+    let stride = width * bpp;
+    let pixels: Vec<u8> = if opaque {
+        rgba.chunks_exact(4).flat_map(|px| [px[0], px[1], px[2]]).collect()
+    } else {
+        rgba.to_vec()
+    };
+
+    // every row gets the filter whose output is smallest in absolute value,
+    // the usual guess at what deflates best
+    let mut filtered = Vec::with_capacity(height * (stride + 1));
+    let mut candidate = vec![0u8; stride];
+    let mut best = vec![0u8; stride];
+    for (r, cur) in pixels.chunks_exact(stride).enumerate() {
+        let prev = if r > 0 { &pixels[(r - 1) * stride..r * stride] } else { &[][..] };
+        let up = |i: usize| prev.get(i).copied().unwrap_or(0);
+        let (mut best_kind, mut best_cost) = (0, u64::MAX);
+        for kind in 0..5u8 {
+            for i in 0..stride {
+                let a = if i >= bpp { cur[i - bpp] } else { 0 };
+                let (b, c) = (up(i), if i >= bpp { up(i - bpp) } else { 0 });
+                candidate[i] = cur[i].wrapping_sub(match kind {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => paeth(a, b, c),
+                });
+            }
+            let cost = candidate.iter().map(|&v| (v as i8).unsigned_abs() as u64).sum();
+            if cost < best_cost {
+                (best_kind, best_cost) = (kind, cost);
+                best.copy_from_slice(&candidate);
+            }
+        }
+        filtered.push(best_kind);
+        filtered.extend_from_slice(&best);
+    }
+
+    let mut out = Vec::with_capacity(pixels.len() / 2 + 64);
+    out.extend_from_slice(&SIGNATURE);
+    let mut header = [0u8; 13];
+    header[..4].copy_from_slice(&(width as u32).to_be_bytes());
+    header[4..8].copy_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits, RGB or RGBA, deflate, adaptive filtering, not interlaced
+    header[8..].copy_from_slice(&[8, if opaque { 2 } else { 6 }, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &header);
+    // the pixels are sRGB, perceptual rendering intent
+    chunk(&mut out, b"sRGB", &[0]);
+    chunk(&mut out, b"IDAT", &zlib::compress(&filtered));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    out.extend_from_slice(&zlib::crc32(&[kind, body]).to_be_bytes());
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // smooth and noisy stretches, so rows pick different filters
+    fn image(width: usize, height: usize, alpha: bool) -> Vec<u8> {
+        (0..width * height).flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            let a = if alpha { (x * 9 + y) as u8 } else { 255 };
+            if y % 3 == 0 {
+                [(x * 4) as u8, (y * 4) as u8, 128, a]
+            } else {
+                [(x * x * 31 + y) as u8, ((y * 77) ^ x) as u8, (x * y) as u8, a]
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for (width, height) in [(1, 1), (7, 1), (1, 9), (40, 23)] {
+            for alpha in [false, true] {
+                let rgba = image(width, height, alpha);
+                let png = encode(width, height, &rgba);
+                assert_eq!(bit_depth(&png), Some(8));
+                assert_eq!(decode(&png), Ok((width, height, rgba.clone())), "{}x{}", width, height);
+                let wide = rgba.iter().map(|&v| v as u16 * 257).collect();
+                assert_eq!(decode_u16(&png), Ok((width, height, wide)));
+            }
+        }
+    }
+
+    // the color type says whether alpha was written
+    #[test]
+    fn opaque_images_drop_alpha() {
+        assert_eq!(encode(4, 4, &image(4, 4, false))[25], 2);
+        assert_eq!(encode(4, 4, &image(4, 4, true))[25], 6);
+    }
+
+    #[test]
+    fn truncated_data_errs() {
+        let png = encode(19, 11, &image(19, 11, true));
+        for len in 0..png.len() {
+            assert!(decode(&png[..len]).is_err(), "decoded {} of {} bytes", len, png.len());
+        }
+    }
+
+    #[test]
+    fn bad_data_errs() {
+        let png = encode(5, 5, &image(5, 5, false));
+        assert_eq!(decode(b"\x89PNG"), Err(Error::Signature));
+        let mut crc = png.clone();
+        crc[29] ^= 1;
+        assert_eq!(decode(&crc), Err(Error::Checksum));
+        // a header asking for more pixels than the cap, with its CRC fixed up
+        let mut huge = png;
+        huge[16..24].copy_from_slice(&[0, 1, 0, 0, 0, 1, 0, 0]);
+        let crc = zlib::crc32(&[&huge[12..29]]);
+        huge[29..33].copy_from_slice(&crc.to_be_bytes());
+        assert_eq!(decode(&huge), Err(Error::Format("size out of range")));
+    }
+}
@@ -0,0 +1,261 @@
+// Passes over finished RGBA frames, run in order after the shader wrote them;
+// each can be switched off and on again without rebuilding the chain.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use crate::lut::Lut;
+use crate::{for_each_pixel_rows, Color, Pos};
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+// region Pass
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pass {
+    // darkens towards the corners, by `strength` at them; distances are from
+    // the center over half the diagonal and the darkening starts at `radius`
+    Vignette { strength: f64, radius: f64 },
+    // adds a blurred copy of everything brighter than `threshold` in [0, 1],
+    // shaded at a quarter of the resolution; `radius` is in pixels
+    Bloom { threshold: f64, radius: f64, intensity: f64 },
+    // brightens or darkens each pixel at random by up to `amount` in [0, 1],
+    // anew every frame
+    Grain { amount: f64 },
+    // darkens the last row of every `period` by `darkness` in [0, 1]
+    Scanlines { period: usize, darkness: f64 },
+    // moves each channel by its own offset in pixels, scaled by the distance
+    // from the center over half the diagonal to the power of `falloff`; 0
+    // moves them evenly, higher keeps the middle sharp like a lens would
+    Chromatic { red: Pos, green: Pos, blue: Pos, falloff: f64 },
+    // grades through a 3D lookup table, `mix` in [0, 1] of the way; shared,
+    // since tables run to megabytes. Not in `all`, having no table to default to
+    Lut { lut: Arc<Lut>, mix: f64 },
+}
+
+impl Pass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Vignette { .. } => "vignette",
+            Pass::Bloom { .. } => "bloom",
+            Pass::Grain { .. } => "grain",
+            Pass::Scanlines { .. } => "scanlines",
+            Pass::Chromatic { .. } => "chromatic",
+            Pass::Lut { .. } => "lut",
+        }
+    }
+
+    // each with settings that show without overpowering the image
+    pub fn all() -> [Pass; 5] {
+        [
+            Pass::Vignette { strength: 0.6, radius: 0.4 },
+            Pass::Bloom { threshold: 0.7, radius: 12.0, intensity: 0.8 },
+            Pass::Grain { amount: 0.06 },
+            Pass::Scanlines { period: 3, darkness: 0.35 },
+            Pass::Chromatic { red: Pos::new(-4.0, 0.0), green: Pos::default(), blue: Pos::new(4.0, 0.0), falloff: 1.5 },
+        ]
+    }
+
+    // whether it changes from frame to frame over the same input
+    pub fn animated(&self) -> bool { matches!(self, Pass::Grain { .. }) }
+}
+
+// endregion
+// region Chain
+
+#[derive(Clone, Default)]
+pub struct PostFx {
+    // with whether each is enabled
+    passes: Vec<(Pass, bool)>,
+    // frames so far, which reseeds the grain
+    frame: u64,
+    // the bloom's quarter resolution buffers
+    bright: Vec<Color>,
+    blurred: Vec<Color>,
+    // the frame as it was before a pass that reads around each pixel
+    copy: Vec<u8>,
+}
+
+impl PostFx {
+    pub fn new() -> Self { Self::default() }
+
+    // enabled, after the passes already there
+    pub fn with(mut self, pass: Pass) -> Self {
+        self.push(pass);
+        self
+    }
+
+    pub fn push(&mut self, pass: Pass) {
+        self.passes.push((pass, true));
+    }
+
+    pub fn remove(&mut self, i: usize) -> Option<Pass> {
+        (i < self.passes.len()).then(|| self.passes.remove(i).0)
+    }
+
+    pub fn passes(&self) -> &[(Pass, bool)] { &self.passes }
+
+    pub fn pass_mut(&mut self, i: usize) -> Option<&mut Pass> {
+        self.passes.get_mut(i).map(|(p, _)| p)
+    }
+
+    pub fn set_enabled(&mut self, i: usize, enabled: bool) {
+        if let Some((_, e)) = self.passes.get_mut(i) {
+            *e = enabled;
+        }
+    }
+
+    // flips the pass and returns whether it is now enabled
+    pub fn toggle(&mut self, i: usize) -> bool {
+        self.passes.get_mut(i).is_some_and(|(_, e)| {
+            *e = !*e;
+            *e
+        })
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &Pass> {
+        self.passes.iter().filter(|(_, e)| *e).map(|(p, _)| p)
+    }
+
+    pub fn is_active(&self) -> bool { self.enabled().next().is_some() }
+
+    pub fn animated(&self) -> bool { self.enabled().any(|p| p.animated()) }
+
+    // runs the enabled passes over RGBA `frame` in order; alpha is left alone
+    pub fn apply(&mut self, frame: &mut [u8], width: usize) {
+        self.frame = self.frame.wrapping_add(1);
+        if width == 0 || frame.len() < 4 * width {
+            return;
+        }
+        let height = frame.len() / 4 / width;
+        for i in 0..self.passes.len() {
+            let (pass, enabled) = self.passes[i].clone();
+            if !enabled {
+                continue;
+            }
+            match pass {
+                Pass::Vignette { strength, radius } => {
+                    let center = (width as f64 / 2.0, height as f64 / 2.0);
+                    let half_diagonal = center.0.hypot(center.1).max(1.0);
+                    let radius = radius.clamp(0.0, 0.999);
+                    for_each_pixel_rows(frame, width, |x, y, px| {
+                        let d = (x as f64 + 0.5 - center.0).hypot(y as f64 + 0.5 - center.1) / half_diagonal;
+                        let t = ((d - radius) / (1.0 - radius)).clamp(0.0, 1.0);
+                        scale(px, 1.0 - strength * t * t * (3.0 - 2.0 * t));
+                    });
+                }
+                Pass::Bloom { threshold, radius, intensity } => self.bloom(frame, width, threshold, radius, intensity),
+                Pass::Grain { amount } => {
+                    let seed = self.frame;
+                    for_each_pixel_rows(frame, width, |x, y, px| {
+                        let n = (hash(x as u64, y as u64, seed) * 2.0 - 1.0) * amount * 255.0;
+                        px[..3].iter_mut().for_each(|c| *c = (*c as f64 + n).clamp(0.0, 255.0) as u8);
+                    });
+                }
+                Pass::Scanlines { period, darkness } => {
+                    let period = period.max(1);
+                    for_each_pixel_rows(frame, width, |_, y, px| {
+                        if y % period == period - 1 {
+                            scale(px, 1.0 - darkness);
+                        }
+                    });
+                }
+                Pass::Chromatic { red, green, blue, falloff } => {
+                    self.copy.clear();
+                    self.copy.extend_from_slice(frame);
+                    let (src, center) = (&self.copy[..], Pos::new(width as f64 / 2.0, height as f64 / 2.0));
+                    let half_diagonal = center.len().max(1.0);
+                    for_each_pixel_rows(frame, width, |x, y, px| {
+                        let p = Pos::new(x as f64, y as f64);
+                        let k = if falloff > 0.0 { ((p - center).len() / half_diagonal).min(1.0).powf(falloff) } else { 1.0 };
+                        for (c, offset) in [red, green, blue].into_iter().enumerate() {
+                            px[c] = bilinear(src, width, height, p - offset * k, c);
+                        }
+                    });
+                }
+                Pass::Lut { lut, mix } => lut.apply(frame, width, mix.clamp(0.0, 1.0)),
+            }
+        }
+    }
+
+    // the bright parts averaged into 4 x 4 blocks, blurred with three box
+    // passes each way, which come close to a gaussian, and added back bilinearly
+    fn bloom(&mut self, frame: &mut [u8], width: usize, threshold: f64, radius: f64, intensity: f64) {
+        const K: usize = 4;
+        let height = frame.len() / 4 / width;
+        let (w, h) = (width.div_ceil(K), height.div_ceil(K));
+        let cut = threshold.clamp(0.0, 1.0) * 255.0;
+        self.bright.clear();
+        self.bright.resize(w * h, Color::default());
+        for y in 0..height {
+            for x in 0..width {
+                let px = &frame[4 * (x + y * width)..][..3];
+                let [r, g, b] = [px[0] as f64, px[1] as f64, px[2] as f64];
+                let (c, luma) = (Color::new(r, g, b), 0.299 * r + 0.587 * g + 0.114 * b);
+                let over = (luma - cut).max(0.0) / luma.max(1.0);
+                self.bright[x / K + y / K * w] += c * (over / (K * K) as f64);
+            }
+        }
+        let r = ((radius / K as f64 / 3.0).round() as usize).max(1);
+        self.blurred.resize(w * h, Color::default());
+        for _ in 0..3 {
+            box_blur(&self.bright, &mut self.blurred, w, h, r, 1, w);
+            box_blur(&self.blurred, &mut self.bright, h, w, r, w, 1);
+        }
+        let (bright, offset) = (&self.bright, (K as f64 - 1.0) / 2.0);
+        let at = |x: usize, y: usize| bright[x.min(w - 1) + y.min(h - 1) * w];
+        for_each_pixel_rows(frame, width, |x, y, px| {
+            let (fx, fy) = (((x as f64 - offset) / K as f64).max(0.0), ((y as f64 - offset) / K as f64).max(0.0));
+            let (x0, y0) = (fx as usize, fy as usize);
+            let (tx, ty) = (fx - x0 as f64, fy - y0 as f64);
+            let top = at(x0, y0).lerp(at(x0 + 1, y0), tx);
+            let c = top.lerp(at(x0, y0 + 1).lerp(at(x0 + 1, y0 + 1), tx), ty) * intensity;
+            for (v, add) in px.iter_mut().zip(<[f64; 3]>::from(c)) {
+                *v = (*v as f64 + add).clamp(0.0, 255.0) as u8;
+            }
+        });
+    }
+}
+
+// endregion
+// region Helpers
+
+// channel `c` of RGBA `src` at `p`, with the edges clamped
+fn bilinear(src: &[u8], width: usize, height: usize, p: Pos, c: usize) -> u8 {
+    let (x, y) = (p.x().clamp(0.0, (width - 1) as f64), p.y().clamp(0.0, (height - 1) as f64));
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f64, y - y0 as f64);
+    let at = |x: usize, y: usize| src[4 * (x + y * width) + c] as f64;
+    let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+    let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+    (top + (bottom - top) * ty).round().clamp(0.0, 255.0) as u8
+}
+
+fn scale(px: &mut [u8], f: f64) {
+    px[..3].iter_mut().for_each(|c| *c = (*c as f64 * f).clamp(0.0, 255.0) as u8);
+}
+
+// running mean over 2r + 1 neighbours along lines of `len` values, `step`
+// apart, with `lines` of them starting `stride` apart; edges are clamped
+fn box_blur(src: &[Color], dst: &mut [Color], len: usize, lines: usize, r: usize, step: usize, stride: usize) {
+    let n = (2 * r + 1) as f64;
+    for line in 0..lines {
+        let at = |i: isize| src[line * stride + i.clamp(0, len as isize - 1) as usize * step];
+        let mut sum = (-(r as isize)..=r as isize).fold(Color::default(), |s, i| s + at(i));
+        for i in 0..len {
+            dst[line * stride + i * step] = sum / n;
+            sum += at(i as isize + r as isize + 1) - at(i as isize - r as isize);
+        }
+    }
+}
+
+// uniform in [0, 1), splitmix64 over the pixel and the frame
+fn hash(x: u64, y: u64, seed: u64) -> f64 {
+    let mut z = x.wrapping_mul(0x9e3779b97f4a7c15) ^ y.wrapping_mul(0xc2b2ae3d27d4eb4f) ^ seed.wrapping_mul(0x165667b19e3779f9);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// endregion
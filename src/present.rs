@@ -0,0 +1,242 @@
+// Where rendered frames go. The library only ever writes RGBA bytes, so any
+// backend that can show them implements `Present`; `pixels` and X11 come
+// with their features.
+
+use alloc::boxed::Box;
+use core::error::Error;
+#[cfg(feature = "pixels")]
+use pixels::Pixels;
+#[cfg(feature = "x11")]
+use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+#[cfg(feature = "x11")]
+use winit::window::Window;
+#[cfg(feature = "x11")]
+use x11rb::connection::{Connection, RequestConnection};
+#[cfg(feature = "x11")]
+use x11rb::protocol::xproto::{ConnectionExt, CreateGCAux, ImageFormat, ImageOrder};
+#[cfg(feature = "x11")]
+use x11rb::rust_connection::RustConnection;
+use crate::Pos;
+#[cfg(feature = "pixels")]
+use crate::frame_pos;
+
+pub type PresentError = Box<dyn Error + Send + Sync>;
+
+// region Present
+
+// shows RGBA frames in a window: `Pixels` on the GPU, `X11Present` without one
+pub trait Present {
+    // of the buffer, not the window
+    fn size(&self) -> (u32, u32);
+    // RGBA, at the buffer's size
+    fn frame(&self) -> &[u8];
+    fn frame_mut(&mut self) -> &mut [u8];
+    fn present(&mut self) -> Result<(), PresentError>;
+    // the window's inner size changed, in physical pixels
+    fn resize_surface(&mut self, width: u32, height: u32) -> Result<(), PresentError>;
+    // the frame's size changed; it has to be drawn anew
+    fn resize_buffer(&mut self, width: u32, height: u32) -> Result<(), PresentError>;
+    // where a cursor at `physical` window pixels lands in the frame, clamped to its edges
+    fn frame_pos(&self, physical: Pos) -> Pos;
+}
+
+#[cfg(feature = "pixels")]
+impl Present for Pixels {
+    fn size(&self) -> (u32, u32) {
+        let extent = self.context().texture_extent;
+        (extent.width, extent.height)
+    }
+
+    fn frame(&self) -> &[u8] { Pixels::frame(self) }
+
+    fn frame_mut(&mut self) -> &mut [u8] { Pixels::frame_mut(self) }
+
+    fn present(&mut self) -> Result<(), PresentError> {
+        Ok(self.render()?)
+    }
+
+    fn resize_surface(&mut self, width: u32, height: u32) -> Result<(), PresentError> {
+        Ok(Pixels::resize_surface(self, width, height)?)
+    }
+
+    fn resize_buffer(&mut self, width: u32, height: u32) -> Result<(), PresentError> {
+        Ok(Pixels::resize_buffer(self, width, height)?)
+    }
+
+    fn frame_pos(&self, physical: Pos) -> Pos { frame_pos(self, physical) }
+}
+
+// endregion
+// region X11
+
+// blits frames with plain X11 requests from the CPU, scaled and letterboxed
+// like `Pixels` does; for VMs and X servers without a usable GPU
+#[cfg(feature = "x11")]
+pub struct X11Present {
+    // a connection of its own to the display winit uses
+    conn: RustConnection,
+    window: u32,
+    gc: u32,
+    depth: u8,
+    frame: Vec<u8>,
+    size: (usize, usize),
+    surface: (usize, usize),
+    // the scaled frame in the server's BGRX layout, kept between frames
+    scaled: Vec<u8>,
+}
+
+#[cfg(feature = "x11")]
+impl X11Present {
+    // fails for windows that aren't on X11 or have no 32 bits per pixel visual
+    pub fn new(window: &Window, width: u32, height: u32) -> Result<Self, PresentError> {
+        let id = match window.window_handle()?.as_raw() {
+            RawWindowHandle::Xlib(h) => h.window as u32,
+            RawWindowHandle::Xcb(h) => h.window.get(),
+            _ => return Err("not an X11 window".into()),
+        };
+        let (conn, _) = x11rb::connect(None)?;
+        let depth = conn.get_geometry(id)?.reply()?.depth;
+        let setup = conn.setup();
+        let bpp = setup.pixmap_formats.iter().find(|f| f.depth == depth).map(|f| f.bits_per_pixel);
+        if bpp != Some(32) || setup.image_byte_order != ImageOrder::LSB_FIRST {
+            return Err(format!("unsupported pixel format at depth {}", depth).into());
+        }
+        let gc = conn.generate_id()?;
+        conn.create_gc(gc, id, &CreateGCAux::new().graphics_exposures(0))?;
+        let ws = window.inner_size();
+        Ok(Self {
+            conn,
+            window: id,
+            gc,
+            depth,
+            frame: vec![0; width as usize * height as usize * 4],
+            size: (width as usize, height as usize),
+            surface: (ws.width as usize, ws.height as usize),
+            scaled: Vec::new(),
+        })
+    }
+}
+
+// the frame's scale and top left corner on the surface; whole multiples
+// where it fits, so pixels stay square
+#[cfg(feature = "x11")]
+fn placement((w, h): (usize, usize), (sw, sh): (usize, usize)) -> (f64, Pos) {
+    let fit = (sw as f64 / w.max(1) as f64).min(sh as f64 / h.max(1) as f64);
+    let scale = if fit >= 1.0 { fit.floor() } else { fit };
+    (scale, Pos::new((sw as f64 - w as f64 * scale) / 2.0, (sh as f64 - h as f64 * scale) / 2.0).max(Pos::default()))
+}
+
+// `frame` scaled onto a `surface` sized BGRX image in `out`, black around it
+#[cfg(feature = "x11")]
+fn scale_to_bgrx(frame: &[u8], (w, h): (usize, usize), (sw, sh): (usize, usize), out: &mut Vec<u8>) {
+    let (scale, origin) = placement((w, h), (sw, sh));
+    let source = |v: usize, offset: f64, len: usize| {
+        let s = ((v as f64 + 0.5 - offset) / scale).floor();
+        (s >= 0.0 && s < len as f64).then_some(s as usize)
+    };
+    let columns: Vec<Option<usize>> = (0..sw).map(|x| source(x, origin.x(), w)).collect();
+    out.resize(sw * sh * 4, 0);
+    for (y, row) in out.chunks_exact_mut(sw * 4).enumerate() {
+        let sy = source(y, origin.y(), h);
+        for (out, sx) in row.chunks_exact_mut(4).zip(&columns) {
+            match sy.zip(*sx) {
+                Some((sy, sx)) => {
+                    let p = &frame[4 * (sx + sy * w)..][..4];
+                    out.copy_from_slice(&[p[2], p[1], p[0], 0]);
+                }
+                None => out.fill(0),
+            }
+        }
+    }
+}
+
+// where `physical` surface pixels land in the frame, clamped to its edges
+#[cfg(feature = "x11")]
+fn surface_to_frame(size: (usize, usize), surface: (usize, usize), physical: Pos) -> Pos {
+    let (scale, origin) = placement(size, surface);
+    let p = (physical - origin) / scale;
+    let max = Pos::new(size.0.saturating_sub(1) as f64, size.1.saturating_sub(1) as f64);
+    p.max(Pos::default()).min(max)
+}
+
+#[cfg(feature = "x11")]
+impl Present for X11Present {
+    fn size(&self) -> (u32, u32) { (self.size.0 as u32, self.size.1 as u32) }
+
+    fn frame(&self) -> &[u8] { &self.frame }
+
+    fn frame_mut(&mut self) -> &mut [u8] { &mut self.frame }
+
+    fn present(&mut self) -> Result<(), PresentError> {
+        let ((w, h), (sw, sh)) = (self.size, self.surface);
+        if sw == 0 || sh == 0 || w == 0 || h == 0 {
+            return Ok(());
+        }
+        scale_to_bgrx(&self.frame, self.size, self.surface, &mut self.scaled);
+        // as many rows per request as the server takes
+        let rows = ((self.conn.maximum_request_bytes() - 32) / (sw * 4)).max(1);
+        for (i, strip) in self.scaled.chunks(rows * sw * 4).enumerate() {
+            let (y, height) = (i * rows, strip.len() / (sw * 4));
+            self.conn.put_image(ImageFormat::Z_PIXMAP, self.window, self.gc, sw as u16, height as u16, 0, y as i16, 0, self.depth, strip)?;
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn resize_surface(&mut self, width: u32, height: u32) -> Result<(), PresentError> {
+        self.surface = (width as usize, height as usize);
+        Ok(())
+    }
+
+    fn resize_buffer(&mut self, width: u32, height: u32) -> Result<(), PresentError> {
+        self.size = (width as usize, height as usize);
+        self.frame = vec![0; self.size.0 * self.size.1 * 4];
+        Ok(())
+    }
+
+    fn frame_pos(&self, physical: Pos) -> Pos { surface_to_frame(self.size, self.surface, physical) }
+}
+
+#[cfg(feature = "x11")]
+impl Drop for X11Present {
+    fn drop(&mut self) {
+        let _ = self.conn.free_gc(self.gc);
+        let _ = self.conn.flush();
+    }
+}
+
+// endregion
+
+#[cfg(all(test, feature = "x11"))]
+mod tests {
+    use super::*;
+
+    // two pixels, red-ish and blue-ish
+    const FRAME: [u8; 8] = [1, 2, 3, 255, 5, 6, 7, 255];
+
+    #[test]
+    fn whole_multiples_centered() {
+        assert_eq!(placement((2, 1), (7, 5)), (3.0, Pos::new(0.5, 1.0)));
+        // smaller surfaces shrink to fit
+        assert_eq!(placement((4, 4), (2, 3)), (0.5, Pos::new(0.0, 0.5)));
+    }
+
+    #[test]
+    fn scales_into_bgrx_with_black_bars() {
+        let mut out = Vec::new();
+        scale_to_bgrx(&FRAME, (2, 1), (6, 2), &mut out);
+        let row = [0, 0, 0, 0, 3, 2, 1, 0, 3, 2, 1, 0, 7, 6, 5, 0, 7, 6, 5, 0, 0, 0, 0, 0];
+        assert_eq!(out, [row, row].concat());
+        // reused buffers are resized, and shrinking picks every other pixel
+        let frame: Vec<u8> = (0..16u8).flat_map(|i| [i, 0, 0, 255]).collect();
+        scale_to_bgrx(&frame, (4, 4), (2, 2), &mut out);
+        assert_eq!(out, [0, 0, 5, 0, 0, 0, 7, 0, 0, 0, 13, 0, 0, 0, 15, 0]);
+    }
+
+    #[test]
+    fn cursor_maps_back_into_the_frame() {
+        assert_eq!(surface_to_frame((2, 2), (6, 4), Pos::new(3.0, 1.0)), Pos::new(1.0, 0.5));
+        assert_eq!(surface_to_frame((2, 1), (6, 2), Pos::new(100.0, -5.0)), Pos::new(1.0, 0.0));
+        assert_eq!(surface_to_frame((2, 1), (6, 2), Pos::new(0.0, 0.0)), Pos::new(0.0, 0.0));
+    }
+}
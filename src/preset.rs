@@ -0,0 +1,158 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::config::{parse_toml, ConfigError, Entry, Value};
+use crate::Effect;
+
+// region Preset
+
+// a named effect with its parameters, stored in the same TOML subset as the
+// config file:
+//
+//     name = "slow swirl"
+//     effect = "swirl"
+//
+//     [params]
+//     radius = 260.0
+//     angle = 1.2
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub effect: Effect,
+}
+
+impl Preset {
+    pub fn new(name: impl Into<String>, effect: Effect) -> Self {
+        Self { name: name.into(), effect }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    // parameters left out keep the effect's defaults
+    pub fn parse(src: &str) -> Result<Self, ConfigError> {
+        let entries = parse_toml(src)?;
+        let err = |line: usize, message: String| ConfigError::Parse { line, message };
+        let top = |key: &str| entries.iter().find(|e| e.table.is_empty() && e.key == key);
+        let name = match top("name") {
+            Some(Entry { value: Value::Str(s), .. }) => s.clone(),
+            Some(e) => return Err(err(e.line, "name: expected a string".into())),
+            None => String::new(),
+        };
+        let kind = top("effect").ok_or_else(|| err(1, "missing `effect`".into()))?;
+        let mut effect = kind.value.as_str()
+            .and_then(|s| Effect::all().into_iter().find(|e| e.name() == s))
+            .ok_or_else(|| err(kind.line, format!("effect: unknown effect {}", kind.value)))?;
+        for Entry { table, key, value, line } in &entries {
+            match table.as_str() {
+                "" if key == "name" || key == "effect" => continue,
+                "params" => (),
+                _ => return Err(err(*line, format!("{}: unknown setting", key))),
+            }
+            let mut fields = fields(&mut effect);
+            let field = fields.iter_mut().find(|(k, _)| k == key)
+                .ok_or_else(|| err(*line, format!("params.{}: not a parameter of {}", key, kind.value)))?;
+            field.1.set(value).map_err(|m| err(*line, format!("params.{}: {}", key, m)))?;
+        }
+        Ok(Self { name, effect })
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "name = {}", Value::Str(self.name.clone()))?;
+        writeln!(f, "effect = {}", Value::Str(self.effect.name().into()))?;
+        writeln!(f, "\n[params]")?;
+        let mut effect = self.effect;
+        for (key, field) in fields(&mut effect) {
+            writeln!(f, "{} = {}", key, field.get())?;
+        }
+        Ok(())
+    }
+}
+
+enum Field<'a> {
+    Num(&'a mut f64),
+    Count(&'a mut usize),
+    Flag(&'a mut bool),
+}
+
+impl Field<'_> {
+    fn get(&self) -> Value {
+        match self {
+            Field::Num(n) => Value::Float(**n),
+            Field::Count(n) => Value::Int(**n as i64),
+            Field::Flag(b) => Value::Bool(**b),
+        }
+    }
+
+    fn set(&mut self, value: &Value) -> Result<(), &'static str> {
+        match (self, value) {
+            (Field::Num(n), v) => **n = v.as_f64().ok_or("expected a number")?,
+            (Field::Count(n), &Value::Int(i)) => **n = usize::try_from(i).map_err(|_| "expected a count")?,
+            (Field::Count(_), _) => return Err("expected an integer"),
+            (Field::Flag(b), v) => **b = v.as_bool().ok_or("expected true or false")?,
+        }
+        Ok(())
+    }
+}
+
+// every parameter of `effect`, by name
+fn fields(effect: &mut Effect) -> Vec<(&'static str, Field<'_>)> {
+    use Field::*;
+    match effect {
+        Effect::Warp(w) | Effect::Fluid(w) => vec![
+            ("radius", Num(&mut w.radius)),
+            ("strength", Num(&mut w.strength)),
+            ("taps", Count(&mut w.taps)),
+            ("offset", Num(&mut w.offset)),
+            ("spread", Num(&mut w.spread)),
+            ("separation", Num(&mut w.separation)),
+        ],
+        Effect::Swirl { radius, angle } => vec![("radius", Num(radius)), ("angle", Num(angle))],
+        Effect::Pinch { radius, strength } => vec![("radius", Num(radius)), ("strength", Num(strength))],
+        Effect::Ripple { radius, wavelength, amplitude, speed } => vec![
+            ("radius", Num(radius)),
+            ("wavelength", Num(wavelength)),
+            ("amplitude", Num(amplitude)),
+            ("speed", Num(speed)),
+        ],
+        Effect::Magnifier { radius, zoom, falloff, fisheye } => vec![
+            ("radius", Num(radius)),
+            ("zoom", Num(zoom)),
+            ("falloff", Num(falloff)),
+            ("fisheye", Flag(fisheye)),
+        ],
+        Effect::Water { damping, refraction } => vec![("damping", Num(damping)), ("refraction", Num(refraction))],
+        Effect::Spring { radius } => vec![("radius", Num(radius))],
+        Effect::Kaleidoscope { segments, spin } => vec![("segments", Count(segments)), ("spin", Num(spin))],
+        Effect::Mosaic { radius, block } => vec![("radius", Num(radius)), ("block", Num(block))],
+        Effect::HeatHaze { radius, amplitude, scale, speed } => vec![
+            ("radius", Num(radius)),
+            ("amplitude", Num(amplitude)),
+            ("scale", Num(scale)),
+            ("speed", Num(speed)),
+        ],
+        Effect::Droste { radius, scale, zoom, spin } => vec![
+            ("radius", Num(radius)),
+            ("scale", Num(scale)),
+            ("zoom", Num(zoom)),
+            ("spin", Num(spin)),
+        ],
+        Effect::Flow { radius, strength, scale, steps, speed } => vec![
+            ("radius", Num(radius)),
+            ("strength", Num(strength)),
+            ("scale", Num(scale)),
+            ("steps", Count(steps)),
+            ("speed", Num(speed)),
+        ],
+    }
+}
+
+// endregion
@@ -0,0 +1,244 @@
+// QOI ("Quite OK Image") encoder and decoder for RGBA8: lossless, a single
+// pass each way, and files close to PNG's size at a fraction of the time.
+
+use core::fmt;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::MAX_PIXELS;
+
+// region Error
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Signature,
+    Truncated,
+    Format(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Signature => f.write_str("not a QOI file"),
+            Error::Truncated => f.write_str("QOI data ends early"),
+            Error::Format(e) => write!(f, "malformed QOI: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// endregion
+// region Format
+
+pub const SIGNATURE: [u8; 4] = *b"qoif";
+const HEADER: usize = 14;
+const END: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const OP_RUN: u8 = 0xc0;
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+const MASK: u8 = 0xc0;
+
+// slot of `px` in the table of recently seen pixels
+fn hash(px: [u8; 4]) -> usize {
+    let [r, g, b, a] = px.map(|c| c as usize);
+    (r * 3 + g * 5 + b * 7 + a * 11) % 64
+}
+
+// endregion
+// region Codec
+
+// encodes a tightly packed RGBA buffer, alpha included, tagged as sRGB
+pub fn encode(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    assert!(width > 0 && height > 0 && width * height <= MAX_PIXELS, "unsupported QOI size");
+    assert!(rgba.len() >= width * height * 4, "buffer smaller than width * height * 4");
+    let mut out = Vec::with_capacity(HEADER + width * height * 5 / 2 + END.len());
+    out.extend_from_slice(&SIGNATURE);
+    out.extend_from_slice(&(width as u32).to_be_bytes());
+    out.extend_from_slice(&(height as u32).to_be_bytes());
+    // four channels, sRGB
+    out.extend_from_slice(&[4, 0]);
+    let (mut seen, mut prev, mut run) = ([[0u8; 4]; 64], [0, 0, 0, 255], 0u8);
+    for px in rgba[..width * height * 4].chunks_exact(4) {
+        let px = [px[0], px[1], px[2], px[3]];
+        if px == prev {
+            run += 1;
+            if run == 62 {
+                out.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+        if run > 0 {
+            out.push(OP_RUN | (run - 1));
+            run = 0;
+        }
+        let slot = hash(px);
+        if seen[slot] == px {
+            out.push(OP_INDEX | slot as u8);
+        } else if px[3] != prev[3] {
+            out.push(OP_RGBA);
+            out.extend_from_slice(&px);
+        } else {
+            let [dr, dg, db] = [0, 1, 2].map(|c| px[c].wrapping_sub(prev[c]) as i8);
+            let (dr_dg, db_dg) = (dr.wrapping_sub(dg), db.wrapping_sub(dg));
+            if (-2..2).contains(&dr) && (-2..2).contains(&dg) && (-2..2).contains(&db) {
+                out.push(OP_DIFF | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8);
+            } else if (-32..32).contains(&dg) && (-8..8).contains(&dr_dg) && (-8..8).contains(&db_dg) {
+                out.push(OP_LUMA | (dg + 32) as u8);
+                out.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+            } else {
+                out.push(OP_RGB);
+                out.extend_from_slice(&px[..3]);
+            }
+        }
+        seen[slot] = px;
+        prev = px;
+    }
+    if run > 0 {
+        out.push(OP_RUN | (run - 1));
+    }
+    out.extend_from_slice(&END);
+    out
+}
+
+// returns (width, height, RGBA bytes); files with three channels decode opaque
+pub fn decode(data: &[u8]) -> Result<(usize, usize, Vec<u8>), Error> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err(Error::Signature);
+    }
+    if data.len() < HEADER + END.len() {
+        return Err(Error::Truncated);
+    }
+    let dimension = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize;
+    let (width, height) = (dimension(4), dimension(8));
+    if width == 0 || height == 0 || width.saturating_mul(height) > MAX_PIXELS {
+        return Err(Error::Format("size out of range"));
+    }
+    if !matches!(data[12], 3 | 4) || data[13] > 1 {
+        return Err(Error::Format("bad channels or colorspace"));
+    }
+    let body = &data[HEADER..data.len() - END.len()];
+    let mut pixels = vec![0u8; width * height * 4];
+    let (mut seen, mut px) = ([[0u8; 4]; 64], [0u8, 0, 0, 255]);
+    let (mut i, mut run) = (0, 0);
+    for out in pixels.chunks_exact_mut(4) {
+        if run > 0 {
+            run -= 1;
+        } else {
+            let byte = |k: usize| body.get(i + k).copied().ok_or(Error::Truncated);
+            let op = byte(0)?;
+            match op {
+                OP_RGB => {
+                    px[..3].copy_from_slice(&[byte(1)?, byte(2)?, byte(3)?]);
+                    i += 4;
+                }
+                OP_RGBA => {
+                    px = [byte(1)?, byte(2)?, byte(3)?, byte(4)?];
+                    i += 5;
+                }
+                _ => {
+                    match op & MASK {
+                        OP_INDEX => px = seen[op as usize],
+                        OP_DIFF => {
+                            for (c, shift) in [4, 2, 0].into_iter().enumerate() {
+                                px[c] = px[c].wrapping_add((op >> shift) & 3).wrapping_sub(2);
+                            }
+                        }
+                        OP_LUMA => {
+                            let next = byte(1)?;
+                            let dg = (op & 0x3f).wrapping_sub(32);
+                            px[0] = px[0].wrapping_add(dg.wrapping_sub(8).wrapping_add(next >> 4));
+                            px[1] = px[1].wrapping_add(dg);
+                            px[2] = px[2].wrapping_add(dg.wrapping_sub(8).wrapping_add(next & 0x0f));
+                            i += 1;
+                        }
+                        _ => run = (op & 0x3f) as usize,
+                    }
+                    i += 1;
+                }
+            }
+            seen[hash(px)] = px;
+        }
+        out.copy_from_slice(&px);
+    }
+    Ok((width, height, pixels))
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // flat stretches, gentle and steep gradients, a few colors that keep
+    // coming back and changing alpha, so every op gets used
+    fn image(width: usize, height: usize) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let px = match (x / 16 + y / 4) % 5 {
+                    0 => [40, 80, 120, 255],
+                    1 => [x as u8, y as u8, (x + y) as u8, 255],
+                    2 => [(x * 5) as u8, (x * 7 + y) as u8, (x * 6) as u8, 255],
+                    3 => [(x * 97) as u8, (y * 31) as u8, (x ^ y) as u8, 255],
+                    _ => [[200, 10, 10, 128], [10, 200, 10, 0], [1, 2, 3, 4]][(x + y) % 3],
+                };
+                rgba.extend_from_slice(&px);
+            }
+        }
+        rgba
+    }
+
+    #[test]
+    fn round_trip() {
+        for (width, height) in [(1, 1), (3, 2), (200, 1), (1, 70), (67, 41)] {
+            let rgba = image(width, height);
+            let qoi = encode(width, height, &rgba);
+            assert_eq!(decode(&qoi), Ok((width, height, rgba)), "{}x{}", width, height);
+        }
+    }
+
+    // one run longer than a single op can hold
+    #[test]
+    fn long_runs() {
+        let rgba = [7, 7, 7, 255].repeat(500);
+        let qoi = encode(100, 5, &rgba);
+        assert!(qoi.len() < 40);
+        assert_eq!(decode(&qoi), Ok((100, 5, rgba)));
+    }
+
+    #[test]
+    fn three_channel_files_decode_opaque() {
+        let mut qoi = encode(2, 1, &[1, 2, 3, 255, 4, 5, 6, 255]);
+        qoi[12] = 3;
+        assert_eq!(decode(&qoi), Ok((2, 1, vec![1, 2, 3, 255, 4, 5, 6, 255])));
+    }
+
+    #[test]
+    fn truncated_data_errs() {
+        let qoi = encode(23, 9, &image(23, 9));
+        for len in 0..qoi.len() {
+            assert!(decode(&qoi[..len]).is_err(), "decoded {} of {} bytes", len, qoi.len());
+        }
+    }
+
+    #[test]
+    fn bad_headers() {
+        let qoi = encode(2, 2, &image(2, 2));
+        assert_eq!(decode(b"qoig"), Err(Error::Signature));
+        let mut huge = qoi.clone();
+        huge[4..12].copy_from_slice(&[0, 1, 0, 0, 0, 1, 0, 0]);
+        assert_eq!(decode(&huge), Err(Error::Format("size out of range")));
+        let mut empty = qoi.clone();
+        empty[4..8].fill(0);
+        assert_eq!(decode(&empty), Err(Error::Format("size out of range")));
+        let mut channels = qoi;
+        channels[12] = 2;
+        assert_eq!(decode(&channels), Err(Error::Format("bad channels or colorspace")));
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use winit::event::MouseButton;
+use crate::{Instant, Pos};
+
+// region Recording
+
+// what `Driver` records and replays; positions are in logical pixels, as
+// `AppState::mousemove` gets them
+#[derive(Copy, Clone)]
+pub enum Input {
+    Move(Pos),
+    Button { button: MouseButton, pressed: bool },
+}
+
+// timestamped inputs, one per line in text form:
+//
+//     1250 move 312.5 140
+//     1400 down left
+//     1480 up left
+//
+// the first column is milliseconds since the recording started
+#[derive(Clone, Default)]
+pub struct Recording {
+    events: Vec<(Duration, Input)>,
+}
+
+impl Recording {
+    pub fn events(&self) -> &[(Duration, Input)] { &self.events }
+
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |&(t, _)| t)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::read_to_string(path)?.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+fn button_name(b: MouseButton) -> String {
+    match b {
+        MouseButton::Left => "left".into(),
+        MouseButton::Right => "right".into(),
+        MouseButton::Middle => "middle".into(),
+        MouseButton::Back => "back".into(),
+        MouseButton::Forward => "forward".into(),
+        MouseButton::Other(n) => n.to_string(),
+    }
+}
+
+fn parse_button(s: &str) -> Option<MouseButton> {
+    Some(match s {
+        "left" => MouseButton::Left,
+        "right" => MouseButton::Right,
+        "middle" => MouseButton::Middle,
+        "back" => MouseButton::Back,
+        "forward" => MouseButton::Forward,
+        n => MouseButton::Other(n.parse().ok()?),
+    })
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &(t, input) in &self.events {
+            match input {
+                Input::Move(p) => writeln!(f, "{} move {} {}", t.as_millis(), p.x(), p.y())?,
+                Input::Button { button, pressed } => {
+                    writeln!(f, "{} {} {}", t.as_millis(), if pressed { "down" } else { "up" }, button_name(button))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRecordingError {
+    pub line: usize,
+}
+
+impl fmt::Display for ParseRecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: expected `<ms> move <x> <y>` or `<ms> down|up <button>`", self.line)
+    }
+}
+
+impl std::error::Error for ParseRecordingError {}
+
+impl FromStr for Recording {
+    type Err = ParseRecordingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut events = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let err = ParseRecordingError { line: i + 1 };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let (t, input) = match words[..] {
+                [] => continue,
+                [t, "move", x, y] => (t, Input::Move(Pos::new(x.parse().map_err(|_| err.clone())?, y.parse().map_err(|_| err.clone())?))),
+                [t, state @ ("down" | "up"), b] => {
+                    (t, Input::Button { button: parse_button(b).ok_or(err.clone())?, pressed: state == "down" })
+                }
+                _ => return Err(err),
+            };
+            let t = Duration::from_millis(t.parse().map_err(|_| err.clone())?);
+            // replaying relies on the order
+            if events.last().is_some_and(|&(last, _)| t < last) {
+                return Err(err);
+            }
+            events.push((t, input));
+        }
+        Ok(Self { events })
+    }
+}
+
+// endregion
+// region Recorder
+
+pub struct Recorder {
+    start: Instant,
+    recording: Recording,
+    // saved here when the driver exits
+    path: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { start: Instant::now(), recording: Recording::default(), path: path.into() }
+    }
+
+    pub fn record(&mut self, input: Input) {
+        self.recording.events.push((self.start.elapsed(), input));
+    }
+
+    pub fn recording(&self) -> &Recording { &self.recording }
+
+    pub fn save(&self) -> io::Result<()> {
+        self.recording.save(&self.path)
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+// endregion
+// region Replayer
+
+// plays a recording back on the frame clock, so paused time doesn't count
+pub struct Replayer {
+    recording: Recording,
+    next: usize,
+    time: Duration,
+    // starts over once the recording ends, for unattended kiosk runs
+    pub looping: bool,
+}
+
+impl Replayer {
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, next: 0, time: Duration::ZERO, looping: false }
+    }
+
+    pub fn is_done(&self) -> bool {
+        !self.looping && self.next >= self.recording.events.len()
+    }
+
+    // the inputs that fell due within the next `delta`
+    pub fn advance(&mut self, delta: Duration) -> Vec<Input> {
+        self.time += delta;
+        let mut due = Vec::new();
+        loop {
+            while let Some(&(_, input)) = self.recording.events.get(self.next).filter(|(t, _)| *t <= self.time) {
+                due.push(input);
+                self.next += 1;
+            }
+            let end = self.recording.duration();
+            if !self.looping || self.next < self.recording.events.len() || end.is_zero() {
+                return due;
+            }
+            self.time -= end.min(self.time);
+            self.next = 0;
+        }
+    }
+}
+
+// endregion
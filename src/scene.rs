@@ -0,0 +1,143 @@
+// Images layered over the one the renderer warps, each with its own
+// transform, opacity, blend mode and effect, composited into every frame.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use crate::{warp_displacement_sum, warp_split, BlendMode, Color, Effect, Image, Mat3, Pos};
+
+// region Layer
+
+#[derive(Clone)]
+pub struct Layer {
+    // shared, since images run to megabytes and scenes get cloned along with
+    // the rest of a renderer's settings
+    pub image: Arc<Image>,
+    // from the layer's pixel coordinates to the frame's
+    pub transform: Mat3,
+    // in [0, 1], on top of the image's own alpha
+    pub opacity: f64,
+    pub blend: BlendMode,
+    // shades the layer on its own around the same pointers; the simulated
+    // effects have no state here, so fluid moves like the warp and water and
+    // the spring sheet don't move at all
+    pub effect: Option<Effect>,
+    pub visible: bool,
+}
+
+impl Layer {
+    // at the frame's top left corner, opaque, with no effect
+    pub fn new(image: impl Into<Arc<Image>>) -> Self {
+        Self {
+            image: image.into(),
+            transform: Mat3::IDENTITY,
+            opacity: 1.0,
+            blend: BlendMode::Normal,
+            effect: None,
+            visible: true,
+        }
+    }
+
+    pub fn transform(self, transform: Mat3) -> Self {
+        Self { transform, ..self }
+    }
+
+    pub fn opacity(self, opacity: f64) -> Self {
+        Self { opacity, ..self }
+    }
+
+    pub fn blend(self, blend: BlendMode) -> Self {
+        Self { blend, ..self }
+    }
+
+    pub fn effect(self, effect: Option<Effect>) -> Self {
+        Self { effect, ..self }
+    }
+}
+
+// endregion
+// region Scene
+
+// layers from the bottom up, all of them over the renderer's own image
+#[derive(Clone, Default)]
+pub struct Scene {
+    layers: Vec<Layer>,
+}
+
+impl Scene {
+    pub fn new() -> Self { Self::default() }
+
+    // on top of the layers already there
+    pub fn with(mut self, layer: Layer) -> Self {
+        self.push(layer);
+        self
+    }
+
+    pub fn push(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+
+    pub fn remove(&mut self, i: usize) -> Option<Layer> {
+        (i < self.layers.len()).then(|| self.layers.remove(i))
+    }
+
+    pub fn layers(&self) -> &[Layer] { &self.layers }
+
+    pub fn layer_mut(&mut self, i: usize) -> Option<&mut Layer> { self.layers.get_mut(i) }
+
+    pub fn is_active(&self) -> bool { self.layers.iter().any(|l| l.visible && l.opacity > 0.0) }
+
+    // the visible layers as one frame sees them, with the pointers and the
+    // cursor moved into each one's own coordinates; layers squashed flat by
+    // their transform are left out
+    pub(crate) fn frame(&self, influencers: &[(Pos, Pos)], location: Pos, velocity: Pos) -> Vec<LayerFrame<'_>> {
+        self.layers.iter().filter(|l| l.visible && l.opacity > 0.0).filter_map(|layer| {
+            let inverse = layer.transform.inverse()?;
+            let influencers = influencers.iter().map(|&(l, v)| (inverse * l, inverse.transform_vector(v))).collect();
+            Some(LayerFrame { layer, inverse, influencers, location: inverse * location, velocity: inverse.transform_vector(velocity) })
+        }).collect()
+    }
+}
+
+pub(crate) struct LayerFrame<'a> {
+    layer: &'a Layer,
+    inverse: Mat3,
+    influencers: Vec<(Pos, Pos)>,
+    location: Pos,
+    velocity: Pos,
+}
+
+impl LayerFrame<'_> {
+    // the layer composited over `c`, the color below it at frame pixel `p`;
+    // `linear` is whether `c` is in linear light, which the layer's image
+    // is converted to if it differs
+    pub(crate) fn over(&self, c: Color, p: Pos, t: f64, linear: bool) -> Color {
+        let (layer, img) = (self.layer, &*self.layer.image);
+        let q = self.inverse * p;
+        // the warp's alpha goes with its middle, green tap
+        let (m, tap) = match layer.effect {
+            None => (Pos::default(), 0.0),
+            Some(Effect::Warp(w) | Effect::Fluid(w)) => (warp_displacement_sum(&w, q, &self.influencers), w.offset),
+            Some(e) => (e.displacement(q, self.location, self.velocity, t), 1.0),
+        };
+        let s = q + m * tap;
+        // fading out over the outermost half pixel smooths rotated edges
+        let (w, h) = (img.width as f64, img.height as f64);
+        let coverage = (s.x.min(w - s.x).min(s.y).min(h - s.y) + 0.5).clamp(0.0, 1.0);
+        if coverage <= 0.0 {
+            return c;
+        }
+        let fg = match layer.effect {
+            Some(Effect::Warp(w) | Effect::Fluid(w)) => warp_split(&w, img, q, m),
+            _ => img.sample(s).color(),
+        };
+        let fg = match (img.is_linear(), linear) {
+            (false, true) => fg.to_linear(),
+            (true, false) => fg.to_srgb(),
+            _ => fg,
+        };
+        let fg = fg.with_alpha(img.sample(s).alpha()).with_alpha_mode(img.alpha_mode());
+        (fg.to_premultiplied() * (layer.opacity.clamp(0.0, 1.0) * coverage)).over_with(c, layer.blend)
+    }
+}
+
+// endregion
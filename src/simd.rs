@@ -0,0 +1,192 @@
+// The warp several pixels at a time. Positions, falloff, color sums and the
+// final quantization run in SIMD lanes; only the texture reads are gathered
+// lane by lane. With f64 lanes results match the scalar `warp_split` path;
+// the `simd-f32` feature trades that for twice the lanes. Only these lanes
+// narrow: `Pos`, `Color`, sampling and every other effect stay f64.
+
+use core::array;
+#[cfg(not(feature = "simd-f32"))]
+use wide::f64x4;
+#[cfg(feature = "simd-f32")]
+use wide::f32x8;
+use crate::{warp_displacement_sum, warp_split, Dither, Filter, Image, Pos, Rect, Storage, WarpParams};
+
+#[cfg(not(feature = "simd-f32"))]
+pub type Float = f64;
+#[cfg(not(feature = "simd-f32"))]
+pub type Lanes = f64x4;
+#[cfg(feature = "simd-f32")]
+pub type Float = f32;
+#[cfg(feature = "simd-f32")]
+pub type Lanes = f32x8;
+
+// pixels per step
+pub const LANES: usize = core::mem::size_of::<Lanes>() / core::mem::size_of::<Float>();
+
+// `warp_displacement_sum` at (x, y) for each lane of `xs`, as x and y lanes
+#[inline(always)]
+pub fn warp_displacement_lanes(w: &WarpParams, xs: Lanes, y: Float, influencers: &[(Pos, Pos)]) -> (Lanes, Lanes) {
+    let (zero, one) = (Lanes::ZERO, Lanes::ONE);
+    let (radius, strength) = (Lanes::splat(w.radius as Float), Lanes::splat(w.strength as Float));
+    let (mut mx, mut my) = (zero, zero);
+    for &(l, v) in influencers {
+        let (dx, dy) = (xs - Lanes::splat(l.x() as Float), Lanes::splat(y - l.y() as Float));
+        let m = (one - (dx * dx + dy * dy).sqrt() / radius).max(zero).min(one);
+        // in the scalar order, v * m * m * strength, so rounding matches too
+        mx += Lanes::splat(v.x() as Float) * m * m * strength;
+        my += Lanes::splat(v.y() as Float) * m * m * strength;
+    }
+    (mx, my)
+}
+
+// `warp_split` for each lane, as red, green and blue lanes
+#[inline(always)]
+pub fn warp_split_lanes(w: &WarpParams, img: &Image, xs: Lanes, y: Float, (mx, my): (Lanes, Lanes)) -> [Lanes; 3] {
+    let (taps, ys) = (w.taps.max(1), Lanes::splat(y));
+    let mut c = [Lanes::ZERO; 3];
+    for j in 0..taps {
+        let s = j as f64 * w.spread / taps as f64 + w.offset;
+        for (channel, s) in [s - w.separation, s, s + w.separation].into_iter().enumerate() {
+            let s = Lanes::splat(s as Float);
+            c[channel] += gather(img, xs + mx * s, ys + my * s, channel);
+        }
+    }
+    c.map(|c| c * Lanes::splat(1.0 / taps as Float))
+}
+
+// one channel of `img` at each lane's position; nearest 8-bit sRGB images are
+// read directly, everything else goes through `Image::sample`
+#[inline(always)]
+fn gather(img: &Image, xs: Lanes, ys: Lanes, channel: usize) -> Lanes {
+    let (xs, ys) = (xs.to_array(), ys.to_array());
+    let direct = img.filter() == Filter::Nearest && !img.is_linear() && *img.storage() == Storage::U8;
+    Lanes::new(array::from_fn(|i| {
+        if direct {
+            // float to int casts truncate and saturate, NaN becoming 0
+            let (x, y) = ((xs[i] as usize).min(img.width - 1), (ys[i] as usize).min(img.height - 1));
+            img.data[4 * (x + img.width * y) + channel] as Float
+        } else {
+            // a no-op unless the lanes are f32
+            #[allow(clippy::unnecessary_cast)]
+            let s = img.sample(Pos::new(xs[i] as f64, ys[i] as f64));
+            [s.red(), s.green(), s.blue()][channel] as Float
+        }
+    }))
+}
+
+// like `Color::write_bytes_dithered` for `LANES` pixels of RGBA `out`
+#[inline(always)]
+fn store(out: &mut [u8], colors: [Lanes; 3], thresholds: Lanes) {
+    // max picks the other operand over NaN, so NaN ends up 0 as with `Color::clamp`
+    let [r, g, b] = colors.map(|c| (c + thresholds).max(Lanes::ZERO).min(Lanes::splat(255.0)).to_array());
+    for (i, px) in out.chunks_exact_mut(4).enumerate() {
+        px[0] = r[i] as u8;
+        px[1] = g[i] as u8;
+        px[2] = b[i] as u8;
+    }
+}
+
+// `run_shader_dithered` with the warp of `influencers` over `img` as the
+// shader; rows run in parallel, `LANES` pixels at a time
+pub fn run_warp(frame: &mut [u8], width: usize, dither: Dither, w: &WarpParams, img: &Image, influencers: &[(Pos, Pos)]) {
+    let all = Rect::from_size(Pos::default(), width as f64, (frame.len() / 4 / width.max(1)) as f64);
+    run_warp_region(frame, width, dither, all, w, img, influencers);
+}
+
+// `run_warp` for only the pixels `rect` covers, like `run_shader_region`
+pub fn run_warp_region(frame: &mut [u8], width: usize, dither: Dither, rect: Rect, w: &WarpParams, img: &Image, influencers: &[(Pos, Pos)]) {
+    if width == 0 || img.width == 0 || img.height == 0 {
+        return;
+    }
+    let (xs, ys) = rect.pixel_ranges(width, frame.len() / 4 / width);
+    let offsets = Lanes::new(array::from_fn(|i| i as Float));
+    let row = |(y, row): (usize, &mut [u8])| {
+        let yf = y as Float;
+        let mut chunks = row[4 * xs.start..4 * xs.end].chunks_exact_mut(4 * LANES);
+        for (i, out) in (&mut chunks).enumerate() {
+            let x = xs.start + i * LANES;
+            let lanes = offsets + Lanes::splat(x as Float);
+            let m = warp_displacement_lanes(w, lanes, yf, influencers);
+            let thresholds = Lanes::new(array::from_fn(|k| dither.threshold(x + k, y) as Float));
+            store(out, warp_split_lanes(w, img, lanes, yf, m), thresholds);
+        }
+        // rows that aren't a multiple of `LANES` wide end in scalar pixels
+        for (k, px) in chunks.into_remainder().chunks_exact_mut(4).enumerate() {
+            let x = xs.start + xs.len() / LANES * LANES + k;
+            let p = Pos::new(x as f64, y as f64);
+            warp_split(w, img, p, warp_displacement_sum(w, p, influencers))
+                .write_bytes_dithered(px, dither.threshold(x, y));
+        }
+    };
+    let band = &mut frame[4 * width * ys.start..4 * width * ys.end];
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        band.par_chunks_exact_mut(4 * width).enumerate().for_each(|(i, r)| row((ys.start + i, r)));
+    }
+    #[cfg(not(feature = "parallel"))]
+    band.chunks_exact_mut(4 * width).enumerate().for_each(|(i, r)| row((ys.start + i, r)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    // a busy 8-bit image, so any lane reading the wrong pixel shows
+    fn image(filter: Filter) -> Image {
+        let (w, h) = (61, 43);
+        let data = (0..w * h).flat_map(|i| {
+            let (x, y) = (i % w, i / w);
+            [(x * 37 + y * 11) as u8, (x * x + y * 5) as u8, (y * y * 3 + x) as u8, 255]
+        }).collect();
+        let mut img = Image::new(w, h, data);
+        img.set_filter(filter);
+        img
+    }
+
+    // the scalar warp pixel by pixel, as `run_shader_dithered` writes it
+    fn scalar(width: usize, height: usize, dither: Dither, w: &WarpParams, img: &Image, influencers: &[(Pos, Pos)]) -> Vec<u8> {
+        let mut frame = vec![0; width * height * 4];
+        for (i, px) in frame.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i % width, i / width);
+            let p = Pos::new(x as f64, y as f64);
+            let c: Color = warp_split(w, img, p, warp_displacement_sum(w, p, influencers));
+            c.write_bytes_dithered(px, dither.threshold(x, y));
+        }
+        frame
+    }
+
+    // f64 lanes round exactly like the scalar path; f32 ones can tip a
+    // channel over to the next byte
+    const TOLERANCE: u8 = if cfg!(feature = "simd-f32") { 1 } else { 0 };
+
+    #[test]
+    fn lanes_match_the_scalar_warp() {
+        let influencers = [
+            (Pos::new(20.0, 15.0), Pos::new(14.0, -9.0)),
+            (Pos::new(45.0, 30.0), Pos::new(-30.0, 4.0)),
+            (Pos::new(70.0, -5.0), Pos::new(3.0, 40.0)),
+        ];
+        let params = [
+            WarpParams::default(),
+            WarpParams { radius: 35.0, strength: 2.5, taps: 3, separation: 0.3, ..WarpParams::default() },
+        ];
+        // not a multiple of `LANES`, so rows end in scalar pixels too
+        let (width, height) = (LANES * 13 + 3, 43);
+        for filter in [Filter::Nearest, Filter::Bilinear] {
+            let img = image(filter);
+            for w in &params {
+                for dither in [Dither::None, Dither::Ordered4x4, Dither::InterleavedGradient, Dither::BlueNoise] {
+                    let mut frame = vec![0; width * height * 4];
+                    run_warp(&mut frame, width, dither, w, &img, &influencers);
+                    let expected = scalar(width, height, dither, w, &img, &influencers);
+                    let off = frame.iter().zip(&expected).map(|(a, b)| a.abs_diff(*b)).max();
+                    assert!(off <= Some(TOLERANCE), "{:?}, {:?}: off by {:?}", filter, dither, off);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use crate::jpeg;
+
+// region Mjpeg
+
+#[derive(Default)]
+struct Latest {
+    seq: u64,
+    jpeg: Arc<Vec<u8>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    latest: Mutex<Latest>,
+    fresh: Condvar,
+    clients: AtomicUsize,
+}
+
+// serves published frames as multipart/x-mixed-replace JPEGs, viewable in any
+// browser or embeddable with an <img> tag; every request path gets the stream
+pub struct MjpegServer {
+    shared: Arc<Shared>,
+    addr: SocketAddr,
+    quality: u8,
+}
+
+impl MjpegServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared::default());
+        let s = shared.clone();
+        thread::Builder::new().name("mjpeg-accept".into()).spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let s = s.clone();
+                let _ = thread::Builder::new().name("mjpeg-client".into())
+                    .spawn(move || { let _ = serve(stream, &s); });
+            }
+        })?;
+        Ok(Self { shared, addr, quality: 80 })
+    }
+
+    pub fn quality(self, quality: u8) -> Self {
+        Self { quality: quality.clamp(1, 100), ..self }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr { self.addr }
+
+    pub fn clients(&self) -> usize {
+        self.shared.clients.load(Ordering::Relaxed)
+    }
+
+    // encodes and hands out an RGBA frame; does nothing while nobody is watching
+    pub fn publish(&self, frame: &[u8], width: usize, height: usize) {
+        if self.clients() == 0 {
+            return;
+        }
+        let jpeg = Arc::new(jpeg::encode(width, height, frame, self.quality));
+        let mut latest = self.shared.latest.lock().unwrap();
+        latest.seq += 1;
+        latest.jpeg = jpeg;
+        self.shared.fresh.notify_all();
+    }
+}
+
+struct ClientGuard<'a>(&'a AtomicUsize);
+
+impl Drop for ClientGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn serve(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    // the request itself doesn't matter, just consume the headers
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    stream.write_all(b"HTTP/1.0 200 OK\r\n\
+        Cache-Control: no-cache, private\r\n\
+        Pragma: no-cache\r\n\
+        Content-Type: multipart/x-mixed-replace; boundary=frame\r\n\r\n")?;
+
+    shared.clients.fetch_add(1, Ordering::Relaxed);
+    let _guard = ClientGuard(&shared.clients);
+    let mut seen = 0;
+    loop {
+        let jpeg = {
+            let mut latest = shared.latest.lock().unwrap();
+            while latest.seq == seen {
+                latest = shared.fresh.wait(latest).unwrap();
+            }
+            seen = latest.seq;
+            latest.jpeg.clone()
+        };
+        write!(stream, "--frame\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n", jpeg.len())?;
+        stream.write_all(&jpeg)?;
+        stream.write_all(b"\r\n")?;
+    }
+}
+
+// endregion
@@ -0,0 +1,134 @@
+// Tiny text renderer for overlays, drawing a built-in 5x7 bitmap font straight
+// into RGBA frames.
+
+use crate::Color;
+
+// region Font
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+// cell size including the spacing, at scale 1
+pub const ADVANCE: usize = GLYPH_WIDTH + 1;
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 2;
+
+// printable ASCII from ' ' to '~', five columns each, bit 0 at the top
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5f, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], [0x24, 0x2a, 0x7f, 0x2a, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00], [0x00, 0x1c, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1c, 0x00], [0x14, 0x08, 0x3e, 0x08, 0x14], [0x08, 0x08, 0x3e, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    // 0-9
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], [0x00, 0x42, 0x7f, 0x40, 0x00], [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4b, 0x31], [0x18, 0x14, 0x12, 0x7f, 0x10], [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03], [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1e],
+    [0x00, 0x36, 0x36, 0x00, 0x00], [0x00, 0x56, 0x36, 0x00, 0x00], [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14], [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3e],
+    // A-Z
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], [0x7f, 0x49, 0x49, 0x49, 0x36], [0x3e, 0x41, 0x41, 0x41, 0x22],
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], [0x7f, 0x49, 0x49, 0x49, 0x41], [0x7f, 0x09, 0x09, 0x09, 0x01],
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], [0x7f, 0x08, 0x08, 0x08, 0x7f], [0x00, 0x41, 0x7f, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3f, 0x01], [0x7f, 0x08, 0x14, 0x22, 0x41], [0x7f, 0x40, 0x40, 0x40, 0x40],
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], [0x7f, 0x04, 0x08, 0x10, 0x7f], [0x3e, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x09, 0x09, 0x09, 0x06], [0x3e, 0x41, 0x51, 0x21, 0x5e], [0x7f, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31], [0x01, 0x01, 0x7f, 0x01, 0x01], [0x3f, 0x40, 0x40, 0x40, 0x3f],
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], [0x3f, 0x40, 0x38, 0x40, 0x3f], [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07], [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7f, 0x41, 0x41, 0x00], [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7f, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04], [0x40, 0x40, 0x40, 0x40, 0x40], [0x00, 0x01, 0x02, 0x04, 0x00],
+    // a-z
+    [0x20, 0x54, 0x54, 0x54, 0x78], [0x7f, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7f], [0x38, 0x54, 0x54, 0x54, 0x18], [0x08, 0x7e, 0x09, 0x01, 0x02],
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], [0x7f, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7d, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3d, 0x00], [0x7f, 0x10, 0x28, 0x44, 0x00], [0x00, 0x41, 0x7f, 0x40, 0x00],
+    [0x7c, 0x04, 0x18, 0x04, 0x78], [0x7c, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7c, 0x14, 0x14, 0x14, 0x08], [0x08, 0x14, 0x14, 0x18, 0x7c], [0x7c, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20], [0x04, 0x3f, 0x44, 0x40, 0x20], [0x3c, 0x40, 0x40, 0x20, 0x7c],
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], [0x3c, 0x40, 0x30, 0x40, 0x3c], [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], [0x44, 0x64, 0x54, 0x4c, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00], [0x00, 0x00, 0x7f, 0x00, 0x00], [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+// anything outside printable ASCII shows up as '?'
+fn glyph(c: char) -> &'static [u8; 5] {
+    let i = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    &FONT[i]
+}
+
+// endregion
+// region Draw
+
+#[derive(Copy, Clone)]
+pub struct TextStyle {
+    pub color: Color,
+    // filled box behind the text, for legibility over busy images
+    pub background: Option<Color>,
+    // each font pixel is `scale` x `scale` pixels
+    pub scale: usize,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self { color: Color::WHITE, background: Some(Color::BLACK), scale: 1 }
+    }
+}
+
+// width and height in pixels of `text` at `scale`, lines split at '\n'
+pub fn text_size(text: &str, scale: usize) -> (usize, usize) {
+    let (mut w, mut h) = (0, 0);
+    for line in text.split('\n') {
+        w = w.max(line.chars().count() * ADVANCE * scale);
+        h += LINE_HEIGHT * scale;
+    }
+    // no spacing after the last column or line
+    (w.saturating_sub(scale), h.saturating_sub(2 * scale))
+}
+
+// draws `text` with its top left corner at (x, y) into a `width` pixels wide
+// RGBA frame, clipped at the edges; the background reaches a little past it
+pub fn draw_text(frame: &mut [u8], width: usize, x: isize, y: isize, text: &str, style: &TextStyle) {
+    let scale = style.scale.max(1);
+    if let Some(bg) = style.background {
+        let pad = 2 * scale as isize;
+        let (w, h) = text_size(text, scale);
+        fill(frame, width, (x - pad, y - pad), (w + 4 * scale, h + 4 * scale), bg);
+    }
+    for (row, line) in text.split('\n').enumerate() {
+        let top = y + (row * LINE_HEIGHT * scale) as isize;
+        for (col, c) in line.chars().enumerate() {
+            let left = x + (col * ADVANCE * scale) as isize;
+            for (gx, bits) in glyph(c).iter().enumerate() {
+                for gy in (0..GLYPH_HEIGHT).filter(|gy| bits >> gy & 1 != 0) {
+                    let at = (left + (gx * scale) as isize, top + (gy * scale) as isize);
+                    fill(frame, width, at, (scale, scale), style.color);
+                }
+            }
+        }
+    }
+}
+
+// translucent colors are composited over what's there, whose alpha stays
+pub(crate) fn fill(frame: &mut [u8], width: usize, (x, y): (isize, isize), (w, h): (usize, usize), color: Color) {
+    let height = (frame.len() / 4).checked_div(width).unwrap_or(0);
+    let clip = |v: isize, max: usize| v.clamp(0, max as isize) as usize;
+    let opaque = color.alpha() >= 255.0;
+    for py in clip(y, height)..clip(y + h as isize, height) {
+        for px in clip(x, width)..clip(x + w as isize, width) {
+            let p = &mut frame[4 * (px + py * width)..][..4];
+            if opaque {
+                color.write_bytes(p);
+            } else {
+                color.over(Color::new(p[0] as f64, p[1] as f64, p[2] as f64)).to_straight().write_bytes_dithered(p, 0.5);
+            }
+        }
+    }
+}
+
+// endregion
@@ -0,0 +1,198 @@
+// TGA decoder to RGBA8: true-color, grayscale and color-mapped images, raw or
+// run-length encoded, starting from any corner. TGA has no signature, so
+// `is_tga` checks the header makes sense instead.
+
+use core::fmt;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// region Error
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Signature,
+    Truncated,
+    Format(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Signature => f.write_str("not a TGA file"),
+            Error::Truncated => f.write_str("TGA data ends early"),
+            Error::Format(e) => write!(f, "malformed TGA: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// endregion
+// region Decoder
+
+const HEADER: usize = 18;
+
+// image types, plus 8 when run-length encoded
+const COLOR_MAPPED: u8 = 1;
+const TRUE_COLOR: u8 = 2;
+const GRAYSCALE: u8 = 3;
+const RLE: u8 = 8;
+
+struct Header {
+    id_len: usize,
+    kind: u8,
+    // first index, entries and bits per entry of the color map, if any
+    map: Option<(usize, usize, u8)>,
+    width: usize,
+    height: usize,
+    bpp: u8,
+    descriptor: u8,
+}
+
+fn header(data: &[u8]) -> Result<Header, Error> {
+    let h = data.get(..HEADER).ok_or(Error::Signature)?;
+    let u16_at = |i: usize| u16::from_le_bytes([h[i], h[i + 1]]) as usize;
+    let (map_type, kind, map_bits, bpp, descriptor) = (h[1], h[2], h[7], h[16], h[17]);
+    let map_ok = matches!(map_bits, 15 | 16 | 24 | 32);
+    let valid = match kind & !RLE {
+        COLOR_MAPPED => map_type == 1 && map_ok && matches!(bpp, 8 | 16),
+        TRUE_COLOR => (map_type == 0 || map_type == 1 && map_ok) && matches!(bpp, 15 | 16 | 24 | 32),
+        GRAYSCALE => map_type == 0 && matches!(bpp, 8 | 16),
+        _ => false,
+    };
+    // the top two descriptor bits asked for interleaving, long unused
+    if !valid || descriptor & 0xc0 != 0 || u16_at(12) == 0 || u16_at(14) == 0 {
+        return Err(Error::Signature);
+    }
+    Ok(Header {
+        id_len: h[0] as usize,
+        kind,
+        map: (map_type == 1).then(|| (u16_at(3), u16_at(5), map_bits)),
+        width: u16_at(12),
+        height: u16_at(14),
+        bpp,
+        descriptor,
+    })
+}
+
+pub fn is_tga(data: &[u8]) -> bool {
+    header(data).is_ok()
+}
+
+// a true-color value of `bits` bits; 15 and 16-bit ones decode opaque
+fn color(p: &[u8], bits: u8) -> [u8; 4] {
+    match bits {
+        15 | 16 => {
+            let v = u16::from_le_bytes([p[0], p[1]]);
+            let five = |shift: u16| {
+                let c = ((v >> shift) & 31) as u8;
+                c << 3 | c >> 2
+            };
+            [five(10), five(5), five(0), 255]
+        }
+        24 => [p[2], p[1], p[0], 255],
+        _ => [p[2], p[1], p[0], p[3]],
+    }
+}
+
+// returns (width, height, RGBA bytes)
+pub fn decode(data: &[u8]) -> Result<(usize, usize, Vec<u8>), Error> {
+    let h = header(data)?;
+    let mut pos = HEADER + h.id_len;
+    let mut map = Vec::new();
+    let mut map_first = 0;
+    if let Some((first, len, bits)) = h.map {
+        let entry = (bits as usize).div_ceil(8);
+        let table = data.get(pos..pos + len * entry).ok_or(Error::Truncated)?;
+        map = table.chunks_exact(entry).map(|c| color(c, bits)).collect();
+        map_first = first;
+        pos += len * entry;
+    }
+    let size = (h.bpp as usize).div_ceil(8);
+    let to_rgba = |p: &[u8]| match h.kind & !RLE {
+        COLOR_MAPPED => {
+            let i = if size == 1 { p[0] as usize } else { u16::from_le_bytes([p[0], p[1]]) as usize };
+            i.checked_sub(map_first).and_then(|i| map.get(i)).copied().ok_or(Error::Format("color map index out of range"))
+        }
+        TRUE_COLOR => Ok(color(p, h.bpp)),
+        _ => Ok([p[0], p[0], p[0], if size == 2 { p[1] } else { 255 }]),
+    };
+
+    // pixels in file order; runs may cross rows
+    let total = h.width * h.height;
+    // a run packet covers at most 128 pixels, which bounds what's worth reserving
+    let mut stream = Vec::with_capacity(total.min(data.len().saturating_mul(128)));
+    let pixel = |pos: usize| data.get(pos..pos + size).ok_or(Error::Truncated);
+    if h.kind & RLE == 0 {
+        for i in 0..total {
+            stream.push(to_rgba(pixel(pos + i * size)?)?);
+        }
+    } else {
+        while stream.len() < total {
+            let packet = *data.get(pos).ok_or(Error::Truncated)?;
+            let count = ((packet & 0x7f) as usize + 1).min(total - stream.len());
+            pos += 1;
+            if packet & 0x80 != 0 {
+                let c = to_rgba(pixel(pos)?)?;
+                stream.extend((0..count).map(|_| c));
+                pos += size;
+            } else {
+                for _ in 0..count {
+                    stream.push(to_rgba(pixel(pos)?)?);
+                    pos += size;
+                }
+            }
+        }
+    }
+
+    // rows go bottom-up unless bit 5 says otherwise, and bit 4 flips columns
+    let (right_to_left, top_down) = (h.descriptor & 0x10 != 0, h.descriptor & 0x20 != 0);
+    let mut rgba = vec![0u8; total * 4];
+    for (i, c) in stream.into_iter().enumerate() {
+        let (x, y) = (i % h.width, i / h.width);
+        let x = if right_to_left { h.width - 1 - x } else { x };
+        let y = if top_down { y } else { h.height - 1 - y };
+        rgba[(y * h.width + x) * 4..][..4].copy_from_slice(&c);
+    }
+    Ok((h.width, h.height, rgba))
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 17x9 random pixels in each kind of image, raw or run-length encoded,
+    // in all four corners the rows may start from, next to their RGBA
+    const FILES: [(&str, &[u8], &[u8]); 7] = [
+        ("tc24_rle", include_bytes!("../tests/data/tc24_rle.tga"), include_bytes!("../tests/data/tc24_rle.tga.rgba")),
+        ("tc32_top", include_bytes!("../tests/data/tc32_top.tga"), include_bytes!("../tests/data/tc32_top.tga.rgba")),
+        ("tc16_rtl_rle", include_bytes!("../tests/data/tc16_rtl_rle.tga"), include_bytes!("../tests/data/tc16_rtl_rle.tga.rgba")),
+        ("gray8_top_rtl", include_bytes!("../tests/data/gray8_top_rtl.tga"), include_bytes!("../tests/data/gray8_top_rtl.tga.rgba")),
+        ("gray16_rle", include_bytes!("../tests/data/gray16_rle.tga"), include_bytes!("../tests/data/gray16_rle.tga.rgba")),
+        ("map24_top_rle", include_bytes!("../tests/data/map24_top_rle.tga"), include_bytes!("../tests/data/map24_top_rle.tga.rgba")),
+        ("map16", include_bytes!("../tests/data/map16.tga"), include_bytes!("../tests/data/map16.tga.rgba")),
+    ];
+
+    #[test]
+    fn decodes_every_kind() {
+        for (name, tga, rgba) in FILES {
+            assert!(is_tga(tga));
+            assert_eq!(decode(tga), Ok((17, 9, rgba.to_vec())), "{}", name);
+        }
+    }
+
+    // the files end in 26 bytes that aren't needed
+    #[test]
+    fn truncated_data_errs() {
+        for (name, tga, rgba) in FILES {
+            for len in 0..tga.len() {
+                if let Ok((_, _, short)) = decode(&tga[..len]) {
+                    assert!(tga.len() - len <= 26 && short == rgba, "decoded {} of {} bytes of {}", len, tga.len(), name);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use crate::Image;
+
+// region Thumbnails
+
+// turns a (small) source image into a preview of whatever it stands for
+pub type Preview = Arc<dyn Fn(&Image) -> Image + Send + Sync>;
+
+struct Job {
+    generation: u64,
+    source: Arc<Image>,
+    previews: Vec<(String, Preview)>,
+}
+
+#[derive(Default)]
+struct Shared {
+    generation: AtomicU64,
+    cache: Mutex<HashMap<String, Arc<Image>>>,
+}
+
+// renders small previews of registered effects or presets on a worker thread,
+// e.g. for selection UIs; previews are cached until the source changes
+pub struct Thumbnails {
+    width: usize,
+    height: usize,
+    previews: Vec<(String, Preview)>,
+    source: Option<Arc<Image>>,
+    shared: Arc<Shared>,
+    jobs: Sender<Job>,
+}
+
+impl Thumbnails {
+    // thumbnails fit within `width` x `height`, keeping the source's aspect ratio
+    pub fn new(width: usize, height: usize) -> Self {
+        let shared = Arc::new(Shared::default());
+        let (jobs, rx) = mpsc::channel();
+        let s = shared.clone();
+        thread::Builder::new().name("thumbnails".into())
+            .spawn(move || work(rx, &s))
+            .expect("failed to spawn the thumbnail thread");
+        Self { width, height, previews: Vec::new(), source: None, shared, jobs }
+    }
+
+    // adds or replaces a preview; it is rendered right away if there is a source
+    pub fn register<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&Image) -> Image + Send + Sync + 'static,
+    {
+        let f: Preview = Arc::new(f);
+        self.previews.retain(|(n, _)| n != name);
+        self.previews.push((name.into(), f.clone()));
+        self.shared.cache.lock().unwrap().remove(name);
+        self.submit(vec![(name.into(), f)]);
+    }
+
+    // drops the cache and re-renders everything from a downscaled `source`
+    pub fn set_source(&mut self, source: &Image) {
+        let (w, h) = source.fit(self.width, self.height);
+        self.source = Some(Arc::new(source.resize(w, h)));
+        self.shared.generation.fetch_add(1, Ordering::Relaxed);
+        self.shared.cache.lock().unwrap().clear();
+        self.submit(self.previews.clone());
+    }
+
+    // the finished thumbnail, if it has been rendered for the current source
+    pub fn get(&self, name: &str) -> Option<Arc<Image>> {
+        self.shared.cache.lock().unwrap().get(name).cloned()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.shared.cache.lock().unwrap().len() == self.previews.len()
+    }
+
+    fn submit(&self, previews: Vec<(String, Preview)>) {
+        if let Some(source) = &self.source {
+            let generation = self.shared.generation.load(Ordering::Relaxed);
+            let _ = self.jobs.send(Job { generation, source: source.clone(), previews });
+        }
+    }
+}
+
+// exits once the `Thumbnails` is dropped and the channel closes
+fn work(rx: Receiver<Job>, shared: &Shared) {
+    for job in rx {
+        for (name, f) in job.previews {
+            // skip the rest of a job whose source has been replaced meanwhile
+            if shared.generation.load(Ordering::Relaxed) != job.generation {
+                break;
+            }
+            let thumb = Arc::new(f(&job.source));
+            let mut cache = shared.cache.lock().unwrap();
+            if shared.generation.load(Ordering::Relaxed) == job.generation {
+                cache.insert(name, thumb);
+            }
+        }
+    }
+}
+
+// endregion
@@ -0,0 +1,146 @@
+// Notices files changing on disk by polling them on a thread of its own,
+// which needs nothing beyond std and works the same on every platform and
+// on network drives. Directories are watched for the files right inside them.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+// region Watch
+
+#[derive(Default)]
+struct State {
+    paths: Vec<PathBuf>,
+    // bumped by `set_paths`, so the thread starts over from what is there
+    generation: u64,
+    stop: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+// the files and directories to watch, which can change while a thread
+// spawned from it polls them
+#[derive(Clone, Default)]
+pub struct Watch {
+    shared: Arc<Shared>,
+}
+
+impl Watch {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let watch = Self::default();
+        watch.shared.state.lock().unwrap().paths = paths;
+        watch
+    }
+
+    // files already there when this is called don't count as changed
+    pub fn set_paths(&self, paths: Vec<PathBuf>) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.paths = paths;
+        state.generation += 1;
+        self.shared.changed.notify_one();
+    }
+
+    // polls every `interval` until the returned thread is dropped; `on_change`
+    // gets each file that is new or modified once it has held still for a
+    // poll, so a file still being written isn't read half way
+    pub fn spawn<F>(&self, interval: Duration, on_change: F) -> WatchThread
+    where
+        F: FnMut(&Path) + Send + 'static,
+    {
+        let shared = self.shared.clone();
+        let handle = thread::Builder::new().name("doggowarp-watch".into())
+            .spawn(move || poll(&shared, interval, on_change))
+            .expect("failed to spawn the watch thread");
+        WatchThread { shared: self.shared.clone(), handle: Some(handle) }
+    }
+}
+
+pub struct WatchThread {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for WatchThread {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stop = true;
+        self.shared.changed.notify_one();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// endregion
+// region Polling
+
+// modification time and size, either of which changes with a save
+type Stamp = (Option<SystemTime>, u64);
+
+fn poll(shared: &Shared, interval: Duration, mut on_change: impl FnMut(&Path)) {
+    let (mut paths, mut seen) = {
+        let state = shared.state.lock().unwrap();
+        (state.paths.clone(), state.generation)
+    };
+    let mut known = stamps(&paths);
+    // changed since `known`, as of the last poll
+    let mut pending: HashMap<PathBuf, Stamp> = HashMap::new();
+    loop {
+        {
+            let state = shared.state.lock().unwrap();
+            let (state, _) = shared.changed.wait_timeout_while(state, interval, |s| !s.stop && s.generation == seen).unwrap();
+            if state.stop {
+                return;
+            }
+            if state.generation != seen {
+                (paths, seen) = (state.paths.clone(), state.generation);
+                known = stamps(&paths);
+                pending.clear();
+                continue;
+            }
+        }
+        let now = stamps(&paths);
+        for (path, &stamp) in &now {
+            if known.get(path) == Some(&stamp) {
+                pending.remove(path);
+            } else if pending.insert(path.clone(), stamp) == Some(stamp) {
+                pending.remove(path);
+                known.insert(path.clone(), stamp);
+                on_change(path);
+            }
+        }
+        // a deleted file that comes back counts as new
+        known.retain(|p, _| now.contains_key(p));
+        pending.retain(|p, _| now.contains_key(p));
+    }
+}
+
+// missing paths are left out, since they may yet appear
+fn stamps(paths: &[PathBuf]) -> HashMap<PathBuf, Stamp> {
+    let mut out = HashMap::new();
+    let mut add = |path: PathBuf, meta: fs::Metadata| {
+        out.insert(path, (meta.modified().ok(), meta.len()));
+    };
+    for path in paths {
+        let Ok(meta) = fs::metadata(path) else { continue };
+        if !meta.is_dir() {
+            add(path.clone(), meta);
+            continue;
+        }
+        for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+            match fs::metadata(entry.path()) {
+                Ok(meta) if meta.is_file() => add(entry.path(), meta),
+                _ => (),
+            }
+        }
+    }
+    out
+}
+
+// endregion
@@ -0,0 +1,150 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use crate::Instant;
+
+// region Watchdog
+
+// called from the watchdog's thread while a frame is stuck, with how long it
+// has been rendering so far
+pub type OnStuck = Arc<dyn Fn(Duration) + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub enum Incident {
+    // a frame took this long to render
+    Stall(Duration),
+    // `AppState::render` failed, e.g. because the GPU device was lost
+    RenderError(String),
+}
+
+struct Shared {
+    // start of the frame in progress, if any
+    started: Mutex<Option<Instant>>,
+    // longest frame above the threshold, not yet taken
+    stall: Mutex<Option<Duration>>,
+    incidents: AtomicUsize,
+    alive: AtomicBool,
+    on_stuck: Option<OnStuck>,
+}
+
+// notices frames that don't finish within `threshold`; a helper thread reports
+// them to `on_stuck` while the render thread is still stuck, the render thread
+// picks them up with `take_stall` once it gets going again
+pub struct Watchdog {
+    threshold: Duration,
+    shared: Arc<Shared>,
+    // start of the frame rendering elsewhere last reported, and how often
+    elsewhere: Mutex<Option<(Instant, u32)>>,
+}
+
+impl Watchdog {
+    pub fn new(threshold: Duration, on_stuck: Option<OnStuck>) -> Self {
+        let shared = Arc::new(Shared {
+            started: Mutex::new(None),
+            stall: Mutex::new(None),
+            incidents: AtomicUsize::new(0),
+            alive: AtomicBool::new(true),
+            on_stuck,
+        });
+        let s = shared.clone();
+        thread::Builder::new().name("watchdog".into())
+            .spawn(move || watch(threshold, &s))
+            .expect("failed to spawn the watchdog thread");
+        Self { threshold, shared, elsewhere: Mutex::new(None) }
+    }
+
+    pub fn threshold(&self) -> Duration { self.threshold }
+
+    // brackets the rendering of a frame; time between frames doesn't count
+    pub fn frame_started(&self) {
+        *self.shared.started.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub fn frame_finished(&self) {
+        let Some(started) = self.shared.started.lock().unwrap().take() else { return };
+        let took = started.elapsed();
+        if took > self.threshold {
+            let mut stall = self.shared.stall.lock().unwrap();
+            *stall = Some(stall.map_or(took, |s| s.max(took)));
+        }
+    }
+
+    pub fn take_stall(&self) -> Option<Duration> {
+        self.shared.stall.lock().unwrap().take()
+    }
+
+    // for frames rendered off the event loop, e.g. on a `RenderThread`, given
+    // when the one in progress started: how long it has taken once that's past
+    // the threshold, and again after every further threshold it keeps going
+    pub fn stalled_elsewhere(&self, started: Option<Instant>) -> Option<Duration> {
+        let started = started?;
+        let mut elsewhere = self.elsewhere.lock().unwrap();
+        let reported = match *elsewhere {
+            Some((s, n)) if s == started => n,
+            _ => 0,
+        };
+        let took = started.elapsed();
+        if took <= self.threshold * (reported + 1) {
+            return None;
+        }
+        *elsewhere = Some((started, reported + 1));
+        Some(took)
+    }
+
+    // counts one, which `AppState::recover` is then handed
+    pub fn incident(&self) {
+        self.shared.incidents.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn incidents(&self) -> usize {
+        self.shared.incidents.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.alive.store(false, Ordering::Relaxed);
+    }
+}
+
+fn watch(threshold: Duration, shared: &Shared) {
+    let mut reported = None;
+    while shared.alive.load(Ordering::Relaxed) {
+        thread::sleep(threshold / 4);
+        let started = *shared.started.lock().unwrap();
+        // once per stuck frame
+        if let Some(started) = started.filter(|s| s.elapsed() > threshold && reported != Some(*s)) {
+            reported = Some(started);
+            if let Some(on_stuck) = &shared.on_stuck {
+                on_stuck(started.elapsed());
+            }
+        }
+    }
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_stalled_elsewhere_are_reported_each_threshold() {
+        let watchdog = Watchdog::new(Duration::from_secs(10), None);
+        let ago = |secs| Instant::now().checked_sub(Duration::from_secs(secs));
+        assert_eq!(watchdog.stalled_elsewhere(None), None);
+        let started = ago(5);
+        assert_eq!(watchdog.stalled_elsewhere(started), None);
+        let started = ago(15);
+        assert!(watchdog.stalled_elsewhere(started).is_some());
+        // not again until it's been stuck for another threshold
+        assert_eq!(watchdog.stalled_elsewhere(started), None);
+        // a new frame starts over, and one stuck for two thresholds is
+        // reported twice
+        let started = ago(25);
+        assert!(watchdog.stalled_elsewhere(started).is_some());
+        assert!(watchdog.stalled_elsewhere(started).is_some());
+        assert_eq!(watchdog.stalled_elsewhere(started), None);
+    }
+}
@@ -0,0 +1,1819 @@
+// WebP decoder to RGBA8: lossy VP8 key frames, converted with the same fancy
+// chroma upsampling libwebp uses, lossless VP8L, and the extended format's
+// alpha chunk. Animations aren't supported.
+
+use core::fmt;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::zlib::Bits;
+
+// region Error
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Signature,
+    Truncated,
+    Format(&'static str),
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Signature => f.write_str("not a WebP file"),
+            Error::Truncated => f.write_str("WebP data ends early"),
+            Error::Format(e) => write!(f, "malformed WebP: {}", e),
+            Error::Unsupported(e) => write!(f, "unsupported WebP: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// from the lossless bit reader
+impl From<&'static str> for Error {
+    fn from(e: &'static str) -> Self { Error::Format(e) }
+}
+
+// endregion
+// region Container
+
+// "RIFF", the size of the rest, then "WEBP"
+pub fn is_webp(data: &[u8]) -> bool {
+    data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP"
+}
+
+// returns (width, height, RGBA bytes); lossy images without an alpha chunk
+// decode opaque
+pub fn decode(data: &[u8]) -> Result<(usize, usize, Vec<u8>), Error> {
+    if !is_webp(data) {
+        return Err(Error::Signature);
+    }
+    let u32_at = |d: &[u8], i: usize| u32::from_le_bytes([d[i], d[i + 1], d[i + 2], d[i + 3]]) as usize;
+    let end = (8 + u32_at(data, 4)).min(data.len());
+    let mut rest = data.get(12..end).ok_or(Error::Truncated)?;
+    let (mut canvas, mut alpha) = (None, None);
+    while rest.len() >= 8 {
+        let (id, len) = (&rest[..4], u32_at(rest, 4));
+        let chunk = rest.get(8..8 + len).ok_or(Error::Truncated)?;
+        // chunks are padded to even sizes
+        rest = rest.get(8 + len + (len & 1)..).unwrap_or(&[]);
+        match id {
+            b"VP8X" => {
+                if chunk.len() < 10 {
+                    return Err(Error::Truncated);
+                }
+                if chunk[0] & 0x02 != 0 {
+                    return Err(Error::Unsupported("animation"));
+                }
+                let u24 = |i: usize| chunk[i] as usize | (chunk[i + 1] as usize) << 8 | (chunk[i + 2] as usize) << 16;
+                canvas = Some((u24(4) + 1, u24(7) + 1));
+            }
+            b"ALPH" => alpha = Some(chunk),
+            b"VP8L" => {
+                let (width, height, argb) = lossless(chunk)?;
+                same_size(canvas, width, height)?;
+                let rgba = argb.iter().flat_map(|p| [16, 8, 0, 24].map(|s| (p >> s) as u8)).collect();
+                return Ok((width, height, rgba));
+            }
+            b"VP8 " => {
+                let (width, height, mut rgba) = lossy(chunk)?;
+                same_size(canvas, width, height)?;
+                if let Some(chunk) = alpha {
+                    let a = decode_alpha(chunk, width, height)?;
+                    rgba.chunks_exact_mut(4).zip(a).for_each(|(px, a)| px[3] = a);
+                }
+                return Ok((width, height, rgba));
+            }
+            _ => {}
+        }
+    }
+    Err(Error::Format("no image data"))
+}
+
+fn same_size(canvas: Option<(usize, usize)>, width: usize, height: usize) -> Result<(), Error> {
+    match canvas {
+        Some(c) if c != (width, height) => Err(Error::Format("image size differs from the canvas")),
+        _ => Ok(()),
+    }
+}
+
+// the ALPH chunk of a lossy image: a header byte, then the levels raw or as
+// the green of a lossless image stream, optionally filtered
+fn decode_alpha(chunk: &[u8], width: usize, height: usize) -> Result<Vec<u8>, Error> {
+    let (&header, body) = chunk.split_first().ok_or(Error::Truncated)?;
+    let mut a = match header & 3 {
+        0 => body.get(..width * height).ok_or(Error::Truncated)?.to_vec(),
+        1 => image_stream(&mut Bits::new(body), width, height)?.iter().map(|p| (p >> 8) as u8).collect(),
+        _ => return Err(Error::Format("unknown alpha compression")),
+    };
+    let filter = (header >> 2) & 3;
+    if filter == 0 {
+        return Ok(a);
+    }
+    for i in 0..width * height {
+        let (x, y) = (i % width, i / width);
+        // the first row is predicted from the left and the first column from
+        // above whatever the filter, and the very first level from zero
+        let pred = match (x, y, filter) {
+            (0, 0, _) => 0,
+            (_, 0, _) => a[i - 1],
+            (0, _, _) => a[i - width],
+            (_, _, 1) => a[i - 1],
+            (_, _, 2) => a[i - width],
+            _ => (a[i - 1] as i32 + a[i - width] as i32 - a[i - width - 1] as i32).clamp(0, 255) as u8,
+        };
+        a[i] = a[i].wrapping_add(pred);
+    }
+    Ok(a)
+}
+
+// endregion
+// region Lossless
+
+const ARGB_BLACK: u32 = 0xff00_0000;
+
+fn lossless(data: &[u8]) -> Result<(usize, usize, Vec<u32>), Error> {
+    if data.first() != Some(&0x2f) {
+        return Err(Error::Format("bad VP8L signature"));
+    }
+    let mut bits = Bits::new(&data[1..]);
+    let width = bits.bits(14)? as usize + 1;
+    let height = bits.bits(14)? as usize + 1;
+    // whether any alpha is below 255, which is only a hint
+    bits.bits(1)?;
+    if bits.bits(3)? != 0 {
+        return Err(Error::Unsupported("VP8L version"));
+    }
+    Ok((width, height, image_stream(&mut bits, width, height)?))
+}
+
+// `size` pixels in blocks of 2^`bits`
+fn subsample(size: usize, bits: u32) -> usize {
+    (size + (1 << bits) - 1) >> bits
+}
+
+enum Transform {
+    // per block modes, and per block color multipliers
+    Predictor { bits: u32, modes: Vec<u32> },
+    CrossColor { bits: u32, multipliers: Vec<u32> },
+    SubtractGreen,
+    // up to 2^`bits` indices share a pixel
+    ColorIndexing { bits: u32, palette: Vec<u32> },
+}
+
+// the transforms, then the pixels, of a VP8L image or an alpha chunk; one
+// ARGB value per pixel
+fn image_stream(bits: &mut Bits, width: usize, height: usize) -> Result<Vec<u32>, Error> {
+    // each with the width it works at, as color indexing packs pixels
+    let mut transforms = Vec::new();
+    let (mut xsize, mut seen) = (width, 0);
+    while bits.bits(1)? == 1 {
+        let kind = bits.bits(2)?;
+        if seen & 1 << kind != 0 {
+            return Err(Error::Format("repeated transform"));
+        }
+        seen |= 1 << kind;
+        let transform = match kind {
+            0 | 1 => {
+                let b = bits.bits(3)? + 2;
+                let data = entropy_image(bits, subsample(xsize, b), subsample(height, b), false)?;
+                if kind == 0 {
+                    Transform::Predictor { bits: b, modes: data }
+                } else {
+                    Transform::CrossColor { bits: b, multipliers: data }
+                }
+            }
+            2 => Transform::SubtractGreen,
+            _ => {
+                let n = bits.bits(8)? as usize + 1;
+                let mut palette = entropy_image(bits, n, 1, false)?;
+                // the colors are coded as differences
+                for i in 1..n {
+                    palette[i] = add_pixels(palette[i], palette[i - 1]);
+                }
+                let b = match n {
+                    1..=2 => 3,
+                    3..=4 => 2,
+                    5..=16 => 1,
+                    _ => 0,
+                };
+                Transform::ColorIndexing { bits: b, palette }
+            }
+        };
+        transforms.push((transform, xsize));
+        if let Some((Transform::ColorIndexing { bits, .. }, _)) = transforms.last() {
+            xsize = subsample(xsize, *bits);
+        }
+    }
+    let mut argb = entropy_image(bits, xsize, height, true)?;
+    for (transform, width) in transforms.iter().rev() {
+        argb = transform.invert(argb, *width, height);
+    }
+    Ok(argb)
+}
+
+impl Transform {
+    fn invert(&self, mut argb: Vec<u32>, width: usize, height: usize) -> Vec<u32> {
+        match self {
+            Transform::Predictor { bits, modes } => {
+                let tiles = subsample(width, *bits);
+                for i in 0..width * height {
+                    let (x, y) = (i % width, i / width);
+                    let pred = match (x, y) {
+                        (0, 0) => ARGB_BLACK,
+                        (_, 0) => argb[i - 1],
+                        (0, _) => argb[i - width],
+                        // the above-right of the last column is the first
+                        // pixel of this row, which is what `i - width + 1` reads
+                        _ => predict(
+                            (modes[(x >> bits) + (y >> bits) * tiles] >> 8) & 0xf,
+                            [argb[i - 1], argb[i - width], argb[i - width - 1], argb[i - width + 1]],
+                        ),
+                    };
+                    argb[i] = add_pixels(argb[i], pred);
+                }
+            }
+            Transform::CrossColor { bits, multipliers } => {
+                let tiles = subsample(width, *bits);
+                let delta = |t: u32, c: u32| ((t as u8 as i8 as i32 * c as u8 as i8 as i32) >> 5) as u32;
+                for (i, p) in argb.iter_mut().enumerate() {
+                    let (x, y) = (i % width, i / width);
+                    let m = multipliers[(x >> bits) + (y >> bits) * tiles];
+                    let (green_to_red, green_to_blue, red_to_blue) = (m, m >> 8, m >> 16);
+                    let green = *p >> 8;
+                    let red = ((*p >> 16).wrapping_add(delta(green_to_red, green))) & 0xff;
+                    let blue = (p.wrapping_add(delta(green_to_blue, green)).wrapping_add(delta(red_to_blue, red))) & 0xff;
+                    *p = (*p & 0xff00_ff00) | red << 16 | blue;
+                }
+            }
+            Transform::SubtractGreen => {
+                for p in argb.iter_mut() {
+                    let green = (*p >> 8) & 0xff;
+                    *p = add_pixels(*p, green << 16 | green);
+                }
+            }
+            Transform::ColorIndexing { bits, palette } => {
+                let (packed, per) = (subsample(width, *bits), 8 >> bits);
+                return (0..width * height).map(|i| {
+                    let (x, y) = (i % width, i / width);
+                    let shift = per * (x as u32 & ((1 << bits) - 1));
+                    let index = (argb[y * packed + (x >> bits)] >> 8 >> shift) & ((1 << per) - 1);
+                    // indices past the palette are transparent black
+                    palette.get(index as usize).copied().unwrap_or(0)
+                }).collect();
+            }
+        }
+        argb
+    }
+}
+
+// the 14 predictor modes from the pixels left, top, top-left and top-right
+fn predict(mode: u32, [l, t, tl, tr]: [u32; 4]) -> u32 {
+    match mode {
+        1 => l,
+        2 => t,
+        3 => tr,
+        4 => tl,
+        5 => average(average(l, tr), t),
+        6 => average(l, tl),
+        7 => average(l, t),
+        8 => average(tl, t),
+        9 => average(t, tr),
+        10 => average(average(l, tl), average(t, tr)),
+        11 => {
+            // whichever of left and top is closer to their gradient estimate
+            let distance = |a: u32| (0..4).map(|c| channel(a, c).abs_diff(channel(tl, c))).sum::<u32>();
+            if distance(t) < distance(l) { l } else { t }
+        }
+        12 => per_channel(|c| channel(l, c) as i32 + channel(t, c) as i32 - channel(tl, c) as i32),
+        13 => {
+            let a = average(l, t);
+            per_channel(|c| channel(a, c) as i32 + (channel(a, c) as i32 - channel(tl, c) as i32) / 2)
+        }
+        _ => ARGB_BLACK,
+    }
+}
+
+fn channel(argb: u32, c: u32) -> u32 {
+    (argb >> (8 * c)) & 0xff
+}
+
+// a pixel from four channel values, each clamped to a byte
+fn per_channel(f: impl Fn(u32) -> i32) -> u32 {
+    (0..4).map(|c| (f(c).clamp(0, 255) as u32) << (8 * c)).sum()
+}
+
+fn add_pixels(a: u32, b: u32) -> u32 {
+    let alpha_green = (a & 0xff00_ff00).wrapping_add(b & 0xff00_ff00);
+    let red_blue = (a & 0x00ff_00ff).wrapping_add(b & 0x00ff_00ff);
+    (alpha_green & 0xff00_ff00) | (red_blue & 0x00ff_00ff)
+}
+
+// of each channel, rounded down
+fn average(a: u32, b: u32) -> u32 {
+    (((a ^ b) & 0xfefe_fefe) >> 1) + (a & b)
+}
+
+// the prefix codes of one group: green with lengths and cache indices, red,
+// blue, alpha and distance
+struct Group {
+    green: Code,
+    red: Code,
+    blue: Code,
+    alpha: Code,
+    distance: Code,
+}
+
+// pixels coded with the prefix codes, backward references and a color cache;
+// only the main image of a stream picks its codes per block from a meta image
+fn entropy_image(bits: &mut Bits, width: usize, height: usize, main: bool) -> Result<Vec<u32>, Error> {
+    let cache_bits = if bits.bits(1)? == 1 {
+        let b = bits.bits(4)?;
+        if !(1..=11).contains(&b) {
+            return Err(Error::Format("bad color cache size"));
+        }
+        b
+    } else {
+        0
+    };
+    // the group of each block, from the red and green of the meta image
+    let (meta_bits, meta) = if main && bits.bits(1)? == 1 {
+        let b = bits.bits(3)? + 2;
+        let meta = entropy_image(bits, subsample(width, b), subsample(height, b), false)?;
+        (b, meta.into_iter().map(|p| ((p >> 8) & 0xffff) as usize).collect())
+    } else {
+        (0, Vec::new())
+    };
+    let cache_size = if cache_bits > 0 { 1 << cache_bits } else { 0 };
+    let groups = (0..meta.iter().max().map_or(1, |&m| m + 1)).map(|_| {
+        Ok(Group {
+            green: read_code(bits, 256 + 24 + cache_size)?,
+            red: read_code(bits, 256)?,
+            blue: read_code(bits, 256)?,
+            alpha: read_code(bits, 256)?,
+            distance: read_code(bits, 40)?,
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
+
+    let total = width * height;
+    let meta_width = subsample(width, meta_bits);
+    let (mut cache, mut cached) = (vec![0u32; cache_size], 0);
+    let mut argb = Vec::with_capacity(total);
+    while argb.len() < total {
+        let (x, y) = (argb.len() % width, argb.len() / width);
+        let group = match meta.is_empty() {
+            true => &groups[0],
+            false => &groups[meta[(x >> meta_bits) + (y >> meta_bits) * meta_width]],
+        };
+        let s = group.green.decode(bits)? as usize;
+        if s < 256 {
+            let red = group.red.decode(bits)? as u32;
+            let blue = group.blue.decode(bits)? as u32;
+            let alpha = group.alpha.decode(bits)? as u32;
+            argb.push(alpha << 24 | red << 16 | (s as u32) << 8 | blue);
+        } else if s < 256 + 24 {
+            let len = copy_value(bits, s - 256)?;
+            let prefix = group.distance.decode(bits)? as usize;
+            let code = copy_value(bits, prefix)?;
+            let dist = plane_distance(width, code);
+            if dist > argb.len() || argb.len() + len > total {
+                return Err(Error::Format("backward reference out of range"));
+            }
+            for _ in 0..len {
+                argb.push(argb[argb.len() - dist]);
+            }
+        } else {
+            // catch the cache up on every pixel since the last lookup
+            for &p in &argb[cached..] {
+                cache[(0x1e35_a7bd_u32.wrapping_mul(p) >> (32 - cache_bits)) as usize] = p;
+            }
+            cached = argb.len();
+            argb.push(*cache.get(s - 256 - 24).ok_or(Error::Format("bad color cache index"))?);
+        }
+    }
+    Ok(argb)
+}
+
+// a length or distance from its prefix symbol and extra bits
+fn copy_value(bits: &mut Bits, prefix: usize) -> Result<usize, Error> {
+    if prefix < 4 {
+        return Ok(prefix + 1);
+    }
+    let extra = (prefix as u32 - 2) >> 1;
+    let offset = (2 + (prefix & 1)) << extra;
+    Ok(offset + bits.bits(extra)? as usize + 1)
+}
+
+// the first 120 distance codes stand for nearby (x, y) offsets
+fn plane_distance(width: usize, code: usize) -> usize {
+    if code > 120 {
+        return code - 120;
+    }
+    let d = CODE_TO_PLANE[code - 1] as isize;
+    let (dy, dx) = (d >> 4, 8 - (d & 0xf));
+    (dy * width as isize + dx).max(1) as usize
+}
+
+fn read_code(bits: &mut Bits, size: usize) -> Result<Code, Error> {
+    let mut lengths = vec![0u8; size];
+    if bits.bits(1)? == 1 {
+        // one or two symbols of length 1
+        let two = bits.bits(1)? == 1;
+        let first_bits = if bits.bits(1)? == 1 { 8 } else { 1 };
+        let mut symbols = vec![bits.bits(first_bits)?];
+        if two {
+            symbols.push(bits.bits(8)?);
+        }
+        for s in symbols {
+            *lengths.get_mut(s as usize).ok_or(Error::Format("symbol out of range"))? = 1;
+        }
+        return Code::new(&lengths);
+    }
+    const ORDER: [usize; 19] = [17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+    let mut length_lengths = [0u8; 19];
+    for &i in &ORDER[..bits.bits(4)? as usize + 4] {
+        length_lengths[i] = bits.bits(3)? as u8;
+    }
+    let length_code = Code::new(&length_lengths)?;
+    let mut left = if bits.bits(1)? == 1 {
+        let n = 2 + 2 * bits.bits(3)?;
+        let max = 2 + bits.bits(n)? as usize;
+        if max > size {
+            return Err(Error::Format("too many code lengths"));
+        }
+        max
+    } else {
+        size
+    };
+    let (mut sym, mut prev) = (0, 8);
+    while sym < size && left > 0 {
+        left -= 1;
+        let len = length_code.decode(bits)? as u8;
+        if len < 16 {
+            lengths[sym] = len;
+            sym += 1;
+            if len != 0 {
+                prev = len;
+            }
+            continue;
+        }
+        // 16 repeats the last nonzero length, 17 and 18 zeros
+        let (extra, offset) = [(2, 3), (3, 3), (7, 11)][len as usize - 16];
+        let repeat = bits.bits(extra)? as usize + offset;
+        if sym + repeat > size {
+            return Err(Error::Format("code lengths overrun"));
+        }
+        lengths[sym..sym + repeat].fill(if len == 16 { prev } else { 0 });
+        sym += repeat;
+    }
+    Code::new(&lengths)
+}
+
+const FAST_BITS: u32 = 8;
+
+// canonical prefix code, packed like deflate's. Codes up to `FAST_BITS` long
+// come from a table and longer ones from walking the counts, which keeps the
+// many codes of a large meta image small; a lone symbol takes no bits at all
+struct Code {
+    fast: Vec<(u16, u8)>,
+    counts: [u16; 16],
+    // in code order
+    symbols: Vec<u16>,
+}
+
+impl Code {
+    fn new(lengths: &[u8]) -> Result<Self, Error> {
+        let mut counts = [0u16; 16];
+        lengths.iter().for_each(|&l| counts[l as usize] += 1);
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|&s| lengths[s as usize] > 0).collect();
+        // stable, so symbols of one length stay in order
+        symbols.sort_by_key(|&s| lengths[s as usize]);
+        match symbols.len() {
+            0 => return Err(Error::Format("empty prefix code")),
+            1 => return Ok(Self { fast: Vec::new(), counts, symbols }),
+            _ => {}
+        }
+        // unlike deflate, codes have to be complete
+        let mut open = 1i32;
+        for &count in &counts[1..] {
+            open = 2 * open - count as i32;
+            if open < 0 {
+                return Err(Error::Format("over-subscribed prefix code"));
+            }
+        }
+        if open != 0 {
+            return Err(Error::Format("incomplete prefix code"));
+        }
+        let mut fast = vec![(0u16, 0u8); 1 << FAST_BITS];
+        let mut code = 0u32;
+        let mut next = symbols.iter();
+        for (len, &count) in counts.iter().enumerate().skip(1) {
+            for _ in 0..count {
+                let &sym = next.next().unwrap();
+                if len as u32 <= FAST_BITS {
+                    let rev = code.reverse_bits() >> (32 - len);
+                    for fill in (rev as usize..1 << FAST_BITS).step_by(1 << len) {
+                        fast[fill] = (sym, len as u8);
+                    }
+                }
+                code += 1;
+            }
+            code <<= 1;
+        }
+        Ok(Self { fast, counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, Error> {
+        if self.fast.is_empty() {
+            return Ok(self.symbols[0]);
+        }
+        let next = bits.peek(15);
+        let (sym, len) = self.fast[(next & ((1 << FAST_BITS) - 1)) as usize];
+        if len > 0 {
+            bits.consume(len as u32)?;
+            return Ok(sym);
+        }
+        let (mut code, mut first, mut index) = (0u32, 0u32, 0u32);
+        for len in 1..16 {
+            code |= (next >> (len - 1)) & 1;
+            let count = self.counts[len as usize] as u32;
+            if code < first + count {
+                bits.consume(len)?;
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(Error::Format("invalid prefix code"))
+    }
+}
+
+// endregion
+// region Lossy
+
+// the boolean entropy decoder of VP8, RFC 6386 section 7
+struct BoolDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    // two bytes of the stream; the high one is what decisions look at
+    value: u32,
+    range: u32,
+    bit_count: u32,
+}
+
+impl<'a> BoolDecoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        let mut d = Self { data, pos: 0, value: 0, range: 255, bit_count: 0 };
+        d.value = d.next_byte() << 8 | d.next_byte();
+        d
+    }
+
+    // zeros past the end
+    fn next_byte(&mut self) -> u32 {
+        let b = self.data.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        b as u32
+    }
+
+    // whether decisions have gone into the padding, the way libwebp tells a
+    // cut short stream
+    fn overrun(&self) -> bool {
+        (self.pos - 2) * 8 + self.bit_count as usize + 8 > self.data.len() * 8
+    }
+
+    // true with probability 1 - `prob` / 256
+    fn bit(&mut self, prob: u8) -> bool {
+        let split = 1 + (((self.range - 1) * prob as u32) >> 8);
+        let bit = self.value >= split << 8;
+        if bit {
+            self.range -= split;
+            self.value -= split << 8;
+        } else {
+            self.range = split;
+        }
+        while self.range < 128 {
+            self.value <<= 1;
+            self.range <<= 1;
+            self.bit_count += 1;
+            if self.bit_count == 8 {
+                self.bit_count = 0;
+                self.value |= self.next_byte();
+            }
+        }
+        bit
+    }
+
+    fn literal(&mut self, n: u32) -> u32 {
+        (0..n).fold(0, |v, _| v << 1 | self.bit(128) as u32)
+    }
+
+    // magnitude, then sign
+    fn signed(&mut self, n: u32) -> i32 {
+        let v = self.literal(n) as i32;
+        if self.bit(128) { -v } else { v }
+    }
+
+    // a flag, then the value if set
+    fn optional(&mut self, n: u32) -> i32 {
+        if self.bit(128) { self.signed(n) } else { 0 }
+    }
+}
+
+// intra modes; 16x16 luma and chroma use the first four
+const DC_PRED: u8 = 0;
+const TM_PRED: u8 = 1;
+const V_PRED: u8 = 2;
+const H_PRED: u8 = 3;
+const RD_PRED: u8 = 4;
+const VR_PRED: u8 = 5;
+const LD_PRED: u8 = 6;
+const VL_PRED: u8 = 7;
+const HD_PRED: u8 = 8;
+
+// dequantization factors of a segment, (DC, AC) each
+#[derive(Clone, Copy)]
+struct Quant {
+    y1: [i32; 2],
+    y2: [i32; 2],
+    uv: [i32; 2],
+}
+
+// loop filter settings of a segment, for macroblocks with and without 4x4 modes
+#[derive(Clone, Copy)]
+struct Filter {
+    simple: bool,
+    limit: i32,
+    interior: i32,
+    hev: i32,
+}
+
+type Probs = [[[[u8; 11]; 3]; 8]; 4];
+
+// work buffer strides: a row of neighbors above, a column to the left, and
+// for luma four more columns for the pixels above-right
+const YS: usize = 21;
+const CS: usize = 9;
+
+fn lossy(data: &[u8]) -> Result<(usize, usize, Vec<u8>), Error> {
+    if data.len() < 10 {
+        return Err(Error::Truncated);
+    }
+    let tag = data[0] as usize | (data[1] as usize) << 8 | (data[2] as usize) << 16;
+    if tag & 1 != 0 {
+        return Err(Error::Format("not a key frame"));
+    }
+    if (tag >> 1) & 7 > 3 {
+        return Err(Error::Format("unknown VP8 profile"));
+    }
+    if data[3..6] != [0x9d, 0x01, 0x2a] {
+        return Err(Error::Format("bad VP8 start code"));
+    }
+    // the top two bits are an upscaling hint
+    let width = u16::from_le_bytes([data[6], data[7]]) as usize & 0x3fff;
+    let height = u16::from_le_bytes([data[8], data[9]]) as usize & 0x3fff;
+    if width == 0 || height == 0 {
+        return Err(Error::Format("zero size"));
+    }
+    let first = tag >> 5;
+    let rest = &data[10..];
+    let mut br = BoolDecoder::new(rest.get(..first).ok_or(Error::Truncated)?);
+    // color space and clamping type, neither of which changes decoding
+    br.literal(2);
+
+    // segments, each with its own quantizer and filter level
+    let (mut update_map, mut absolute) = (false, false);
+    let (mut seg_quant, mut seg_level, mut seg_probs) = ([0; 4], [0; 4], [255u8; 3]);
+    let use_segments = br.bit(128);
+    if use_segments {
+        update_map = br.bit(128);
+        if br.bit(128) {
+            absolute = br.bit(128);
+            seg_quant.iter_mut().for_each(|q| *q = br.optional(7));
+            seg_level.iter_mut().for_each(|l| *l = br.optional(6));
+        }
+        if update_map {
+            seg_probs.iter_mut().for_each(|p| *p = if br.bit(128) { br.literal(8) as u8 } else { 255 });
+        }
+    }
+    let segment_value = |values: &[i32; 4], base: i32, s: usize| match (use_segments, absolute) {
+        (false, _) => base,
+        (true, true) => values[s],
+        (true, false) => values[s] + base,
+    };
+
+    let simple = br.bit(128);
+    let level = br.literal(6) as i32;
+    let sharpness = br.literal(3) as i32;
+    let (mut ref_delta, mut mode_delta) = (0, 0);
+    if br.bit(128) && br.bit(128) {
+        // only the intra frame and B_PRED deltas matter in a key frame
+        let mut deltas = [0; 8];
+        deltas.iter_mut().for_each(|d| *d = if br.bit(128) { br.signed(6) } else { 0 });
+        (ref_delta, mode_delta) = (deltas[0], deltas[4]);
+    }
+    let filters: [[Option<Filter>; 2]; 4] = core::array::from_fn(|s| core::array::from_fn(|i4| {
+        let level = (segment_value(&seg_level, level, s) + ref_delta + if i4 == 1 { mode_delta } else { 0 }).clamp(0, 63);
+        let mut interior = level;
+        if sharpness > 0 {
+            interior >>= if sharpness > 4 { 2 } else { 1 };
+            interior = interior.min(9 - sharpness);
+        }
+        let hev = if level >= 40 { 2 } else if level >= 15 { 1 } else { 0 };
+        (level > 0).then_some(Filter { simple, limit: 2 * level + interior.max(1), interior: interior.max(1), hev })
+    }));
+
+    // the token partitions, with the sizes of all but the last up front
+    let count = 1usize << br.literal(2);
+    let sizes = rest[first..].get(..3 * (count - 1)).ok_or(Error::Truncated)?;
+    let mut tail = &rest[first + sizes.len()..];
+    let mut parts = Vec::with_capacity(count);
+    for size in sizes.chunks_exact(3) {
+        let size = (size[0] as usize | (size[1] as usize) << 8 | (size[2] as usize) << 16).min(tail.len());
+        parts.push(BoolDecoder::new(&tail[..size]));
+        tail = &tail[size..];
+    }
+    parts.push(BoolDecoder::new(tail));
+
+    let base = br.literal(7) as i32;
+    let [y1_dc, y2_dc, y2_ac, uv_dc, uv_ac] = [0; 5].map(|_| br.optional(4));
+    let quants: [Quant; 4] = core::array::from_fn(|s| {
+        let q = segment_value(&seg_quant, base, s);
+        let dc = |d: i32, max: i32| DC_TABLE[(q + d).clamp(0, max) as usize] as i32;
+        let ac = |d: i32| AC_TABLE[(q + d).clamp(0, 127) as usize] as i32;
+        Quant {
+            y1: [dc(y1_dc, 127), ac(0)],
+            y2: [dc(y2_dc, 127) * 2, ((ac(y2_ac) * 101581) >> 16).max(8)],
+            uv: [dc(uv_dc, 117), ac(uv_ac)],
+        }
+    });
+    // whether to keep these probabilities for the next frame, of which there are none
+    br.bit(128);
+    let mut probs: Probs = COEFF_PROBS;
+    for (t, bands) in probs.iter_mut().enumerate() {
+        for (b, contexts) in bands.iter_mut().enumerate() {
+            for (c, ps) in contexts.iter_mut().enumerate() {
+                for (p, prob) in ps.iter_mut().enumerate() {
+                    if br.bit(COEFF_UPDATE_PROBS[t][b][c][p]) {
+                        *prob = br.literal(8) as u8;
+                    }
+                }
+            }
+        }
+    }
+    let skip_prob = br.bit(128).then(|| br.literal(8) as u8);
+
+    let (mbw, mbh) = (width.div_ceil(16), height.div_ceil(16));
+    let stride = mbw * 16;
+    let mut planes = [vec![0u8; stride * mbh * 16], vec![0u8; stride / 2 * mbh * 8], vec![0u8; stride / 2 * mbh * 8]];
+    // 4x4 modes and nonzero flags along the bottom of the row above: four
+    // luma, two of each chroma and the luma DC block per macroblock
+    let mut top_modes = vec![DC_PRED; mbw * 4];
+    let mut top_nz = vec![[false; 9]; mbw];
+    // unfiltered bottom rows of the row above, which prediction works from
+    let mut top = [vec![127u8; stride], vec![127u8; stride / 2], vec![127u8; stride / 2]];
+    let mut blocks = Vec::with_capacity(mbw * mbh);
+    for mby in 0..mbh {
+        let tokens = &mut parts[mby % count];
+        let (mut left_modes, mut left_nz) = ([DC_PRED; 4], [false; 9]);
+        let mut left = [[129u8; 16]; 3];
+        // above-left of the macroblock, per plane
+        let mut corner = [if mby == 0 { 127 } else { 129 }; 3];
+        for mbx in 0..mbw {
+            let segment = match update_map {
+                false => 0,
+                true if !br.bit(seg_probs[0]) => br.bit(seg_probs[1]) as usize,
+                true => 2 + br.bit(seg_probs[2]) as usize,
+            };
+            let skip = skip_prob.is_some_and(|p| br.bit(p));
+            let i4 = !br.bit(145);
+            let mut modes = [DC_PRED; 16];
+            let ymode = if i4 {
+                for y in 0..4 {
+                    for x in 0..4 {
+                        let p = &BMODE_PROBS[top_modes[mbx * 4 + x] as usize][left_modes[y] as usize];
+                        let mut i = YMODES_INTRA4[br.bit(p[0]) as usize];
+                        while i > 0 {
+                            i = YMODES_INTRA4[2 * i as usize + br.bit(p[i as usize]) as usize];
+                        }
+                        modes[y * 4 + x] = -i as u8;
+                        top_modes[mbx * 4 + x] = -i as u8;
+                        left_modes[y] = -i as u8;
+                    }
+                }
+                DC_PRED
+            } else {
+                let mode = match br.bit(156) {
+                    true if br.bit(128) => TM_PRED,
+                    true => H_PRED,
+                    false if br.bit(163) => V_PRED,
+                    false => DC_PRED,
+                };
+                top_modes[mbx * 4..][..4].fill(mode);
+                left_modes.fill(mode);
+                mode
+            };
+            let uvmode = match br.bit(142) {
+                false => DC_PRED,
+                true if !br.bit(114) => V_PRED,
+                true if br.bit(183) => TM_PRED,
+                true => H_PRED,
+            };
+
+            // 16 luma blocks, then 4 of each chroma plane
+            let mut coeffs = [0i32; 384];
+            let mut nonzero = false;
+            let tnz = &mut top_nz[mbx];
+            if skip {
+                tnz[..8].fill(false);
+                left_nz[..8].fill(false);
+                if !i4 {
+                    (tnz[8], left_nz[8]) = (false, false);
+                }
+            } else {
+                let q = &quants[segment];
+                let (first, luma) = if i4 {
+                    (0, 3)
+                } else {
+                    // the DCs of the luma blocks come Walsh-Hadamard transformed
+                    let mut dc = [0i32; 16];
+                    let nz = coefficients(tokens, &probs[1], tnz[8] as usize + left_nz[8] as usize, q.y2, 0, &mut dc) > 0;
+                    (tnz[8], left_nz[8]) = (nz, nz);
+                    inverse_wht(&dc, &mut coeffs);
+                    (1, 0)
+                };
+                for n in 0..16 {
+                    let (x, y) = (n % 4, n / 4);
+                    let block = &mut coeffs[n * 16..][..16];
+                    let nz = coefficients(tokens, &probs[luma], tnz[x] as usize + left_nz[y] as usize, q.y1, first, block);
+                    (tnz[x], left_nz[y]) = (nz > first, nz > first);
+                    nonzero |= nz > 1 || block[0] != 0;
+                }
+                for n in 0..8 {
+                    // u then v, each with two flags above and two to the left
+                    let (x, y, flags) = (n % 2, (n / 2) % 2, 4 + n / 4 * 2);
+                    let block = &mut coeffs[256 + n * 16..][..16];
+                    let nz = coefficients(tokens, &probs[2], tnz[flags + x] as usize + left_nz[flags + y] as usize, q.uv, 0, block);
+                    (tnz[flags + x], left_nz[flags + y]) = (nz > 0, nz > 0);
+                    nonzero |= nz > 1 || block[0] != 0;
+                }
+            }
+            if br.overrun() || tokens.overrun() {
+                return Err(Error::Truncated);
+            }
+            blocks.push((filters[segment][i4 as usize], i4 || nonzero));
+
+            // predict from the unfiltered neighbors and add the residuals
+            for (p, plane) in planes.iter_mut().enumerate() {
+                let (size, ws_stride) = if p == 0 { (16, YS) } else { (8, CS) };
+                let plane_stride = stride * size / 16;
+                let mut ws = [0u8; YS * 17];
+                let t = &top[p][mbx * size..][..size];
+                ws[0] = corner[p];
+                ws[1..=size].copy_from_slice(t);
+                for (j, &l) in left[p][..size].iter().enumerate() {
+                    ws[(j + 1) * ws_stride] = l;
+                }
+                let mode = if p == 0 { ymode } else { uvmode };
+                if p == 0 && i4 {
+                    // the rightmost macroblock has nothing above-right, so
+                    // the last pixel above stands in
+                    let right: [u8; 4] = match top[0].get((mbx + 1) * 16..(mbx + 1) * 16 + 4) {
+                        Some(r) => r.try_into().unwrap(),
+                        None => [t[15]; 4],
+                    };
+                    for r in [0, 4, 8, 12] {
+                        ws[r * YS + 17..][..4].copy_from_slice(&right);
+                    }
+                    for (n, &m) in modes.iter().enumerate() {
+                        let at = (n / 4 * 4 + 1) * YS + n % 4 * 4 + 1;
+                        predict4(&mut ws, at, m);
+                        idct_add(&coeffs[n * 16..][..16], &mut ws, at, YS);
+                    }
+                } else {
+                    predict_block(&mut ws, ws_stride, size, mode, mbx > 0, mby > 0);
+                    let base = if p == 0 { 0 } else { 256 + (p - 1) * 64 };
+                    for n in 0..size * size / 16 {
+                        let per_row = size / 4;
+                        let at = (n / per_row * 4 + 1) * ws_stride + n % per_row * 4 + 1;
+                        idct_add(&coeffs[base + n * 16..][..16], &mut ws, at, ws_stride);
+                    }
+                }
+                for j in 0..size {
+                    let row = &ws[(j + 1) * ws_stride + 1..][..size];
+                    plane[(mby * size + j) * plane_stride + mbx * size..][..size].copy_from_slice(row);
+                    left[p][j] = row[size - 1];
+                }
+                corner[p] = ws[size];
+                top[p][mbx * size..][..size].copy_from_slice(&ws[size * ws_stride + 1..][..size]);
+            }
+        }
+    }
+
+    // the loop filter runs over the whole frame in macroblock order
+    for (i, &(filter, inner)) in blocks.iter().enumerate() {
+        let Some(f) = filter else { continue };
+        let (mbx, mby) = (i % mbw, i / mbw);
+        let planes = if f.simple { &mut planes[..1] } else { &mut planes[..] };
+        for (p, plane) in planes.iter_mut().enumerate() {
+            let size = if p == 0 { 16 } else { 8 };
+            let s = stride * size / 16;
+            let at = mby * size * s + mbx * size;
+            let inner_edges = (4..size).step_by(4).filter(|_| inner);
+            if mbx > 0 {
+                f.edge(plane, at, 1, s, size, true);
+            }
+            for x in inner_edges.clone() {
+                f.edge(plane, at + x, 1, s, size, false);
+            }
+            if mby > 0 {
+                f.edge(plane, at, s, 1, size, true);
+            }
+            for y in inner_edges {
+                f.edge(plane, at + y * s, s, 1, size, false);
+            }
+        }
+    }
+    Ok((width, height, yuv_to_rgba(&planes, stride, width, height)))
+}
+
+// reads the tokens of one 4x4 block from coefficient `n` on, dequantized into
+// `out` in raster order; returns one past the last nonzero coefficient
+fn coefficients(br: &mut BoolDecoder, probs: &[[[u8; 11]; 3]; 8], ctx: usize, dq: [i32; 2], mut n: usize, out: &mut [i32]) -> usize {
+    let mut p = &probs[BANDS[n]][ctx];
+    while n < 16 {
+        if !br.bit(p[0]) {
+            return n;
+        }
+        while !br.bit(p[1]) {
+            n += 1;
+            if n == 16 {
+                return 16;
+            }
+            p = &probs[BANDS[n]][0];
+        }
+        let v = if !br.bit(p[2]) {
+            p = &probs[BANDS[n + 1]][1];
+            1
+        } else {
+            let v = large_value(br, p);
+            p = &probs[BANDS[n + 1]][2];
+            v
+        };
+        let v = if br.bit(128) { -v } else { v };
+        // libwebp keeps coefficients in 16 bits, which corrupt streams overflow
+        out[ZIGZAG[n]] = (v * dq[(n > 0) as usize]) as i16 as i32;
+        n += 1;
+    }
+    16
+}
+
+// tokens above one: small values from the tree, larger ones as categories
+// with extra bits of fixed probabilities
+fn large_value(br: &mut BoolDecoder, p: &[u8; 11]) -> i32 {
+    if !br.bit(p[3]) {
+        if !br.bit(p[4]) { 2 } else { 3 + br.bit(p[5]) as i32 }
+    } else if !br.bit(p[6]) {
+        if !br.bit(p[7]) {
+            5 + br.bit(159) as i32
+        } else {
+            7 + 2 * br.bit(165) as i32 + br.bit(145) as i32
+        }
+    } else {
+        let high = br.bit(p[8]) as usize;
+        let cat = 2 * high + br.bit(p[9 + high]) as usize;
+        let extra = CATEGORIES[cat].iter().fold(0, |v, &prob| 2 * v + br.bit(prob) as i32);
+        extra + 3 + (8 << cat)
+    }
+}
+
+fn inverse_wht(input: &[i32; 16], out: &mut [i32; 384]) {
+    let mut tmp = [0; 16];
+    for i in 0..4 {
+        let a0 = input[i] + input[12 + i];
+        let a1 = input[4 + i] + input[8 + i];
+        let a2 = input[4 + i] - input[8 + i];
+        let a3 = input[i] - input[12 + i];
+        tmp[i] = a0 + a1;
+        tmp[8 + i] = a0 - a1;
+        tmp[4 + i] = a3 + a2;
+        tmp[12 + i] = a3 - a2;
+    }
+    for i in 0..4 {
+        let dc = tmp[i * 4] + 3;
+        let a0 = dc + tmp[3 + i * 4];
+        let a1 = tmp[1 + i * 4] + tmp[2 + i * 4];
+        let a2 = tmp[1 + i * 4] - tmp[2 + i * 4];
+        let a3 = dc - tmp[3 + i * 4];
+        out[i * 64] = (a0 + a1) >> 3;
+        out[i * 64 + 16] = (a3 + a2) >> 3;
+        out[i * 64 + 32] = (a0 - a1) >> 3;
+        out[i * 64 + 48] = (a3 - a2) >> 3;
+    }
+}
+
+// the inverse DCT of a 4x4 block, added onto the prediction at `at`
+fn idct_add(input: &[i32], ws: &mut [u8], at: usize, stride: usize) {
+    let mul1 = |a: i32| ((a as i64 * 20091) >> 16) as i32 + a;
+    let mul2 = |a: i32| ((a as i64 * 35468) >> 16) as i32;
+    let mut tmp = [0; 16];
+    for i in 0..4 {
+        let a = input[i] + input[8 + i];
+        let b = input[i] - input[8 + i];
+        let c = mul2(input[4 + i]) - mul1(input[12 + i]);
+        let d = mul1(input[4 + i]) + mul2(input[12 + i]);
+        tmp[i * 4..][..4].copy_from_slice(&[a + d, b + c, b - c, a - d]);
+    }
+    for i in 0..4 {
+        let dc = tmp[i] + 4;
+        let a = dc + tmp[8 + i];
+        let b = dc - tmp[8 + i];
+        let c = mul2(tmp[4 + i]) - mul1(tmp[12 + i]);
+        let d = mul1(tmp[4 + i]) + mul2(tmp[12 + i]);
+        for (x, v) in [a + d, b + c, b - c, a - d].into_iter().enumerate() {
+            let px = &mut ws[at + i * stride + x];
+            *px = (*px as i32 + (v >> 3)).clamp(0, 255) as u8;
+        }
+    }
+}
+
+// 16x16 luma or 8x8 chroma prediction; the DC mode averages only the
+// neighbors inside the frame
+fn predict_block(ws: &mut [u8], stride: usize, size: usize, mode: u8, has_left: bool, has_top: bool) {
+    let top: u32 = ws[1..=size].iter().map(|&v| v as u32).sum();
+    let left: u32 = (1..=size).map(|j| ws[j * stride] as u32).sum();
+    let shift = size.trailing_zeros();
+    let dc = match (has_top, has_left) {
+        (true, true) => (top + left + size as u32) >> (shift + 1),
+        (true, false) => (top + size as u32 / 2) >> shift,
+        (false, true) => (left + size as u32 / 2) >> shift,
+        (false, false) => 128,
+    } as u8;
+    for y in 1..=size {
+        for x in 1..=size {
+            ws[y * stride + x] = match mode {
+                DC_PRED => dc,
+                TM_PRED => (ws[y * stride] as i32 + ws[x] as i32 - ws[0] as i32).clamp(0, 255) as u8,
+                V_PRED => ws[x],
+                _ => ws[y * stride],
+            };
+        }
+    }
+}
+
+// one 4x4 luma block at `at` in the luma work buffer
+fn predict4(ws: &mut [u8], at: usize, mode: u8) {
+    let avg2 = |a: u32, b: u32| ((a + b + 1) >> 1) as u8;
+    let avg3 = |a: u32, b: u32, c: u32| ((a + 2 * b + c + 2) >> 2) as u8;
+    // A to H above and above-right, I to L to the left, X above-left
+    let t: [u32; 8] = core::array::from_fn(|i| ws[at - YS + i] as u32);
+    let [i, j, k, l] = [0, 1, 2, 3].map(|y| ws[at + y * YS - 1] as u32);
+    let x = ws[at - YS - 1] as u32;
+    let [a, b, c, d, e, f, g, h] = t;
+    let rows: [[u8; 4]; 4] = match mode {
+        DC_PRED => [[((t[..4].iter().sum::<u32>() + i + j + k + l + 4) >> 3) as u8; 4]; 4],
+        TM_PRED => core::array::from_fn(|y| {
+            let left = [i, j, k, l][y] as i32;
+            core::array::from_fn(|c| (left + t[c] as i32 - x as i32).clamp(0, 255) as u8)
+        }),
+        V_PRED => [[avg3(x, a, b), avg3(a, b, c), avg3(b, c, d), avg3(c, d, e)]; 4],
+        H_PRED => [[avg3(x, i, j); 4], [avg3(i, j, k); 4], [avg3(j, k, l); 4], [avg3(k, l, l); 4]],
+        RD_PRED => {
+            let edge = [l, k, j, i, x, a, b, c, d];
+            core::array::from_fn(|y| core::array::from_fn(|c| avg3(edge[3 + c - y], edge[4 + c - y], edge[5 + c - y])))
+        }
+        LD_PRED => core::array::from_fn(|y| core::array::from_fn(|c| {
+            let s = c + y;
+            avg3(t[s], t[s + 1], t[(s + 2).min(7)])
+        })),
+        VR_PRED => [
+            [avg2(x, a), avg2(a, b), avg2(b, c), avg2(c, d)],
+            [avg3(i, x, a), avg3(x, a, b), avg3(a, b, c), avg3(b, c, d)],
+            [avg3(j, i, x), avg2(x, a), avg2(a, b), avg2(b, c)],
+            [avg3(k, j, i), avg3(i, x, a), avg3(x, a, b), avg3(a, b, c)],
+        ],
+        VL_PRED => [
+            [avg2(a, b), avg2(b, c), avg2(c, d), avg2(d, e)],
+            [avg3(a, b, c), avg3(b, c, d), avg3(c, d, e), avg3(d, e, f)],
+            [avg2(b, c), avg2(c, d), avg2(d, e), avg3(e, f, g)],
+            [avg3(b, c, d), avg3(c, d, e), avg3(d, e, f), avg3(f, g, h)],
+        ],
+        HD_PRED => [
+            [avg2(i, x), avg3(i, x, a), avg3(x, a, b), avg3(a, b, c)],
+            [avg2(j, i), avg3(j, i, x), avg2(i, x), avg3(i, x, a)],
+            [avg2(k, j), avg3(k, j, i), avg2(j, i), avg3(j, i, x)],
+            [avg2(l, k), avg3(l, k, j), avg2(k, j), avg3(k, j, i)],
+        ],
+        _ => {
+            let l8 = l as u8;
+            [
+                [avg2(i, j), avg3(i, j, k), avg2(j, k), avg3(j, k, l)],
+                [avg2(j, k), avg3(j, k, l), avg2(k, l), avg3(k, l, l)],
+                [avg2(k, l), avg3(k, l, l), l8, l8],
+                [l8; 4],
+            ]
+        }
+    };
+    for (y, row) in rows.iter().enumerate() {
+        ws[at + y * YS..][..4].copy_from_slice(row);
+    }
+}
+
+impl Filter {
+    // the `len` lines across one edge, `along` apart, with pixels `across`
+    // apart in each; macroblock edges are filtered harder than inner ones
+    fn edge(&self, plane: &mut [u8], at: usize, across: usize, along: usize, len: usize, outer: bool) {
+        let thresh = 2 * (self.limit + if outer { 4 } else { 0 }) + 1;
+        for n in 0..len {
+            let q0 = at + n * along;
+            // p3, p2, p1, p0 before the edge, q0 to q3 after it
+            let mut v: [i32; 8] = core::array::from_fn(|k| plane[q0 + k * across - 4 * across] as i32);
+            let [p3, p2, p1, p0, q0v, q1, q2, q3] = v;
+            if 4 * (p0 - q0v).abs() + (p1 - q1).abs() > thresh {
+                continue;
+            }
+            if self.simple {
+                filter2(&mut v);
+            } else {
+                let interior = [p3 - p2, p2 - p1, p1 - p0, q3 - q2, q2 - q1, q1 - q0v];
+                if interior.iter().any(|d| d.abs() > self.interior) {
+                    continue;
+                }
+                if (p1 - p0).abs() > self.hev || (q1 - q0v).abs() > self.hev {
+                    filter2(&mut v);
+                } else if outer {
+                    filter6(&mut v);
+                } else {
+                    filter4(&mut v);
+                }
+            }
+            for (k, &value) in v.iter().enumerate().take(7).skip(1) {
+                plane[q0 + k * across - 4 * across] = value as u8;
+            }
+        }
+    }
+}
+
+fn clip_i8(v: i32) -> i32 { v.clamp(-128, 127) }
+fn clip_i4(v: i32) -> i32 { v.clamp(-16, 15) }
+fn clip_u8(v: i32) -> i32 { v.clamp(0, 255) }
+
+// moves p0 and q0 towards each other
+fn filter2(v: &mut [i32; 8]) {
+    let a = 3 * (v[4] - v[3]) + clip_i8(v[2] - v[5]);
+    let (a1, a2) = (clip_i4((a + 4) >> 3), clip_i4((a + 3) >> 3));
+    v[3] = clip_u8(v[3] + a2);
+    v[4] = clip_u8(v[4] - a1);
+}
+
+// p1 to q1, for inner edges
+fn filter4(v: &mut [i32; 8]) {
+    let a = 3 * (v[4] - v[3]);
+    let (a1, a2) = (clip_i4((a + 4) >> 3), clip_i4((a + 3) >> 3));
+    let a3 = (a1 + 1) >> 1;
+    v[2] = clip_u8(v[2] + a3);
+    v[3] = clip_u8(v[3] + a2);
+    v[4] = clip_u8(v[4] - a1);
+    v[5] = clip_u8(v[5] - a3);
+}
+
+// p2 to q2, for macroblock edges
+fn filter6(v: &mut [i32; 8]) {
+    let a = clip_i8(3 * (v[4] - v[3]) + clip_i8(v[2] - v[5]));
+    let a1 = (27 * a + 63) >> 7;
+    let a2 = (18 * a + 63) >> 7;
+    let a3 = (9 * a + 63) >> 7;
+    v[1] = clip_u8(v[1] + a3);
+    v[2] = clip_u8(v[2] + a2);
+    v[3] = clip_u8(v[3] + a1);
+    v[4] = clip_u8(v[4] - a1);
+    v[5] = clip_u8(v[5] - a2);
+    v[6] = clip_u8(v[6] - a3);
+}
+
+// endregion
+// region Color
+
+// the planes of `lossy` to RGBA, with chroma interpolated between samples the
+// way libwebp's fancy upsampler does, so decodes match it exactly
+fn yuv_to_rgba(planes: &[Vec<u8>; 3], stride: usize, width: usize, height: usize) -> Vec<u8> {
+    let mut rgba = vec![255u8; width * height * 4];
+    let chroma_row = |p: usize, r: usize| &planes[p][r * stride / 2..][..width.div_ceil(2)];
+    let (mut near, mut far) = ([vec![0u8; width], vec![0u8; width]], [vec![0u8; width], vec![0u8; width]]);
+    let mut emit = |y: usize, u: &[u8], v: &[u8]| {
+        let luma = &planes[0][y * stride..][..width];
+        for (x, px) in rgba[y * width * 4..][..width * 4].chunks_exact_mut(4).enumerate() {
+            px[..3].copy_from_slice(&yuv_to_rgb(luma[x], u[x], v[x]));
+        }
+    };
+    // the first row sits on the first chroma row, and so does the last of an
+    // even height on the last; every other row is between two chroma rows
+    for p in 0..2 {
+        upsample(chroma_row(p + 1, 0), chroma_row(p + 1, 0), &mut near[p], &mut far[p]);
+    }
+    emit(0, &near[0], &near[1]);
+    for r in 1..height.div_ceil(2) {
+        for p in 0..2 {
+            upsample(chroma_row(p + 1, r - 1), chroma_row(p + 1, r), &mut near[p], &mut far[p]);
+        }
+        emit(2 * r - 1, &near[0], &near[1]);
+        emit(2 * r, &far[0], &far[1]);
+    }
+    if height.is_multiple_of(2) && height > 1 {
+        let last = height / 2 - 1;
+        for p in 0..2 {
+            upsample(chroma_row(p + 1, last), chroma_row(p + 1, last), &mut near[p], &mut far[p]);
+        }
+        emit(height - 1, &near[0], &near[1]);
+    }
+    rgba
+}
+
+// chroma for the two rows of pixels between chroma rows `top` and `bottom`:
+// 3:1 towards the nearer row, and across too between columns
+fn upsample(top: &[u8], bottom: &[u8], upper: &mut [u8], lower: &mut [u8]) {
+    let len = upper.len();
+    let (mut tl, mut l) = (top[0] as u32, bottom[0] as u32);
+    upper[0] = ((3 * tl + l + 2) >> 2) as u8;
+    lower[0] = ((3 * l + tl + 2) >> 2) as u8;
+    for x in 1..=(len - 1) >> 1 {
+        let (t, b) = (top[x] as u32, bottom[x] as u32);
+        let avg = tl + t + l + b + 8;
+        let diag12 = (avg + 2 * (t + l)) >> 3;
+        let diag03 = (avg + 2 * (tl + b)) >> 3;
+        upper[2 * x - 1] = ((diag12 + tl) >> 1) as u8;
+        upper[2 * x] = ((diag03 + t) >> 1) as u8;
+        lower[2 * x - 1] = ((diag03 + l) >> 1) as u8;
+        lower[2 * x] = ((diag12 + b) >> 1) as u8;
+        (tl, l) = (t, b);
+    }
+    if len.is_multiple_of(2) {
+        upper[len - 1] = ((3 * tl + l + 2) >> 2) as u8;
+        lower[len - 1] = ((3 * l + tl + 2) >> 2) as u8;
+    }
+}
+
+// BT.601 studio range in libwebp's fixed point
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let mul = |a: u8, k: i32| (a as i32 * k) >> 8;
+    let clip = |v: i32| if v & !16383 == 0 { (v >> 6) as u8 } else if v < 0 { 0 } else { 255 };
+    let luma = mul(y, 19077);
+    [
+        clip(luma + mul(v, 26149) - 14234),
+        clip(luma - mul(u, 6419) - mul(v, 13320) + 8708),
+        clip(luma + mul(u, 33050) - 17685),
+    ]
+}
+
+// endregion
+// region Tables
+
+const BANDS: [usize; 17] = [0, 1, 2, 3, 6, 4, 5, 6, 6, 6, 6, 6, 6, 6, 6, 7, 0];
+const ZIGZAG: [usize; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
+
+// probabilities of the extra bits of token categories 3 to 6
+const CATEGORIES: [&[u8]; 4] = [
+    &[173, 148, 140],
+    &[176, 155, 140, 135],
+    &[180, 157, 141, 134, 130],
+    &[254, 254, 243, 230, 196, 177, 153, 140, 133, 130, 129],
+];
+
+// the 4x4 mode tree: positive entries lead on, the rest are negated modes
+const YMODES_INTRA4: [i8; 18] = [0, 1, -1, 2, -2, 3, 4, 6, -3, 5, -4, -5, -6, 7, -7, 8, -8, -9];
+
+// step sizes by quantizer index
+const DC_TABLE: [u8; 128] = [
+    4, 5, 6, 7, 8, 9, 10, 10, 11, 12, 13, 14, 15, 16, 17, 17,
+    18, 19, 20, 20, 21, 21, 22, 22, 23, 23, 24, 25, 25, 26, 27, 28,
+    29, 30, 31, 32, 33, 34, 35, 36, 37, 37, 38, 39, 40, 41, 42, 43,
+    44, 45, 46, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58,
+    59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74,
+    75, 76, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89,
+    91, 93, 95, 96, 98, 100, 101, 102, 104, 106, 108, 110, 112, 114, 116, 118,
+    122, 124, 126, 128, 130, 132, 134, 136, 138, 140, 143, 145, 148, 151, 154, 157,
+];
+
+const AC_TABLE: [u16; 128] = [
+    4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+    20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35,
+    36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51,
+    52, 53, 54, 55, 56, 57, 58, 60, 62, 64, 66, 68, 70, 72, 74, 76,
+    78, 80, 82, 84, 86, 88, 90, 92, 94, 96, 98, 100, 102, 104, 106, 108,
+    110, 112, 114, 116, 119, 122, 125, 128, 131, 134, 137, 140, 143, 146, 149, 152,
+    155, 158, 161, 164, 167, 170, 173, 177, 181, 185, 189, 193, 197, 201, 205, 209,
+    213, 217, 221, 225, 229, 234, 239, 245, 249, 254, 259, 264, 269, 274, 279, 284,
+];
+
+// token probabilities by block type, band, context and tree node
+const COEFF_PROBS: [[[[u8; 11]; 3]; 8]; 4] = [
+    [
+        [
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [253, 136, 254, 255, 228, 219, 128, 128, 128, 128, 128],
+            [189, 129, 242, 255, 227, 213, 255, 219, 128, 128, 128],
+            [106, 126, 227, 252, 214, 209, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 98, 248, 255, 236, 226, 255, 255, 128, 128, 128],
+            [181, 133, 238, 254, 221, 234, 255, 154, 128, 128, 128],
+            [78, 134, 202, 247, 198, 180, 255, 219, 128, 128, 128],
+        ],
+        [
+            [1, 185, 249, 255, 243, 255, 128, 128, 128, 128, 128],
+            [184, 150, 247, 255, 236, 224, 128, 128, 128, 128, 128],
+            [77, 110, 216, 255, 236, 230, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 101, 251, 255, 241, 255, 128, 128, 128, 128, 128],
+            [170, 139, 241, 252, 236, 209, 255, 255, 128, 128, 128],
+            [37, 116, 196, 243, 228, 255, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 204, 254, 255, 245, 255, 128, 128, 128, 128, 128],
+            [207, 160, 250, 255, 238, 128, 128, 128, 128, 128, 128],
+            [102, 103, 231, 255, 211, 171, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 152, 252, 255, 240, 255, 128, 128, 128, 128, 128],
+            [177, 135, 243, 255, 234, 225, 128, 128, 128, 128, 128],
+            [80, 129, 211, 255, 194, 224, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [246, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [255, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [198, 35, 237, 223, 193, 187, 162, 160, 145, 155, 62],
+            [131, 45, 198, 221, 172, 176, 220, 157, 252, 221, 1],
+            [68, 47, 146, 208, 149, 167, 221, 162, 255, 223, 128],
+        ],
+        [
+            [1, 149, 241, 255, 221, 224, 255, 255, 128, 128, 128],
+            [184, 141, 234, 253, 222, 220, 255, 199, 128, 128, 128],
+            [81, 99, 181, 242, 176, 190, 249, 202, 255, 255, 128],
+        ],
+        [
+            [1, 129, 232, 253, 214, 197, 242, 196, 255, 255, 128],
+            [99, 121, 210, 250, 201, 198, 255, 202, 128, 128, 128],
+            [23, 91, 163, 242, 170, 187, 247, 210, 255, 255, 128],
+        ],
+        [
+            [1, 200, 246, 255, 234, 255, 128, 128, 128, 128, 128],
+            [109, 178, 241, 255, 231, 245, 255, 255, 128, 128, 128],
+            [44, 130, 201, 253, 205, 192, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 132, 239, 251, 219, 209, 255, 165, 128, 128, 128],
+            [94, 136, 225, 251, 218, 190, 255, 255, 128, 128, 128],
+            [22, 100, 174, 245, 186, 161, 255, 199, 128, 128, 128],
+        ],
+        [
+            [1, 182, 249, 255, 232, 235, 128, 128, 128, 128, 128],
+            [124, 143, 241, 255, 227, 234, 128, 128, 128, 128, 128],
+            [35, 77, 181, 251, 193, 211, 255, 205, 128, 128, 128],
+        ],
+        [
+            [1, 157, 247, 255, 236, 231, 255, 255, 128, 128, 128],
+            [121, 141, 235, 255, 225, 227, 255, 255, 128, 128, 128],
+            [45, 99, 188, 251, 195, 217, 255, 224, 128, 128, 128],
+        ],
+        [
+            [1, 1, 251, 255, 213, 255, 128, 128, 128, 128, 128],
+            [203, 1, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+            [137, 1, 177, 255, 224, 255, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [253, 9, 248, 251, 207, 208, 255, 192, 128, 128, 128],
+            [175, 13, 224, 243, 193, 185, 249, 198, 255, 255, 128],
+            [73, 17, 171, 221, 161, 179, 236, 167, 255, 234, 128],
+        ],
+        [
+            [1, 95, 247, 253, 212, 183, 255, 255, 128, 128, 128],
+            [239, 90, 244, 250, 211, 209, 255, 255, 128, 128, 128],
+            [155, 77, 195, 248, 188, 195, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 24, 239, 251, 218, 219, 255, 205, 128, 128, 128],
+            [201, 51, 219, 255, 196, 186, 128, 128, 128, 128, 128],
+            [69, 46, 190, 239, 201, 218, 255, 228, 128, 128, 128],
+        ],
+        [
+            [1, 191, 251, 255, 255, 128, 128, 128, 128, 128, 128],
+            [223, 165, 249, 255, 213, 255, 128, 128, 128, 128, 128],
+            [141, 124, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 16, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+            [190, 36, 230, 255, 236, 255, 128, 128, 128, 128, 128],
+            [149, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 226, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [247, 192, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [240, 128, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 134, 252, 255, 255, 128, 128, 128, 128, 128, 128],
+            [213, 62, 250, 255, 255, 128, 128, 128, 128, 128, 128],
+            [55, 93, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [202, 24, 213, 235, 186, 191, 220, 160, 240, 175, 255],
+            [126, 38, 182, 232, 169, 184, 228, 174, 255, 187, 128],
+            [61, 46, 138, 219, 151, 178, 240, 170, 255, 216, 128],
+        ],
+        [
+            [1, 112, 230, 250, 199, 191, 247, 159, 255, 255, 128],
+            [166, 109, 228, 252, 211, 215, 255, 174, 128, 128, 128],
+            [39, 77, 162, 232, 172, 180, 245, 178, 255, 255, 128],
+        ],
+        [
+            [1, 52, 220, 246, 198, 199, 249, 220, 255, 255, 128],
+            [124, 74, 191, 243, 183, 193, 250, 221, 255, 255, 128],
+            [24, 71, 130, 219, 154, 170, 243, 182, 255, 255, 128],
+        ],
+        [
+            [1, 182, 225, 249, 219, 240, 255, 224, 128, 128, 128],
+            [149, 150, 226, 252, 216, 205, 255, 171, 128, 128, 128],
+            [28, 108, 170, 242, 183, 194, 254, 223, 255, 255, 128],
+        ],
+        [
+            [1, 81, 230, 252, 204, 203, 255, 192, 128, 128, 128],
+            [123, 102, 209, 247, 188, 196, 255, 233, 128, 128, 128],
+            [20, 95, 153, 243, 164, 173, 255, 203, 128, 128, 128],
+        ],
+        [
+            [1, 222, 248, 255, 216, 213, 128, 128, 128, 128, 128],
+            [168, 175, 246, 252, 235, 205, 255, 255, 128, 128, 128],
+            [47, 116, 215, 255, 211, 212, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 121, 236, 253, 212, 214, 255, 255, 128, 128, 128],
+            [141, 84, 213, 252, 201, 202, 255, 219, 128, 128, 128],
+            [42, 80, 160, 240, 162, 185, 255, 205, 128, 128, 128],
+        ],
+        [
+            [1, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [244, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [238, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+];
+
+// how likely the frame header is to replace each of those
+const COEFF_UPDATE_PROBS: [[[[u8; 11]; 3]; 8]; 4] = [
+    [
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [176, 246, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [223, 241, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 244, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [234, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 246, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [239, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 248, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 253, 255, 254, 255, 255, 255, 255, 255, 255],
+            [250, 255, 254, 255, 254, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [217, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [225, 252, 241, 253, 255, 255, 254, 255, 255, 255, 255],
+            [234, 250, 241, 250, 253, 255, 253, 254, 255, 255, 255],
+        ],
+        [
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [223, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [238, 253, 254, 254, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 248, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [247, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [186, 251, 250, 255, 255, 255, 255, 255, 255, 255, 255],
+            [234, 251, 244, 254, 255, 255, 255, 255, 255, 255, 255],
+            [251, 251, 243, 253, 254, 255, 254, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [236, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 253, 253, 254, 254, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [248, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 254, 252, 254, 255, 255, 255, 255, 255, 255, 255],
+            [248, 254, 249, 253, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [246, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 254, 251, 254, 254, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [248, 254, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 254, 254, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 251, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [245, 251, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 251, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 252, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+];
+
+// 4x4 mode tree probabilities by the modes above and to the left
+const BMODE_PROBS: [[[u8; 9]; 10]; 10] = [
+    [
+        [231, 120, 48, 89, 115, 113, 120, 152, 112],
+        [152, 179, 64, 126, 170, 118, 46, 70, 95],
+        [175, 69, 143, 80, 85, 82, 72, 155, 103],
+        [56, 58, 10, 171, 218, 189, 17, 13, 152],
+        [114, 26, 17, 163, 44, 195, 21, 10, 173],
+        [121, 24, 80, 195, 26, 62, 44, 64, 85],
+        [144, 71, 10, 38, 171, 213, 144, 34, 26],
+        [170, 46, 55, 19, 136, 160, 33, 206, 71],
+        [63, 20, 8, 114, 114, 208, 12, 9, 226],
+        [81, 40, 11, 96, 182, 84, 29, 16, 36],
+    ],
+    [
+        [134, 183, 89, 137, 98, 101, 106, 165, 148],
+        [72, 187, 100, 130, 157, 111, 32, 75, 80],
+        [66, 102, 167, 99, 74, 62, 40, 234, 128],
+        [41, 53, 9, 178, 241, 141, 26, 8, 107],
+        [74, 43, 26, 146, 73, 166, 49, 23, 157],
+        [65, 38, 105, 160, 51, 52, 31, 115, 128],
+        [104, 79, 12, 27, 217, 255, 87, 17, 7],
+        [87, 68, 71, 44, 114, 51, 15, 186, 23],
+        [47, 41, 14, 110, 182, 183, 21, 17, 194],
+        [66, 45, 25, 102, 197, 189, 23, 18, 22],
+    ],
+    [
+        [88, 88, 147, 150, 42, 46, 45, 196, 205],
+        [43, 97, 183, 117, 85, 38, 35, 179, 61],
+        [39, 53, 200, 87, 26, 21, 43, 232, 171],
+        [56, 34, 51, 104, 114, 102, 29, 93, 77],
+        [39, 28, 85, 171, 58, 165, 90, 98, 64],
+        [34, 22, 116, 206, 23, 34, 43, 166, 73],
+        [107, 54, 32, 26, 51, 1, 81, 43, 31],
+        [68, 25, 106, 22, 64, 171, 36, 225, 114],
+        [34, 19, 21, 102, 132, 188, 16, 76, 124],
+        [62, 18, 78, 95, 85, 57, 50, 48, 51],
+    ],
+    [
+        [193, 101, 35, 159, 215, 111, 89, 46, 111],
+        [60, 148, 31, 172, 219, 228, 21, 18, 111],
+        [112, 113, 77, 85, 179, 255, 38, 120, 114],
+        [40, 42, 1, 196, 245, 209, 10, 25, 109],
+        [88, 43, 29, 140, 166, 213, 37, 43, 154],
+        [61, 63, 30, 155, 67, 45, 68, 1, 209],
+        [100, 80, 8, 43, 154, 1, 51, 26, 71],
+        [142, 78, 78, 16, 255, 128, 34, 197, 171],
+        [41, 40, 5, 102, 211, 183, 4, 1, 221],
+        [51, 50, 17, 168, 209, 192, 23, 25, 82],
+    ],
+    [
+        [138, 31, 36, 171, 27, 166, 38, 44, 229],
+        [67, 87, 58, 169, 82, 115, 26, 59, 179],
+        [63, 59, 90, 180, 59, 166, 93, 73, 154],
+        [40, 40, 21, 116, 143, 209, 34, 39, 175],
+        [47, 15, 16, 183, 34, 223, 49, 45, 183],
+        [46, 17, 33, 183, 6, 98, 15, 32, 183],
+        [57, 46, 22, 24, 128, 1, 54, 17, 37],
+        [65, 32, 73, 115, 28, 128, 23, 128, 205],
+        [40, 3, 9, 115, 51, 192, 18, 6, 223],
+        [87, 37, 9, 115, 59, 77, 64, 21, 47],
+    ],
+    [
+        [104, 55, 44, 218, 9, 54, 53, 130, 226],
+        [64, 90, 70, 205, 40, 41, 23, 26, 57],
+        [54, 57, 112, 184, 5, 41, 38, 166, 213],
+        [30, 34, 26, 133, 152, 116, 10, 32, 134],
+        [39, 19, 53, 221, 26, 114, 32, 73, 255],
+        [31, 9, 65, 234, 2, 15, 1, 118, 73],
+        [75, 32, 12, 51, 192, 255, 160, 43, 51],
+        [88, 31, 35, 67, 102, 85, 55, 186, 85],
+        [56, 21, 23, 111, 59, 205, 45, 37, 192],
+        [55, 38, 70, 124, 73, 102, 1, 34, 98],
+    ],
+    [
+        [125, 98, 42, 88, 104, 85, 117, 175, 82],
+        [95, 84, 53, 89, 128, 100, 113, 101, 45],
+        [75, 79, 123, 47, 51, 128, 81, 171, 1],
+        [57, 17, 5, 71, 102, 57, 53, 41, 49],
+        [38, 33, 13, 121, 57, 73, 26, 1, 85],
+        [41, 10, 67, 138, 77, 110, 90, 47, 114],
+        [115, 21, 2, 10, 102, 255, 166, 23, 6],
+        [101, 29, 16, 10, 85, 128, 101, 196, 26],
+        [57, 18, 10, 102, 102, 213, 34, 20, 43],
+        [117, 20, 15, 36, 163, 128, 68, 1, 26],
+    ],
+    [
+        [102, 61, 71, 37, 34, 53, 31, 243, 192],
+        [69, 60, 71, 38, 73, 119, 28, 222, 37],
+        [68, 45, 128, 34, 1, 47, 11, 245, 171],
+        [62, 17, 19, 70, 146, 85, 55, 62, 70],
+        [37, 43, 37, 154, 100, 163, 85, 160, 1],
+        [63, 9, 92, 136, 28, 64, 32, 201, 85],
+        [75, 15, 9, 9, 64, 255, 184, 119, 16],
+        [86, 6, 28, 5, 64, 255, 25, 248, 1],
+        [56, 8, 17, 132, 137, 255, 55, 116, 128],
+        [58, 15, 20, 82, 135, 57, 26, 121, 40],
+    ],
+    [
+        [164, 50, 31, 137, 154, 133, 25, 35, 218],
+        [51, 103, 44, 131, 131, 123, 31, 6, 158],
+        [86, 40, 64, 135, 148, 224, 45, 183, 128],
+        [22, 26, 17, 131, 240, 154, 14, 1, 209],
+        [45, 16, 21, 91, 64, 222, 7, 1, 197],
+        [56, 21, 39, 155, 60, 138, 23, 102, 213],
+        [83, 12, 13, 54, 192, 255, 68, 47, 28],
+        [85, 26, 85, 85, 128, 128, 32, 146, 171],
+        [18, 11, 7, 63, 144, 171, 4, 4, 246],
+        [35, 27, 10, 146, 174, 171, 12, 26, 128],
+    ],
+    [
+        [190, 80, 35, 99, 180, 80, 126, 54, 45],
+        [85, 126, 47, 87, 176, 51, 41, 20, 32],
+        [101, 75, 128, 139, 118, 146, 116, 128, 85],
+        [56, 41, 15, 176, 236, 85, 37, 9, 62],
+        [71, 30, 17, 119, 118, 255, 17, 18, 138],
+        [101, 38, 60, 138, 55, 70, 43, 26, 142],
+        [146, 36, 19, 30, 171, 255, 97, 27, 20],
+        [138, 45, 61, 62, 219, 1, 81, 188, 64],
+        [32, 41, 20, 117, 151, 142, 20, 21, 163],
+        [112, 19, 12, 61, 195, 128, 48, 4, 24],
+    ],
+];
+
+// lossless distance codes 1 to 120 as (dy, 8 - dx) nibbles
+const CODE_TO_PLANE: [u8; 120] = [
+    0x18, 0x07, 0x17, 0x19, 0x28, 0x06, 0x27, 0x29, 0x16, 0x1a, 0x26, 0x2a, 0x38, 0x05, 0x37, 0x39,
+    0x15, 0x1b, 0x36, 0x3a, 0x25, 0x2b, 0x48, 0x04, 0x47, 0x49, 0x14, 0x1c, 0x35, 0x3b, 0x46, 0x4a,
+    0x24, 0x2c, 0x58, 0x45, 0x4b, 0x34, 0x3c, 0x03, 0x57, 0x59, 0x13, 0x1d, 0x56, 0x5a, 0x23, 0x2d,
+    0x44, 0x4c, 0x55, 0x5b, 0x33, 0x3d, 0x68, 0x02, 0x67, 0x69, 0x12, 0x1e, 0x66, 0x6a, 0x22, 0x2e,
+    0x54, 0x5c, 0x43, 0x4d, 0x65, 0x6b, 0x32, 0x3e, 0x78, 0x01, 0x77, 0x79, 0x53, 0x5d, 0x11, 0x1f,
+    0x64, 0x6c, 0x42, 0x4e, 0x76, 0x7a, 0x21, 0x2f, 0x75, 0x7b, 0x31, 0x3f, 0x63, 0x6d, 0x52, 0x5e,
+    0x00, 0x74, 0x7c, 0x41, 0x4f, 0x10, 0x20, 0x62, 0x6e, 0x30, 0x73, 0x7d, 0x51, 0x5f, 0x40, 0x72,
+    0x7e, 0x61, 0x6f, 0x50, 0x71, 0x7f, 0x60, 0x70,
+];
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the same 61x45 crop of doggo in each, with the RGBA libwebp decodes
+    const LOSSY: (&[u8], &[u8]) = (include_bytes!("../tests/data/lossy.webp"), include_bytes!("../tests/data/lossy.webp.rgba"));
+    const LOSSLESS: (&[u8], &[u8]) = (include_bytes!("../tests/data/lossless.webp"), include_bytes!("../tests/data/lossless.webp.rgba"));
+    // lossy color with a round, filtered lossless alpha channel
+    const ALPHA: (&[u8], &[u8]) = (include_bytes!("../tests/data/alpha.webp"), include_bytes!("../tests/data/alpha.webp.rgba"));
+
+    #[test]
+    fn matches_libwebp() {
+        for (name, (webp, rgba)) in [("lossy", LOSSY), ("lossless", LOSSLESS), ("alpha", ALPHA)] {
+            assert!(is_webp(webp));
+            assert_eq!(decode(webp), Ok((61, 45, rgba.to_vec())), "{}", name);
+        }
+    }
+
+    #[test]
+    fn truncated_data_errs() {
+        for (webp, _) in [LOSSY, LOSSLESS, ALPHA] {
+            for len in 0..webp.len() {
+                assert!(decode(&webp[..len]).is_err(), "decoded {} of {} bytes", len, webp.len());
+            }
+        }
+    }
+}
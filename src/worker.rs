@@ -0,0 +1,172 @@
+use std::mem;
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::{Effect, Instant, Renderer};
+
+// region Render thread
+
+type Update = Box<dyn FnOnce(&mut Renderer) + Send>;
+
+// what a finished frame was rendered with
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrameInfo {
+    pub width: usize,
+    pub height: usize,
+    pub effect: Effect,
+    // `Renderer::settled` right after rendering it
+    pub settled: Option<Duration>,
+    // rendering alone, without waiting to be taken
+    pub took: Duration,
+    // how many updates the renderer had seen by then, queries aside
+    updates: u64,
+}
+
+// the middle of the three buffers, which the render thread fills and
+// `latest` swaps out
+#[derive(Default)]
+struct Slot {
+    frame: Vec<u8>,
+    info: FrameInfo,
+    // finished and not taken yet
+    fresh: bool,
+    updates: Vec<Update>,
+    // updates handed over so far, queries aside
+    sent: u64,
+    // start of the frame being rendered, if one is
+    rendering: Option<Instant>,
+    stop: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    slot: Mutex<Slot>,
+    changed: Condvar,
+}
+
+// renders on a thread of its own, one frame ahead of the one being shown, so
+// a slow frame doesn't hold up the event loop; that only ever swaps in the
+// newest finished frame and hands input over as updates
+pub struct RenderThread {
+    shared: Arc<Shared>,
+    front: Vec<u8>,
+    info: FrameInfo,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RenderThread {
+    pub fn spawn(renderer: Renderer) -> Self {
+        let shared = Arc::new(Shared::default());
+        let s = shared.clone();
+        let handle = thread::Builder::new().name("doggowarp-render".into())
+            .spawn(move || work(renderer, &s))
+            .expect("failed to spawn the render thread");
+        Self { shared, front: Vec::new(), info: FrameInfo::default(), handle: Some(handle) }
+    }
+
+    // runs on the render thread before its next frame, in the order given; a
+    // frame that never finishes keeps them waiting for good
+    pub fn update<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Renderer) + Send + 'static,
+    {
+        self.push(Box::new(f), true);
+    }
+
+    // reads from the renderer, waiting for the frame in progress to finish
+    pub fn query<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&Renderer) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.push(Box::new(move |r| { let _ = tx.send(f(r)); }), false);
+        rx.recv().expect("the render thread has stopped")
+    }
+
+    // counted under the same lock, so the render thread never sees one
+    // without the other
+    fn push(&self, f: Update, counted: bool) {
+        let mut slot = self.shared.slot.lock().unwrap();
+        slot.sent += counted as u64;
+        slot.updates.push(f);
+        self.shared.changed.notify_one();
+    }
+
+    // swaps in the newest finished frame, if there is one since the last
+    // call, and lets the render thread start on the next
+    pub fn latest(&mut self) -> bool {
+        let mut slot = self.shared.slot.lock().unwrap();
+        if !slot.fresh {
+            return false;
+        }
+        mem::swap(&mut self.front, &mut slot.frame);
+        self.info = slot.info;
+        slot.fresh = false;
+        self.shared.changed.notify_one();
+        true
+    }
+
+    // RGBA, empty until the first frame; `info` has its size
+    pub fn frame(&self) -> &[u8] { &self.front }
+
+    pub fn info(&self) -> &FrameInfo { &self.info }
+
+    // when the frame in progress started, for `AppState::rendering_since`;
+    // none while the thread waits for the last one to be taken
+    pub fn rendering_since(&self) -> Option<Instant> {
+        self.shared.slot.lock().unwrap().rendering
+    }
+
+    // `Renderer::settled` as of the latest frame, unless updates have been
+    // handed over since that it doesn't show yet
+    pub fn settled(&self) -> Option<Duration> {
+        self.info.settled.filter(|_| self.info.updates == self.shared.slot.lock().unwrap().sent)
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        self.shared.slot.lock().unwrap().stop = true;
+        self.shared.changed.notify_one();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// renders whenever the last frame has been taken; updates that come in while
+// it waits are applied right away, so `query` doesn't wait for the next take
+fn work(mut renderer: Renderer, shared: &Shared) {
+    let (mut back, mut last) = (Vec::new(), Instant::now());
+    loop {
+        let (pending, updates, waiting) = {
+            let slot = shared.slot.lock().unwrap();
+            let mut slot = shared.changed.wait_while(slot, |s| !s.stop && s.fresh && s.updates.is_empty()).unwrap();
+            if slot.stop {
+                return;
+            }
+            (mem::take(&mut slot.updates), slot.sent, slot.fresh)
+        };
+        pending.into_iter().for_each(|f| f(&mut renderer));
+        if waiting {
+            continue;
+        }
+        let (width, height) = (renderer.image().width, renderer.image().height);
+        // the renderer never touches alpha
+        back.resize(width * height * 4, 255);
+        let started = Instant::now();
+        shared.slot.lock().unwrap().rendering = Some(started);
+        renderer.render(started - last, &mut back);
+        last = started;
+        let info = FrameInfo { width, height, effect: renderer.effect(), settled: renderer.settled(), took: started.elapsed(), updates };
+        let mut slot = shared.slot.lock().unwrap();
+        mem::swap(&mut slot.frame, &mut back);
+        slot.info = info;
+        slot.fresh = true;
+        slot.rendering = None;
+    }
+}
+
+// endregion
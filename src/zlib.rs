@@ -0,0 +1,645 @@
+// zlib (RFC 1950) around deflate (RFC 1951), just enough for the image codecs.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+// region Checksums
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before `b` may overflow
+    for chunk in data.chunks(5552) {
+        for &d in chunk {
+            a += d as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+// CRC-32 as used by PNG chunks (and gzip, zip)
+pub(crate) fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut c = !0u32;
+    for part in parts {
+        for &b in *part {
+            c = CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+        }
+    }
+    !c
+}
+
+// endregion
+// region Inflate
+
+// LSB first, as both deflate and WebP's lossless format pack their bits
+pub(crate) struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u64,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0, buf: 0, count: 0 }
+    }
+
+    // makes `n` <= 32 bits available, padding with zeros past the end
+    fn fill(&mut self, n: u32) {
+        while self.count < n {
+            let b = self.data.get(self.pos).copied().unwrap_or(0);
+            self.buf |= (b as u64) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+    }
+
+    pub(crate) fn peek(&mut self, n: u32) -> u32 {
+        self.fill(n);
+        (self.buf & ((1u64 << n) - 1)) as u32
+    }
+
+    pub(crate) fn consume(&mut self, n: u32) -> Result<(), &'static str> {
+        self.buf >>= n;
+        self.count -= n;
+        // reading into the padding means the stream was cut short
+        if self.pos * 8 - self.count as usize > self.data.len() * 8 {
+            return Err("truncated bit stream");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn bits(&mut self, n: u32) -> Result<u32, &'static str> {
+        let v = self.peek(n);
+        self.consume(n)?;
+        Ok(v)
+    }
+
+    fn align(&mut self) {
+        let r = self.count % 8;
+        self.buf >>= r;
+        self.count -= r;
+    }
+}
+
+// canonical Huffman code as a table indexed by the next `max` bits (LSB first)
+struct Huffman {
+    table: Vec<(u16, u8)>,
+    max: u32,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, &'static str> {
+        let max = lengths.iter().copied().max().unwrap_or(0) as u32;
+        let mut count = [0u32; 16];
+        lengths.iter().for_each(|&l| count[l as usize] += 1);
+        count[0] = 0;
+        let mut next = [0u32; 16];
+        let mut code = 0;
+        for len in 1..16 {
+            code = (code + count[len - 1]) << 1;
+            next[len] = code;
+            if code + count[len] > 1 << len {
+                return Err("over-subscribed Huffman code");
+            }
+        }
+        // (symbol, length 0) marks codes that are not in use
+        let mut table = vec![(0u16, 0u8); 1 << max];
+        for (sym, &len) in lengths.iter().enumerate() {
+            if len == 0 {
+                continue;
+            }
+            let len = len as u32;
+            let code = next[len as usize];
+            next[len as usize] += 1;
+            let rev = code.reverse_bits() >> (32 - len);
+            for fill in (rev as usize..1 << max).step_by(1 << len) {
+                table[fill] = (sym as u16, len as u8);
+            }
+        }
+        Ok(Self { table, max })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, &'static str> {
+        let (sym, len) = self.table[bits.peek(self.max) as usize];
+        if len == 0 {
+            return Err("invalid Huffman code");
+        }
+        bits.consume(len as u32)?;
+        Ok(sym)
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+fn fixed() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    // both can't fail, the fixed code is complete
+    (Huffman::new(&lengths).unwrap(), Huffman::new(&[5; 30]).unwrap())
+}
+
+fn dynamic(bits: &mut Bits) -> Result<(Huffman, Huffman), &'static str> {
+    let hlit = bits.bits(5)? as usize + 257;
+    let hdist = bits.bits(5)? as usize + 1;
+    let hclen = bits.bits(4)? as usize + 4;
+    let mut cl = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..hclen] {
+        cl[i] = bits.bits(3)? as u8;
+    }
+    let cl = Huffman::new(&cl)?;
+    let mut lengths = Vec::with_capacity(hlit + hdist);
+    while lengths.len() < hlit + hdist {
+        let (len, times) = match cl.decode(bits)? {
+            sym @ 0..=15 => (sym as u8, 1),
+            16 => (*lengths.last().ok_or("repeat with no previous length")?, 3 + bits.bits(2)?),
+            17 => (0, 3 + bits.bits(3)?),
+            _ => (0, 11 + bits.bits(7)?),
+        };
+        lengths.extend((0..times).map(|_| len));
+    }
+    if lengths.len() > hlit + hdist {
+        return Err("code lengths overrun");
+    }
+    Ok((Huffman::new(&lengths[..hlit])?, Huffman::new(&lengths[hlit..])?))
+}
+
+// fails rather than produce more than `limit` bytes
+pub(crate) fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, &'static str> {
+    const TOO_LONG: &str = "data longer than expected";
+    let mut out = Vec::new();
+    let mut bits = Bits::new(data);
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.align();
+                let len = bits.bits(16)?;
+                if bits.bits(16)? != !len & 0xffff {
+                    return Err("stored block length mismatch");
+                }
+                if len as usize > limit - out.len() {
+                    return Err(TOO_LONG);
+                }
+                for _ in 0..len {
+                    out.push(bits.bits(8)? as u8);
+                }
+            }
+            kind @ (1 | 2) => {
+                let (lit, dist) = if kind == 1 { fixed() } else { dynamic(&mut bits)? };
+                loop {
+                    let sym = lit.decode(&mut bits)? as usize;
+                    if sym < 256 {
+                        if out.len() == limit {
+                            return Err(TOO_LONG);
+                        }
+                        out.push(sym as u8);
+                        continue;
+                    }
+                    if sym == 256 {
+                        break;
+                    }
+                    let i = sym - 257;
+                    if i >= LENGTH_BASE.len() {
+                        return Err("invalid length symbol");
+                    }
+                    let len = LENGTH_BASE[i] as usize + bits.bits(LENGTH_EXTRA[i] as u32)? as usize;
+                    let d = dist.decode(&mut bits)? as usize;
+                    if d >= DIST_BASE.len() {
+                        return Err("invalid distance symbol");
+                    }
+                    let d = DIST_BASE[d] as usize + bits.bits(DIST_EXTRA[d] as u32)? as usize;
+                    if d > out.len() {
+                        return Err("distance too far back");
+                    }
+                    if len > limit - out.len() {
+                        return Err(TOO_LONG);
+                    }
+                    // byte by byte, the copy may overlap what it produces
+                    let start = out.len() - d;
+                    for k in 0..len {
+                        out.push(out[start + k]);
+                    }
+                }
+            }
+            _ => return Err("invalid block type"),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+// zlib stream -> at most `limit` bytes, checking the header and the Adler-32
+// trailer
+pub(crate) fn decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, &'static str> {
+    let &[cmf, flg] = data.first_chunk::<2>().ok_or("truncated zlib stream")?;
+    let rest = &data[2..];
+    if cmf & 0x0f != 8 || !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) || flg & 0x20 != 0 {
+        return Err("unsupported zlib header");
+    }
+    let out = inflate(rest, limit)?;
+    let trailer = rest.len().checked_sub(4).map(|i| &rest[i..]).ok_or("truncated zlib stream")?;
+    if trailer != adler32(&out).to_be_bytes() {
+        return Err("zlib checksum mismatch");
+    }
+    Ok(out)
+}
+
+// endregion
+// region Deflate
+
+// LSB first, the counterpart of `Bits`
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    n: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        self.acc |= (value as u64) << self.n;
+        self.n += bits;
+        while self.n >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.n -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.n > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+// code lengths of at most `limit` bits for symbols seen `freq` times; when
+// the plain Huffman code gets too deep, the counts are flattened and it's
+// built again
+fn code_lengths(freq: &[u32], limit: u8) -> Vec<u8> {
+    let mut freq = freq.to_vec();
+    loop {
+        // (weight, symbols below) for every tree still to be merged
+        let mut nodes: Vec<(u64, Vec<usize>)> = freq.iter().enumerate()
+            .filter(|&(_, &f)| f > 0).map(|(s, &f)| (f as u64, vec![s])).collect();
+        let mut lengths = vec![0u8; freq.len()];
+        while nodes.len() > 1 {
+            nodes.sort_by_key(|n| core::cmp::Reverse(n.0));
+            let (wa, a) = nodes.pop().unwrap();
+            let (wb, b) = nodes.pop().unwrap();
+            a.iter().chain(&b).for_each(|&s| lengths[s] += 1);
+            nodes.push((wa + wb, [a, b].concat()));
+        }
+        if lengths.iter().all(|&l| l <= limit) {
+            return lengths;
+        }
+        freq.iter_mut().filter(|f| **f > 0).for_each(|f| *f = (*f >> 1) | 1);
+    }
+}
+
+// canonical codes for `lengths`, bit-reversed for writing LSB first
+fn codes(lengths: &[u8]) -> Vec<u32> {
+    let mut count = [0u32; 16];
+    lengths.iter().for_each(|&l| count[l as usize] += 1);
+    count[0] = 0;
+    let mut next = [0u32; 16];
+    let mut code = 0;
+    for len in 1..16 {
+        code = (code + count[len - 1]) << 1;
+        next[len] = code;
+    }
+    lengths.iter().map(|&len| {
+        if len == 0 {
+            return 0;
+        }
+        let code = next[len as usize];
+        next[len as usize] += 1;
+        code.reverse_bits() >> (32 - len as u32)
+    }).collect()
+}
+
+// symbol and extra bits for a match length or distance
+fn length_symbol(len: usize) -> (usize, u32, u32) {
+    let i = LENGTH_BASE.iter().rposition(|&b| b as usize <= len).unwrap();
+    (257 + i, LENGTH_EXTRA[i] as u32, (len - LENGTH_BASE[i] as usize) as u32)
+}
+
+fn dist_symbol(dist: usize) -> (usize, u32, u32) {
+    let i = DIST_BASE.iter().rposition(|&b| b as usize <= dist).unwrap();
+    (i, DIST_EXTRA[i] as u32, (dist - DIST_BASE[i] as usize) as u32)
+}
+
+const WINDOW: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+// how many earlier positions to try per match, and a match long enough to
+// stop looking
+const MAX_CHAIN: usize = 128;
+const GOOD_MATCH: usize = 128;
+// tokens per block, each block getting its own codes
+const BLOCK_TOKENS: usize = 1 << 16;
+
+// a literal byte when `dist` is 0, a back reference otherwise
+#[derive(Copy, Clone)]
+struct Token {
+    len: u16,
+    dist: u16,
+}
+
+// hash chains over the 32K window, of three byte prefixes
+struct Matcher<'a> {
+    data: &'a [u8],
+    // most recent position + 1 per hash, and the one before it per position
+    head: Vec<u32>,
+    prev: Vec<u32>,
+}
+
+impl Matcher<'_> {
+    fn hash(&self, i: usize) -> usize {
+        let v = u32::from_le_bytes([self.data[i], self.data[i + 1], self.data[i + 2], 0]);
+        (v.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, i: usize) {
+        if i + MIN_MATCH <= self.data.len() {
+            let h = self.hash(i);
+            self.prev[i % WINDOW] = self.head[h];
+            self.head[h] = i as u32 + 1;
+        }
+    }
+
+    // (length, distance) of the longest earlier match at `i`, length 0 if none
+    fn longest(&self, i: usize) -> (usize, usize) {
+        let data = self.data;
+        if i + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+        let max = (data.len() - i).min(MAX_MATCH);
+        let (mut best, mut best_dist) = (0, 0);
+        let mut cand = self.head[self.hash(i)] as usize;
+        for _ in 0..MAX_CHAIN {
+            if cand == 0 || i - (cand - 1) >= WINDOW {
+                break;
+            }
+            let j = cand - 1;
+            if data[j + best] == data[i + best] {
+                let len = data[j..j + max].iter().zip(&data[i..i + max]).take_while(|(a, b)| a == b).count();
+                if len > best {
+                    (best, best_dist) = (len, i - j);
+                    if len >= GOOD_MATCH.min(max) {
+                        break;
+                    }
+                }
+            }
+            // chains only go back in time; anything else is a reused slot
+            let next = self.prev[j % WINDOW] as usize;
+            if next >= cand {
+                break;
+            }
+            cand = next;
+        }
+        if best >= MIN_MATCH { (best, best_dist) } else { (0, 0) }
+    }
+}
+
+// LZ77 with one step of lazy matching: a longer match one byte later is worth
+// a literal first
+fn tokenize(data: &[u8]) -> Vec<Token> {
+    let mut m = Matcher { data, head: vec![0; 1 << HASH_BITS], prev: vec![0; WINDOW] };
+    let mut tokens = Vec::with_capacity(data.len() / 2);
+    let mut i = 0;
+    while i < data.len() {
+        let (len, dist) = m.longest(i);
+        m.insert(i);
+        if len == 0 || len < GOOD_MATCH && m.longest(i + 1).0 > len {
+            tokens.push(Token { len: data[i] as u16, dist: 0 });
+            i += 1;
+            continue;
+        }
+        tokens.push(Token { len: len as u16, dist: dist as u16 });
+        for k in i + 1..i + len {
+            m.insert(k);
+        }
+        i += len;
+    }
+    tokens
+}
+
+fn write_block(w: &mut BitWriter, tokens: &[Token], last: bool) {
+    let mut lit_freq = [0u32; 286];
+    let mut dist_freq = [0u32; 30];
+    for t in tokens {
+        if t.dist == 0 {
+            lit_freq[t.len as usize] += 1;
+        } else {
+            lit_freq[length_symbol(t.len as usize).0] += 1;
+            dist_freq[dist_symbol(t.dist as usize).0] += 1;
+        }
+    }
+    lit_freq[256] = 1;
+    // decoders want at least two codes in each tree
+    for freq in [&mut lit_freq[..], &mut dist_freq[..]] {
+        for s in 0..2 {
+            if freq.iter().filter(|&&f| f > 0).count() < 2 && freq[s] == 0 {
+                freq[s] = 1;
+            }
+        }
+    }
+    let (lit_len, dist_len) = (code_lengths(&lit_freq, 15), code_lengths(&dist_freq, 15));
+    let hlit = 257.max(lit_len.iter().rposition(|&l| l > 0).unwrap() + 1);
+    let hdist = 1.max(dist_len.iter().rposition(|&l| l > 0).unwrap() + 1);
+
+    // both length lists, run-length coded with symbols 16 to 18
+    let all = [&lit_len[..hlit], &dist_len[..hdist]].concat();
+    let mut cl_symbols = Vec::new();
+    let mut i = 0;
+    while i < all.len() {
+        let run = all[i..].iter().take_while(|&&l| l == all[i]).count();
+        if all[i] == 0 && run >= 3 {
+            let n = run.min(138);
+            cl_symbols.push(if n <= 10 { (17, n as u32 - 3) } else { (18, n as u32 - 11) });
+            i += n;
+        } else if all[i] != 0 && run >= 4 {
+            cl_symbols.push((all[i] as usize, 0));
+            let n = (run - 1).min(6);
+            cl_symbols.push((16, n as u32 - 3));
+            i += 1 + n;
+        } else {
+            cl_symbols.push((all[i] as usize, 0));
+            i += 1;
+        }
+    }
+    let mut cl_freq = [0u32; 19];
+    cl_symbols.iter().for_each(|&(s, _)| cl_freq[s] += 1);
+    let cl_len = code_lengths(&cl_freq, 7);
+    let cl_codes = codes(&cl_len);
+    let hclen = 4.max(CODE_LENGTH_ORDER.iter().rposition(|&s| cl_len[s] > 0).unwrap() + 1);
+
+    w.write(last as u32, 1);
+    w.write(2, 2);
+    w.write((hlit - 257) as u32, 5);
+    w.write((hdist - 1) as u32, 5);
+    w.write((hclen - 4) as u32, 4);
+    for &s in &CODE_LENGTH_ORDER[..hclen] {
+        w.write(cl_len[s] as u32, 3);
+    }
+    for &(s, extra) in &cl_symbols {
+        w.write(cl_codes[s], cl_len[s] as u32);
+        match s {
+            16 => w.write(extra, 2),
+            17 => w.write(extra, 3),
+            18 => w.write(extra, 7),
+            _ => {}
+        }
+    }
+
+    let (lit_codes, dist_codes) = (codes(&lit_len), codes(&dist_len));
+    for t in tokens {
+        if t.dist == 0 {
+            w.write(lit_codes[t.len as usize], lit_len[t.len as usize] as u32);
+            continue;
+        }
+        let (s, bits, extra) = length_symbol(t.len as usize);
+        w.write(lit_codes[s], lit_len[s] as u32);
+        w.write(extra, bits);
+        let (s, bits, extra) = dist_symbol(t.dist as usize);
+        w.write(dist_codes[s], dist_len[s] as u32);
+        w.write(extra, bits);
+    }
+    w.write(lit_codes[256], lit_len[256] as u32);
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let tokens = tokenize(data);
+    let mut w = BitWriter { out: Vec::with_capacity(data.len() / 2 + 64), acc: 0, n: 0 };
+    let blocks = tokens.len().div_ceil(BLOCK_TOKENS).max(1);
+    for b in 0..blocks {
+        let end = ((b + 1) * BLOCK_TOKENS).min(tokens.len());
+        write_block(&mut w, &tokens[b * BLOCK_TOKENS..end], b + 1 == blocks);
+    }
+    w.finish()
+}
+
+// bytes -> zlib stream, default compression level in the header
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x9c];
+    out.extend(deflate(data));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // an LCG's high bytes, which barely compress
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 1u32;
+        (0..len).map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 24) as u8
+        }).collect()
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(crc32(&[b""]), 0);
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = b"the quick brown fox jumps over the lazy dog; ".repeat(500);
+        let mixed: Vec<u8> = (0..200_000u64).map(|i| if i % 1000 < 300 { (i % 7) as u8 } else { ((i * i) >> 9) as u8 }).collect();
+        for data in [vec![], vec![42], text, noise(100_000), vec![0; 300_000], (0..=255).collect(), mixed] {
+            let z = compress(&data);
+            assert_eq!(decompress(&z, data.len()).as_deref(), Ok(&data[..]), "{} bytes", data.len());
+        }
+    }
+
+    // stored, fixed and dynamic Huffman blocks, as zlib itself writes them
+    #[test]
+    fn reads_zlib_streams() {
+        let dynamic: Vec<u8> = (0..300u32).map(|i| ((i * i / 7) % 23 + 97) as u8).collect();
+        let cases = [
+            ("7801010600f9ff73746f726564093c0292", b"stored".to_vec()),
+            ("78014b4c4a4e8421001de00499", b"abcabcabcabc".to_vec()),
+            ("78dacdce8111c3200840d15911212a885182aedff4ae43f40ff0ee0340c2cca5e95891b89903eb82cb827ab09dba68243db2e5\
+              28589e65f7ec82ae14a362dcc229a64921841d8faf377f620352119baf2877601d41ea289efb2e331bfcc03468d5631c9dc22e\
+              58cae0d65e710d6d853326f8f62f831f170d7e51", dynamic),
+        ];
+        for (z, data) in cases {
+            let z = hex(z);
+            assert_eq!(decompress(&z, data.len()), Ok(data.clone()));
+            assert_eq!(decompress(&z, data.len() - 1), Err("data longer than expected"));
+        }
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        let data = vec![7; 100_000];
+        let z = compress(&data);
+        assert_eq!(decompress(&z, 99_999), Err("data longer than expected"));
+        assert_eq!(decompress(&z, 0), Err("data longer than expected"));
+        assert_eq!(decompress(&z, usize::MAX).map(|d| d.len()), Ok(100_000));
+    }
+
+    #[test]
+    fn bad_streams_err() {
+        let data = b"the quick brown fox jumps over the lazy dog".repeat(20);
+        let z = compress(&data);
+        for len in 0..z.len() {
+            assert!(decompress(&z[..len], data.len()).is_err(), "decompressed {} of {} bytes", len, z.len());
+        }
+        let mut sum = z.clone();
+        *sum.last_mut().unwrap() ^= 1;
+        assert_eq!(decompress(&sum, data.len()), Err("zlib checksum mismatch"));
+        let mut header = z;
+        header[0] = 0x79;
+        assert_eq!(decompress(&header, data.len()), Err("unsupported zlib header"));
+    }
+}